@@ -0,0 +1,227 @@
+use crate::export::document::{ExportDocument, ExportEntry};
+use crate::paths::{sanitize_file_name, write_if_changed};
+use crate::zotero_api::item::Item;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+/// Writes the PDF annotations (highlights, comments, ...) of every exported item to one file per item,
+/// named by its citation key.
+///
+/// Zotero stores annotations as children of attachment items, so the annotations of all attachments of an item are combined.
+pub struct AnnotationsExporter {
+    dir: PathBuf,
+    format: AnnotationsFormat,
+}
+
+/// File format for exported annotations
+#[derive(clap::ValueEnum, Clone, Default, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum AnnotationsFormat {
+    #[default]
+    Markdown,
+    Json,
+}
+
+impl Display for AnnotationsFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            serde_variant::to_variant_name(self).unwrap_or_default()
+        )
+    }
+}
+
+/// A single annotation, as written to JSON
+#[derive(Serialize, Debug, PartialEq)]
+struct Annotation<'a> {
+    key: &'a str,
+    attachment: &'a str,
+    #[serde(rename = "type")]
+    annotation_type: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    color: Option<&'a str>,
+    #[serde(skip)]
+    sort_index: &'a str,
+}
+
+impl<'a> Annotation<'a> {
+    fn from_item(item: &'a Item, attachment: &'a Item) -> Self {
+        Self {
+            key: &item.key,
+            attachment: attachment
+                .data
+                .field("filename")
+                .or(attachment.data.field("title"))
+                .unwrap_or(&attachment.key),
+            annotation_type: item.data.field("annotationType").unwrap_or("unknown"),
+            text: item.data.field("annotationText"),
+            comment: item.data.field("annotationComment"),
+            page: item.data.field("annotationPageLabel"),
+            color: item.data.field("annotationColor"),
+            sort_index: item.data.field("annotationSortIndex").unwrap_or_default(),
+        }
+    }
+}
+
+impl AnnotationsExporter {
+    pub fn new(dir: PathBuf, format: AnnotationsFormat) -> Self {
+        Self { dir, format }
+    }
+
+    pub async fn write(&self, document: &ExportDocument) -> Result<(), AnnotationsError> {
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(|e| file_error(&self.dir, e))?;
+        let extension = match self.format {
+            AnnotationsFormat::Markdown => "md",
+            AnnotationsFormat::Json => "json",
+        };
+        let mut written = 0;
+        for entry in &document.entries {
+            let annotations = collect_annotations(document, entry);
+            if annotations.is_empty() {
+                continue;
+            }
+            let content = match self.format {
+                AnnotationsFormat::Markdown => render_markdown(entry, &annotations),
+                AnnotationsFormat::Json => {
+                    serde_json::to_string_pretty(&annotations).unwrap_or_default()
+                }
+            };
+            let path = self.dir.join(format!(
+                "{}.{}",
+                sanitize_file_name(&entry.entry.key),
+                extension
+            ));
+            if write_if_changed(&path, &content)
+                .await
+                .map_err(|e| file_error(&path, e))?
+            {
+                written += 1;
+            }
+        }
+        log::info!(
+            "Wrote {} annotation file(s) to '{}'",
+            written,
+            self.dir.display()
+        );
+        Ok(())
+    }
+}
+
+/// All annotations of the attachments of an entry, in reading order
+fn collect_annotations<'a>(
+    document: &'a ExportDocument,
+    entry: &ExportEntry,
+) -> Vec<Annotation<'a>> {
+    let mut annotations = Vec::new();
+    for attachment in document.children_of(entry) {
+        let Some(children) = document.children.get(&attachment.key) else {
+            continue;
+        };
+        let mut attachment_annotations: Vec<Annotation> = children
+            .iter()
+            .filter(|child| child.data.item_type == "annotation")
+            .map(|child| Annotation::from_item(child, attachment))
+            .collect();
+        attachment_annotations.sort_by(|a, b| a.sort_index.cmp(b.sort_index));
+        annotations.extend(attachment_annotations);
+    }
+    annotations
+}
+
+fn render_markdown(entry: &ExportEntry, annotations: &[Annotation]) -> String {
+    let mut content = format!("# {} (@{})\n", entry.item.data.title, entry.entry.key);
+    let mut current_attachment = None;
+    for annotation in annotations {
+        if current_attachment != Some(annotation.attachment) {
+            content.push_str(&format!("\n## {}\n", annotation.attachment));
+            current_attachment = Some(annotation.attachment);
+        }
+        content.push('\n');
+        if let Some(text) = annotation.text {
+            for line in text.lines() {
+                content.push_str(&format!("> {}\n", line));
+            }
+        }
+        if let Some(page) = annotation.page {
+            content.push_str(&format!("> — p. {}\n", page));
+        }
+        if let Some(comment) = annotation.comment {
+            if annotation.text.is_some() || annotation.page.is_some() {
+                content.push('\n');
+            }
+            content.push_str(comment);
+            content.push('\n');
+        }
+    }
+    content
+}
+
+fn file_error(path: &Path, io_error: std::io::Error) -> AnnotationsError {
+    AnnotationsError::FileError {
+        path: path.display().to_string(),
+        io_error,
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum AnnotationsError {
+    #[error("Error with annotation file '{path}'")]
+    FileError {
+        path: String,
+        #[source]
+        io_error: std::io::Error,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zotero_api::{ExportFormat, item::Item};
+    use pretty_assertions::assert_eq;
+
+    fn document() -> ExportDocument {
+        let items: Vec<Item> = serde_json::from_value(serde_json::json!([
+            {"key":"PARENT01","version":1,"data":{"itemType":"book","title":"Title"},
+             "biblatex":"@book{smith_2020,\n}"},
+            {"key":"ATTACH01","version":1,"data":{"itemType":"attachment","parentItem":"PARENT01","filename":"smith.pdf"},
+             "biblatex":""},
+            {"key":"ANNOT002","version":1,"data":{"itemType":"annotation","parentItem":"ATTACH01","annotationType":"note",
+             "annotationComment":"Later comment","annotationPageLabel":"7","annotationSortIndex":"00006|000100|00000"}},
+            {"key":"ANNOT001","version":1,"data":{"itemType":"annotation","parentItem":"ATTACH01","annotationType":"highlight",
+             "annotationText":"Highlighted text","annotationComment":"","annotationPageLabel":"3","annotationColor":"#ffd400",
+             "annotationSortIndex":"00002|000100|00000"}}
+        ]))
+        .unwrap();
+        ExportDocument::try_from_items(items, &ExportFormat::Biblatex).unwrap()
+    }
+
+    #[test]
+    fn collect_annotations_in_reading_order() {
+        let document = document();
+        let annotations = collect_annotations(&document, &document.entries[0]);
+        let keys: Vec<&str> = annotations.iter().map(|a| a.key).collect();
+        assert_eq!(keys, vec!["ANNOT001", "ANNOT002"]);
+        assert_eq!(annotations[0].attachment, "smith.pdf");
+        assert_eq!(annotations[0].comment, None);
+    }
+
+    #[test]
+    fn render_annotations_as_markdown() {
+        let document = document();
+        let annotations = collect_annotations(&document, &document.entries[0]);
+        assert_eq!(
+            render_markdown(&document.entries[0], &annotations),
+            "# Title (@smith_2020)\n\n## smith.pdf\n\n> Highlighted text\n> — p. 3\n\n> — p. 7\n\nLater comment\n"
+        );
+    }
+}
@@ -0,0 +1,199 @@
+use crate::attachments::is_stored;
+use crate::bibtex::ParseError;
+use crate::export::document::{ExportDocument, SortOrder};
+use crate::notes::{child_notes, render_notes};
+use crate::paths::sanitize_file_name;
+use crate::zotero_api::item::{Collection, Item};
+use crate::zotero_api::{
+    ApiError, ExportFormat, FetchDataResponse, FetchItemsParams, client::ZoteroClient,
+};
+use std::io::Write;
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+
+/// Bundles the whole library into a single zip file, as a backup that is independent of Zotero's sync.
+///
+/// The archive contains
+/// - `items.json` and `collections.json` with the full metadata as returned by the Zotero API,
+/// - `library.bib` with the export in the chosen format,
+/// - `notes/<citekey>.md` with the child notes of every item,
+/// - optionally `attachments/<attachment key>/<filename>` with the attachment files stored in Zotero.
+pub struct LibraryArchive {
+    format: ExportFormat,
+    attachments: bool,
+    reproducible: bool,
+}
+
+impl LibraryArchive {
+    pub fn new(format: ExportFormat) -> Self {
+        Self {
+            format,
+            attachments: false,
+            reproducible: false,
+        }
+    }
+
+    /// Include the attachment files stored in Zotero
+    pub fn with_attachments(mut self) -> Self {
+        self.attachments = true;
+        self
+    }
+
+    /// Sort the items and collections and don't record modification times in the zip file,
+    /// so that archives of the same library version are byte-identical
+    pub fn with_reproducible(mut self) -> Self {
+        self.reproducible = true;
+        self
+    }
+
+    pub async fn write(&self, client: &impl ZoteroClient, path: &Path) -> Result<(), ArchiveError> {
+        let params = FetchItemsParams {
+            last_modified_version: None,
+            since: None,
+            format: self.format.clone(),
+            collection: None,
+            tags: Vec::new(),
+            raw_format: None,
+        };
+        let mut items = match client.fetch_items_with_data(&params).await? {
+            FetchDataResponse::Updated { data, .. } => data,
+            FetchDataResponse::UpToDate => Vec::new(),
+        };
+        let mut collections = match client.fetch_collections(None).await? {
+            FetchDataResponse::Updated { data, .. } => data,
+            FetchDataResponse::UpToDate => Vec::new(),
+        };
+        if self.reproducible {
+            items.sort_by(|a, b| a.key.cmp(&b.key));
+            collections.sort_by(|a, b| a.key.cmp(&b.key));
+        }
+        let attachments: Vec<(String, String)> = items
+            .iter()
+            .filter(|item| self.attachments && is_stored(item))
+            .filter_map(|item| {
+                item.data
+                    .field("filename")
+                    .map(|filename| (item.key.clone(), sanitize_file_name(filename)))
+            })
+            .collect();
+
+        let file = std::fs::File::create(path).map_err(|e| file_error(path, e))?;
+        let mut zip = zip::ZipWriter::new(file);
+        let mut options = SimpleFileOptions::default();
+        if self.reproducible {
+            // the earliest time of the zip format instead of the current time
+            options = options.last_modified_time(zip::DateTime::default());
+        }
+        for (name, content) in metadata_files(items, &collections, &self.format, self.reproducible)?
+        {
+            zip.start_file(name, options)?;
+            zip.write_all(content.as_bytes())
+                .map_err(|e| file_error(path, e))?;
+        }
+        for (key, filename) in &attachments {
+            let bytes = client.fetch_attachment_file(key).await?;
+            zip.start_file(format!("attachments/{}/{}", key, filename), options)?;
+            zip.write_all(&bytes).map_err(|e| file_error(path, e))?;
+        }
+        zip.finish()?;
+        log::info!(
+            "Archived {} attachment file(s) to '{}'",
+            attachments.len(),
+            path.display()
+        );
+        Ok(())
+    }
+}
+
+/// Names and contents of all files in the archive except for the attachments
+fn metadata_files(
+    items: Vec<Item>,
+    collections: &[Collection],
+    format: &ExportFormat,
+    reproducible: bool,
+) -> Result<Vec<(String, String)>, ArchiveError> {
+    let mut files = vec![
+        (
+            "items.json".to_owned(),
+            serde_json::to_string_pretty(&items)?,
+        ),
+        (
+            "collections.json".to_owned(),
+            serde_json::to_string_pretty(collections)?,
+        ),
+    ];
+    let mut document = ExportDocument::try_from_items(items, format)?;
+    if reproducible {
+        document.sort(&SortOrder::Key);
+    }
+    files.push(("library.bib".to_owned(), document.render()));
+    for entry in &document.entries {
+        let notes = child_notes(&document, entry);
+        if !notes.is_empty() {
+            files.push((
+                format!("notes/{}.md", sanitize_file_name(&entry.entry.key)),
+                render_notes(entry, &notes),
+            ));
+        }
+    }
+    Ok(files)
+}
+
+fn file_error(path: &Path, io_error: std::io::Error) -> ArchiveError {
+    ArchiveError::FileError {
+        path: path.display().to_string(),
+        io_error,
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ArchiveError {
+    #[error("Error with archive file '{path}'")]
+    FileError {
+        path: String,
+        #[source]
+        io_error: std::io::Error,
+    },
+    #[error("Error while fetching the library")]
+    Client(#[from] ApiError),
+    #[error("Error while parsing the export")]
+    Parse(#[from] ParseError),
+    #[error("Error while serializing the metadata")]
+    Json(#[from] serde_json::Error),
+    #[error("Error while writing the zip archive")]
+    Zip(#[from] zip::result::ZipError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::document::tests::ITEMS_JSON;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn archive_metadata_files() {
+        let mut items: Vec<Item> = serde_json::from_str(ITEMS_JSON).unwrap();
+        items.push(
+            serde_json::from_value(serde_json::json!({
+                "key": "NOTE0002",
+                "version": 6,
+                "data": { "itemType": "note", "parentItem": "PARENT01", "note": "<p>Child</p>" }
+            }))
+            .unwrap(),
+        );
+        let files = metadata_files(items, &[], &ExportFormat::Biblatex, false).unwrap();
+        let names: Vec<&str> = files.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "items.json",
+                "collections.json",
+                "library.bib",
+                "notes/smith_title_2020.md"
+            ]
+        );
+        assert!(files[0].1.contains("\"NOTE0001\""));
+        assert_eq!(files[1].1, "[]");
+        assert_eq!(files[3].1, "# Title (@smith_title_2020)\n\nChild\n");
+    }
+}
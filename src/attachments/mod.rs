@@ -0,0 +1,555 @@
+use crate::bibtex::Entry;
+use crate::export::document::{ExportDocument, ExportEntry};
+use crate::paths::sanitize_file_name;
+use crate::zotero_api::throttle::{Throttle, read_body};
+use crate::zotero_api::{ApiError, client::ZoteroClient, item::Item};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Local mirror of the attachment files stored in Zotero.
+///
+/// Files are only downloaded when they are missing locally or when the attachment item changed since the last download.
+/// Every entry with mirrored attachments gets a `file` field listing the local paths, separated by `;`.
+pub struct AttachmentMirror {
+    dir: PathBuf,
+    layout: AttachmentLayout,
+    webdav: Option<WebDavSource>,
+    prune: Option<PruneMode>,
+}
+
+/// What to do with mirrored files of attachments that were deleted in Zotero
+#[derive(clap::ValueEnum, Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum PruneMode {
+    /// Delete the local files
+    Delete,
+    /// Move the local files into the `.quarantine` directory of the mirror
+    Quarantine,
+}
+
+/// Directory layout of the mirrored attachments
+#[derive(clap::ValueEnum, Clone, Default, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum AttachmentLayout {
+    /// `<DIR>/<attachment key>/<filename>`, like Zotero's own storage directory
+    #[default]
+    Key,
+    /// `<DIR>/<citation key>/<filename>`
+    Citekey,
+}
+
+impl Display for AttachmentLayout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            serde_variant::to_variant_name(self).unwrap_or_default()
+        )
+    }
+}
+
+/// Which attachment versions have been downloaded to which path, persisted in the mirror directory
+#[derive(Serialize, Deserialize, Default)]
+struct MirrorState {
+    /// Library version of the last mirroring, used to query deletions since then
+    #[serde(default)]
+    library_version: u64,
+    files: BTreeMap<String, MirroredFile>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct MirroredFile {
+    version: u64,
+    path: PathBuf,
+}
+
+impl AttachmentMirror {
+    const STATE_FILE: &'static str = ".zotexon-attachments.json";
+    const QUARANTINE_DIR: &'static str = ".quarantine";
+
+    pub fn new(dir: PathBuf, layout: AttachmentLayout) -> Self {
+        Self {
+            dir,
+            layout,
+            webdav: None,
+            prune: None,
+        }
+    }
+
+    /// Remove the mirrored files of attachments once they are deleted in Zotero
+    pub fn with_pruning(mut self, mode: PruneMode) -> Self {
+        self.prune = Some(mode);
+        self
+    }
+
+    /// Download the attachment files from a WebDAV server instead of the Zotero storage
+    pub fn with_webdav(mut self, webdav: WebDavSource) -> Self {
+        self.webdav = Some(webdav);
+        self
+    }
+
+    /// Download missing or outdated attachment files and add `file` fields to the entries of the document.
+    ///
+    /// Failing downloads are logged and skipped, so that a single broken attachment doesn't block the export.
+    pub async fn mirror(
+        &self,
+        client: &impl ZoteroClient,
+        document: &mut ExportDocument,
+        library_version: u64,
+    ) -> Result<(), AttachmentError> {
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(|e| file_error(&self.dir, e))?;
+        let dir = std::path::absolute(&self.dir).map_err(|e| file_error(&self.dir, e))?;
+        let mut state = self.read_state().await;
+        let mut files_by_entry = Vec::with_capacity(document.entries.len());
+        for entry in &document.entries {
+            let mut files = Vec::new();
+            for attachment in document.children_of(entry).iter().filter(|a| is_stored(a)) {
+                let path = dir.join(self.relative_path(entry, attachment));
+                match self.download(client, &mut state, attachment, &path).await {
+                    Ok(()) => files.push(path),
+                    Err(e) => log::warn!(
+                        "Skipping attachment {} of entry '{}': {}",
+                        attachment.key,
+                        entry.entry.key,
+                        e
+                    ),
+                }
+            }
+            files_by_entry.push(files);
+        }
+        if let Some(mode) = &self.prune {
+            self.prune(client, &mut state, mode).await?;
+        }
+        state.library_version = library_version;
+        self.write_state(&state).await?;
+        for (entry, files) in document.entries.iter_mut().zip(files_by_entry) {
+            add_files(&mut entry.entry, &files);
+        }
+        Ok(())
+    }
+
+    async fn download(
+        &self,
+        client: &impl ZoteroClient,
+        state: &mut MirrorState,
+        attachment: &Item,
+        path: &Path,
+    ) -> Result<(), AttachmentError> {
+        let up_to_date = state.files.get(&attachment.key).is_some_and(|mirrored| {
+            mirrored.version == attachment.version && mirrored.path == path
+        });
+        if up_to_date && tokio::fs::try_exists(path).await.unwrap_or(false) {
+            return Ok(());
+        }
+        log::info!("Downloading attachment to '{}'", path.display());
+        let content = match &self.webdav {
+            Some(webdav) => webdav.fetch_attachment_file(attachment).await?,
+            None => client.fetch_attachment_file(&attachment.key).await?,
+        };
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|e| file_error(parent, e))?;
+        }
+        tokio::fs::write(path, content)
+            .await
+            .map_err(|e| file_error(path, e))?;
+        state.files.insert(
+            attachment.key.clone(),
+            MirroredFile {
+                version: attachment.version,
+                path: path.to_owned(),
+            },
+        );
+        Ok(())
+    }
+
+    /// Delete or quarantine the files of attachments that were deleted since the last mirroring
+    async fn prune(
+        &self,
+        client: &impl ZoteroClient,
+        state: &mut MirrorState,
+        mode: &PruneMode,
+    ) -> Result<(), AttachmentError> {
+        let deleted = client.fetch_deleted(state.library_version).await?;
+        for key in &deleted.items {
+            let Some(mirrored) = state.files.remove(key) else {
+                continue;
+            };
+            let result = match mode {
+                PruneMode::Delete => {
+                    log::info!("Deleting attachment '{}'", mirrored.path.display());
+                    tokio::fs::remove_file(&mirrored.path).await
+                }
+                PruneMode::Quarantine => {
+                    let target = self.quarantine_path(key, &mirrored.path);
+                    log::info!(
+                        "Moving attachment '{}' to '{}'",
+                        mirrored.path.display(),
+                        target.display()
+                    );
+                    move_file(&mirrored.path, &target).await
+                }
+            };
+            match result {
+                Ok(()) => {
+                    // the key layout creates one directory per attachment, which is empty now
+                    if let Some(parent) = mirrored.path.parent() {
+                        let _ = tokio::fs::remove_dir(parent).await;
+                    }
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(file_error(&mirrored.path, e)),
+            }
+        }
+        Ok(())
+    }
+
+    fn quarantine_path(&self, key: &str, path: &Path) -> PathBuf {
+        let mut quarantine = self.dir.join(Self::QUARANTINE_DIR).join(key);
+        if let Some(filename) = path.file_name() {
+            quarantine.push(filename);
+        }
+        quarantine
+    }
+
+    fn relative_path(&self, entry: &ExportEntry, attachment: &Item) -> PathBuf {
+        let folder = match self.layout {
+            AttachmentLayout::Key => &attachment.key,
+            AttachmentLayout::Citekey => &entry.entry.key,
+        };
+        let filename = attachment
+            .data
+            .field("filename")
+            .unwrap_or(attachment.key.as_str());
+        PathBuf::from(sanitize_file_name(folder)).join(sanitize_file_name(filename))
+    }
+
+    async fn read_state(&self) -> MirrorState {
+        tokio::fs::read_to_string(self.dir.join(Self::STATE_FILE))
+            .await
+            .ok()
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default()
+    }
+
+    async fn write_state(&self, state: &MirrorState) -> Result<(), AttachmentError> {
+        let path = self.dir.join(Self::STATE_FILE);
+        let json = serde_json::to_string_pretty(state).unwrap_or_default();
+        tokio::fs::write(&path, json)
+            .await
+            .map_err(|e| file_error(&path, e))
+    }
+}
+
+/// Attachment files synced to a WebDAV server by Zotero instead of the Zotero storage.
+///
+/// Zotero keeps every attachment as `<URL>/zotero/<KEY>.zip`, containing the single attachment file,
+/// next to a `<KEY>.prop` file with its modification time and hash.
+pub struct WebDavSource {
+    http_client: reqwest::Client,
+    url: String,
+    username: Option<String>,
+    password: Option<String>,
+    throttle: Option<Arc<Throttle>>,
+}
+
+impl WebDavSource {
+    /// `url` is the server URL as configured in Zotero, the `zotero/` directory is appended automatically
+    pub fn new(url: &str, username: Option<String>, password: Option<String>) -> Self {
+        let url = url.trim_end_matches('/');
+        let url = if url.ends_with("/zotero") {
+            url.to_owned()
+        } else {
+            format!("{}/zotero", url)
+        };
+        Self {
+            http_client: reqwest::Client::new(),
+            url,
+            username,
+            password,
+            throttle: None,
+        }
+    }
+
+    /// Limit the rate of the downloads, sharing the bandwidth with the other downloads of the throttle
+    pub fn with_throttle(mut self, throttle: Arc<Throttle>) -> Self {
+        self.throttle = Some(throttle);
+        self
+    }
+
+    async fn fetch_attachment_file(&self, attachment: &Item) -> Result<Vec<u8>, AttachmentError> {
+        let url = format!("{}/{}.zip", self.url, attachment.key);
+        log::debug!("Fetching attachment from WebDAV: {}", url);
+        let mut request = self.http_client.get(&url);
+        if let Some(username) = &self.username {
+            request = request.basic_auth(username, self.password.as_ref());
+        }
+        let response = request.send().await.map_err(ApiError::from)?;
+        if response.status() != reqwest::StatusCode::OK {
+            return Err(AttachmentError::ClientError(ApiError::UnexpectedStatus {
+                status: response.status(),
+                body: response.text().await.unwrap_or_default(),
+            }));
+        }
+        let zip = read_body(response, self.throttle.as_deref())
+            .await
+            .map_err(ApiError::from)?;
+        extract_attachment(&zip, attachment.data.field("filename"))
+    }
+}
+
+/// Extract the attachment file from a Zotero WebDAV zip, preferring the entry with the expected file name
+fn extract_attachment(zip: &[u8], filename: Option<&str>) -> Result<Vec<u8>, AttachmentError> {
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(zip))?;
+    let index = filename
+        .and_then(|name| archive.index_for_name(name))
+        .or_else(|| {
+            (0..archive.len())
+                .find(|&i| archive.name_for_index(i).is_some_and(|n| !n.ends_with('/')))
+        })
+        .ok_or(AttachmentError::EmptyArchive)?;
+    let mut file = archive.by_index(index)?;
+    let mut content = Vec::new();
+    std::io::Read::read_to_end(&mut file, &mut content)
+        .map_err(|e| file_error(Path::new(file.name()), e))?;
+    Ok(content)
+}
+
+/// Adds linked attachment files to the `file` fields.
+///
+/// Zotero stores linked files either with an absolute path, or relative to the "Linked Attachment Base Directory"
+/// with an `attachments:` prefix. The relative paths are resolved against a base directory on this machine,
+/// so that the same library can be exported on machines that keep the linked files in different locations.
+pub struct LinkedAttachments {
+    base_dir: PathBuf,
+}
+
+impl LinkedAttachments {
+    const RELATIVE_PREFIX: &'static str = "attachments:";
+
+    pub fn new(base_dir: PathBuf) -> Self {
+        Self { base_dir }
+    }
+
+    pub fn apply(&self, document: &mut ExportDocument) {
+        let files_by_entry: Vec<Vec<PathBuf>> = document
+            .entries
+            .iter()
+            .map(|entry| {
+                document
+                    .children_of(entry)
+                    .iter()
+                    .filter(|a| a.data.field("linkMode") == Some("linked_file"))
+                    .filter_map(|a| a.data.field("path"))
+                    .map(|path| self.resolve(path))
+                    .collect()
+            })
+            .collect();
+        for (entry, files) in document.entries.iter_mut().zip(files_by_entry) {
+            add_files(&mut entry.entry, &files);
+        }
+    }
+
+    fn resolve(&self, path: &str) -> PathBuf {
+        match path.strip_prefix(Self::RELATIVE_PREFIX) {
+            // Zotero always uses `/` as separator in relative paths
+            Some(relative) => relative
+                .split(['/', '\\'])
+                .filter(|part| !part.is_empty())
+                .fold(self.base_dir.clone(), |dir, part| dir.join(part)),
+            None => PathBuf::from(path),
+        }
+    }
+}
+
+/// Adds the files of stored attachments in the storage directory of a local Zotero installation to the `file` fields,
+/// without downloading them.
+///
+/// Zotero keeps the file of every stored attachment in `<storage>/<attachment key>/<filename>`,
+/// the storage directory is `storage` in the Zotero data directory, e.g. `~/Zotero/storage`.
+pub struct StorageAttachments {
+    storage_dir: PathBuf,
+}
+
+impl StorageAttachments {
+    pub fn new(storage_dir: PathBuf) -> Self {
+        Self { storage_dir }
+    }
+
+    pub fn apply(&self, document: &mut ExportDocument) {
+        let files_by_entry: Vec<Vec<PathBuf>> = document
+            .entries
+            .iter()
+            .map(|entry| {
+                document
+                    .children_of(entry)
+                    .iter()
+                    .filter(|a| is_stored(a))
+                    .filter_map(|a| {
+                        Some(
+                            self.storage_dir
+                                .join(&a.key)
+                                .join(a.data.field("filename")?),
+                        )
+                    })
+                    .collect()
+            })
+            .collect();
+        for (entry, files) in document.entries.iter_mut().zip(files_by_entry) {
+            add_files(&mut entry.entry, &files);
+        }
+    }
+}
+
+/// Append files to the `file` field of an entry, separated by `;`
+fn add_files(entry: &mut Entry, files: &[PathBuf]) {
+    if files.is_empty() {
+        return;
+    }
+    let mut paths: Vec<String> = entry
+        .get("file")
+        .map(|existing| vec![existing.to_owned()])
+        .unwrap_or_default();
+    paths.extend(files.iter().map(|p| p.display().to_string()));
+    entry.set("file", paths.join(";"));
+}
+
+async fn move_file(from: &Path, to: &Path) -> std::io::Result<()> {
+    if let Some(parent) = to.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    tokio::fs::rename(from, to).await
+}
+
+/// Only imported attachments have a file stored in Zotero, linked ones live on the user's disk
+pub(crate) fn is_stored(item: &Item) -> bool {
+    item.data.item_type == "attachment"
+        && matches!(
+            item.data.field("linkMode"),
+            Some("imported_file" | "imported_url")
+        )
+}
+
+fn file_error(path: &Path, io_error: std::io::Error) -> AttachmentError {
+    AttachmentError::FileError {
+        path: path.display().to_string(),
+        io_error,
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum AttachmentError {
+    #[error("Error with attachment file '{path}'")]
+    FileError {
+        path: String,
+        #[source]
+        io_error: std::io::Error,
+    },
+    #[error("Error while downloading attachment")]
+    ClientError(#[from] ApiError),
+    #[error("Invalid attachment archive from WebDAV")]
+    ArchiveError(#[from] zip::result::ZipError),
+    #[error("Attachment archive from WebDAV is empty")]
+    EmptyArchive,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::document::tests::document;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(AttachmentLayout::Key, "ATTACH01/smith.pdf")]
+    #[case(AttachmentLayout::Citekey, "smith_title_2020/smith.pdf")]
+    fn relative_path_by_layout(#[case] layout: AttachmentLayout, #[case] expected: &str) {
+        let document = document();
+        let entry = &document.entries[0];
+        let attachment = &document.children_of(entry)[0];
+        let mirror = AttachmentMirror::new(PathBuf::from("pdfs"), layout);
+        assert_eq!(
+            mirror.relative_path(entry, attachment),
+            PathBuf::from(expected)
+        );
+    }
+
+    #[rstest]
+    #[case("attachments:papers/smith.pdf", "/home/me/zotero/papers/smith.pdf")]
+    #[case("/other/place/smith.pdf", "/other/place/smith.pdf")]
+    fn resolve_linked_paths(#[case] path: &str, #[case] expected: &str) {
+        let linked = LinkedAttachments::new(PathBuf::from("/home/me/zotero"));
+        assert_eq!(linked.resolve(path), PathBuf::from(expected));
+    }
+
+    #[test]
+    fn add_storage_files() {
+        let mut document = document();
+        StorageAttachments::new(PathBuf::from("/home/me/Zotero/storage")).apply(&mut document);
+        assert_eq!(
+            document.entries[0].entry.get("file").map(PathBuf::from),
+            Some(PathBuf::from("/home/me/Zotero/storage/ATTACH01/smith.pdf"))
+        );
+    }
+
+    #[test]
+    fn add_files_appends_to_file_field() {
+        let mut document = document();
+        let entry = &mut document.entries[0].entry;
+        add_files(entry, &[PathBuf::from("/a.pdf")]);
+        add_files(entry, &[PathBuf::from("/b.pdf"), PathBuf::from("/c.pdf")]);
+        add_files(entry, &[]);
+        assert_eq!(entry.get("file"), Some("/a.pdf;/b.pdf;/c.pdf"));
+    }
+
+    #[rstest]
+    #[case("https://dav.example.org/", "https://dav.example.org/zotero")]
+    #[case("https://dav.example.org/zotero", "https://dav.example.org/zotero")]
+    fn webdav_url_points_to_zotero_directory(#[case] url: &str, #[case] expected: &str) {
+        assert_eq!(WebDavSource::new(url, None, None).url, expected);
+    }
+
+    #[test]
+    fn extract_attachment_from_zip() {
+        use std::io::Write;
+        let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        let options = zip::write::SimpleFileOptions::default();
+        zip.start_file("other.txt", options).unwrap();
+        zip.write_all(b"other").unwrap();
+        zip.start_file("smith.pdf", options).unwrap();
+        zip.write_all(b"%PDF").unwrap();
+        let zip = zip.finish().unwrap().into_inner();
+
+        assert_eq!(
+            extract_attachment(&zip, Some("smith.pdf")).unwrap(),
+            b"%PDF"
+        );
+        assert_eq!(
+            extract_attachment(&zip, Some("missing.pdf")).unwrap(),
+            b"other"
+        );
+    }
+
+    #[test]
+    fn quarantine_path_keeps_file_name() {
+        let mirror = AttachmentMirror::new(PathBuf::from("pdfs"), AttachmentLayout::Citekey);
+        assert_eq!(
+            mirror.quarantine_path("ATTACH01", Path::new("/x/pdfs/smith_2020/smith.pdf")),
+            PathBuf::from("pdfs/.quarantine/ATTACH01/smith.pdf")
+        );
+    }
+
+    #[test]
+    fn only_imported_attachments_are_stored() {
+        let document = document();
+        let attachment = &document.children_of(&document.entries[0])[0];
+        assert!(is_stored(attachment));
+        assert!(!is_stored(&document.entries[0].item));
+    }
+}
@@ -0,0 +1,289 @@
+use crate::bibtex::{self, Block, ParseError};
+use crate::zotero_api::client::ZoteroClient;
+use crate::zotero_api::{ApiError, ExportFormat};
+use futures::{StreamExt, TryStreamExt};
+use std::fmt::Display;
+use std::time::{Duration, Instant};
+
+/// Page sizes that are measured, the API returns at most 100 items per page
+pub const PAGE_SIZES: [u32; 3] = [25, 50, 100];
+
+/// Numbers of parallel requests that are measured
+pub const CONCURRENCIES: [usize; 3] = [1, 2, 4];
+
+/// Slower settings are still recommended if they use fewer parallel requests and are within this share of the fastest
+const TOLERANCE: f64 = 0.1;
+
+/// Fetching the sample with one page size and number of parallel requests
+#[derive(Debug, Clone, PartialEq)]
+pub struct FetchMeasurement {
+    pub page_size: u32,
+    pub concurrency: usize,
+    pub items: usize,
+    pub elapsed: Duration,
+    pub page_latencies: Vec<Duration>,
+}
+
+impl FetchMeasurement {
+    fn items_per_second(&self) -> f64 {
+        self.items as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+
+    fn mean_latency(&self) -> Duration {
+        match self.page_latencies.len() {
+            0 => Duration::ZERO,
+            pages => self.page_latencies.iter().sum::<Duration>() / pages as u32,
+        }
+    }
+
+    fn max_latency(&self) -> Duration {
+        self.page_latencies
+            .iter()
+            .max()
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+/// Measures how fast the library can be fetched with different page sizes and numbers of parallel requests,
+/// and how long parsing, rendering and writing the export take, to tune the settings for a library and connection.
+pub struct Bench<'a, C: ZoteroClient> {
+    client: &'a C,
+    format: ExportFormat,
+    sample: u32,
+}
+
+impl<'a, C: ZoteroClient> Bench<'a, C> {
+    /// Bench that fetches the first `sample` items of the library with every setting
+    pub fn new(client: &'a C, format: ExportFormat, sample: u32) -> Self {
+        Self {
+            client,
+            format,
+            sample,
+        }
+    }
+
+    pub async fn run(&self) -> Result<BenchReport, BenchError> {
+        let mut fetches = Vec::new();
+        let mut pages = Vec::new();
+        for page_size in PAGE_SIZES {
+            for concurrency in CONCURRENCIES {
+                log::info!(
+                    "Fetching {} items in pages of {} with {} parallel requests",
+                    self.sample,
+                    page_size,
+                    concurrency
+                );
+                let (measurement, texts) = self.measure_fetch(page_size, concurrency).await?;
+                fetches.push(measurement);
+                pages = texts;
+            }
+        }
+        let text = pages.concat();
+
+        let started = Instant::now();
+        let blocks = bibtex::parse(&text)?;
+        let rendered = bibtex::write(&blocks);
+        let render = started.elapsed();
+        let entries = blocks
+            .iter()
+            .filter(|block| matches!(block, Block::Entry(_)))
+            .count();
+
+        let path = std::env::temp_dir().join(format!("zotexon-bench-{}.bib", std::process::id()));
+        let started = Instant::now();
+        let written = tokio::fs::write(&path, rendered).await;
+        let write = started.elapsed();
+        let _ = tokio::fs::remove_file(&path).await;
+        written.map_err(BenchError::Write)?;
+
+        Ok(BenchReport {
+            fetches,
+            entries,
+            render,
+            write,
+        })
+    }
+
+    /// Fetch the sample, returning the measurement and the texts of the pages in order
+    async fn measure_fetch(
+        &self,
+        page_size: u32,
+        concurrency: usize,
+    ) -> Result<(FetchMeasurement, Vec<String>), ApiError> {
+        let started = Instant::now();
+        let mut pages: Vec<(u32, Duration, String)> =
+            futures::stream::iter((0..self.sample).step_by(page_size as usize))
+                .map(|start| async move {
+                    let limit = page_size.min(self.sample - start);
+                    let page_started = Instant::now();
+                    let text = self
+                        .client
+                        .fetch_items_page(&self.format, start, limit)
+                        .await?;
+                    Ok::<_, ApiError>((start, page_started.elapsed(), text))
+                })
+                .buffer_unordered(concurrency)
+                .try_collect()
+                .await?;
+        let elapsed = started.elapsed();
+        pages.sort_by_key(|(start, _, _)| *start);
+        let items = pages
+            .iter()
+            .map(|(_, _, text)| {
+                bibtex::parse(text)
+                    .map(|blocks| {
+                        blocks
+                            .iter()
+                            .filter(|block| matches!(block, Block::Entry(_)))
+                            .count()
+                    })
+                    .unwrap_or_default()
+            })
+            .sum();
+        let measurement = FetchMeasurement {
+            page_size,
+            concurrency,
+            items,
+            elapsed,
+            page_latencies: pages.iter().map(|(_, latency, _)| *latency).collect(),
+        };
+        Ok((
+            measurement,
+            pages.into_iter().map(|(_, _, text)| text).collect(),
+        ))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BenchReport {
+    pub fetches: Vec<FetchMeasurement>,
+    /// Number of entries that were parsed, rendered and written
+    pub entries: usize,
+    pub render: Duration,
+    pub write: Duration,
+}
+
+impl BenchReport {
+    /// The fastest setting, or a setting with fewer parallel requests that is almost as fast
+    pub fn recommended(&self) -> Option<&FetchMeasurement> {
+        let fastest = self
+            .fetches
+            .iter()
+            .map(FetchMeasurement::items_per_second)
+            .fold(0.0, f64::max);
+        self.fetches
+            .iter()
+            .filter(|fetch| fetch.items_per_second() >= fastest * (1.0 - TOLERANCE))
+            .min_by(|a, b| {
+                a.concurrency
+                    .cmp(&b.concurrency)
+                    .then(b.items_per_second().total_cmp(&a.items_per_second()))
+            })
+    }
+}
+
+impl Display for BenchReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{:>9}  {:>8}  {:>6}  {:>8}  {:>12}  {:>12}  {:>9}",
+            "Page size", "Parallel", "Items", "Time", "Mean latency", "Max latency", "Items/s"
+        )?;
+        for fetch in &self.fetches {
+            writeln!(
+                f,
+                "{:>9}  {:>8}  {:>6}  {:>6} ms  {:>9} ms  {:>9} ms  {:>9.1}",
+                fetch.page_size,
+                fetch.concurrency,
+                fetch.items,
+                fetch.elapsed.as_millis(),
+                fetch.mean_latency().as_millis(),
+                fetch.max_latency().as_millis(),
+                fetch.items_per_second()
+            )?;
+        }
+        writeln!(
+            f,
+            "\nParsing and rendering {} entries: {} ms, writing: {} ms",
+            self.entries,
+            self.render.as_millis(),
+            self.write.as_millis()
+        )?;
+        let Some(recommended) = self.recommended() else {
+            return Ok(());
+        };
+        writeln!(
+            f,
+            "\nRecommended: pages of {} items with {} parallel requests ({:.1} items/s)",
+            recommended.page_size,
+            recommended.concurrency,
+            recommended.items_per_second()
+        )?;
+        // exports fetch their pages one after another, starting at the largest page size
+        if recommended.page_size < PAGE_SIZES[PAGE_SIZES.len() - 1] {
+            writeln!(
+                f,
+                "  Large pages are slow here, exports reduce the page size by themselves when responses are slow"
+            )?;
+        }
+        if recommended.concurrency > 1 {
+            writeln!(
+                f,
+                "  Use `daemon --max-parallel {}` to export that many libraries in parallel",
+                recommended.concurrency
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum BenchError {
+    #[error("Error fetching the items")]
+    Fetch(#[from] ApiError),
+    #[error("Error parsing the fetched items")]
+    Parse(#[from] ParseError),
+    #[error("Error writing the export")]
+    Write(#[source] std::io::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn fetch(page_size: u32, concurrency: usize, millis: u64) -> FetchMeasurement {
+        FetchMeasurement {
+            page_size,
+            concurrency,
+            items: 200,
+            elapsed: Duration::from_millis(millis),
+            page_latencies: vec![Duration::from_millis(300), Duration::from_millis(500)],
+        }
+    }
+
+    #[test]
+    fn bench_report() {
+        let report = BenchReport {
+            fetches: vec![fetch(50, 1, 4000), fetch(50, 2, 2100), fetch(50, 4, 2000)],
+            entries: 200,
+            render: Duration::from_millis(12),
+            write: Duration::from_millis(3),
+        };
+        assert_eq!(
+            report.to_string(),
+            "Page size  Parallel   Items      Time  Mean latency   Max latency    Items/s
+       50         1     200    4000 ms        400 ms        500 ms       50.0
+       50         2     200    2100 ms        400 ms        500 ms       95.2
+       50         4     200    2000 ms        400 ms        500 ms      100.0
+
+Parsing and rendering 200 entries: 12 ms, writing: 3 ms
+
+Recommended: pages of 50 items with 2 parallel requests (95.2 items/s)
+  Large pages are slow here, exports reduce the page size by themselves when responses are slow
+  Use `daemon --max-parallel 2` to export that many libraries in parallel
+"
+        );
+    }
+}
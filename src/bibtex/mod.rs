@@ -0,0 +1,183 @@
+//! Minimal BibTeX/BibLaTeX model: just enough to parse the exports produced by Zotero,
+//! modify single fields, and write the entries back in the same layout.
+
+use std::fmt::Display;
+
+mod parser;
+
+pub use parser::{ParseError, parse};
+
+/// Top-level building block of a `.bib` file
+#[derive(Debug, Clone, PartialEq)]
+pub enum Block {
+    Entry(Entry),
+    /// `@comment`, `@string`, `@preamble` or any other text, kept verbatim
+    Other(String),
+}
+
+/// A single bibliography entry like `@article{key, title = {...}}`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub entry_type: String,
+    pub key: String,
+    pub fields: Vec<Field>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub name: String,
+    pub value: Value,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    /// `{text}`
+    Braced(String),
+    /// `"text"`
+    Quoted(String),
+    /// Numbers, macros like `mar` and concatenations with `#`, kept verbatim
+    Bare(String),
+}
+
+impl Value {
+    /// Content of the value without the delimiters
+    pub fn text(&self) -> &str {
+        match self {
+            Value::Braced(text) | Value::Quoted(text) | Value::Bare(text) => text,
+        }
+    }
+}
+
+impl Entry {
+    /// Text of the field with the given name (case-insensitive)
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|field| field.name.eq_ignore_ascii_case(name))
+            .map(|field| field.value.text())
+    }
+
+    /// Set a field to a braced value, replacing an existing field with the same name
+    pub fn set(&mut self, name: &str, text: impl Into<String>) {
+        self.set_value(name, Value::Braced(text.into()));
+    }
+
+    /// Remove the field with the given name (case-insensitive)
+    pub fn remove(&mut self, name: &str) {
+        self.fields
+            .retain(|field| !field.name.eq_ignore_ascii_case(name));
+    }
+
+    /// Rename a field. If a field with the new name exists already, it is kept and the renamed field is dropped.
+    pub fn rename(&mut self, from: &str, to: &str) {
+        if self.get(to).is_some() {
+            self.remove(from);
+        } else if let Some(field) = self
+            .fields
+            .iter_mut()
+            .find(|field| field.name.eq_ignore_ascii_case(from))
+        {
+            field.name = to.to_owned();
+        }
+    }
+
+    pub fn set_value(&mut self, name: &str, value: Value) {
+        match self
+            .fields
+            .iter_mut()
+            .find(|field| field.name.eq_ignore_ascii_case(name))
+        {
+            Some(field) => field.value = value,
+            None => self.fields.push(Field {
+                name: name.to_owned(),
+                value,
+            }),
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Braced(text) => write!(f, "{{{}}}", text),
+            Value::Quoted(text) => write!(f, "\"{}\"", text),
+            Value::Bare(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+/// Writes the entry in the layout that Zotero uses for its exports
+impl Display for Entry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "@{}{{{}", self.entry_type, self.key)?;
+        for field in &self.fields {
+            write!(f, ",\n\t{} = {}", field.name, field.value)?;
+        }
+        write!(f, "\n}}")
+    }
+}
+
+impl Display for Block {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Block::Entry(entry) => entry.fmt(f),
+            Block::Other(text) => write!(f, "{}", text),
+        }
+    }
+}
+
+/// Write blocks separated by blank lines
+pub fn write(blocks: &[Block]) -> String {
+    let mut text = String::new();
+    for block in blocks {
+        text.push('\n');
+        text.push_str(&block.to_string());
+        text.push('\n');
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn entry() -> Entry {
+        Entry {
+            entry_type: "article".into(),
+            key: "smith_title_2020".into(),
+            fields: vec![
+                Field {
+                    name: "title".into(),
+                    value: Value::Braced("A {Title}".into()),
+                },
+                Field {
+                    name: "month".into(),
+                    value: Value::Bare("mar".into()),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn write_entry() {
+        assert_eq!(
+            entry().to_string(),
+            "@article{smith_title_2020,\n\ttitle = {A {Title}},\n\tmonth = mar\n}"
+        );
+    }
+
+    #[test]
+    fn set_and_get_fields() {
+        let mut entry = entry();
+        assert_eq!(entry.get("TITLE"), Some("A {Title}"));
+        assert_eq!(entry.get("month"), Some("mar"));
+        entry.set("title", "Other");
+        entry.set("doi", "10.1000/xyz");
+        assert_eq!(entry.get("title"), Some("Other"));
+        assert_eq!(entry.get("doi"), Some("10.1000/xyz"));
+        assert_eq!(entry.fields.len(), 3);
+        entry.remove("DOI");
+        assert_eq!(entry.get("doi"), None);
+    }
+}
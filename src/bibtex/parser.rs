@@ -0,0 +1,294 @@
+use crate::bibtex::{Block, Entry, Field, Value};
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+#[error("Invalid BibTeX at byte {position}: {message}")]
+pub struct ParseError {
+    pub position: usize,
+    pub message: &'static str,
+}
+
+/// Parse the content of a `.bib` file into blocks
+pub fn parse(text: &str) -> Result<Vec<Block>, ParseError> {
+    Parser { text, pos: 0 }.blocks()
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn blocks(mut self) -> Result<Vec<Block>, ParseError> {
+        let mut blocks = Vec::new();
+        loop {
+            let start = self.pos;
+            let Some(at) = self.rest().find('@') else {
+                push_other(&mut blocks, &self.text[start..]);
+                return Ok(blocks);
+            };
+            push_other(&mut blocks, &self.text[start..start + at]);
+            self.pos = start + at + 1;
+            let entry_type = self.identifier();
+            self.skip_whitespace();
+            let close = match self.peek() {
+                Some('{') => '}',
+                Some('(') => ')',
+                _ => return Err(self.error("expected '{' or '(' after entry type")),
+            };
+            if ["comment", "string", "preamble"]
+                .iter()
+                .any(|special| entry_type.eq_ignore_ascii_case(special))
+            {
+                self.balanced()?;
+                blocks.push(Block::Other(self.text[start + at..self.pos].to_owned()));
+            } else {
+                self.pos += 1;
+                blocks.push(Block::Entry(self.entry(entry_type, close)?));
+            }
+        }
+    }
+
+    fn entry(&mut self, entry_type: String, close: char) -> Result<Entry, ParseError> {
+        let key_len = self
+            .rest()
+            .find([',', close])
+            .ok_or_else(|| self.error("unterminated entry"))?;
+        let key = self.rest()[..key_len].trim().to_owned();
+        self.pos += key_len;
+        let mut fields = Vec::new();
+        loop {
+            match self.next() {
+                Some(c) if c == close => break,
+                Some(',') => {}
+                _ => return Err(self.error("expected ',' or end of entry")),
+            }
+            self.skip_whitespace();
+            if self.peek() == Some(close) {
+                self.pos += 1;
+                break;
+            }
+            let name = self.identifier();
+            if name.is_empty() {
+                return Err(self.error("expected field name"));
+            }
+            self.skip_whitespace();
+            if self.next() != Some('=') {
+                return Err(self.error("expected '=' after field name"));
+            }
+            let value = self.value(close)?;
+            fields.push(Field { name, value });
+            self.skip_whitespace();
+        }
+        Ok(Entry {
+            entry_type,
+            key,
+            fields,
+        })
+    }
+
+    /// A field value, consisting of one or more parts joined by `#`
+    fn value(&mut self, close: char) -> Result<Value, ParseError> {
+        self.skip_whitespace();
+        let start = self.pos;
+        let mut parts = 0;
+        let mut single;
+        loop {
+            self.skip_whitespace();
+            parts += 1;
+            single = match self.peek() {
+                Some('{') => {
+                    let content_start = self.pos + 1;
+                    self.balanced()?;
+                    Some(Value::Braced(
+                        self.text[content_start..self.pos - 1].to_owned(),
+                    ))
+                }
+                Some('"') => {
+                    let content_start = self.pos + 1;
+                    self.quoted()?;
+                    Some(Value::Quoted(
+                        self.text[content_start..self.pos - 1].to_owned(),
+                    ))
+                }
+                Some(_) => {
+                    let len = self
+                        .rest()
+                        .find(|c: char| c == ',' || c == close || c == '#' || c.is_whitespace())
+                        .unwrap_or(self.rest().len());
+                    if len == 0 {
+                        return Err(self.error("expected field value"));
+                    }
+                    self.pos += len;
+                    None
+                }
+                None => return Err(self.error("unterminated field value")),
+            };
+            self.skip_whitespace();
+            if self.peek() == Some('#') {
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        Ok(match single {
+            Some(value) if parts == 1 => value,
+            _ => Value::Bare(self.text[start..self.pos].trim_end().to_owned()),
+        })
+    }
+
+    /// Skip a group delimited by `{}` or `()`, including nested braces
+    fn balanced(&mut self) -> Result<(), ParseError> {
+        let open = self.next();
+        let close = if open == Some('(') { ')' } else { '}' };
+        let mut depth = 0usize;
+        while let Some(c) = self.next() {
+            match c {
+                '{' => depth += 1,
+                '}' if depth > 0 => depth -= 1,
+                c if c == close && depth == 0 => return Ok(()),
+                _ => {}
+            }
+        }
+        Err(self.error("unbalanced braces"))
+    }
+
+    /// Skip a `"`-delimited string, quotes inside braces do not end the string
+    fn quoted(&mut self) -> Result<(), ParseError> {
+        self.pos += 1;
+        let mut depth = 0usize;
+        while let Some(c) = self.next() {
+            match c {
+                '{' => depth += 1,
+                '}' => depth = depth.saturating_sub(1),
+                '"' if depth == 0 => return Ok(()),
+                _ => {}
+            }
+        }
+        Err(self.error("unterminated string"))
+    }
+
+    fn identifier(&mut self) -> String {
+        let len = self
+            .rest()
+            .find(|c: char| !(c.is_alphanumeric() || "_-:.+/".contains(c)))
+            .unwrap_or(self.rest().len());
+        let identifier = self.rest()[..len].to_owned();
+        self.pos += len;
+        identifier
+    }
+
+    fn skip_whitespace(&mut self) {
+        let trimmed = self.rest().trim_start();
+        self.pos = self.text.len() - trimmed.len();
+    }
+
+    fn rest(&self) -> &str {
+        &self.text[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn error(&self, message: &'static str) -> ParseError {
+        ParseError {
+            position: self.pos,
+            message,
+        }
+    }
+}
+
+fn push_other(blocks: &mut Vec<Block>, text: &str) {
+    let text = text.trim();
+    if !text.is_empty() {
+        blocks.push(Block::Other(text.to_owned()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bibtex::write;
+    use assert_matches::assert_matches;
+    use pretty_assertions::assert_eq;
+
+    const ZOTERO_EXPORT: &str = "
+@article{smith_title_2020,
+\ttitle = {A {Title} with ünïcode},
+\tvolume = {3},
+\tissn = {1234-5678},
+\tdoi = {10.1000/xyz},
+\tjournaltitle = {Journal},
+\tauthor = {Smith, Jane and Doe, John},
+\tdate = {2020-03}
+}
+
+@book{doe_book_2019,
+\ttitle = {Book},
+\tyear = 2019,
+\tmonth = mar,
+\tnote = \"quoted {\"} text\",
+\tpublisher = \"Pub\" # { and } # macro
+}
+";
+
+    #[test]
+    fn parse_zotero_export() {
+        let blocks = parse(ZOTERO_EXPORT).unwrap();
+        assert_eq!(blocks.len(), 2);
+        assert_matches!(&blocks[0], Block::Entry(entry) => {
+            assert_eq!(entry.entry_type, "article");
+            assert_eq!(entry.key, "smith_title_2020");
+            assert_eq!(entry.fields.len(), 7);
+            assert_eq!(entry.fields[0].value, Value::Braced("A {Title} with ünïcode".into()));
+        });
+        assert_matches!(&blocks[1], Block::Entry(entry) => {
+            assert_eq!(entry.fields[1].value, Value::Bare("2019".into()));
+            assert_eq!(entry.fields[2].value, Value::Bare("mar".into()));
+            assert_eq!(entry.fields[3].value, Value::Quoted("quoted {\"} text".into()));
+            assert_eq!(
+                entry.fields[4].value,
+                Value::Bare("\"Pub\" # { and } # macro".into())
+            );
+        });
+    }
+
+    #[test]
+    fn roundtrip_keeps_content() {
+        let blocks = parse(ZOTERO_EXPORT).unwrap();
+        assert_eq!(parse(&write(&blocks)).unwrap(), blocks);
+    }
+
+    #[test]
+    fn parse_special_blocks_and_comments() {
+        let text =
+            "% comment line\n@string{jan = {January}}\n@Comment{jabref-meta: x;}\n@misc{key,}\n";
+        let blocks = parse(text).unwrap();
+        assert_eq!(
+            blocks,
+            vec![
+                Block::Other("% comment line".into()),
+                Block::Other("@string{jan = {January}}".into()),
+                Block::Other("@Comment{jabref-meta: x;}".into()),
+                Block::Entry(Entry {
+                    entry_type: "misc".into(),
+                    key: "key".into(),
+                    fields: vec![],
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_matches!(parse("@article{key, title = {unbalanced}"), Err(_));
+        assert_matches!(parse("@article{key, title {x}}"), Err(_));
+        assert_matches!(parse("@article key"), Err(_));
+    }
+}
@@ -0,0 +1,180 @@
+use crate::zotero_api::{
+    ApiError, FetchDataResponse,
+    client::ZoteroClient,
+    item::{self, Collection, Item},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/// Local copy of the JSON data of a Zotero library.
+///
+/// The cache is refreshed incrementally: only objects modified or deleted since the cached library version are fetched.
+pub struct ItemCache {
+    path: PathBuf,
+    data: CacheData,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct CacheData {
+    library_version: u64,
+    items: BTreeMap<String, Item>,
+    collections: BTreeMap<String, Collection>,
+}
+
+impl ItemCache {
+    /// Default location of the cache for the library of the given client
+    pub fn default_path(client: &impl ZoteroClient) -> Result<PathBuf, CacheError> {
+        let cache_dir = dirs::cache_dir().ok_or(CacheError::NoCacheDir)?;
+        Ok(cache_dir
+            .join("zotexon")
+            .join(format!("users-{}.json", client.user_id())))
+    }
+
+    /// Open the cache at the given path. A missing or unreadable cache results in an empty cache.
+    pub async fn open(path: PathBuf) -> Self {
+        let data = match tokio::fs::read_to_string(&path).await {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                log::warn!("Ignoring unreadable cache '{}': {}", path.display(), e);
+                CacheData::default()
+            }),
+            Err(_) => CacheData::default(),
+        };
+        Self { path, data }
+    }
+
+    /// Bring the cache up to date with the library and persist it. Returns whether anything changed.
+    pub async fn refresh(&mut self, client: &impl ZoteroClient) -> Result<bool, CacheError> {
+        let since = Some(self.data.library_version).filter(|version| *version > 0);
+        let mut new_version = None;
+        if let FetchDataResponse::Updated {
+            last_modified_version,
+            data,
+        } = client.fetch_item_data(since).await?
+        {
+            log::debug!("Fetched {} new or modified items", data.len());
+            self.data
+                .items
+                .extend(data.into_iter().map(|item| (item.key.clone(), item)));
+            new_version = Some(last_modified_version);
+        }
+        if let FetchDataResponse::Updated {
+            last_modified_version,
+            data,
+        } = client.fetch_collections(since).await?
+        {
+            self.data.collections.extend(
+                data.into_iter()
+                    .map(|collection| (collection.key.clone(), collection)),
+            );
+            new_version = new_version.max(Some(last_modified_version));
+        }
+        let Some(new_version) = new_version else {
+            log::debug!(
+                "Cache is up to date with library version {}",
+                self.data.library_version
+            );
+            return Ok(false);
+        };
+        if let Some(since) = since {
+            let deleted = client.fetch_deleted(since).await?;
+            for key in &deleted.items {
+                self.data.items.remove(key);
+            }
+            for key in &deleted.collections {
+                self.data.collections.remove(key);
+            }
+        }
+        self.data.library_version = new_version;
+        self.save().await?;
+        log::info!(
+            "Updated cache '{}' to library version {}",
+            self.path.display(),
+            new_version
+        );
+        Ok(true)
+    }
+
+    pub fn library_version(&self) -> u64 {
+        self.data.library_version
+    }
+
+    pub fn items(&self) -> impl Iterator<Item = &Item> {
+        self.data.items.values()
+    }
+
+    /// Full path of a collection, e.g. `Thesis/Chapter 2`
+    pub fn collection_path(&self, key: &str) -> Option<String> {
+        item::collection_path(&self.data.collections, key)
+    }
+
+    async fn save(&self) -> Result<(), CacheError> {
+        if let Some(dir) = self.path.parent() {
+            tokio::fs::create_dir_all(dir)
+                .await
+                .map_err(|e| self.file_error(e))?;
+        }
+        let json = serde_json::to_string(&self.data).unwrap_or_default();
+        tokio::fs::write(&self.path, json)
+            .await
+            .map_err(|e| self.file_error(e))
+    }
+
+    fn file_error(&self, io_error: std::io::Error) -> CacheError {
+        CacheError::FileError {
+            path: self.path.display().to_string(),
+            io_error,
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum CacheError {
+    #[error("Error with cache file '{path}'")]
+    FileError {
+        path: String,
+        #[source]
+        io_error: std::io::Error,
+    },
+    #[error("Could not determine the cache directory of this system")]
+    NoCacheDir,
+    #[error("Error in Zotero client")]
+    ClientError(#[from] ApiError),
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    /// Build a cache from JSON fixtures, without touching the file system
+    pub(crate) fn cache_from_json(items: &str, collections: &str) -> ItemCache {
+        let items: Vec<Item> = serde_json::from_str(items).unwrap();
+        let collections: Vec<Collection> = serde_json::from_str(collections).unwrap();
+        ItemCache {
+            path: PathBuf::new(),
+            data: CacheData {
+                library_version: 1,
+                items: items.into_iter().map(|i| (i.key.clone(), i)).collect(),
+                collections: collections
+                    .into_iter()
+                    .map(|c| (c.key.clone(), c))
+                    .collect(),
+            },
+        }
+    }
+
+    #[test]
+    fn collection_path_follows_parents() {
+        let cache = cache_from_json(
+            "[]",
+            r#"[
+                {"key":"A","version":1,"data":{"name":"Thesis","parentCollection":false}},
+                {"key":"B","version":1,"data":{"name":"Chapter 2","parentCollection":"A"}}
+            ]"#,
+        );
+        assert_eq!(cache.collection_path("B"), Some("Thesis/Chapter 2".into()));
+        assert_eq!(cache.collection_path("A"), Some("Thesis".into()));
+        assert_eq!(cache.collection_path("C"), None);
+    }
+}
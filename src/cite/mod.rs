@@ -0,0 +1,102 @@
+use crate::export::document::ExportDocument;
+
+/// Key of the Zotero item that was exported with the given citation key
+pub fn item_key<'a>(document: &'a ExportDocument, citekey: &str) -> Option<&'a str> {
+    document
+        .entries
+        .iter()
+        .find(|entry| entry.entry.key == citekey)
+        .map(|entry| entry.item.key.as_str())
+}
+
+/// Plain text of a bibliography that the Zotero API formatted as HTML, for pasting into emails and documents
+pub fn html_to_text(html: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                text.push(' ');
+            }
+            c if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    decode_entities(&text)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .replace(" ,", ",")
+        .replace(" .", ".")
+}
+
+/// Replace the character references of HTML, e.g. `&amp;` or `&#8220;`, with the characters
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let character = rest.find(';').and_then(|end| {
+            let character = match &rest[1..end] {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                "nbsp" => Some(' '),
+                reference => match reference.strip_prefix('#') {
+                    Some(hex) if hex.starts_with(['x', 'X']) => u32::from_str_radix(&hex[1..], 16)
+                        .ok()
+                        .and_then(char::from_u32),
+                    Some(decimal) => decimal.parse().ok().and_then(char::from_u32),
+                    None => None,
+                },
+            };
+            character.map(|character| (character, end))
+        });
+        match character {
+            Some((character, end)) => {
+                decoded.push(character);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zotero_api::ExportFormat;
+    use crate::zotero_api::item::Item;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn find_item_by_citekey() {
+        let items: Vec<Item> = serde_json::from_value(serde_json::json!([
+            {"key":"ITEM0001","version":1,"data":{"itemType":"book"},"biblatex":"@book{smith_2020,\n}"}
+        ]))
+        .unwrap();
+        let document = ExportDocument::try_from_items(items, &ExportFormat::Biblatex).unwrap();
+        assert_eq!(item_key(&document, "smith_2020"), Some("ITEM0001"));
+        assert_eq!(item_key(&document, "doe_2020"), None);
+    }
+
+    #[test]
+    fn format_bibliography_as_text() {
+        let html = "<div class=\"csl-bib-body\" style=\"line-height: 2;\">\n  \
+            <div class=\"csl-entry\">Smith, A. &amp; Doe, B. (2020). <i>On optics</i>. Light &#x2013; Press.</div>\n</div>";
+        assert_eq!(
+            html_to_text(html),
+            "Smith, A. & Doe, B. (2020). On optics. Light \u{2013} Press."
+        );
+    }
+}
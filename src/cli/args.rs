@@ -0,0 +1,635 @@
+use crate::annotations::AnnotationsFormat;
+use crate::attachments::{AttachmentLayout, PruneMode};
+use crate::export::document::SortOrder;
+use crate::export::{CollisionMode, ExportTarget, StrictMode};
+use crate::network::IpFamily;
+use crate::transform::{DEFAULT_PATTERN, KeyPattern, PageRangeDash};
+use crate::zotero_api::client::GroupId;
+use crate::zotero_api::throttle::parse_rate;
+use crate::zotero_api::{DEFAULT_API_VERSION, ExportFormat};
+use clap::{Parser, Subcommand};
+use std::net::IpAddr;
+use std::path::PathBuf;
+
+#[derive(Parser, Debug)]
+#[clap(version, about, long_about = None)]
+pub(super) struct Args {
+    #[command(subcommand)]
+    pub(super) command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+pub(super) enum Command {
+    /// Export the library to a file. The export arguments can also be given without this subcommand
+    #[command(args_override_self = true)]
+    Export(Box<ExportArgs>),
+
+    /// Export the library and export it again on every change until interrupted, like `export --sync`
+    #[command(args_override_self = true)]
+    Watch(Box<ExportArgs>),
+
+    /// Restore a previous export from the snapshots taken with `--snapshots`
+    Rollback {
+        /// Exported file that shall be restored
+        #[arg(long)]
+        file: String,
+
+        /// Library version to restore. Defaults to the export before the current one, so that repeated rollbacks step back one export at a time.
+        #[arg(long, value_name = "VERSION")]
+        to: Option<u64>,
+    },
+
+    /// Print statistics about the library: item counts by type, year, tag and collection, plus attachment totals
+    Stats {
+        #[command(flatten)]
+        api_key: ApiKeyArgs,
+
+        /// Show the number of items over time instead, as recorded by every export and `stats` run
+        #[arg(long)]
+        history: bool,
+    },
+
+    /// List the collections of the library with their keys and numbers of items, e.g. to find the collection to export with `--collection`
+    Collections {
+        #[command(flatten)]
+        api_key: ApiKeyArgs,
+    },
+
+    /// List the groups whose libraries the API key can read with their IDs and numbers of items, e.g. to find the group to export with `--group`
+    Groups {
+        #[command(flatten)]
+        api_key: ApiKeyArgs,
+    },
+
+    /// Check the API key and print what it can access: the user, and read, notes and write access to the library and groups. Fails if the key can't read the library, e.g. to check the secret of a CI pipeline
+    CheckKey {
+        #[command(flatten)]
+        api_key: ApiKeyArgs,
+
+        /// Require read access to the library of this group instead of the user's library
+        #[arg(long, value_name = "ID")]
+        group: Option<GroupId>,
+    },
+
+    /// Measure fetch throughput, page latency and render and write times for the library with different page sizes and numbers of parallel requests, and recommend settings
+    Bench {
+        #[command(flatten)]
+        api_key: ApiKeyArgs,
+
+        /// Format of the fetched export
+        #[arg(long, default_value_t, value_enum)]
+        format: ExportFormat,
+
+        /// Number of items that are fetched with every setting
+        #[arg(long, default_value_t = 200, value_name = "ITEMS")]
+        sample: u32,
+    },
+
+    /// Bundle the library metadata (JSON and bib export), notes and optionally attachments into a zip archive, as a backup independent of Zotero's sync
+    Archive {
+        #[command(flatten)]
+        api_key: ApiKeyArgs,
+
+        /// Zip file that the archive will be written to
+        output: PathBuf,
+
+        /// Format of the bib export in the archive
+        #[arg(long, default_value_t, value_enum)]
+        format: ExportFormat,
+
+        /// Include the attachment files stored in Zotero
+        #[arg(long)]
+        attachments: bool,
+
+        /// Write the same archive for the same library version, without modification times in the zip file
+        #[arg(long)]
+        reproducible: bool,
+    },
+
+    /// Keep the exports of several accounts or libraries in sync in one process, as configured in the `[[accounts]]` of the config file
+    Daemon {
+        /// TOML file with an `[[accounts]]` section per export
+        #[arg(long, value_name = "FILE")]
+        config: PathBuf,
+
+        /// Maximum number of accounts that are exported at the same time
+        #[arg(long, value_name = "N", default_value_t = 2, value_parser = clap::value_parser!(u64).range(1..))]
+        max_parallel: u64,
+    },
+
+    /// Report quality issues of the library (missing DOIs, empty authors, suspicious years, duplicate titles, non-ASCII citation keys) with links to the items in Zotero
+    Lint {
+        #[command(flatten)]
+        api_key: ApiKeyArgs,
+
+        /// Format of the export whose citation keys are checked
+        #[arg(long, default_value_t, value_enum)]
+        format: ExportFormat,
+    },
+
+    /// Compare an existing, hand-maintained bib file with the library: list its entries that are missing from the library (matched by DOI or title) and those that differ from the export
+    Reconcile {
+        #[command(flatten)]
+        api_key: ApiKeyArgs,
+
+        /// Existing bibliography file
+        bib: PathBuf,
+
+        /// Format of the export that the entries are compared with
+        #[arg(long, default_value_t, value_enum)]
+        format: ExportFormat,
+    },
+
+    /// Search the library for items matching all words of the query in any field or the full text, and list them with citation key, authors and year
+    Search {
+        #[command(flatten)]
+        api_key: ApiKeyArgs,
+
+        /// Words to search for, e.g. "transformer attention"
+        query: String,
+
+        /// Format of the export whose citation keys are listed and emitted
+        #[arg(long, default_value_t, value_enum)]
+        format: ExportFormat,
+
+        /// Only keep the results with these numbers of the list, e.g. `1,3`
+        #[arg(long, value_name = "NUMBERS", value_delimiter = ',')]
+        select: Vec<usize>,
+
+        /// Print the export of the (selected) results instead of the list, e.g. to append them to a bib file
+        #[arg(long)]
+        emit: bool,
+
+        /// Copy the export of the (selected) results to the clipboard, e.g. to paste them into a shared document
+        #[arg(long)]
+        clipboard: bool,
+
+        /// Also list the attachments and notes of every result
+        #[arg(long)]
+        children: bool,
+    },
+
+    /// Pick items of the library in an interactive list that is filtered while typing, and export just those to a file, e.g. for a reading list or the bibliography of a talk
+    Pick {
+        #[command(flatten)]
+        api_key: ApiKeyArgs,
+
+        /// File that the picked items will be exported to
+        #[arg(long, required_unless_present = "clipboard")]
+        file: Option<PathBuf>,
+
+        /// Format in which the picked items will be exported
+        #[arg(long, default_value_t, value_enum)]
+        format: ExportFormat,
+
+        /// Copy the export of the picked items to the clipboard
+        #[arg(long)]
+        clipboard: bool,
+    },
+
+    /// Check the citations of a LaTeX, Typst or Markdown project against an exported bibliography, and fail if cited keys are missing, e.g. in CI
+    Verify {
+        /// Exported bibliography file
+        #[arg(long, value_name = "FILE")]
+        bib: PathBuf,
+
+        /// Files and directories of the project. Defaults to the working directory
+        paths: Vec<PathBuf>,
+
+        /// Also fail if entries of the bibliography are never cited
+        #[arg(long)]
+        deny_unused: bool,
+    },
+
+    /// Print the formatted citation of an item, for pasting into emails and documents
+    Cite {
+        #[command(flatten)]
+        api_key: ApiKeyArgs,
+
+        /// Citation key of the item, as in the export
+        citekey: String,
+
+        /// CSL style of the citation, e.g. `apa`, `ieee` or `chicago-author-date`. See https://www.zotero.org/styles for all styles
+        #[arg(long, default_value = "apa")]
+        style: String,
+
+        /// Format of the export that the citation key is from
+        #[arg(long, default_value_t, value_enum)]
+        format: ExportFormat,
+
+        /// Also copy the citation to the clipboard
+        #[arg(long)]
+        clipboard: bool,
+    },
+
+    /// Open the item with a citation key in the Zotero web library, to get from a citation back to its source
+    Open {
+        #[command(flatten)]
+        api_key: ApiKeyArgs,
+
+        /// Citation key of the item, as in the export
+        citekey: String,
+
+        /// Format of the export that the citation key is from
+        #[arg(long, default_value_t, value_enum)]
+        format: ExportFormat,
+
+        /// Select the item in the local Zotero app (via a `zotero://select` link) instead
+        #[arg(long)]
+        local: bool,
+    },
+
+    /// Print the completions of the commands and arguments for a shell, e.g. `zotexon completions bash > /etc/bash_completion.d/zotexon`
+    Completions {
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
+    },
+
+    /// Check an API key and store it in the keyring of the system, so that it doesn't have to be given to the other commands anymore
+    #[cfg(feature = "keyring")]
+    Login {
+        #[command(flatten)]
+        api_key: ApiKeyArgs,
+    },
+
+    /// Remove the API key stored with `login` from the keyring of the system
+    #[cfg(feature = "keyring")]
+    Logout,
+}
+
+impl Command {
+    /// Format of the export that the command works with, if any
+    pub(super) fn format(&self) -> Option<&ExportFormat> {
+        match self {
+            Command::Bench { format, .. }
+            | Command::Archive { format, .. }
+            | Command::Lint { format, .. }
+            | Command::Reconcile { format, .. }
+            | Command::Search { format, .. }
+            | Command::Pick { format, .. }
+            | Command::Cite { format, .. }
+            | Command::Open { format, .. } => Some(format),
+            _ => None,
+        }
+    }
+
+    /// Arguments of the export that the command runs, if it is one
+    pub(super) fn export_args(&self) -> Option<&ExportArgs> {
+        match self {
+            Command::Export(export) | Command::Watch(export) => Some(export),
+            _ => None,
+        }
+    }
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub(super) struct ApiKeyArgs {
+    /// Zotero API Key with read access to your library. Generate a key in your Zotero settings: https://www.zotero.org/settings/keys/new. Prefer `--api-key-file` or the environment variable `ZOTEXON_API_KEY`, which don't show up in the shell history and process list
+    #[arg(long)]
+    pub(super) api_key: Option<String>,
+
+    /// File containing the Zotero API key, e.g. a secret like `/run/secrets/zotero`
+    #[arg(long, value_name = "FILE", conflicts_with = "api_key")]
+    pub(super) api_key_file: Option<PathBuf>,
+
+    /// Version of the Zotero API to request. Responses of another version are rejected
+    #[arg(long, value_name = "VERSION", env = "ZOTEXON_API_VERSION", default_value_t = DEFAULT_API_VERSION)]
+    pub(super) api_version: u32,
+
+    /// Connect to the Zotero servers only over this IP version, e.g. when the other one is broken in a VPN
+    #[arg(long, value_enum)]
+    pub(super) ip_family: Option<IpFamily>,
+
+    /// Local address to connect to the Zotero servers from, e.g. the address of a VPN interface
+    #[arg(long, value_name = "ADDRESS")]
+    pub(super) local_address: Option<IpAddr>,
+
+    /// Network interface to connect to the Zotero servers through, e.g. `wg0` (Linux only)
+    #[arg(long, value_name = "NAME")]
+    pub(super) interface: Option<String>,
+
+    /// Record all requests to the Zotero API with their responses in this HAR file (with the API key redacted), e.g. for bug reports
+    #[arg(long, value_name = "FILE")]
+    pub(super) capture_har: Option<PathBuf>,
+}
+
+/// Export arguments of an account of the `daemon` command, parsed like the command line
+#[derive(Parser, Debug)]
+#[command(no_binary_name = true)]
+pub(super) struct AccountArgs {
+    #[command(flatten)]
+    pub(super) export: ExportArgs,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub(super) struct ExportArgs {
+    #[command(flatten)]
+    pub(super) api: ApiKeyArgs,
+
+    /// File that the library will be exported to
+    #[arg(long, value_hint = clap::ValueHint::FilePath)]
+    pub(super) file: String,
+
+    /// Format to be used for the export
+    #[arg(long, default_value_t, value_enum)]
+    pub(super) format: ExportFormat,
+
+    /// Request this format from the Zotero API instead, e.g. `mods`, `refer` or `csljson`, and write it as it is returned, without header and page after page. Options that modify or write out single entries can't be used with it
+    #[arg(long, value_name = "NAME", conflicts_with = "format")]
+    pub(super) raw_format: Option<String>,
+
+    /// Also export the library to this file in another format in the same run, given like `refs.json=json`. Can be given multiple times. Targets get the filters and header of the export and, unless written as JSON, its checks, transforms and attachment links, but none of its other outputs, which log a warning
+    #[arg(long = "target", value_name = "FILE=FORMAT")]
+    pub(super) targets: Vec<ExportTarget>,
+
+    /// Let the program listen for changes in the Zotero library and automatically export on every change. Program will run until interrupted (e.g. with Ctrl+C).
+    #[arg(long)]
+    pub(super) sync: bool,
+
+    /// Exit with code 3 instead of 0 if the export wrote changes, see "Exit codes" in the README
+    #[arg(long, conflicts_with = "sync")]
+    pub(super) detailed_exit_codes: bool,
+
+    /// Fail the export if any warning was logged, e.g. about incomplete entries, colliding citation keys or skipped attachments. The export is still written.
+    #[arg(long, conflicts_with = "sync")]
+    pub(super) fail_on_warnings: bool,
+
+    /// Print the status of the export without colors and symbols. The status is only printed if stderr is a terminal, colors are also turned off by `NO_COLOR`
+    #[arg(long)]
+    pub(super) plain: bool,
+
+    /// Sort the entries by citation key, so that exports of the same library version are byte-identical, e.g. for reproducible document builds. A `{timestamp}` in `--header-template` is then the time of `SOURCE_DATE_EPOCH`
+    #[arg(long)]
+    pub(super) reproducible: bool,
+
+    /// Write the entries in this order instead of the order of the API responses, which keeps the diffs of a committed bibliography small
+    #[arg(long, value_enum, value_name = "ORDER")]
+    pub(super) sort: Option<SortOrder>,
+
+    /// Export only the items in "My Publications" of the user, e.g. for the bibliography of a publication page
+    #[arg(long)]
+    pub(super) publications: bool,
+
+    /// Export only the items of this collection, given by its key, name or full path like `Thesis/Chapter 2`, instead of the whole library
+    #[arg(long, value_name = "KEY_OR_NAME", conflicts_with = "publications")]
+    pub(super) collection: Option<String>,
+
+    /// Export only the items matching this saved search of Zotero, given by its key or name. Its conditions on the collection and tags are used as filters, other conditions are left out with a warning. The search is read once at the start of the export
+    #[arg(long, value_name = "KEY_OR_NAME", conflicts_with_all = ["publications", "collection"])]
+    pub(super) saved_search: Option<String>,
+
+    /// Export only the items with this tag. Can be given multiple times, items then need all of the tags; `a || b` matches items with either tag
+    #[arg(long = "tag", value_name = "TAG")]
+    pub(super) tags: Vec<String>,
+
+    /// Leave out the items with this tag, e.g. `toread`. Can be given multiple times
+    #[arg(long = "exclude-tag", value_name = "TAG")]
+    pub(super) exclude_tags: Vec<String>,
+
+    /// Export the library of this group instead of the personal library. Can be given multiple times, every library then gets a file of its own, named like `refs-group-12345.bib`
+    #[arg(long = "group", value_name = "ID", conflicts_with = "publications")]
+    pub(super) groups: Vec<GroupId>,
+
+    /// Also export the personal library to the file given with `--file` when exporting groups
+    #[arg(long, requires = "groups")]
+    pub(super) personal: bool,
+
+    /// Serve a read-only web page with the state of the sync, recent changes and a download of the export on this address, e.g. `127.0.0.1:8080`. Requires `--sync`.
+    #[arg(long, value_name = "ADDRESS", requires = "sync")]
+    pub(super) web_ui: Option<String>,
+
+    /// Show the connection state, the last export and recent changes and errors in a terminal dashboard instead of the log. Requires `--sync`.
+    #[arg(long, requires = "sync", conflicts_with = "serve_rpc")]
+    pub(super) dashboard: bool,
+
+    /// Split the export into numbered files of at most N entries next to the file (e.g. `refs-001.bib`), and write the names of these files to the file itself
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..), conflicts_with = "snapshots")]
+    pub(super) split_entries: Option<u64>,
+
+    /// Keep the library version, time and hash of the export in this JSON file (e.g. `refs.bib.zotexon.json`) instead of a header comment in the exported file, for tools that strip comments. The header of an existing export is taken over
+    #[arg(long, value_name = "FILE", conflicts_with = "split_entries")]
+    pub(super) state_file: Option<PathBuf>,
+
+    /// Leave out the header comment with the metadata of the export, e.g. for bibliography linters that reject it. Without `--state-file` the whole library is exported every time
+    #[arg(long)]
+    pub(super) no_header: bool,
+
+    /// Write this line instead of the header comment with the metadata, with the placeholders `{version}`, `{timestamp}`, `{entries}`, `{library}`, `{format}` and `{zotexon_version}`, e.g. `% exported {version} at {timestamp}`. Without `--state-file` the whole library is exported every time
+    #[arg(long, value_name = "TEMPLATE", conflicts_with = "no_header")]
+    pub(super) header_template: Option<String>,
+
+    /// After the first export, fetch only the items that changed since the previous export and replace or add their entries in the file, instead of exporting the whole library again. Needs `--state-file`, which keeps the citation keys of the items, and can't be used with options that modify or write out single entries, or with filters like `--tag` or `--collection`, whose items can change without being modified
+    #[arg(long, requires = "state_file", conflicts_with_all = ["split_entries", "raw_format", "tags", "exclude_tags", "collection", "saved_search", "publications"])]
+    pub(super) incremental: bool,
+
+    /// Overwrite the file even if it was exported from another Zotero library
+    #[arg(long)]
+    pub(super) force: bool,
+
+    /// Answer JSON-RPC requests of editor plugins (`completeKeys`, `getItem`, `exportNow`) on stdin/stdout, or on a TCP address like `127.0.0.1:7878`. Keeps running until stdin is closed
+    #[arg(long, value_name = "ADDRESS", num_args = 0..=1, default_missing_value = "stdio")]
+    pub(super) serve_rpc: Option<String>,
+
+    /// Overwrite the file even if the export is empty while the file had entries before, e.g. after deleting all items on purpose
+    #[arg(long)]
+    pub(super) allow_empty: bool,
+
+    /// Maximum download rate for the library and attachment files in bytes per second, e.g. `500K` or `2M`
+    #[arg(long, value_name = "RATE", value_parser = parse_rate)]
+    pub(super) max_download_rate: Option<u64>,
+
+    /// Give up an export after this many retries of failed requests (connection problems, server errors) in total
+    #[arg(long, value_name = "N", default_value_t = 10)]
+    pub(super) max_retries: u32,
+
+    /// Give up an export after this many requests failed in a row
+    #[arg(long, value_name = "N", default_value_t = 3, value_parser = clap::value_parser!(u32).range(1..))]
+    pub(super) max_consecutive_failures: u32,
+
+    /// Seconds to wait before retrying a failed request, doubled for every further failure in a row (up to a minute), or as long as the API asks for with a `Retry-After` or `Backoff` header if that is longer
+    #[arg(long, value_name = "SECONDS", default_value_t = 2, value_parser = clap::value_parser!(u64).range(1..))]
+    pub(super) retry_delay: u64,
+
+    /// Add up to this percentage of the delay before a retry at random, so that parallel exports don't retry at the same time
+    #[arg(long, value_name = "PERCENT", default_value_t = 20, value_parser = clap::value_parser!(u32).range(0..=100))]
+    pub(super) retry_jitter: u32,
+
+    /// Keep a snapshot of every export next to the file (in `<FILE>.snapshots`), so that previous versions can be restored with the `rollback` command
+    #[arg(long)]
+    pub(super) snapshots: bool,
+
+    /// Append the items that changed since the previous export to this journal file (JSON lines), with the user who changed them for group libraries
+    #[arg(long, value_name = "FILE")]
+    pub(super) change_journal: Option<PathBuf>,
+
+    /// Mirror the attachment files (PDFs, snapshots) of the exported items into this directory and add `file` fields pointing at them
+    #[arg(long, value_name = "DIR")]
+    pub(super) attachments_dir: Option<PathBuf>,
+
+    /// Directory layout of the mirrored attachments
+    #[arg(long, default_value_t, value_enum)]
+    pub(super) attachment_layout: AttachmentLayout,
+
+    /// Remove mirrored attachment files once the attachments are deleted in Zotero
+    #[arg(long, value_enum, value_name = "MODE", requires = "attachments_dir")]
+    pub(super) prune_attachments: Option<PruneMode>,
+
+    /// Download the mirrored attachments from this WebDAV server (as configured in Zotero's file syncing settings) instead of the Zotero storage
+    #[arg(long, value_name = "URL", requires = "attachments_dir")]
+    pub(super) webdav_url: Option<String>,
+
+    /// Username for the WebDAV server
+    #[arg(long, requires = "webdav_url")]
+    pub(super) webdav_username: Option<String>,
+
+    /// Password for the WebDAV server
+    #[arg(
+        long,
+        env = "ZOTEXON_WEBDAV_PASSWORD",
+        hide_env_values = true,
+        requires = "webdav_url"
+    )]
+    pub(super) webdav_password: Option<String>,
+
+    /// Add linked attachment files to the `file` fields, resolving paths relative to Zotero's "Linked Attachment Base Directory" against this directory
+    #[arg(long, value_name = "DIR")]
+    pub(super) linked_attachments_base: Option<PathBuf>,
+
+    /// Add the files of stored attachments to the `file` fields, pointing into the storage directory of Zotero on this machine (`storage` in the Zotero data directory, e.g. `~/Zotero/storage`) instead of downloading them
+    #[arg(long, value_name = "DIR", conflicts_with = "attachments_dir")]
+    pub(super) zotero_storage: Option<PathBuf>,
+
+    /// Write the citation keys to this file after every export, one per line, or with title, authors and year if the file ends in `.json`
+    #[arg(long, value_name = "FILE")]
+    pub(super) keys_file: Option<PathBuf>,
+
+    /// Write a JSON manifest of the exported files with their format, library version, number of entries and SHA-256 hash after every run, e.g. for build systems
+    #[arg(long, value_name = "FILE")]
+    pub(super) manifest: Option<PathBuf>,
+
+    /// Write the exported items as NDJSON, one JSON object per line with the citation key, the entry fields and the Zotero item data, to this file or with `-` to stdout
+    #[arg(long, value_name = "FILE", conflicts_with = "dashboard")]
+    pub(super) ndjson: Option<PathBuf>,
+
+    /// Export the child notes of every item as Markdown file `<CITEKEY>.md` into this directory, and every standalone note as a file named by its first line
+    #[arg(long, value_name = "DIR")]
+    pub(super) notes_dir: Option<PathBuf>,
+
+    /// Export the PDF annotations (highlights, comments) of every item as file `<CITEKEY>.<EXTENSION>` into this directory
+    #[arg(long, value_name = "DIR")]
+    pub(super) annotations_dir: Option<PathBuf>,
+
+    /// File format of the exported annotations
+    #[arg(long, default_value_t, value_enum)]
+    pub(super) annotations_format: AnnotationsFormat,
+
+    /// Write one Markdown note per item (with YAML front matter: citekey, authors, DOI, tags) into this Obsidian vault folder. Existing notes keep their content, only the front matter is updated.
+    #[arg(long, value_name = "DIR")]
+    pub(super) obsidian_vault: Option<PathBuf>,
+
+    /// Create a literature-note stub `<citekey>.org` for every item that has no note in this directory yet (citar/org-roam-bibtex conventions). Existing notes are never modified.
+    #[arg(long, value_name = "DIR")]
+    pub(super) note_skeletons_dir: Option<PathBuf>,
+
+    /// Template for new note skeletons, its file extension is used for the notes. Placeholders: ${citekey}, ${title}, ${authors}, ${year}, ${doi}, ${zotero-key}, ${id}
+    #[arg(long, value_name = "FILE", requires = "note_skeletons_dir")]
+    pub(super) note_skeleton_template: Option<PathBuf>,
+
+    /// Generate citation keys like Better BibTeX instead of using Zotero's, from this pattern of fields like `[auth]`, `[authors2]`, `[year]`, `[title]`, `[shorttitle]` or `[veryshorttitle]` with modifiers `:lower` and `:upper`. Keys pinned with a `Citation Key: <key>` line in the Extra field are kept as they are
+    #[arg(long, value_name = "PATTERN", num_args = 0..=1, default_missing_value = DEFAULT_PATTERN)]
+    pub(super) citekey_pattern: Option<KeyPattern>,
+
+    /// Apply Better BibTeX-style `tex.<field>: <value>` lines from the Extra field of items, setting or overriding fields in the export
+    #[arg(long)]
+    pub(super) extra_overrides: bool,
+
+    /// Replace journal names with `@string` macros for their abbreviations, defined at the top of the file. Uses a bundled list of common journals.
+    #[arg(long)]
+    pub(super) journal_strings: bool,
+
+    /// Additional journal abbreviations for `--journal-strings`, in JabRef's format: one `<full name>;<abbreviation>` per line
+    #[arg(long, value_name = "FILE", requires = "journal_strings")]
+    pub(super) journal_abbreviations: Option<PathBuf>,
+
+    /// Normalize identifiers: strip `https://doi.org/` prefixes from DOIs, drop URLs that only link to the DOI, validate and hyphenate ISBNs
+    #[arg(long)]
+    pub(super) normalize_identifiers: bool,
+
+    /// With `--normalize-identifiers`, drop the `url` of every entry that has a DOI
+    #[arg(long, requires = "normalize_identifiers")]
+    pub(super) drop_url_with_doi: bool,
+
+    /// Normalize the page ranges in `pages` fields to use this dash, removing repeated pages like in `123-123-145`
+    #[arg(long, value_enum, value_name = "DASH")]
+    pub(super) page_range_dash: Option<PageRangeDash>,
+
+    /// Write dates based on the date parsed by Zotero: an ISO `date` field for BibLaTeX, `year` and a three-letter `month` macro for BibTeX
+    #[arg(long)]
+    pub(super) normalize_dates: bool,
+
+    /// Write Zotero's language field as `langid` (BibLaTeX) or `hyphenation` (BibTeX) with a language name that babel and polyglossia understand, e.g. `ngerman` for `de` or `German`
+    #[arg(long)]
+    pub(super) langid: bool,
+
+    /// TOML file with further settings, e.g. `[biblatex.entry-types]` to map Zotero item types to other entry types. Defaults to the closest `.zotexon.toml` in the working directory or its parents, whose `args` are used as default export arguments
+    #[arg(long, value_name = "FILE")]
+    pub(super) config: Option<PathBuf>,
+
+    /// Add a `@book`/`@collection` entry for every book that several exported chapters are part of, and refer to it with `crossref` from the chapters
+    #[arg(long)]
+    pub(super) crossref_books: bool,
+
+    /// Add the citation keys of Zotero's "Related" items to the BibLaTeX `related` field
+    #[arg(long)]
+    pub(super) related: bool,
+
+    /// `relatedtype` written along with `--related`, e.g. `reprintof`. Single items can set it with `tex.relatedtype` in their Extra field.
+    #[arg(long, value_name = "TYPE", requires = "related")]
+    pub(super) related_type: Option<String>,
+
+    /// Write the tags of every item to the `keywords` field, following the `[keywords]` rules of the config file (exclude automatic tags, only colored tags, strip prefixes, include/exclude patterns, renaming)
+    #[arg(long)]
+    pub(super) tags_as_keywords: bool,
+
+    /// Add the collections of every item to its `keywords`, e.g. `collection:Thesis/Chapter 2`, to filter bibliographies by collection with BibLaTeX
+    #[arg(long)]
+    pub(super) collections_as_keywords: bool,
+
+    /// Prefix of the keywords added with `--collections-as-keywords`
+    #[arg(
+        long,
+        value_name = "PREFIX",
+        default_value = "collection:",
+        requires = "collections_as_keywords"
+    )]
+    pub(super) collection_keyword_prefix: String,
+
+    /// Add JabRef group metadata with a group for every collection, to see the collections as groups in JabRef
+    #[arg(long)]
+    pub(super) jabref_groups: bool,
+
+    /// Shorten abstracts to at most N characters, cut at a word boundary
+    #[arg(long, value_name = "N")]
+    pub(super) abstract_max_length: Option<usize>,
+
+    /// Remove the abstracts of entries of these types, e.g. `book,online`
+    #[arg(long, value_name = "TYPES", value_delimiter = ',')]
+    pub(super) drop_abstracts: Vec<String>,
+
+    /// WebAssembly module that transforms every entry, applied after the built-in transforms. Can be given multiple times, the plugins are applied in order
+    #[cfg(feature = "plugins")]
+    #[arg(long = "plugin", value_name = "FILE")]
+    pub(super) plugins: Vec<PathBuf>,
+
+    /// Check that the entries have the required fields of their type (e.g. an article needs a journal and a year), and list the incomplete ones. Fails the export unless `warn` is given
+    #[arg(long, value_enum, value_name = "MODE", num_args = 0..=1, default_missing_value = "fail")]
+    pub(super) strict: Option<StrictMode>,
+
+    /// How to handle entries with the same citation key: fail the export when given without a mode or with `fail`, with `ask` choose to rename, skip or prefer the newer item, or with `suffix` keep the key of the first added item and add `b`, `c`, ... to the keys of the others. The decisions are read from and can be saved to the `[key-collisions]` table of the config. Without it, colliding keys get suffixes like with `suffix`
+    #[arg(long, value_enum, value_name = "MODE", num_args = 0..=1, default_missing_value = "fail")]
+    pub(super) key_collisions: Option<CollisionMode>,
+
+    /// Account of the `daemon` command that runs the export, to select its `[[documents]]` of the config
+    #[arg(skip)]
+    pub(super) account: Option<String>,
+}
@@ -0,0 +1,48 @@
+use crate::cli::args::ApiKeyArgs;
+use crate::network::NetworkOptions;
+use crate::zotero_api::api_key::{ApiKey, KeyInfoCache};
+use crate::zotero_api::builder::ZoteroClientBuilder;
+use crate::zotero_api::client::ReqwestZoteroClient;
+use anyhow::Context;
+use std::path::PathBuf;
+
+pub(super) async fn build_client(args: &ApiKeyArgs) -> anyhow::Result<ReqwestZoteroClient> {
+    let (api_key, builder) = client_builder(args)?;
+    build_client_with(&api_key, builder, args.capture_har.clone()).await
+}
+
+pub(super) fn client_builder(args: &ApiKeyArgs) -> anyhow::Result<(ApiKey, ZoteroClientBuilder)> {
+    let api_key = ApiKey::resolve(args.api_key.as_deref(), args.api_key_file.as_deref())?;
+    let builder = ZoteroClientBuilder::new(api_key.clone())
+        .with_api_version(args.api_version)
+        .with_network(network_options(args)?);
+    Ok((api_key, builder))
+}
+
+pub(super) fn network_options(args: &ApiKeyArgs) -> anyhow::Result<NetworkOptions> {
+    Ok(NetworkOptions::new(
+        args.ip_family,
+        args.local_address,
+        args.interface.clone(),
+    )?)
+}
+
+pub(super) async fn build_client_with(
+    api_key: &ApiKey,
+    mut builder: ZoteroClientBuilder,
+    capture_har: Option<PathBuf>,
+) -> anyhow::Result<ReqwestZoteroClient> {
+    match capture_har {
+        // a cached key info would leave the key check out of the capture
+        Some(path) => builder = builder.with_har_capture(path),
+        None => {
+            if let Some(cache) = KeyInfoCache::for_key(api_key) {
+                builder = builder.with_key_info_cache(cache);
+            }
+        }
+    }
+    builder
+        .build()
+        .await
+        .with_context(|| "Error during Zotero client initialization.")
+}
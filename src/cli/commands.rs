@@ -0,0 +1,462 @@
+use crate::archive::LibraryArchive;
+use crate::bench::Bench;
+use crate::cache::ItemCache;
+use crate::cli::args::{AccountArgs, ApiKeyArgs};
+use crate::cli::client::{build_client, build_client_with, client_builder};
+use crate::cli::exports::{cancel_on_ctrl_c, export};
+use crate::config::Config;
+use crate::digest::Digest;
+use crate::export::document::ExportDocument;
+use crate::lint::LintReport;
+use crate::listing::{CollectionList, GroupList};
+use crate::reconcile::ReconcileReport;
+use crate::search::SearchResults;
+use crate::stats::{HistoryRecord, HistoryReport, LibraryHistory, LibraryStats};
+use crate::zotero_api::api_key::{ApiKeyError, KeyAccessReport};
+use crate::zotero_api::client::{GroupId, ZoteroClient};
+use crate::zotero_api::{ExportFormat, FetchDataResponse, FetchItemsParams};
+use anyhow::Context;
+use chrono::Datelike;
+use clap::Parser;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::Semaphore;
+use tokio_util::sync::CancellationToken;
+
+/// Store the API key in the keyring once the client accepted it, so that a mistyped key isn't stored
+#[cfg(feature = "keyring")]
+pub(super) async fn run_login(args: ApiKeyArgs) -> anyhow::Result<()> {
+    let api_key = ApiKey::resolve(args.api_key.as_deref(), args.api_key_file.as_deref())?;
+    build_client(&args).await?;
+    api_key.store_in_keyring()?;
+    println!("Stored the API key in the keyring of the system");
+    Ok(())
+}
+
+#[cfg(feature = "keyring")]
+pub(super) fn run_logout() -> anyhow::Result<()> {
+    if ApiKey::remove_from_keyring()? {
+        println!("Removed the API key from the keyring of the system");
+    } else {
+        println!("No API key is stored in the keyring of the system");
+    }
+    Ok(())
+}
+
+pub(super) async fn run_stats(args: ApiKeyArgs, show_history: bool) -> anyhow::Result<()> {
+    let client = build_client(&args).await?;
+    let mut cache = ItemCache::open(ItemCache::default_path(&client)?).await;
+    cache
+        .refresh(&client)
+        .await
+        .with_context(|| "Error while updating the local item cache.")?;
+    let stats = LibraryStats::from_cache(&cache);
+    let history = LibraryHistory::default_path(client.library()).map(LibraryHistory::new);
+    if let Some(history) = &history {
+        let record = HistoryRecord {
+            date: chrono::Utc::now(),
+            library_version: stats.library_version,
+            items: stats.regular_items,
+        };
+        if let Err(e) = history.record(record).await {
+            log::warn!(
+                "Could not record the library history in '{}': {}",
+                history.path().display(),
+                e
+            );
+        }
+    }
+    match history {
+        Some(history) if show_history => {
+            let records = history.records().await.with_context(|| {
+                format!("Error reading the history '{}'", history.path().display())
+            })?;
+            print!("{}", HistoryReport(&records));
+        }
+        _ => print!("{}", stats),
+    }
+    Ok(())
+}
+
+pub(super) async fn run_collections(args: ApiKeyArgs) -> anyhow::Result<()> {
+    let client = build_client(&args).await?;
+    let collections = client
+        .fetch_collections(None)
+        .await
+        .with_context(|| "Error fetching the collections.")?;
+    let collections = match collections {
+        FetchDataResponse::Updated { data, .. } => data,
+        FetchDataResponse::UpToDate => Vec::new(),
+    };
+    print!(
+        "{}",
+        CollectionList(
+            collections
+                .into_iter()
+                .map(|collection| (collection.key.clone(), collection))
+                .collect()
+        )
+    );
+    Ok(())
+}
+
+pub(super) async fn run_groups(args: ApiKeyArgs) -> anyhow::Result<()> {
+    let (api_key, builder) = client_builder(&args)?;
+    let client =
+        build_client_with(&api_key, builder.without_library_check(), args.capture_har).await?;
+    let groups = client
+        .fetch_groups()
+        .await
+        .with_context(|| "Error fetching the groups.")?;
+    let key_info = client.key_info();
+    print!(
+        "{}",
+        GroupList(
+            groups
+                .into_iter()
+                .filter(|group| key_info.can_access_group(group.id))
+                .collect()
+        )
+    );
+    Ok(())
+}
+
+pub(super) async fn run_check_key(args: ApiKeyArgs, group: Option<GroupId>) -> anyhow::Result<()> {
+    let (_, mut builder) = client_builder(&args)?;
+    // without the cached key info, so that the key is checked afresh
+    if let Some(path) = args.capture_har {
+        builder = builder.with_har_capture(path);
+    }
+    let client = builder
+        .without_library_check()
+        .build()
+        .await
+        .with_context(|| "Error checking the API key.")?;
+    let key_info = client.key_info();
+    print!("{}", KeyAccessReport(key_info));
+    match group {
+        Some(group_id) if !key_info.can_access_group(group_id) => {
+            Err(ApiKeyError::NoGroupAccess(group_id).into())
+        }
+        None if !key_info.can_access_library() => Err(ApiKeyError::InsufficientRights.into()),
+        _ => Ok(()),
+    }
+}
+
+pub(super) async fn run_archive(
+    args: ApiKeyArgs,
+    output: PathBuf,
+    format: ExportFormat,
+    attachments: bool,
+    reproducible: bool,
+) -> anyhow::Result<()> {
+    let client = build_client(&args).await?;
+    let mut archive = LibraryArchive::new(format);
+    if attachments {
+        archive = archive.with_attachments();
+    }
+    if reproducible {
+        archive = archive.with_reproducible();
+    }
+    archive
+        .write(&client, &output)
+        .await
+        .with_context(|| "Error while creating the library archive.")
+}
+
+pub(super) async fn run_lint(args: ApiKeyArgs, format: ExportFormat) -> anyhow::Result<()> {
+    let client = build_client(&args).await?;
+    let document = fetch_document(&client, &format).await?;
+    print!(
+        "{}",
+        LintReport::from_document(&document, chrono::Local::now().year())
+    );
+    Ok(())
+}
+
+pub(super) async fn run_bench(
+    args: ApiKeyArgs,
+    format: ExportFormat,
+    sample: u32,
+) -> anyhow::Result<()> {
+    let client = build_client(&args).await?;
+    let report = Bench::new(&client, format, sample)
+        .run()
+        .await
+        .with_context(|| "Error during the benchmark.")?;
+    print!("{}", report);
+    Ok(())
+}
+
+pub(super) async fn run_reconcile(
+    args: ApiKeyArgs,
+    bib: PathBuf,
+    format: ExportFormat,
+) -> anyhow::Result<()> {
+    let text = std::fs::read_to_string(&bib)
+        .with_context(|| format!("Error reading bibliography '{}'", bib.display()))?;
+    let existing: Vec<crate::bibtex::Entry> = crate::bibtex::parse(&text)?
+        .into_iter()
+        .filter_map(|block| match block {
+            crate::bibtex::Block::Entry(entry) => Some(entry),
+            crate::bibtex::Block::Other(_) => None,
+        })
+        .collect();
+    let client = build_client(&args).await?;
+    let document = fetch_document(&client, &format).await?;
+    print!("{}", ReconcileReport::new(&existing, &document));
+    Ok(())
+}
+
+pub(super) async fn run_search(
+    args: ApiKeyArgs,
+    query: String,
+    format: ExportFormat,
+    select: Vec<usize>,
+    emit: bool,
+    clipboard: bool,
+    children: bool,
+) -> anyhow::Result<()> {
+    let client = build_client(&args).await?;
+    let items = client
+        .search_items(&query, &format)
+        .await
+        .with_context(|| "Error while searching the library.")?;
+    let mut document = ExportDocument::try_from_items(items, &format)?;
+    if !select.is_empty() {
+        crate::search::select(&mut document, &select).map_err(|number| {
+            anyhow::anyhow!(
+                "There is no result number {}, the search found {} items",
+                number,
+                document.entries.len()
+            )
+        })?;
+    }
+    if children && !emit {
+        for entry in &document.entries {
+            // the search returns top-level items only, with the number of their children
+            if entry.item.meta.num_children.unwrap_or_default() == 0 {
+                continue;
+            }
+            let children = client
+                .fetch_children(&entry.item.key)
+                .await
+                .with_context(|| "Error while fetching attachments and notes.")?;
+            document.children.insert(entry.item.key.clone(), children);
+        }
+    }
+    if emit {
+        print!("{}", document.render());
+    } else {
+        print!(
+            "{}",
+            SearchResults {
+                document: &document
+            }
+        );
+    }
+    if clipboard {
+        copy_to_clipboard(document.render())?;
+        eprintln!("Copied {} entries to the clipboard", document.entries.len());
+    }
+    Ok(())
+}
+
+pub(super) async fn run_pick(
+    args: ApiKeyArgs,
+    file: Option<PathBuf>,
+    format: ExportFormat,
+    clipboard: bool,
+) -> anyhow::Result<()> {
+    let client = build_client(&args).await?;
+    let mut cache = ItemCache::open(ItemCache::default_path(&client)?).await;
+    cache
+        .refresh(&client)
+        .await
+        .with_context(|| "Error while updating the local item cache.")?;
+    let Some(picked) = crate::picker::pick(crate::picker::options(cache.items()))? else {
+        println!("Nothing exported");
+        return Ok(());
+    };
+    let keys: Vec<String> = picked.into_iter().map(|option| option.key).collect();
+    let items = client
+        .fetch_items_by_key(&keys, &format)
+        .await
+        .with_context(|| "Error while fetching the picked items.")?;
+    let document = ExportDocument::try_from_items(items, &format)?;
+    if let Some(file) = file {
+        tokio::fs::write(&file, document.render())
+            .await
+            .with_context(|| format!("Error writing file '{}'", file.display()))?;
+        println!(
+            "Exported {} items to '{}'",
+            document.entries.len(),
+            file.display()
+        );
+    }
+    if clipboard {
+        copy_to_clipboard(document.render())?;
+        println!("Copied {} items to the clipboard", document.entries.len());
+    }
+    Ok(())
+}
+
+pub(super) fn run_verify(
+    bib: PathBuf,
+    paths: Vec<PathBuf>,
+    deny_unused: bool,
+) -> anyhow::Result<()> {
+    let text = std::fs::read_to_string(&bib)
+        .with_context(|| format!("Error reading bibliography '{}'", bib.display()))?;
+    let bibliography_keys = crate::bibtex::parse(&text)?
+        .into_iter()
+        .filter_map(|block| match block {
+            crate::bibtex::Block::Entry(entry) => Some(entry.key),
+            crate::bibtex::Block::Other(_) => None,
+        })
+        .collect();
+    let paths = if paths.is_empty() {
+        vec![PathBuf::from(".")]
+    } else {
+        paths
+    };
+    let mut citations = Vec::new();
+    for (path, kind) in crate::verify::source_files(&paths)? {
+        let text = std::fs::read_to_string(&path)
+            .with_context(|| format!("Error reading '{}'", path.display()))?;
+        citations.push((
+            path.display().to_string(),
+            crate::verify::citation_keys(&text, kind),
+        ));
+    }
+    let report = crate::verify::VerifyReport::new(&citations, &bibliography_keys);
+    print!("{}", report);
+    if !report.missing.is_empty() {
+        anyhow::bail!(
+            "{} cited keys are missing from the bibliography",
+            report.missing.len()
+        );
+    }
+    if deny_unused && !report.unused.is_empty() {
+        anyhow::bail!(
+            "{} entries of the bibliography are never cited",
+            report.unused.len()
+        );
+    }
+    Ok(())
+}
+
+pub(super) async fn run_cite(
+    args: ApiKeyArgs,
+    citekey: String,
+    style: String,
+    format: ExportFormat,
+    clipboard: bool,
+) -> anyhow::Result<()> {
+    let client = build_client(&args).await?;
+    let document = fetch_document(&client, &format).await?;
+    let Some(key) = crate::cite::item_key(&document, &citekey) else {
+        anyhow::bail!("No item with citation key '{}' in the library", citekey);
+    };
+    let bibliography = client
+        .fetch_bibliography(key, &style)
+        .await
+        .with_context(|| format!("Error while formatting the citation in style '{}'.", style))?;
+    let citation = crate::cite::html_to_text(&bibliography);
+    println!("{}", citation);
+    if clipboard {
+        copy_to_clipboard(citation)?;
+    }
+    Ok(())
+}
+
+pub(super) async fn run_open(
+    args: ApiKeyArgs,
+    citekey: String,
+    format: ExportFormat,
+    local: bool,
+) -> anyhow::Result<()> {
+    let client = build_client(&args).await?;
+    let document = fetch_document(&client, &format).await?;
+    let Some(key) = crate::cite::item_key(&document, &citekey) else {
+        anyhow::bail!("No item with citation key '{}' in the library", citekey);
+    };
+    let url = if local {
+        crate::links::zotero_select_url(key)
+    } else {
+        crate::links::web_library_url(client.user_id(), key)
+    };
+    println!("{}", url);
+    open::that(&url).with_context(|| format!("Error opening '{}'", url))
+}
+
+/// Export of the whole library, for commands that look up items by their citation key
+async fn fetch_document(
+    client: &impl ZoteroClient,
+    format: &ExportFormat,
+) -> anyhow::Result<ExportDocument> {
+    let params = FetchItemsParams {
+        last_modified_version: None,
+        since: None,
+        format: format.clone(),
+        collection: None,
+        tags: Vec::new(),
+        raw_format: None,
+    };
+    let items = match client
+        .fetch_items_with_data(&params)
+        .await
+        .with_context(|| "Error while fetching the library.")?
+    {
+        FetchDataResponse::Updated { data, .. } => data,
+        FetchDataResponse::UpToDate => Vec::new(),
+    };
+    Ok(ExportDocument::try_from_items(items, format)?)
+}
+
+fn copy_to_clipboard(text: String) -> anyhow::Result<()> {
+    arboard::Clipboard::new()
+        .and_then(|mut clipboard| clipboard.set_text(text))
+        .with_context(|| "Error copying to the clipboard.")
+}
+
+pub(super) async fn run_daemon(config_path: PathBuf, max_parallel: usize) -> anyhow::Result<()> {
+    let config = Config::load(&config_path)?;
+    if config.accounts.is_empty() {
+        anyhow::bail!("No [[accounts]] configured in '{}'", config_path.display());
+    }
+    let cancellation_token = CancellationToken::new();
+    let export_permits = Arc::new(Semaphore::new(max_parallel));
+    let digest = config.digest.map(Digest::new);
+    if let Some(digest) = &digest {
+        tokio::spawn(digest.clone().run(cancellation_token.clone()));
+    }
+    let mut exports = Vec::new();
+    for account in config.accounts {
+        let mut args = AccountArgs::try_parse_from(&account.args)
+            .with_context(|| format!("Invalid arguments of account '{}'", account.name))?
+            .export;
+        args.sync = true;
+        args.config.get_or_insert_with(|| config_path.clone());
+        args.account = Some(account.name.clone());
+        let cancellation_token = cancellation_token.child_token();
+        let export_permits = export_permits.clone();
+        let digest = digest.as_ref().map(|digest| digest.recorder(&account.name));
+        exports.push(async move {
+            log::info!("Starting export of account '{}'", account.name);
+            // a failing account is reported, but doesn't stop the others
+            let result = export(args, Some(export_permits), digest, None, cancellation_token).await;
+            if let Err(e) = &result {
+                log::error!("Export of account '{}' failed: {:?}", account.name, e);
+            }
+            result.is_ok()
+        });
+    }
+    cancel_on_ctrl_c(cancellation_token);
+    let results = futures::future::join_all(exports).await;
+    let failed = results.iter().filter(|ok| !**ok).count();
+    if failed > 0 {
+        anyhow::bail!("Export of {} of {} accounts failed", failed, results.len());
+    }
+    Ok(())
+}
@@ -0,0 +1,814 @@
+use crate::annotations::AnnotationsExporter;
+use crate::attachments::{AttachmentMirror, LinkedAttachments, StorageAttachments, WebDavSource};
+use crate::cli::args::ExportArgs;
+use crate::cli::client::{build_client_with, client_builder, network_options};
+use crate::config::Config;
+use crate::digest::DigestRecorder;
+use crate::documents::ProjectDocuments;
+use crate::export::document::SortOrder;
+use crate::export::{
+    ChangeJournal, EventSender, ExportEvent, ExportSuccess, ExportTarget, ExportTrigger,
+    FileExporter, FileHeader, KeyCollisions, Manifest,
+};
+use crate::keys::KeyList;
+use crate::ndjson::NdjsonWriter;
+use crate::notes::NotesExporter;
+use crate::obsidian::ObsidianVault;
+use crate::paths::platform_path;
+use crate::rpc::RpcServer;
+use crate::skeletons::NoteSkeletons;
+use crate::stats::LibraryHistory;
+use crate::status::StatusPrinter;
+use crate::transform::{
+    Abstracts, BibtexFields, CitationKeys, CollectionKeywords, Crossref, Dates, EntryTypes,
+    ExtraOverrides, FieldMappings, Identifiers, JabrefGroups, JournalStrings, Keywords, Languages,
+    PageRanges, Related,
+};
+use crate::web::WebUi;
+use crate::zotero_api::client::{GroupId, ReqwestZoteroClient, ZoteroClient};
+use crate::zotero_api::item::{
+    SavedSearch, Schema, SearchFilters, collection_path, find_collections,
+};
+use crate::zotero_api::retry::RetryBudget;
+use crate::zotero_api::throttle::Throttle;
+use crate::zotero_api::{ApiError, ExportFormat, FetchDataResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use std::io::IsTerminal;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Semaphore, mpsc};
+use tokio_util::sync::CancellationToken;
+
+pub(super) async fn run_export(
+    args: ExportArgs,
+    dashboard_events: Option<(EventSender, mpsc::UnboundedReceiver<ExportEvent>)>,
+) -> anyhow::Result<ExportSuccess> {
+    let cancellation_token = CancellationToken::new();
+    cancel_on_ctrl_c(cancellation_token.clone());
+    let Some((sender, receiver)) = dashboard_events else {
+        if !std::io::stderr().is_terminal() {
+            return export(args, None, None, None, cancellation_token).await;
+        }
+        let mut status = StatusPrinter::new(args.file.clone());
+        if args.plain {
+            status = status.with_plain();
+        }
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let status = tokio::spawn(status.run(receiver));
+        let result = export(args, None, None, Some(sender), cancellation_token.clone()).await;
+        // stops the sync tasks that still hold a sender, so that the status printer finishes
+        cancellation_token.cancel();
+        status.await?;
+        return result;
+    };
+    let dashboard = tokio::spawn(crate::dashboard::run(receiver, cancellation_token.clone()));
+    let result = export(args, None, None, Some(sender), cancellation_token.clone()).await;
+    // the terminal has to be restored before any error is printed
+    cancellation_token.cancel();
+    dashboard
+        .await?
+        .with_context(|| "Error in the terminal dashboard.")?;
+    result
+}
+
+pub(super) fn cancel_on_ctrl_c(cancellation_token: CancellationToken) {
+    tokio::spawn(async move {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("failed to listen for signal");
+        log::info!("Signal received, cancelling...");
+        cancellation_token.cancel();
+    });
+}
+
+/// Prepare the file and directory arguments of an export for this platform, see `paths::platform_path()`
+fn prepare_paths(args: &mut ExportArgs) -> anyhow::Result<()> {
+    for file in std::iter::once(&mut args.file)
+        .chain(args.targets.iter_mut().map(|target| &mut target.file))
+    {
+        *file = platform_path(Path::new(file.as_str()))?
+            .to_string_lossy()
+            .into_owned();
+    }
+    for path in [
+        &mut args.api.api_key_file,
+        &mut args.api.capture_har,
+        &mut args.attachments_dir,
+        &mut args.linked_attachments_base,
+        &mut args.zotero_storage,
+        &mut args.keys_file,
+        &mut args.manifest,
+        &mut args.state_file,
+        &mut args.notes_dir,
+        &mut args.annotations_dir,
+        &mut args.obsidian_vault,
+        &mut args.note_skeletons_dir,
+        &mut args.note_skeleton_template,
+        &mut args.journal_abbreviations,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        *path = platform_path(path)?;
+    }
+    Ok(())
+}
+
+/// Export the personal library or the groups of `--group`, every library to a file of its own
+pub(super) async fn export(
+    args: ExportArgs,
+    export_permits: Option<Arc<Semaphore>>,
+    digest: Option<DigestRecorder>,
+    events: Option<EventSender>,
+    cancellation_token: CancellationToken,
+) -> anyhow::Result<ExportSuccess> {
+    let libraries = library_args(&args)?;
+    if libraries.len() == 1 {
+        return export_library(args, export_permits, digest, events, cancellation_token).await;
+    }
+    let exports = libraries.into_iter().map(|args| {
+        let digest = digest.as_ref().map(|digest| match args.groups.first() {
+            Some(group_id) => digest.for_library(&format!("groups/{}", group_id)),
+            None => digest.clone(),
+        });
+        export_library(
+            args,
+            export_permits.clone(),
+            digest,
+            events.clone(),
+            cancellation_token.clone(),
+        )
+    });
+    let mut success = ExportSuccess::NoChanges;
+    for result in futures::future::join_all(exports).await {
+        if result? == ExportSuccess::Changes {
+            success = ExportSuccess::Changes;
+        }
+    }
+    Ok(success)
+}
+
+/// Arguments of the export of every library, with at most one group each and the file of the library
+fn library_args(args: &ExportArgs) -> anyhow::Result<Vec<ExportArgs>> {
+    let several = args.groups.len() > 1 || (args.personal && !args.groups.is_empty());
+    if !several {
+        return Ok(vec![args.clone()]);
+    }
+    let single_outputs = [
+        (!args.targets.is_empty(), "--target"),
+        (args.web_ui.is_some(), "--web-ui"),
+        (args.serve_rpc.is_some(), "--serve-rpc"),
+        (args.dashboard, "--dashboard"),
+        (args.api.capture_har.is_some(), "--capture-har"),
+        (args.attachments_dir.is_some(), "--attachments-dir"),
+        (args.keys_file.is_some(), "--keys-file"),
+        (args.manifest.is_some(), "--manifest"),
+        (args.state_file.is_some(), "--state-file"),
+        (args.ndjson.is_some(), "--ndjson"),
+        (args.notes_dir.is_some(), "--notes-dir"),
+        (args.annotations_dir.is_some(), "--annotations-dir"),
+        (args.obsidian_vault.is_some(), "--obsidian-vault"),
+        (args.note_skeletons_dir.is_some(), "--note-skeletons-dir"),
+    ];
+    if let Some((_, option)) = single_outputs.iter().find(|(used, _)| *used) {
+        anyhow::bail!(
+            "{} can't be used when exporting several libraries, configure an account per library for the `daemon` command instead",
+            option
+        );
+    }
+    let mut libraries = Vec::new();
+    if args.personal {
+        let mut personal = args.clone();
+        personal.groups.clear();
+        personal.personal = false;
+        libraries.push(personal);
+    }
+    for group_id in &args.groups {
+        let mut group = args.clone();
+        group.groups = vec![*group_id];
+        group.personal = false;
+        group.file = group_file(&args.file, *group_id);
+        libraries.push(group);
+    }
+    Ok(libraries)
+}
+
+/// File of a group library next to the given file, e.g. `refs-group-12345.bib` for `refs.bib`
+fn group_file(file: &str, group_id: GroupId) -> String {
+    let path = Path::new(file);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(extension) => format!(
+            "{}-group-{}.{}",
+            stem,
+            group_id,
+            extension.to_string_lossy()
+        ),
+        None => format!("{}-group-{}", stem, group_id),
+    };
+    path.with_file_name(name).to_string_lossy().into_owned()
+}
+
+async fn export_library(
+    mut args: ExportArgs,
+    export_permits: Option<Arc<Semaphore>>,
+    digest: Option<DigestRecorder>,
+    events: Option<EventSender>,
+    cancellation_token: CancellationToken,
+) -> anyhow::Result<ExportSuccess> {
+    prepare_paths(&mut args)?;
+    let config = match &args.config {
+        Some(path) => Config::load(path)?,
+        None => Config::default(),
+    };
+    let ndjson = args.ndjson.clone().map(NdjsonWriter::new);
+    if ndjson.as_ref().is_some_and(NdjsonWriter::is_stdout)
+        && args.serve_rpc.as_deref() == Some("stdio")
+    {
+        anyhow::bail!("NDJSON on stdout can't be combined with JSON-RPC on stdio");
+    }
+    // checked before any request is made
+    reproducible_header_timestamp(&args)?;
+    let (api_key, builder) = client_builder(&args.api)?;
+    let network = network_options(&args.api)?;
+    let throttle = args
+        .max_download_rate
+        .map(|rate| Arc::new(Throttle::new(rate)));
+    let retry_budget = Arc::new(
+        RetryBudget::new(args.max_retries, args.max_consecutive_failures).with_backoff(
+            Duration::from_secs(args.retry_delay),
+            f64::from(args.retry_jitter) / 100.0,
+        ),
+    );
+    let mut builder = builder
+        .with_retry_budget(retry_budget.clone())
+        .with_cancellation(cancellation_token.child_token());
+    if let Some(throttle) = &throttle {
+        builder = builder.with_throttle(throttle.clone());
+    }
+    if args.publications {
+        builder = builder.with_publications();
+    }
+    if let Some(group_id) = args.groups.first() {
+        builder = builder.with_group(*group_id);
+    }
+    let client = build_client_with(&api_key, builder, args.api.capture_har.clone()).await?;
+    if config.uses_schema() {
+        let schema = fetch_schema(&client, &config)
+            .await
+            .with_context(|| "Error while fetching the Zotero schema.")?;
+        let issues = config.schema_issues(&schema);
+        if !issues.is_empty() {
+            anyhow::bail!(
+                "Invalid config file '{}':\n{}",
+                args.config.unwrap_or_default().display(),
+                issues.join("\n")
+            );
+        }
+    }
+    let mut collection = match &args.collection {
+        Some(key_or_name) => Some(resolve_collection(&client, key_or_name).await?),
+        None => None,
+    };
+    if let Some(key_or_name) = &args.saved_search {
+        let filters = resolve_saved_search(&client, key_or_name).await?;
+        collection = filters.collection;
+        args.tags.extend(filters.tags);
+    }
+    let events = match &args.web_ui {
+        Some(address) => {
+            let listener = tokio::net::TcpListener::bind(address)
+                .await
+                .with_context(|| format!("Error listening for the web UI on '{}'", address))?;
+            log::info!("Serving the web UI on http://{}", address);
+            let web_ui = WebUi::new(&args.file);
+            let events = web_ui.observe(events);
+            tokio::spawn(web_ui.serve(listener, cancellation_token.child_token()));
+            Some(events)
+        }
+        None => events,
+    };
+    let trigger = if args.sync {
+        ExportTrigger::websocket(
+            api_key,
+            client.topic(),
+            cancellation_token.child_token(),
+            events.clone(),
+            network,
+        )
+        .await
+        .with_context(|| "Error during WebSocket trigger initialization.")?
+    } else {
+        ExportTrigger::none()
+    };
+    let trigger = match args.serve_rpc.clone() {
+        Some(transport) => {
+            let (trigger_sender, trigger_receiver) = mpsc::channel(1);
+            let server = RpcServer::new(&args.file, trigger_sender);
+            let cancellation_token = cancellation_token.child_token();
+            tokio::spawn(async move {
+                tokio::select! {
+                    result = server.run(transport.into()) => {
+                        if let Err(e) = result {
+                            log::error!("JSON-RPC server encountered an error: {}", e);
+                        }
+                    }
+                    _ = cancellation_token.cancelled() => {}
+                }
+            });
+            trigger.merge(trigger_receiver)
+        }
+        None => trigger,
+    };
+    for option in options_not_for_targets(&args) {
+        log::warn!(
+            "{} only applies to '{}', not to the files of --target",
+            option,
+            args.file
+        );
+    }
+    let mut targets = Vec::new();
+    for target in &args.targets {
+        // the JSON export is written as it is returned, without entries to check or transform
+        let entries = target.format != ExportFormat::Json;
+        let exporter = file_exporter(
+            &args,
+            &config,
+            client.clone(),
+            target,
+            ExportTrigger::none(),
+            collection.clone(),
+            entries,
+        )
+        .await?;
+        targets.push(exporter);
+    }
+    let history = LibraryHistory::default_path(client.library()).map(LibraryHistory::new);
+    let output = ExportTarget {
+        file: args.file.clone(),
+        format: args.format.clone(),
+    };
+    let mut exporter =
+        file_exporter(&args, &config, client, &output, trigger, collection, true).await?;
+    exporter = exporter.with_retry_budget(retry_budget);
+    for target in targets {
+        exporter = exporter.with_target(target);
+    }
+    if let Some(name) = args.raw_format.clone() {
+        exporter = exporter.with_raw_format(name);
+    }
+    if let Some(history) = history {
+        exporter = exporter.with_history(history);
+    }
+    if args.sync {
+        exporter = exporter.with_key_check();
+    }
+    if let Some(permits) = export_permits {
+        exporter = exporter.with_export_permits(permits);
+    }
+    if let Some(events) = events {
+        exporter = exporter.with_events(events);
+    }
+    if args.snapshots {
+        exporter = exporter.with_snapshots();
+    }
+    if let Some(path) = args.change_journal {
+        exporter = exporter.with_change_journal(ChangeJournal::new(path));
+    }
+    if let Some(entries_per_file) = args.split_entries {
+        exporter = exporter.with_split(entries_per_file as usize);
+    }
+    if let Some(dir) = args.attachments_dir {
+        let mut mirror = AttachmentMirror::new(dir, args.attachment_layout);
+        if let Some(url) = &args.webdav_url {
+            let mut webdav = WebDavSource::new(url, args.webdav_username, args.webdav_password);
+            if let Some(throttle) = &throttle {
+                webdav = webdav.with_throttle(throttle.clone());
+            }
+            mirror = mirror.with_webdav(webdav);
+        }
+        if let Some(mode) = args.prune_attachments {
+            mirror = mirror.with_pruning(mode);
+        }
+        exporter = exporter.with_attachments(mirror);
+    }
+    if let Some(path) = args.keys_file {
+        exporter = exporter.with_keys(KeyList::new(path));
+    }
+    let documents = config.documents_of(args.account.as_deref());
+    if !documents.is_empty() {
+        let documents = documents
+            .into_iter()
+            .map(|mut document| {
+                document.file = platform_path(&document.file)?;
+                Ok(document)
+            })
+            .collect::<anyhow::Result<_>>()?;
+        exporter = exporter.with_documents(ProjectDocuments::new(documents));
+    }
+    if let Some(path) = args.manifest {
+        exporter = exporter.with_manifest(Manifest::new(path));
+    }
+    if let Some(path) = args.state_file {
+        exporter = exporter.with_state_file(path);
+    }
+    if args.incremental {
+        exporter = exporter.with_incremental();
+    }
+    if let Some(digest) = digest {
+        exporter = exporter.with_digest(digest);
+    }
+    if let Some(ndjson) = ndjson {
+        exporter = exporter.with_ndjson(ndjson);
+    }
+    if let Some(dir) = args.notes_dir {
+        exporter = exporter.with_notes(NotesExporter::new(dir));
+    }
+    if let Some(dir) = args.annotations_dir {
+        exporter =
+            exporter.with_annotations(AnnotationsExporter::new(dir, args.annotations_format));
+    }
+    if let Some(dir) = args.obsidian_vault {
+        exporter = exporter.with_obsidian(ObsidianVault::new(dir));
+    }
+    if let Some(dir) = args.note_skeletons_dir {
+        let mut skeletons = NoteSkeletons::new(dir);
+        if let Some(template_path) = args.note_skeleton_template {
+            let template = std::fs::read_to_string(&template_path).with_context(|| {
+                format!("Failed to read note template '{}'", template_path.display())
+            })?;
+            let extension = template_path
+                .extension()
+                .map(|ext| ext.to_string_lossy().into_owned())
+                .unwrap_or_else(|| "org".to_owned());
+            skeletons = skeletons.with_template(template, extension);
+        }
+        exporter = exporter.with_skeletons(skeletons);
+    }
+
+    exporter
+        .run()
+        .await
+        .with_context(|| "Error during export process.")
+}
+
+/// Add the transforms of the arguments to the exporter of a file in the given format
+fn with_transforms<C: ZoteroClient>(
+    mut exporter: FileExporter<C>,
+    args: &ExportArgs,
+    config: &Config,
+    format: &ExportFormat,
+) -> anyhow::Result<FileExporter<C>> {
+    // the other transforms refer to entries by their final citation keys
+    if let Some(pattern) = &args.citekey_pattern {
+        exporter = exporter.with_transform(CitationKeys::new(pattern.clone()));
+    }
+    if args.extra_overrides {
+        exporter = exporter.with_transform(ExtraOverrides);
+    }
+    if *format == ExportFormat::Bibtex {
+        exporter = exporter.with_transform(BibtexFields);
+    }
+    if args.journal_strings {
+        let mut strings = JournalStrings::bundled();
+        if let Some(path) = &args.journal_abbreviations {
+            let list = std::fs::read_to_string(path).with_context(|| {
+                format!("Failed to read journal abbreviations '{}'", path.display())
+            })?;
+            strings = strings.with_list(&list);
+        }
+        exporter = exporter.with_transform(strings);
+    }
+    if args.normalize_identifiers {
+        exporter = exporter.with_transform(Identifiers::new(args.drop_url_with_doi));
+    }
+    if let Some(dash) = args.page_range_dash.clone() {
+        exporter = exporter.with_transform(PageRanges::new(dash));
+    }
+    if args.normalize_dates {
+        exporter = exporter.with_transform(Dates);
+    }
+    if args.langid {
+        exporter = exporter.with_transform(Languages);
+    }
+    if args.crossref_books {
+        exporter = exporter.with_transform(Crossref);
+    }
+    if args.related {
+        exporter = exporter.with_transform(Related::new(args.related_type.clone()));
+    }
+    if args.tags_as_keywords {
+        exporter = exporter.with_transform(Keywords::new(config.keywords.clone()));
+    }
+    if args.collections_as_keywords {
+        exporter = exporter.with_transform(CollectionKeywords::new(
+            args.collection_keyword_prefix.clone(),
+        ));
+    }
+    if args.jabref_groups {
+        exporter = exporter.with_transform(JabrefGroups);
+    }
+    if args.abstract_max_length.is_some() || !args.drop_abstracts.is_empty() {
+        exporter = exporter.with_transform(Abstracts::new(
+            args.abstract_max_length,
+            args.drop_abstracts.clone(),
+        ));
+    }
+    if let Some(format_config) = config.format(format)
+        && !format_config.entry_types.is_empty()
+    {
+        exporter = exporter.with_transform(EntryTypes::new(format_config.entry_types.clone()));
+    }
+    if let Some(format_config) = config.format(format)
+        && format_config.maps_fields()
+    {
+        exporter = exporter.with_transform(FieldMappings::new(
+            format_config.fields.clone(),
+            format_config.creator_types.clone(),
+        ));
+    }
+    #[cfg(feature = "plugins")]
+    for path in &args.plugins {
+        let plugin = crate::transform::WasmPlugin::load(path)
+            .with_context(|| format!("Error loading plugin '{}'", path.display()))?;
+        exporter = exporter.with_transform(plugin);
+    }
+    Ok(exporter)
+}
+
+/// Exporter of the file of the export or of a `--target`, with the filters and header of the export,
+/// and its checks, transforms and attachment links if the file gets the `entries` of the export.
+/// The other outputs are only added to the exporter of the export's own file.
+async fn file_exporter(
+    args: &ExportArgs,
+    config: &Config,
+    client: ReqwestZoteroClient,
+    output: &ExportTarget,
+    trigger: ExportTrigger,
+    collection: Option<String>,
+    entries: bool,
+) -> anyhow::Result<FileExporter<ReqwestZoteroClient>> {
+    let mut exporter = FileExporter::try_new(client, output.file.clone(), output.format.clone(), trigger)
+        .await
+        .with_context(|| {
+            format!(
+                "Error during initialization of the exporter of '{}'. Please ensure the file path is valid, the directory exists and is accessible.",
+                output.file
+            )
+        })?;
+    if let Some(key) = collection {
+        exporter = exporter.with_collection(key);
+    }
+    let tags = tag_filters(args);
+    if !tags.is_empty() {
+        exporter = exporter.with_tags(tags);
+    }
+    if args.force {
+        exporter = exporter.with_overwrite_other_library();
+    }
+    if args.allow_empty {
+        exporter = exporter.with_allow_empty();
+    }
+    if args.no_header {
+        exporter = exporter.with_header(FileHeader::None);
+    } else if let Some(template) = &args.header_template {
+        exporter = exporter.with_header(FileHeader::Template(template.clone()));
+    }
+    if let Some(timestamp) = reproducible_header_timestamp(args)? {
+        exporter = exporter.with_header_timestamp(timestamp);
+    }
+    if !entries {
+        return Ok(exporter);
+    }
+    if let Some(mode) = &args.strict {
+        exporter = exporter.with_strict(mode.clone());
+    }
+    if args.key_collisions.is_some() || !config.key_collisions.is_empty() {
+        exporter = exporter.with_collisions(KeyCollisions::new(
+            args.key_collisions.clone().unwrap_or_default(),
+            config.key_collisions.clone(),
+            args.config.clone(),
+        ));
+    }
+    if let Some(order) = sort_order(args) {
+        exporter = exporter.with_sort(order);
+    }
+    if let Some(base_dir) = &args.linked_attachments_base {
+        exporter = exporter.with_linked_attachments(LinkedAttachments::new(base_dir.clone()));
+    }
+    if let Some(storage_dir) = &args.zotero_storage {
+        exporter = exporter.with_storage_attachments(StorageAttachments::new(storage_dir.clone()));
+    }
+    with_transforms(exporter, args, config, &output.format)
+}
+
+/// Options of the arguments that only apply to the export's own file although there are `--target` files,
+/// which get neither these outputs nor these ways of writing the file
+fn options_not_for_targets(args: &ExportArgs) -> Vec<&'static str> {
+    if args.targets.is_empty() {
+        return Vec::new();
+    }
+    let main_only = [
+        (args.raw_format.is_some(), "--raw-format"),
+        (args.split_entries.is_some(), "--split-entries"),
+        (args.incremental, "--incremental"),
+        (args.state_file.is_some(), "--state-file"),
+        (args.snapshots, "--snapshots"),
+        (args.change_journal.is_some(), "--change-journal"),
+        (args.manifest.is_some(), "--manifest"),
+        (args.keys_file.is_some(), "--keys-file"),
+        (args.ndjson.is_some(), "--ndjson"),
+    ];
+    main_only
+        .into_iter()
+        .filter_map(|(used, option)| used.then_some(option))
+        .collect()
+}
+
+/// Order of the entries of `--sort`, or by citation key for `--reproducible`
+fn sort_order(args: &ExportArgs) -> Option<SortOrder> {
+    args.sort
+        .clone()
+        .or(args.reproducible.then_some(SortOrder::Key))
+}
+
+/// Time of the `{timestamp}` of `--header-template` with `--reproducible`, taken from `SOURCE_DATE_EPOCH`
+/// like in other reproducible builds, because the time of the export would change the file every time
+fn reproducible_header_timestamp(args: &ExportArgs) -> anyhow::Result<Option<DateTime<Utc>>> {
+    let has_timestamp = args
+        .header_template
+        .as_ref()
+        .is_some_and(|template| template.contains("{timestamp}"));
+    if !args.reproducible || !has_timestamp {
+        return Ok(None);
+    }
+    match std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.trim().parse().ok())
+        .and_then(|epoch| DateTime::from_timestamp(epoch, 0))
+    {
+        Some(timestamp) => Ok(Some(timestamp)),
+        None => anyhow::bail!(
+            "{{timestamp}} in --header-template changes with every export and can't be used with --reproducible. Set SOURCE_DATE_EPOCH to the time to write instead"
+        ),
+    }
+}
+
+/// Tag filters of the arguments, like the `tag` parameter of the Zotero API
+fn tag_filters(args: &ExportArgs) -> Vec<String> {
+    args.tags
+        .iter()
+        .cloned()
+        .chain(args.exclude_tags.iter().map(|tag| format!("-{}", tag)))
+        .collect()
+}
+
+/// Key of the collection with the given key, name or path, which has to be unique in the library
+async fn resolve_collection(
+    client: &impl ZoteroClient,
+    key_or_name: &str,
+) -> anyhow::Result<String> {
+    let collections = match client
+        .fetch_collections(None)
+        .await
+        .with_context(|| "Error while fetching the collections of the library.")?
+    {
+        FetchDataResponse::Updated { data, .. } => data
+            .into_iter()
+            .map(|collection| (collection.key.clone(), collection))
+            .collect(),
+        FetchDataResponse::UpToDate => Default::default(),
+    };
+    match find_collections(&collections, key_or_name).as_slice() {
+        [key] => {
+            log::info!(
+                "Exporting collection '{}' ({})",
+                collection_path(&collections, key).unwrap_or_default(),
+                key
+            );
+            Ok(key.clone())
+        }
+        [] => anyhow::bail!("No collection '{}' in the library", key_or_name),
+        keys => anyhow::bail!(
+            "Several collections are named '{}', use the full path or the key of one of them: {}",
+            key_or_name,
+            keys.iter()
+                .map(|key| format!(
+                    "{} ({})",
+                    collection_path(&collections, key).unwrap_or_default(),
+                    key
+                ))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+/// Names of the Zotero schema that the config can refer to. The creator types need a request for every item type,
+/// so they are only fetched if the config maps any.
+async fn fetch_schema(client: &impl ZoteroClient, config: &Config) -> Result<Schema, ApiError> {
+    let item_types = client.fetch_item_types().await?;
+    let item_fields = client.fetch_item_fields().await?;
+    let mut creator_types: Vec<String> = Vec::new();
+    if config.uses_creator_types() {
+        // notes, attachments and annotations have no creators
+        let with_creators = item_types.iter().filter(|item_type| {
+            !["note", "attachment", "annotation"].contains(&item_type.as_str())
+        });
+        for item_type in with_creators {
+            for creator_type in client.fetch_creator_types(item_type).await? {
+                if !creator_types.contains(&creator_type) {
+                    creator_types.push(creator_type);
+                }
+            }
+        }
+    }
+    Ok(Schema {
+        item_types,
+        item_fields,
+        creator_types,
+    })
+}
+
+/// Filters of the items query for the saved search with the given key or name, which has to be unique in the library
+async fn resolve_saved_search(
+    client: &impl ZoteroClient,
+    key_or_name: &str,
+) -> anyhow::Result<SearchFilters> {
+    let searches = client
+        .fetch_searches()
+        .await
+        .with_context(|| "Error while fetching the saved searches of the library.")?;
+    let matching: Vec<&SavedSearch> = searches
+        .iter()
+        .filter(|search| search.key == key_or_name || search.data.name == key_or_name)
+        .collect();
+    let search = match matching.as_slice() {
+        [search] => search,
+        [] => anyhow::bail!("No saved search '{}' in the library", key_or_name),
+        searches => anyhow::bail!(
+            "Several saved searches are named '{}', use the key of one of them: {}",
+            key_or_name,
+            searches
+                .iter()
+                .map(|search| search.key.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    let filters = search.filters();
+    for condition in &filters.unsupported {
+        log::warn!(
+            "Condition \"{}\" of saved search '{}' can't be used to filter the export, leaving it out",
+            condition,
+            search.data.name
+        );
+    }
+    log::info!(
+        "Exporting saved search '{}' ({})",
+        search.data.name,
+        search.key
+    );
+    Ok(filters)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::args::AccountArgs;
+    use clap::Parser;
+    use pretty_assertions::assert_eq;
+
+    fn export_args(args: &[&str]) -> ExportArgs {
+        AccountArgs::try_parse_from(args).unwrap().export
+    }
+
+    #[test]
+    fn options_only_for_the_export_file() {
+        let args = export_args(&[
+            "--file",
+            "refs.bib",
+            "--snapshots",
+            "--keys-file",
+            "keys.txt",
+        ]);
+        assert_eq!(options_not_for_targets(&args), Vec::<&str>::new());
+        let args = export_args(&[
+            "--file",
+            "refs.bib",
+            "--target",
+            "refs.json=json",
+            "--snapshots",
+            "--keys-file",
+            "keys.txt",
+            "--sort",
+            "key",
+        ]);
+        assert_eq!(
+            options_not_for_targets(&args),
+            vec!["--snapshots", "--keys-file"]
+        );
+    }
+}
@@ -0,0 +1,205 @@
+mod args;
+mod client;
+mod commands;
+mod exports;
+
+use crate::cli::args::{Args, Command, ExportArgs};
+use crate::cli::commands::{
+    run_archive, run_bench, run_check_key, run_cite, run_collections, run_daemon, run_groups,
+    run_lint, run_open, run_pick, run_reconcile, run_search, run_stats, run_verify,
+};
+#[cfg(feature = "keyring")]
+use crate::cli::commands::{run_login, run_logout};
+use crate::cli::exports::run_export;
+use crate::config::{Config, PROJECT_CONFIG_FILE};
+use crate::dashboard::LogWriter;
+use crate::exit::Exit;
+use crate::export::{EventSender, ExportEvent};
+use crate::zotero_api::ExportFormat;
+use anyhow::Context;
+use clap::{CommandFactory, Parser};
+use std::ffi::OsString;
+use std::path::PathBuf;
+use tokio::sync::mpsc;
+
+pub async fn run() -> anyhow::Result<Exit> {
+    let (command_line, project_config) = with_project_args(std::env::args_os().collect())?;
+    let args = Args::parse_from(command_line);
+    if let Command::Watch(export) = &args.command
+        && (export.detailed_exit_codes || export.fail_on_warnings)
+    {
+        Args::command()
+            .error(
+                clap::error::ErrorKind::ArgumentConflict,
+                "--detailed-exit-codes and --fail-on-warnings can't be used with watch",
+            )
+            .exit();
+    }
+    let mut logger = env_logger::Builder::new();
+    if args
+        .command
+        .export_args()
+        .is_some_and(|export| export.fail_on_warnings)
+    {
+        // the warnings that fail the export have to be visible, unless RUST_LOG says otherwise
+        logger.filter_level(log::LevelFilter::Warn);
+    }
+    logger.parse_default_env();
+    let dashboard_events = match args.command.export_args() {
+        Some(export_args) if export_args.dashboard => {
+            let (sender, receiver) = mpsc::unbounded_channel();
+            logger.target(env_logger::Target::Pipe(Box::new(LogWriter::new(
+                sender.clone(),
+            ))));
+            Some((sender, receiver))
+        }
+        _ => None,
+    };
+    let warnings = crate::warnings::init(logger.build());
+    if let Some(path) = project_config {
+        log::info!("Using project config file '{}'", path.display());
+    }
+    if args.command.format() == Some(&ExportFormat::Json) {
+        anyhow::bail!("The json format is only supported by exports, use biblatex or bibtex");
+    }
+    let result = match args.command {
+        Command::Rollback { file, to } => crate::export::rollback(&file, to)
+            .await
+            .map(|_| ())
+            .with_context(|| "Error during rollback."),
+        Command::Stats { api_key, history } => run_stats(api_key, history).await,
+        Command::Collections { api_key } => run_collections(api_key).await,
+        Command::Groups { api_key } => run_groups(api_key).await,
+        Command::CheckKey { api_key, group } => run_check_key(api_key, group).await,
+        Command::Bench {
+            api_key,
+            format,
+            sample,
+        } => run_bench(api_key, format, sample).await,
+        Command::Archive {
+            api_key,
+            output,
+            format,
+            attachments,
+            reproducible,
+        } => run_archive(api_key, output, format, attachments, reproducible).await,
+        Command::Lint { api_key, format } => run_lint(api_key, format).await,
+        Command::Reconcile {
+            api_key,
+            bib,
+            format,
+        } => run_reconcile(api_key, bib, format).await,
+        Command::Search {
+            api_key,
+            query,
+            format,
+            select,
+            emit,
+            clipboard,
+            children,
+        } => run_search(api_key, query, format, select, emit, clipboard, children).await,
+        Command::Pick {
+            api_key,
+            file,
+            format,
+            clipboard,
+        } => run_pick(api_key, file, format, clipboard).await,
+        Command::Verify {
+            bib,
+            paths,
+            deny_unused,
+        } => run_verify(bib, paths, deny_unused),
+        Command::Cite {
+            api_key,
+            citekey,
+            style,
+            format,
+            clipboard,
+        } => run_cite(api_key, citekey, style, format, clipboard).await,
+        Command::Open {
+            api_key,
+            citekey,
+            format,
+            local,
+        } => run_open(api_key, citekey, format, local).await,
+        Command::Daemon {
+            config,
+            max_parallel,
+        } => run_daemon(config, max_parallel as usize).await,
+        Command::Completions { shell } => {
+            let mut command = Args::command();
+            let name = command.get_name().to_owned();
+            clap_complete::generate(shell, &mut command, name, &mut std::io::stdout());
+            Ok(())
+        }
+        #[cfg(feature = "keyring")]
+        Command::Login { api_key } => run_login(api_key).await,
+        #[cfg(feature = "keyring")]
+        Command::Logout => run_logout(),
+        Command::Export(export_args) => {
+            return run_export_command(*export_args, dashboard_events, &warnings).await;
+        }
+        Command::Watch(export_args) => {
+            let export_args = ExportArgs {
+                sync: true,
+                ..*export_args
+            };
+            return run_export_command(export_args, dashboard_events, &warnings).await;
+        }
+    };
+    result.map(|_| Exit::Success)
+}
+
+async fn run_export_command(
+    args: ExportArgs,
+    dashboard_events: Option<(EventSender, mpsc::UnboundedReceiver<ExportEvent>)>,
+    warnings: &crate::warnings::WarningCounter,
+) -> anyhow::Result<Exit> {
+    let detailed = args.detailed_exit_codes;
+    let fail_on_warnings = args.fail_on_warnings;
+    let success = run_export(args, dashboard_events).await?;
+    if fail_on_warnings {
+        warnings.check()?;
+    }
+    Ok(Exit::for_export(success, detailed))
+}
+
+/// Insert the `export` subcommand into a command line that gives the export arguments without it
+fn with_export_subcommand(mut command_line: Vec<OsString>) -> Vec<OsString> {
+    let needs_subcommand = match command_line.get(1).and_then(|arg| arg.to_str()) {
+        None | Some("-h" | "--help" | "-V" | "--version" | "help") => false,
+        Some(arg) => Args::command().find_subcommand(arg).is_none(),
+    };
+    if needs_subcommand {
+        command_line.insert(1, "export".into());
+    }
+    command_line
+}
+
+/// Insert the `args` of the project config file in front of the export arguments of the command line,
+/// so that the command line overrides them. Also returns the path of the project config file, if any.
+fn with_project_args(
+    command_line: Vec<OsString>,
+) -> anyhow::Result<(Vec<OsString>, Option<PathBuf>)> {
+    let command_line = with_export_subcommand(command_line);
+    // the project args go behind the name of an export subcommand
+    if !matches!(
+        command_line.get(1).and_then(|arg| arg.to_str()),
+        Some("export" | "watch")
+    ) {
+        return Ok((command_line, None));
+    }
+    let working_dir = std::env::current_dir()
+        .with_context(|| format!("Error searching for {}", PROJECT_CONFIG_FILE))?;
+    let Some(path) = Config::discover(&working_dir) else {
+        return Ok((command_line, None));
+    };
+    let config = Config::load(&path)?;
+    let mut command_line = command_line.into_iter();
+    let mut args: Vec<OsString> = command_line.by_ref().take(2).collect();
+    args.push("--config".into());
+    args.push(path.clone().into());
+    args.extend(config.args.into_iter().map(OsString::from));
+    args.extend(command_line);
+    Ok((args, Some(path)))
+}
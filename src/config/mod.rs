@@ -0,0 +1,447 @@
+use crate::export::KeyDecision;
+use crate::zotero_api::ExportFormat;
+use crate::zotero_api::item::Schema;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Name of the config file of a project, which is found in the working directory or any of its parents
+pub const PROJECT_CONFIG_FILE: &str = ".zotexon.toml";
+
+/// Settings that are too detailed for command line arguments, read from a TOML file.
+///
+/// ```toml
+/// [biblatex.entry-types]
+/// preprint = "online"
+///
+/// [bibtex.entry-types]
+/// preprint = "unpublished"
+///
+/// [biblatex.fields]
+/// archiveLocation = "location"
+///
+/// [biblatex.creator-types]
+/// contributor = "editora"
+///
+/// [keywords]
+/// exclude-automatic = true
+/// strip-prefixes = ["#"]
+///
+/// [[accounts]]
+/// name = "lab"
+/// args = ["--api-key", "...", "--file", "lab.bib"]
+///
+/// [key-collisions]
+/// ITEM0002 = { rename = "smith_2020b" }
+///
+/// [digest]
+/// interval = "weekly"
+/// webhook = "https://chat.example.org/hooks/..."
+/// email = ["lab@example.org"]
+///
+/// [[documents]]
+/// name = "chapter-1"
+/// file = "chapters/1/references.bib"
+/// collections = ["Book/Chapter 1"]
+/// tags = ["ch1"]
+/// ```
+///
+/// A project config (`.zotexon.toml`) can also set default export arguments:
+///
+/// ```toml
+/// args = ["--file", "references.bib", "--format", "bibtex"]
+/// ```
+#[derive(Deserialize, Default, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    /// Export arguments that are used as if they were given before those of the command line
+    #[serde(default)]
+    pub args: Vec<String>,
+    #[serde(default)]
+    pub biblatex: FormatConfig,
+    #[serde(default)]
+    pub bibtex: FormatConfig,
+    #[serde(default)]
+    pub keywords: KeywordsConfig,
+    /// Exports that the `daemon` command keeps in sync
+    #[serde(default)]
+    pub accounts: Vec<AccountConfig>,
+    /// Decisions for items whose citation key collides with others, by item key
+    #[serde(default)]
+    pub key_collisions: BTreeMap<String, KeyDecision>,
+    /// Periodic summary of the changes of the accounts of the `daemon` command
+    #[serde(default)]
+    pub digest: Option<DigestConfig>,
+    /// Documents of a project that get a filtered bibliography of their own with every export
+    #[serde(default)]
+    pub documents: Vec<DocumentConfig>,
+}
+
+/// Settings that only apply to one export format
+#[derive(Deserialize, Default, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct FormatConfig {
+    /// Entry type by Zotero item type, overriding the mapping of Zotero's translator
+    #[serde(default)]
+    pub entry_types: BTreeMap<String, String>,
+    /// Entry field by Zotero item field, for fields that Zotero's translator leaves out or writes to another field
+    #[serde(default)]
+    pub fields: BTreeMap<String, String>,
+    /// Entry field by Zotero creator type, for creators that Zotero's translator leaves out, e.g. contributors
+    #[serde(default)]
+    pub creator_types: BTreeMap<String, String>,
+}
+
+impl FormatConfig {
+    /// Whether any field or creator type of Zotero is mapped to an entry field
+    pub fn maps_fields(&self) -> bool {
+        !self.fields.is_empty() || !self.creator_types.is_empty()
+    }
+}
+
+/// Rules for exporting tags as keywords
+#[derive(Deserialize, Default, Debug, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct KeywordsConfig {
+    /// Skip tags that Zotero added automatically, e.g. subjects from imported metadata
+    #[serde(default)]
+    pub exclude_automatic: bool,
+    /// Only export tags that were assigned a color in Zotero
+    #[serde(default)]
+    pub colored_only: bool,
+    /// Prefixes removed from tags, e.g. `#`
+    #[serde(default)]
+    pub strip_prefixes: Vec<String>,
+    /// Only export tags matching one of these patterns. A trailing `*` matches any suffix.
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Skip tags matching one of these patterns. A trailing `*` matches any suffix.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Keyword by tag, for tags that shall be exported with a different name
+    #[serde(default)]
+    pub map: BTreeMap<String, String>,
+}
+
+/// An export run by the `daemon` command, e.g. for another API key or library
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct AccountConfig {
+    /// Name of the account in log messages
+    pub name: String,
+    /// Arguments of the export, as they would be given on the command line
+    pub args: Vec<String>,
+}
+
+/// Where and how often the `daemon` command sends the digest of the changed items
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct DigestConfig {
+    pub interval: DigestInterval,
+    /// URL that the digest is posted to as JSON, with the summary in `text` for chat services
+    #[serde(default)]
+    pub webhook: Option<String>,
+    /// Recipients of the digest, sent with the local `sendmail` command
+    #[serde(default)]
+    pub email: Vec<String>,
+}
+
+/// A document of a project, e.g. a chapter of a book, with the entries of some collections or tags.
+/// Without collections and tags, the document gets all entries.
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct DocumentConfig {
+    /// Name of the document in log messages
+    pub name: String,
+    /// Bibliography file of the document
+    pub file: PathBuf,
+    /// Paths of the collections whose items are included, e.g. `Book/Chapter 1`, including their subcollections
+    #[serde(default)]
+    pub collections: Vec<String>,
+    /// Tags whose items are included
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Account of the `daemon` command whose export the document is filtered from. Defaults to every export using the config.
+    #[serde(default)]
+    pub account: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum DigestInterval {
+    Daily,
+    Weekly,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let text = std::fs::read_to_string(path).map_err(|io_error| ConfigError::FileError {
+            path: path.display().to_string(),
+            io_error,
+        })?;
+        toml::from_str(&text).map_err(|toml_error| ConfigError::Parse {
+            path: path.display().to_string(),
+            toml_error,
+        })
+    }
+
+    /// Find the project config file in the given directory or the closest of its parents that has one
+    pub fn discover(dir: &Path) -> Option<PathBuf> {
+        dir.ancestors()
+            .map(|dir| dir.join(PROJECT_CONFIG_FILE))
+            .find(|path| path.is_file())
+    }
+
+    /// Settings for the given export format, the JSON export has none
+    pub fn format(&self, format: &ExportFormat) -> Option<&FormatConfig> {
+        match format {
+            ExportFormat::Biblatex => Some(&self.biblatex),
+            ExportFormat::Bibtex => Some(&self.bibtex),
+            ExportFormat::Json => None,
+        }
+    }
+
+    /// Whether any setting refers to names of the Zotero schema, which have to be checked against it
+    pub fn uses_schema(&self) -> bool {
+        [&self.biblatex, &self.bibtex]
+            .iter()
+            .any(|config| !config.entry_types.is_empty() || config.maps_fields())
+    }
+
+    /// Whether any setting refers to Zotero creator types, whose check needs a request for every item type
+    pub fn uses_creator_types(&self) -> bool {
+        !self.biblatex.creator_types.is_empty() || !self.bibtex.creator_types.is_empty()
+    }
+
+    /// The documents filtered from the export of the given `daemon` account, or of a single export
+    pub fn documents_of(&self, account: Option<&str>) -> Vec<DocumentConfig> {
+        self.documents
+            .iter()
+            .filter(|document| document.account.is_none() || document.account.as_deref() == account)
+            .cloned()
+            .collect()
+    }
+
+    /// Descriptions of the settings that refer to item types, item fields or creator types that are not in the Zotero schema
+    pub fn schema_issues(&self, schema: &Schema) -> Vec<String> {
+        [("biblatex", &self.biblatex), ("bibtex", &self.bibtex)]
+            .into_iter()
+            .flat_map(|(format, config)| {
+                [
+                    (
+                        &config.entry_types,
+                        &schema.item_types,
+                        "item type",
+                        "entry-types",
+                    ),
+                    (&config.fields, &schema.item_fields, "item field", "fields"),
+                    (
+                        &config.creator_types,
+                        &schema.creator_types,
+                        "creator type",
+                        "creator-types",
+                    ),
+                ]
+                .into_iter()
+                .flat_map(move |(settings, names, kind, table)| {
+                    settings
+                        .keys()
+                        .filter(|name| !names.contains(name))
+                        .map(move |name| {
+                            let mut issue = format!(
+                                "Unknown Zotero {} '{}' in [{}.{}]",
+                                kind, name, format, table
+                            );
+                            if let Some(similar) = closest(name, names) {
+                                issue.push_str(&format!(", did you mean '{}'?", similar));
+                            }
+                            issue
+                        })
+                })
+            })
+            .collect()
+    }
+}
+
+/// The candidate that is most similar to the name, if any is similar enough to be a likely typo
+fn closest<'a>(name: &str, candidates: &'a [String]) -> Option<&'a str> {
+    candidates
+        .iter()
+        .map(|candidate| {
+            (
+                edit_distance(&name.to_lowercase(), &candidate.to_lowercase()),
+                candidate,
+            )
+        })
+        .filter(|(distance, _)| *distance <= 2)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate.as_str())
+}
+
+/// Number of inserted, removed and replaced characters to turn one text into the other
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let replace = previous[j] + usize::from(a_char != *b_char);
+            current.push(replace.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ConfigError {
+    #[error("Error reading config file '{path}'")]
+    FileError {
+        path: String,
+        #[source]
+        io_error: std::io::Error,
+    },
+    #[error("Invalid config file '{path}'")]
+    Parse {
+        path: String,
+        #[source]
+        toml_error: toml::de::Error,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parse_config() {
+        let config: Config = toml::from_str(
+            r#"
+            [biblatex.entry-types]
+            preprint = "online"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.format(&ExportFormat::Biblatex).unwrap().entry_types,
+            BTreeMap::from([("preprint".to_owned(), "online".to_owned())])
+        );
+        assert_eq!(
+            config.format(&ExportFormat::Bibtex),
+            Some(&FormatConfig::default())
+        );
+        assert_eq!(config.format(&ExportFormat::Json), None);
+        assert!(toml::from_str::<Config>("[biblatex]\nentry-typos = {}").is_err());
+    }
+
+    #[test]
+    fn find_unknown_schema_names() {
+        let config: Config = toml::from_str(
+            r#"
+            [biblatex.entry-types]
+            preprint = "online"
+            journalArtical = "article"
+
+            [biblatex.fields]
+            archiveLocation = "location"
+            archiveLoc = "library"
+
+            [biblatex.creator-types]
+            contributer = "editora"
+
+            [bibtex.entry-types]
+            podcast = "misc"
+            "#,
+        )
+        .unwrap();
+        let schema = Schema {
+            item_types: ["preprint", "journalArticle", "book"]
+                .map(str::to_owned)
+                .to_vec(),
+            item_fields: ["archiveLocation", "title"].map(str::to_owned).to_vec(),
+            creator_types: ["author", "contributor"].map(str::to_owned).to_vec(),
+        };
+        assert_eq!(
+            config.schema_issues(&schema),
+            vec![
+                "Unknown Zotero item type 'journalArtical' in [biblatex.entry-types], did you mean 'journalArticle'?",
+                "Unknown Zotero item field 'archiveLoc' in [biblatex.fields]",
+                "Unknown Zotero creator type 'contributer' in [biblatex.creator-types], did you mean 'contributor'?",
+                "Unknown Zotero item type 'podcast' in [bibtex.entry-types]",
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_accounts() {
+        let config: Config = toml::from_str(
+            r#"
+            [[accounts]]
+            name = "personal"
+            args = ["--api-key", "KEY1", "--file", "personal.bib"]
+
+            [[accounts]]
+            name = "lab"
+            args = ["--api-key", "KEY2", "--file", "lab.bib", "--format", "bibtex"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config
+                .accounts
+                .iter()
+                .map(|a| a.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["personal", "lab"]
+        );
+        assert_eq!(config.accounts[1].args.len(), 6);
+    }
+
+    #[test]
+    fn documents_of_account() {
+        let config: Config = toml::from_str(
+            r#"
+            [[documents]]
+            name = "chapter-1"
+            file = "chapters/1/references.bib"
+            collections = ["Book/Chapter 1"]
+
+            [[documents]]
+            name = "talk"
+            file = "talk/references.bib"
+            tags = ["talk"]
+            account = "lab"
+            "#,
+        )
+        .unwrap();
+        let names = |account| {
+            config
+                .documents_of(account)
+                .into_iter()
+                .map(|document| document.name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(None), vec!["chapter-1"]);
+        assert_eq!(names(Some("personal")), vec!["chapter-1"]);
+        assert_eq!(names(Some("lab")), vec!["chapter-1", "talk"]);
+    }
+
+    #[test]
+    fn parse_project_args() {
+        let config: Config = toml::from_str(
+            r#"
+            args = ["--file", "paper.bib", "--format", "bibtex"]
+
+            [keywords]
+            colored-only = true
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.args,
+            vec!["--file", "paper.bib", "--format", "bibtex"]
+        );
+        assert!(config.keywords.colored_only);
+    }
+}
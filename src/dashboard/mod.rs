@@ -0,0 +1,312 @@
+use crate::export::{EventSender, ExportEvent};
+use chrono::{DateTime, Local};
+use ratatui::Frame;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, Paragraph};
+use std::collections::VecDeque;
+use std::io::Write;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+/// Number of entries kept in the lists of the dashboard
+const HISTORY: usize = 100;
+
+/// Interval in which the dashboard is redrawn and checked for key presses
+const TICK: Duration = Duration::from_millis(250);
+
+#[derive(Debug, Clone, PartialEq)]
+enum Connection {
+    Connecting,
+    Connected,
+    Disconnected(String),
+}
+
+/// Everything the dashboard shows, updated from the export events
+#[derive(Debug, PartialEq)]
+pub struct DashboardState {
+    connection: Connection,
+    exporting: bool,
+    last_export: Option<(DateTime<Local>, u64)>,
+    /// Notified changes and exports, the latest first
+    activity: VecDeque<(DateTime<Local>, String)>,
+    /// Errors and log lines, the latest first
+    messages: VecDeque<(DateTime<Local>, String)>,
+}
+
+impl DashboardState {
+    pub fn new() -> Self {
+        Self {
+            connection: Connection::Connecting,
+            exporting: false,
+            last_export: None,
+            activity: VecDeque::new(),
+            messages: VecDeque::new(),
+        }
+    }
+
+    pub fn apply(&mut self, event: ExportEvent, now: DateTime<Local>) {
+        match event {
+            ExportEvent::Connected => self.connection = Connection::Connected,
+            ExportEvent::Disconnected(reason) => {
+                self.push_message(now, format!("Connection lost: {}", reason));
+                self.connection = Connection::Disconnected(reason);
+            }
+            ExportEvent::LibraryChanged => self.push_activity(now, "Library changed".to_owned()),
+            ExportEvent::ExportStarted => self.exporting = true,
+            ExportEvent::Exported { version, .. } => {
+                self.exporting = false;
+                self.last_export = Some((now, version));
+                self.push_activity(now, format!("Exported version {}", version));
+            }
+            ExportEvent::UpToDate => {
+                self.exporting = false;
+                self.push_activity(now, "Up to date".to_owned());
+            }
+            ExportEvent::ExportFailed(error) => {
+                self.exporting = false;
+                self.push_message(now, format!("Export failed: {}", error));
+            }
+            ExportEvent::Log(line) => self.push_message(now, line),
+        }
+    }
+
+    /// State of the connection for change notifications: `connecting`, `connected` or `disconnected`
+    pub fn connection(&self) -> &'static str {
+        match &self.connection {
+            Connection::Connecting => "connecting",
+            Connection::Connected => "connected",
+            Connection::Disconnected(_) => "disconnected",
+        }
+    }
+
+    pub fn is_exporting(&self) -> bool {
+        self.exporting
+    }
+
+    /// Time and library version of the last export
+    pub fn last_export(&self) -> Option<(DateTime<Local>, u64)> {
+        self.last_export
+    }
+
+    /// Notified changes and exports, the latest first
+    pub fn activity(&self) -> &VecDeque<(DateTime<Local>, String)> {
+        &self.activity
+    }
+
+    /// Errors and log lines, the latest first
+    pub fn messages(&self) -> &VecDeque<(DateTime<Local>, String)> {
+        &self.messages
+    }
+
+    fn push_activity(&mut self, now: DateTime<Local>, text: String) {
+        self.activity.push_front((now, text));
+        self.activity.truncate(HISTORY);
+    }
+
+    fn push_message(&mut self, now: DateTime<Local>, text: String) {
+        self.messages.push_front((now, text));
+        self.messages.truncate(HISTORY);
+    }
+
+    /// Lines of the status box at the top
+    fn status(&self) -> Vec<Line<'_>> {
+        let color = match &self.connection {
+            Connection::Connecting => Color::Yellow,
+            Connection::Connected => Color::Green,
+            Connection::Disconnected(_) => Color::Red,
+        };
+        let connection = Span::styled(self.connection(), Style::new().fg(color));
+        let last_export = match &self.last_export {
+            Some((time, version)) => format!("{} (version {})", time.format("%H:%M:%S"), version),
+            None => "none yet".to_owned(),
+        };
+        vec![
+            Line::from(vec![Span::raw("Connection:  "), connection]),
+            Line::from(format!("Last export: {}", last_export)),
+            Line::from(format!(
+                "State:       {}",
+                if self.exporting { "exporting" } else { "idle" }
+            )),
+        ]
+    }
+
+    fn render(&self, frame: &mut Frame) {
+        let [status, activity, messages, help] = Layout::vertical([
+            Constraint::Length(5),
+            Constraint::Fill(1),
+            Constraint::Fill(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        frame.render_widget(
+            Paragraph::new(self.status()).block(Block::bordered().title(" zotexon ")),
+            status,
+        );
+        frame.render_widget(
+            List::new(list_items(&self.activity))
+                .block(Block::bordered().title(" Recent changes ")),
+            activity,
+        );
+        frame.render_widget(
+            List::new(list_items(&self.messages))
+                .style(Style::new().fg(Color::Red))
+                .block(Block::bordered().title(" Errors and messages ")),
+            messages,
+        );
+        frame.render_widget(Paragraph::new("Press q to quit"), help);
+    }
+}
+
+fn list_items(entries: &VecDeque<(DateTime<Local>, String)>) -> Vec<ListItem<'_>> {
+    entries
+        .iter()
+        .map(|(time, text)| ListItem::new(format!("{}  {}", time.format("%H:%M:%S"), text)))
+        .collect()
+}
+
+/// Show the dashboard until it is cancelled or the user quits it, which cancels the token
+pub async fn run(
+    mut events: mpsc::UnboundedReceiver<ExportEvent>,
+    cancellation_token: CancellationToken,
+) -> std::io::Result<()> {
+    let mut terminal = ratatui::try_init()?;
+    let mut state = DashboardState::new();
+    let mut tick = tokio::time::interval(TICK);
+    let result = loop {
+        tokio::select! {
+            _ = cancellation_token.cancelled() => break Ok(()),
+            Some(event) = events.recv() => state.apply(event, Local::now()),
+            _ = tick.tick() => {
+                if let Err(e) = terminal.draw(|frame| state.render(frame)) {
+                    break Err(e);
+                }
+                match quit_requested() {
+                    Ok(true) => {
+                        cancellation_token.cancel();
+                        break Ok(());
+                    }
+                    Ok(false) => {}
+                    Err(e) => break Err(e),
+                }
+            }
+        }
+    };
+    ratatui::try_restore()?;
+    result
+}
+
+/// Whether `q`, `Esc` or `Ctrl+C` was pressed since the last check (raw mode doesn't send signals)
+fn quit_requested() -> std::io::Result<bool> {
+    while event::poll(Duration::ZERO)? {
+        if let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+            && (matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+                || (key.code == KeyCode::Char('c')
+                    && key.modifiers.contains(KeyModifiers::CONTROL)))
+        {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Target of the logger while the dashboard is shown, which turns every log line into an event
+/// instead of scrolling over the dashboard
+pub struct LogWriter {
+    events: EventSender,
+    line: Vec<u8>,
+}
+
+impl LogWriter {
+    pub fn new(events: EventSender) -> Self {
+        Self {
+            events,
+            line: Vec::new(),
+        }
+    }
+}
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        for byte in buf {
+            if *byte == b'\n' {
+                let line = String::from_utf8_lossy(&self.line).into_owned();
+                let _ = self.events.send(ExportEvent::Log(line));
+                self.line.clear();
+            } else {
+                self.line.push(*byte);
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn apply_events() {
+        let now = Local.with_ymd_and_hms(2025, 10, 1, 12, 30, 0).unwrap();
+        let mut state = DashboardState::new();
+        for event in [
+            ExportEvent::Connected,
+            ExportEvent::ExportStarted,
+            ExportEvent::Exported {
+                version: 42,
+                entries: 3,
+            },
+            ExportEvent::LibraryChanged,
+            ExportEvent::ExportStarted,
+            ExportEvent::ExportFailed("timeout".to_owned()),
+        ] {
+            state.apply(event, now);
+        }
+        assert_eq!(
+            state.status(),
+            vec![
+                Line::from(vec![
+                    Span::raw("Connection:  "),
+                    Span::styled("connected", Style::new().fg(Color::Green))
+                ]),
+                Line::from("Last export: 12:30:00 (version 42)"),
+                Line::from("State:       idle"),
+            ]
+        );
+        assert_eq!(
+            state
+                .activity
+                .iter()
+                .map(|(_, text)| text.as_str())
+                .collect::<Vec<_>>(),
+            vec!["Library changed", "Exported version 42"]
+        );
+        assert_eq!(
+            state.messages.front().map(|(_, text)| text.as_str()),
+            Some("Export failed: timeout")
+        );
+    }
+
+    #[test]
+    fn log_lines_as_events() {
+        let (events, mut receiver) = mpsc::unbounded_channel();
+        let mut writer = LogWriter::new(events);
+        write!(writer, "[ERROR] first").unwrap();
+        writeln!(writer, " part").unwrap();
+        assert_eq!(
+            receiver.try_recv(),
+            Ok(ExportEvent::Log("[ERROR] first part".to_owned()))
+        );
+        assert!(receiver.try_recv().is_err());
+    }
+}
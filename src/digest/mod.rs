@@ -0,0 +1,336 @@
+use crate::config::{DigestConfig, DigestInterval};
+use crate::export::document::ExportDocument;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio_util::sync::CancellationToken;
+
+impl DigestInterval {
+    fn period(&self) -> Duration {
+        match self {
+            DigestInterval::Daily => Duration::from_secs(24 * 60 * 60),
+            DigestInterval::Weekly => Duration::from_secs(7 * 24 * 60 * 60),
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChangeKind {
+    Added,
+    Modified,
+    Removed,
+}
+
+/// A change of an item between two exports
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Change {
+    pub account: String,
+    pub kind: ChangeKind,
+    pub item_key: String,
+    pub citekey: String,
+    pub title: String,
+    /// User of a group library who made the change, unknown for removed items
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct ItemState {
+    version: u64,
+    citekey: String,
+    title: String,
+    /// User who last modified the item, for items of group libraries
+    user: Option<String>,
+}
+
+#[derive(Default)]
+struct DigestState {
+    /// Items of the last export by item key, per account
+    items: BTreeMap<String, BTreeMap<String, ItemState>>,
+    /// Changes since the last digest
+    changes: Vec<Change>,
+}
+
+/// Collects the added, modified and removed items of the exports of the daemon, and sends them
+/// periodically as digest to a webhook or by email, so that members of a group library can follow its activity.
+///
+/// The first export of an account after the start only records the items, changes are reported from then on.
+#[derive(Clone)]
+pub struct Digest {
+    config: DigestConfig,
+    state: Arc<Mutex<DigestState>>,
+}
+
+/// Records the exports of one account for the digest
+#[derive(Clone)]
+pub struct DigestRecorder {
+    digest: Digest,
+    account: String,
+}
+
+impl DigestRecorder {
+    /// Recorder for another library of the same account, e.g. a group
+    pub fn for_library(&self, library: &str) -> Self {
+        Self {
+            digest: self.digest.clone(),
+            account: format!("{} ({})", self.account, library),
+        }
+    }
+
+    pub fn record(&self, document: &ExportDocument) {
+        let items: BTreeMap<String, ItemState> = document
+            .entries
+            .iter()
+            .map(|entry| {
+                (
+                    entry.item.key.clone(),
+                    ItemState {
+                        version: entry.item.version,
+                        citekey: entry.entry.key.clone(),
+                        title: entry.item.data.title.clone(),
+                        user: entry.item.meta.last_modified_by().map(str::to_owned),
+                    },
+                )
+            })
+            .collect();
+        let mut state = self.digest.state.lock().unwrap();
+        if let Some(previous) = state.items.get(&self.account) {
+            let changes = changes(&self.account, previous, &items);
+            state.changes.extend(changes);
+        }
+        state.items.insert(self.account.clone(), items);
+    }
+}
+
+impl Digest {
+    pub fn new(config: DigestConfig) -> Self {
+        Self {
+            config,
+            state: Arc::default(),
+        }
+    }
+
+    pub fn recorder(&self, account: &str) -> DigestRecorder {
+        DigestRecorder {
+            digest: self.clone(),
+            account: account.to_owned(),
+        }
+    }
+
+    /// Send the collected changes in every interval, until cancelled
+    pub async fn run(self, cancellation_token: CancellationToken) {
+        let period = self.config.interval.period();
+        let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = cancellation_token.cancelled() => return,
+            }
+            let changes = std::mem::take(&mut self.state.lock().unwrap().changes);
+            if changes.is_empty() {
+                log::debug!("No changes for the digest");
+                continue;
+            }
+            let report = DigestReport { changes };
+            if let Err(e) = self.send(&report).await {
+                log::error!("Could not send the digest: {:?}", e);
+            }
+        }
+    }
+
+    async fn send(&self, report: &DigestReport) -> Result<(), DigestError> {
+        if let Some(url) = &self.config.webhook {
+            reqwest::Client::new()
+                .post(url)
+                .json(&serde_json::json!({
+                    // `text` is shown by chat services like Slack or Mattermost
+                    "text": report.to_string(),
+                    "changes": report.changes,
+                }))
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())?;
+            log::info!(
+                "Sent digest of {} changes to the webhook",
+                report.changes.len()
+            );
+        }
+        if !self.config.email.is_empty() {
+            send_email(&self.config.email, &report.subject(), &report.to_string()).await?;
+            log::info!(
+                "Sent digest of {} changes to {}",
+                report.changes.len(),
+                self.config.email.join(", ")
+            );
+        }
+        Ok(())
+    }
+}
+
+fn changes(
+    account: &str,
+    previous: &BTreeMap<String, ItemState>,
+    current: &BTreeMap<String, ItemState>,
+) -> Vec<Change> {
+    let change = |kind, key: &String, item: &ItemState| Change {
+        account: account.to_owned(),
+        kind,
+        item_key: key.clone(),
+        citekey: item.citekey.clone(),
+        title: item.title.clone(),
+        user: match kind {
+            ChangeKind::Removed => None,
+            _ => item.user.clone(),
+        },
+    };
+    let mut changes = Vec::new();
+    for (key, item) in current {
+        match previous.get(key) {
+            None => changes.push(change(ChangeKind::Added, key, item)),
+            Some(old) if old.version != item.version => {
+                changes.push(change(ChangeKind::Modified, key, item))
+            }
+            Some(_) => {}
+        }
+    }
+    for (key, item) in previous {
+        if !current.contains_key(key) {
+            changes.push(change(ChangeKind::Removed, key, item));
+        }
+    }
+    changes
+}
+
+/// Send a plain text email with the local `sendmail` command
+async fn send_email(recipients: &[String], subject: &str, body: &str) -> Result<(), DigestError> {
+    let mut sendmail = tokio::process::Command::new("sendmail")
+        .arg("-t")
+        .stdin(std::process::Stdio::piped())
+        .spawn()
+        .map_err(DigestError::Email)?;
+    if let Some(mut stdin) = sendmail.stdin.take() {
+        let message = format!(
+            "To: {}\nSubject: {}\nContent-Type: text/plain; charset=utf-8\n\n{}",
+            recipients.join(", "),
+            subject,
+            body
+        );
+        stdin
+            .write_all(message.as_bytes())
+            .await
+            .map_err(DigestError::Email)?;
+    }
+    let status = sendmail.wait().await.map_err(DigestError::Email)?;
+    if !status.success() {
+        return Err(DigestError::Sendmail(status));
+    }
+    Ok(())
+}
+
+struct DigestReport {
+    changes: Vec<Change>,
+}
+
+impl DigestReport {
+    fn count(&self, kind: ChangeKind) -> usize {
+        self.changes
+            .iter()
+            .filter(|change| change.kind == kind)
+            .count()
+    }
+
+    fn subject(&self) -> String {
+        format!(
+            "Zotero library changes: {} added, {} modified, {} removed",
+            self.count(ChangeKind::Added),
+            self.count(ChangeKind::Modified),
+            self.count(ChangeKind::Removed)
+        )
+    }
+}
+
+impl Display for DigestReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}", self.subject())?;
+        let mut account = None;
+        for change in &self.changes {
+            if account != Some(&change.account) {
+                writeln!(f, "\n{}:", change.account)?;
+                account = Some(&change.account);
+            }
+            let symbol = match change.kind {
+                ChangeKind::Added => '+',
+                ChangeKind::Modified => '~',
+                ChangeKind::Removed => '-',
+            };
+            write!(f, "  {} {}: {}", symbol, change.citekey, change.title)?;
+            match &change.user {
+                Some(user) => writeln!(f, " (by {})", user)?,
+                None => writeln!(f)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum DigestError {
+    #[error("Error sending the digest to the webhook")]
+    Webhook(#[from] reqwest::Error),
+    #[error("Error running sendmail")]
+    Email(#[source] std::io::Error),
+    #[error("sendmail failed with {0}")]
+    Sendmail(std::process::ExitStatus),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zotero_api::ExportFormat;
+    use crate::zotero_api::item::Item;
+    use pretty_assertions::assert_eq;
+
+    fn document(items: serde_json::Value) -> ExportDocument {
+        let items: Vec<Item> = serde_json::from_value(items).unwrap();
+        ExportDocument::try_from_items(items, &ExportFormat::Biblatex).unwrap()
+    }
+
+    #[test]
+    fn digest_of_changes() {
+        let digest = Digest::new(DigestConfig {
+            interval: DigestInterval::Daily,
+            webhook: None,
+            email: Vec::new(),
+        });
+        let recorder = digest.recorder("lab");
+        recorder.record(&document(serde_json::json!([
+            {"key":"ITEM0001","version":1,"data":{"itemType":"book","title":"Kept"},"biblatex":"@book{kept,\n}"},
+            {"key":"ITEM0002","version":1,"data":{"itemType":"book","title":"Edited"},"biblatex":"@book{edited,\n}"},
+            {"key":"ITEM0003","version":1,"data":{"itemType":"book","title":"Deleted"},"biblatex":"@book{deleted,\n}"}
+        ])));
+        recorder.record(&document(serde_json::json!([
+            {"key":"ITEM0001","version":1,"data":{"itemType":"book","title":"Kept"},"biblatex":"@book{kept,\n}"},
+            {"key":"ITEM0002","version":5,"data":{"itemType":"book","title":"Edited"},"biblatex":"@book{edited,\n}",
+             "meta":{"createdByUser":{"id":1,"username":"ada"},"lastModifiedByUser":{"id":2,"username":"bob"}}},
+            {"key":"ITEM0004","version":5,"data":{"itemType":"book","title":"New"},"biblatex":"@book{new,\n}",
+             "meta":{"createdByUser":{"id":1,"username":"ada"}}}
+        ])));
+        let report = DigestReport {
+            changes: std::mem::take(&mut digest.state.lock().unwrap().changes),
+        };
+        assert_eq!(
+            report.to_string(),
+            "Zotero library changes: 1 added, 1 modified, 1 removed
+
+lab:
+  ~ edited: Edited (by bob)
+  + new: New (by ada)
+  - deleted: Deleted
+"
+        );
+    }
+}
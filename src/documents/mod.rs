@@ -0,0 +1,162 @@
+use crate::bibtex::{self, Block};
+use crate::config::DocumentConfig;
+use crate::export::document::{ExportDocument, ExportEntry};
+use crate::paths::write_if_changed;
+use crate::zotero_api::item::collection_path;
+
+/// Writes a bibliography of its own for every document of a project, e.g. for every chapter of a book,
+/// with the entries of the collections (including their subcollections) or tags of the document.
+pub struct ProjectDocuments {
+    documents: Vec<DocumentConfig>,
+}
+
+impl ProjectDocuments {
+    pub fn new(documents: Vec<DocumentConfig>) -> Self {
+        Self { documents }
+    }
+
+    /// Whether the collections of the library have to be fetched to filter the entries
+    pub fn needs_collections(&self) -> bool {
+        self.documents
+            .iter()
+            .any(|document| !document.collections.is_empty())
+    }
+
+    pub async fn write(&self, document: &ExportDocument) -> Result<(), DocumentsError> {
+        for config in &self.documents {
+            let (entries, content) = render(config, document);
+            if let Some(parent) = config.file.parent() {
+                tokio::fs::create_dir_all(parent)
+                    .await
+                    .map_err(|io_error| file_error(config, io_error))?;
+            }
+            if write_if_changed(&config.file, &content)
+                .await
+                .map_err(|io_error| file_error(config, io_error))?
+            {
+                log::info!(
+                    "Wrote {} entries of document '{}' to '{}'",
+                    entries,
+                    config.name,
+                    config.file.display()
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The filtered bibliography of the document and its number of entries
+fn render(config: &DocumentConfig, document: &ExportDocument) -> (usize, String) {
+    let entries: Vec<Block> = document
+        .entries
+        .iter()
+        .filter(|entry| includes(config, entry, document))
+        .map(|entry| Block::Entry(entry.entry.clone()))
+        .collect();
+    let count = entries.len();
+    let blocks: Vec<Block> = document
+        .preamble
+        .iter()
+        .cloned()
+        .chain(entries)
+        .chain(document.trailer.iter().cloned())
+        .collect();
+    (count, bibtex::write(&blocks))
+}
+
+fn includes(config: &DocumentConfig, entry: &ExportEntry, document: &ExportDocument) -> bool {
+    if config.collections.is_empty() && config.tags.is_empty() {
+        return true;
+    }
+    let data = &entry.item.data;
+    data.tags.iter().any(|tag| config.tags.contains(&tag.tag))
+        || data
+            .collections
+            .iter()
+            .filter_map(|key| collection_path(&document.collections, key))
+            .any(|path| {
+                config.collections.iter().any(|collection| {
+                    path == *collection || path.starts_with(&format!("{}/", collection))
+                })
+            })
+}
+
+fn file_error(config: &DocumentConfig, io_error: std::io::Error) -> DocumentsError {
+    DocumentsError::FileError {
+        document: config.name.clone(),
+        path: config.file.display().to_string(),
+        io_error,
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum DocumentsError {
+    #[error("Error with bibliography '{path}' of document '{document}'")]
+    FileError {
+        document: String,
+        path: String,
+        #[source]
+        io_error: std::io::Error,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zotero_api::ExportFormat;
+    use crate::zotero_api::item::{Collection, Item};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn filter_document_entries() {
+        let items: Vec<Item> = serde_json::from_value(serde_json::json!([
+            {"key":"ITEM0001","version":1,"data":{"itemType":"book","title":"Optics","collections":["C"]},
+             "biblatex":"@book{optics,\n}"},
+            {"key":"ITEM0002","version":1,"data":{"itemType":"book","title":"Lenses","tags":[{"tag":"ch1"}]},
+             "biblatex":"@book{lenses,\n}"},
+            {"key":"ITEM0003","version":1,"data":{"itemType":"book","title":"Mirrors","collections":["B"]},
+             "biblatex":"@book{mirrors,\n}"}
+        ]))
+        .unwrap();
+        let collections: Vec<Collection> = serde_json::from_str(
+            r#"[
+                {"key":"A","version":1,"data":{"name":"Book","parentCollection":false}},
+                {"key":"B","version":1,"data":{"name":"Chapter 1","parentCollection":"A"}},
+                {"key":"C","version":1,"data":{"name":"Figures","parentCollection":"B"}}
+            ]"#,
+        )
+        .unwrap();
+        let mut document = ExportDocument::try_from_items(items, &ExportFormat::Biblatex).unwrap();
+        document.collections = collections
+            .into_iter()
+            .map(|collection| (collection.key.clone(), collection))
+            .collect();
+        let config = |collections: &[&str], tags: &[&str]| DocumentConfig {
+            name: "chapter-1".to_owned(),
+            file: "chapter-1.bib".into(),
+            collections: collections.iter().map(|c| c.to_string()).collect(),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            account: None,
+        };
+        let keys = |config: &DocumentConfig| -> Vec<&str> {
+            document
+                .entries
+                .iter()
+                .filter(|entry| includes(config, entry, &document))
+                .map(|entry| entry.entry.key.as_str())
+                .collect()
+        };
+        assert_eq!(
+            keys(&config(&["Book/Chapter 1"], &[])),
+            vec!["optics", "mirrors"]
+        );
+        assert_eq!(
+            keys(&config(&["Book/Chapter 1/Figures"], &["ch1"])),
+            vec!["optics", "lenses"]
+        );
+        assert_eq!(keys(&config(&["Book/Chapter"], &[])), Vec::<&str>::new());
+        assert_eq!(keys(&config(&[], &[])).len(), 3);
+        assert_eq!(render(&config(&[], &["ch1"]), &document).0, 1);
+    }
+}
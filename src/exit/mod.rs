@@ -0,0 +1,149 @@
+use crate::config::ConfigError;
+use crate::export::{ExportError, ExportSuccess};
+use crate::network::NetworkError;
+use crate::paths::PathError;
+use crate::warnings::WarningsError;
+use crate::zotero_api::ApiError;
+use crate::zotero_api::api_key::ApiKeyError;
+use crate::zotero_api::builder::ClientBuildError;
+use reqwest::StatusCode;
+use std::process::ExitCode;
+
+/// Exit codes of all commands. They are part of the interface, so that wrapper scripts can branch on
+/// the outcome: existing codes must never change their meaning, new ones are only appended.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Exit {
+    /// The command succeeded. For exports: the file is up to date, or changes were written without `--detailed-exit-codes`
+    Success = 0,
+    /// Any error without a more specific code
+    Failure = 1,
+    /// Invalid command line arguments
+    Usage = 2,
+    /// An export wrote changes, only with `--detailed-exit-codes`
+    Changes = 3,
+    /// The API key is invalid, was revoked or lacks access to the library
+    Auth = 4,
+    /// The Zotero API couldn't be reached or failed to answer
+    Network = 5,
+    /// The file wasn't overwritten to protect it, e.g. because it was exported from another library
+    FileConflict = 6,
+    /// A file couldn't be read or written
+    File = 7,
+    /// The exported data or the config failed a check, e.g. `--strict`, `--key-collisions` or `--fail-on-warnings`
+    Validation = 8,
+}
+
+impl Exit {
+    /// Outcome of a successful export
+    pub fn for_export(success: ExportSuccess, detailed: bool) -> Self {
+        match success {
+            ExportSuccess::Changes if detailed => Self::Changes,
+            _ => Self::Success,
+        }
+    }
+
+    /// The most specific code for the first error in the chain that has one
+    pub fn for_error(error: &anyhow::Error) -> Self {
+        error
+            .chain()
+            .find_map(|cause| {
+                if let Some(e) = cause.downcast_ref::<ExportError>() {
+                    for_export_error(e)
+                } else if let Some(e) = cause.downcast_ref::<ApiError>() {
+                    Some(for_api_error(e))
+                } else if let Some(ClientBuildError::Timeout(_)) = cause.downcast_ref() {
+                    Some(Self::Network)
+                } else if cause.is::<ApiKeyError>() {
+                    Some(Self::Auth)
+                } else if cause.is::<ConfigError>()
+                    || cause.is::<NetworkError>()
+                    || cause.is::<WarningsError>()
+                {
+                    Some(Self::Validation)
+                } else if cause.is::<PathError>() || cause.is::<std::io::Error>() {
+                    Some(Self::File)
+                } else {
+                    None
+                }
+            })
+            .unwrap_or(Self::Failure)
+    }
+}
+
+impl From<Exit> for ExitCode {
+    fn from(exit: Exit) -> Self {
+        ExitCode::from(exit as u8)
+    }
+}
+
+/// Code of an export error, `None` for errors whose cause decides
+fn for_export_error(error: &ExportError) -> Option<Exit> {
+    match error {
+        ExportError::KeyRevoked => Some(Exit::Auth),
+        ExportError::OtherLibrary { .. } | ExportError::EmptyExport { .. } => {
+            Some(Exit::FileConflict)
+        }
+        ExportError::UnsupportedForFormat(_)
+        | ExportError::UnsupportedForIncremental
+        | ExportError::UnsupportedForFifo { .. } => Some(Exit::Usage),
+        ExportError::Incomplete(_) | ExportError::Collisions(_) => Some(Exit::Validation),
+        ExportError::FileError { .. } => Some(Exit::File),
+        _ => None,
+    }
+}
+
+fn for_api_error(error: &ApiError) -> Exit {
+    match error {
+        ApiError::UnexpectedStatus { status, .. }
+            if *status == StatusCode::FORBIDDEN || *status == StatusCode::UNAUTHORIZED =>
+        {
+            Exit::Auth
+        }
+        ApiError::UnexpectedStatus { status, .. } if status.is_client_error() => Exit::Failure,
+        ApiError::UnexpectedStatus { .. }
+        | ApiError::HttpError(_)
+        | ApiError::RetriesExhausted { .. } => Exit::Network,
+        ApiError::JsonError(_) | ApiError::VersionMismatch { .. } => Exit::Failure,
+        ApiError::IoError(_) => Exit::File,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(ExportError::KeyRevoked, Exit::Auth)]
+    #[case(ExportError::ClientError(ApiError::UnexpectedStatus { status: StatusCode::FORBIDDEN, body: String::new() }), Exit::Auth)]
+    #[case(ExportError::ClientError(ApiError::UnexpectedStatus { status: StatusCode::BAD_GATEWAY, body: String::new() }), Exit::Network)]
+    #[case(ExportError::OtherLibrary { file_path: "refs.bib".into(), library: "users/1".into() }, Exit::FileConflict)]
+    #[case(ExportError::Incomplete(2), Exit::Validation)]
+    #[case(ExportError::UnsupportedForFifo { file_path: "refs.bib".into(), option: "--state-file" }, Exit::Usage)]
+    #[case(ExportError::ClientError(ApiError::VersionMismatch { requested: 3, received: "2".into() }), Exit::Failure)]
+    fn exit_code_of_export_error(#[case] error: ExportError, #[case] expected: Exit) {
+        let error = Err::<(), _>(error)
+            .context("Error during export process.")
+            .unwrap_err();
+        assert_eq!(Exit::for_error(&error), expected);
+    }
+
+    #[test]
+    fn unknown_error_fails() {
+        assert_eq!(Exit::for_error(&anyhow::anyhow!("oops")), Exit::Failure);
+    }
+
+    #[rstest]
+    #[case(ExportSuccess::Changes, false, Exit::Success)]
+    #[case(ExportSuccess::Changes, true, Exit::Changes)]
+    #[case(ExportSuccess::NoChanges, true, Exit::Success)]
+    fn exit_code_of_export(
+        #[case] success: ExportSuccess,
+        #[case] detailed: bool,
+        #[case] expected: Exit,
+    ) {
+        assert_eq!(Exit::for_export(success, detailed), expected);
+    }
+}
@@ -0,0 +1,431 @@
+use crate::export::document::{ExportDocument, ExportEntry};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+/// What to do with entries whose citation key is also the key of other entries
+#[derive(clap::ValueEnum, Clone, Debug, Default, PartialEq)]
+pub enum CollisionMode {
+    /// List the colliding entries and fail the export
+    Fail,
+    /// Ask how to resolve every collision: rename, skip or prefer the newer item
+    Ask,
+    /// Keep the key of the item that was added first and add letter suffixes to the keys of the others
+    #[default]
+    Suffix,
+}
+
+/// Decision for an item whose citation key collides with others, stored by item key in the config:
+///
+/// ```toml
+/// [key-collisions]
+/// ITEM0002 = { rename = "smith_2020b" }
+/// ITEM0003 = "skip"
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeyDecision {
+    /// Export the entry with this citation key instead
+    Rename(String),
+    /// Leave the entry out of the export
+    Skip,
+}
+
+/// Applies the decisions for colliding citation keys, and checks for or asks about the remaining collisions
+pub struct KeyCollisions {
+    mode: CollisionMode,
+    /// Decisions of the config and those made during this run
+    decisions: Mutex<BTreeMap<String, KeyDecision>>,
+    /// Config file that new decisions can be saved to
+    config_path: Option<PathBuf>,
+}
+
+impl KeyCollisions {
+    pub fn new(
+        mode: CollisionMode,
+        decisions: BTreeMap<String, KeyDecision>,
+        config_path: Option<PathBuf>,
+    ) -> Self {
+        Self {
+            mode,
+            decisions: Mutex::new(decisions),
+            config_path,
+        }
+    }
+
+    pub fn resolve(&self, document: &mut ExportDocument) -> Result<(), CollisionError> {
+        loop {
+            let decisions = self.decisions.lock().expect("poisoned lock").clone();
+            apply_decisions(document, &decisions);
+            let collisions = colliding_keys(document);
+            if collisions.is_empty() {
+                return Ok(());
+            }
+            match &self.mode {
+                CollisionMode::Fail => {
+                    warn_collisions(document, &collisions);
+                    return Err(CollisionError::Collisions(collisions.len()));
+                }
+                CollisionMode::Suffix => {
+                    add_suffixes(document, &collisions);
+                    return Ok(());
+                }
+                CollisionMode::Ask => {
+                    let mut new_decisions = BTreeMap::new();
+                    for (citekey, indices) in &collisions {
+                        let entries: Vec<&ExportEntry> = indices
+                            .iter()
+                            .map(|index| &document.entries[*index])
+                            .collect();
+                        new_decisions.extend(ask(citekey, &entries)?);
+                    }
+                    self.save(&new_decisions)?;
+                    self.decisions
+                        .lock()
+                        .expect("poisoned lock")
+                        .extend(new_decisions);
+                    // check again, the new keys might collide as well
+                }
+            }
+        }
+    }
+
+    /// Save the decisions to the config file if the user wants to
+    fn save(&self, decisions: &BTreeMap<String, KeyDecision>) -> Result<(), CollisionError> {
+        let Some(path) = &self.config_path else {
+            return Ok(());
+        };
+        let remember = inquire::Confirm::new(&format!(
+            "Remember these decisions in '{}'?",
+            path.display()
+        ))
+        .with_default(true)
+        .prompt()?;
+        if !remember {
+            return Ok(());
+        }
+        let config_error = |source| CollisionError::Config {
+            path: path.display().to_string(),
+            source,
+        };
+        let text = std::fs::read_to_string(path).map_err(|e| config_error(e.into()))?;
+        let text = with_decisions(&text, decisions).map_err(|e| config_error(e.into()))?;
+        std::fs::write(path, text).map_err(|e| config_error(e.into()))?;
+        Ok(())
+    }
+}
+
+/// Rename and skip the entries of the items with decisions
+fn apply_decisions(document: &mut ExportDocument, decisions: &BTreeMap<String, KeyDecision>) {
+    document
+        .entries
+        .retain(|entry| decisions.get(&entry.item.key) != Some(&KeyDecision::Skip));
+    for entry in &mut document.entries {
+        if let Some(KeyDecision::Rename(citekey)) = decisions.get(&entry.item.key) {
+            entry.entry.key = citekey.clone();
+        }
+    }
+}
+
+/// Add letter suffixes to colliding citation keys, what exports do without `--key-collisions`
+pub fn suffix_collisions(document: &mut ExportDocument) {
+    let collisions = colliding_keys(document);
+    add_suffixes(document, &collisions);
+}
+
+fn warn_collisions(document: &ExportDocument, collisions: &BTreeMap<String, Vec<usize>>) {
+    for (citekey, indices) in collisions {
+        let items: Vec<&str> = indices
+            .iter()
+            .map(|index| document.entries[*index].item.key.as_str())
+            .collect();
+        log::warn!(
+            "Citation key '{}' is used by the items {}",
+            citekey,
+            items.join(", ")
+        );
+    }
+}
+
+/// Indices of the entries by citation key, for the keys of more than one entry
+fn colliding_keys(document: &ExportDocument) -> BTreeMap<String, Vec<usize>> {
+    let mut indices: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (index, entry) in document.entries.iter().enumerate() {
+        indices
+            .entry(entry.entry.key.clone())
+            .or_default()
+            .push(index);
+    }
+    indices.retain(|_, indices| indices.len() > 1);
+    indices
+}
+
+/// Rename all but the first added of the colliding entries with letter suffixes, skipping keys that are in use,
+/// so that every export of the library gets the same keys
+fn add_suffixes(document: &mut ExportDocument, collisions: &BTreeMap<String, Vec<usize>>) {
+    let mut used: HashSet<String> = document
+        .entries
+        .iter()
+        .map(|entry| entry.entry.key.clone())
+        .collect();
+    for (citekey, indices) in collisions {
+        let mut indices = indices.clone();
+        indices.sort_by_key(|index| {
+            let item = &document.entries[*index].item;
+            (item.data.field("dateAdded"), item.key.clone())
+        });
+        let mut number = 0;
+        let mut renamed = Vec::new();
+        for index in &indices[1..] {
+            let new_key = loop {
+                number += 1;
+                let key = suffixed_key(citekey, number);
+                if used.insert(key.clone()) {
+                    break key;
+                }
+            };
+            let entry = &mut document.entries[*index];
+            renamed.push(format!("{} as '{}'", entry.item.key, new_key));
+            entry.entry.key = new_key;
+        }
+        log::warn!(
+            "Citation key '{}' is used by the items {}, exporting {}",
+            citekey,
+            indices
+                .iter()
+                .map(|index| document.entries[*index].item.key.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+            renamed.join(", ")
+        );
+    }
+}
+
+/// Ask the user how to resolve the collision of the entries, returns the decisions by item key
+fn ask(
+    citekey: &str,
+    entries: &[&ExportEntry],
+) -> Result<Vec<(String, KeyDecision)>, inquire::InquireError> {
+    let mut options = vec![
+        "Keep the newest item, skip the others".to_owned(),
+        "Rename the entries".to_owned(),
+    ];
+    options.extend(
+        entries
+            .iter()
+            .map(|entry| format!("Keep only {}", label(entry))),
+    );
+    let choice = inquire::Select::new(
+        &format!(
+            "{} items have the citation key '{}':",
+            entries.len(),
+            citekey
+        ),
+        options,
+    )
+    .raw_prompt()?;
+    match choice.index {
+        0 => {
+            let newest = entries
+                .iter()
+                .enumerate()
+                .max_by_key(|(_, entry)| entry.item.version)
+                .map(|(index, _)| index)
+                .unwrap_or_default();
+            Ok(keep_only(entries, newest))
+        }
+        1 => {
+            let mut decisions = Vec::new();
+            for (number, entry) in entries.iter().enumerate().skip(1) {
+                let new_key = inquire::Text::new(&format!("Citation key for {}:", label(entry)))
+                    .with_default(&suffixed_key(citekey, number))
+                    .prompt()?;
+                decisions.push((entry.item.key.clone(), KeyDecision::Rename(new_key)));
+            }
+            Ok(decisions)
+        }
+        keep => Ok(keep_only(entries, keep - 2)),
+    }
+}
+
+/// Decisions to skip all entries but the one with the index
+fn keep_only(entries: &[&ExportEntry], keep: usize) -> Vec<(String, KeyDecision)> {
+    entries
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| *index != keep)
+        .map(|(_, entry)| (entry.item.key.clone(), KeyDecision::Skip))
+        .collect()
+}
+
+/// Citation key with a letter suffix for the entry with the number, `b` for the second entry and so on
+fn suffixed_key(citekey: &str, number: usize) -> String {
+    match u8::try_from(number).ok().filter(|number| *number < 26) {
+        Some(number) => format!("{}{}", citekey, (b'a' + number) as char),
+        None => format!("{}_{}", citekey, number + 1),
+    }
+}
+
+fn label(entry: &ExportEntry) -> String {
+    format!(
+        "{} ({})  {}  [{}]",
+        entry.item.data.short_authors(),
+        entry.item.year().unwrap_or("n.d."),
+        entry.item.data.title,
+        entry.item.key
+    )
+}
+
+/// Config text with the decisions added to the `[key-collisions]` table, keeping the rest as it is
+fn with_decisions(
+    text: &str,
+    decisions: &BTreeMap<String, KeyDecision>,
+) -> Result<String, toml_edit::TomlError> {
+    let mut config: toml_edit::DocumentMut = text.parse()?;
+    let table = config
+        .entry("key-collisions")
+        .or_insert_with(toml_edit::table);
+    for (item_key, decision) in decisions {
+        table[item_key.as_str()] = match decision {
+            KeyDecision::Skip => toml_edit::value("skip"),
+            KeyDecision::Rename(citekey) => {
+                let mut rename = toml_edit::InlineTable::new();
+                rename.insert("rename", citekey.as_str().into());
+                toml_edit::value(rename)
+            }
+        };
+    }
+    Ok(config.to_string())
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum CollisionError {
+    #[error("{0} citation keys are used by more than one entry, see the warnings above")]
+    Collisions(usize),
+    #[error("Error while asking how to resolve a citation key collision")]
+    Prompt(#[from] inquire::InquireError),
+    #[error("Error saving the decisions to config file '{path}'")]
+    Config {
+        path: String,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::document::tests::document;
+    use pretty_assertions::assert_eq;
+
+    /// Document with three entries of the same citation key
+    fn colliding_document() -> ExportDocument {
+        let mut document = document();
+        for key in ["SECOND01", "THIRD001"] {
+            let mut item = document.entries[0].item.clone();
+            item.key = key.to_owned();
+            document.entries.push(ExportEntry {
+                item,
+                entry: document.entries[0].entry.clone(),
+            });
+        }
+        document
+    }
+
+    #[test]
+    fn resolve_collisions_with_decisions() {
+        let mut document = colliding_document();
+        assert_eq!(
+            colliding_keys(&document),
+            BTreeMap::from([("smith_title_2020".to_owned(), vec![0, 1, 2])])
+        );
+        let decisions = BTreeMap::from([
+            (
+                "SECOND01".to_owned(),
+                KeyDecision::Rename(suffixed_key("smith_title_2020", 1)),
+            ),
+            ("THIRD001".to_owned(), KeyDecision::Skip),
+        ]);
+        apply_decisions(&mut document, &decisions);
+        let keys: Vec<&str> = document
+            .entries
+            .iter()
+            .map(|entry| entry.entry.key.as_str())
+            .collect();
+        assert_eq!(keys, vec!["smith_title_2020", "smith_title_2020b"]);
+        assert!(colliding_keys(&document).is_empty());
+    }
+
+    #[test]
+    fn suffix_colliding_keys() {
+        let mut document = colliding_document();
+        let mut item = document.entries[2].item.clone();
+        item.key = "FOURTH01".to_owned();
+        document.entries.push(ExportEntry {
+            item,
+            entry: document.entries[2].entry.clone(),
+        });
+        for (index, date_added) in [(0, "2021"), (2, "2020"), (3, "2021")] {
+            document.entries[index]
+                .item
+                .data
+                .fields
+                .insert("dateAdded".to_owned(), date_added.into());
+        }
+        // the second suffix is in use already
+        document.entries[1].entry.key = "smith_title_2020b".to_owned();
+        KeyCollisions::new(CollisionMode::Suffix, BTreeMap::new(), None)
+            .resolve(&mut document)
+            .unwrap();
+        let keys: Vec<&str> = document
+            .entries
+            .iter()
+            .map(|entry| entry.entry.key.as_str())
+            .collect();
+        assert_eq!(
+            keys,
+            vec![
+                "smith_title_2020d",
+                "smith_title_2020b",
+                "smith_title_2020",
+                "smith_title_2020c"
+            ]
+        );
+    }
+
+    #[test]
+    fn suffix_collisions_by_default() {
+        let mut document = colliding_document();
+        suffix_collisions(&mut document);
+        let keys: Vec<&str> = document
+            .entries
+            .iter()
+            .map(|entry| entry.entry.key.as_str())
+            .collect();
+        assert_eq!(
+            keys,
+            vec!["smith_title_2020", "smith_title_2020b", "smith_title_2020c"]
+        );
+        assert_eq!(CollisionMode::default(), CollisionMode::Suffix);
+    }
+
+    #[test]
+    fn save_decisions_in_config() {
+        let decisions = BTreeMap::from([
+            (
+                "SECOND01".to_owned(),
+                KeyDecision::Rename("smith_2020b".to_owned()),
+            ),
+            ("THIRD001".to_owned(), KeyDecision::Skip),
+        ]);
+        let text = with_decisions("# my exports\nargs = [\"--sync\"]\n", &decisions).unwrap();
+        assert_eq!(
+            text,
+            "# my exports\nargs = [\"--sync\"]\n\n[key-collisions]\nSECOND01 = { rename = \"smith_2020b\" }\nTHIRD001 = \"skip\"\n"
+        );
+        let parsed: BTreeMap<String, BTreeMap<String, KeyDecision>> =
+            toml::from_str(text.trim_start_matches("# my exports\nargs = [\"--sync\"]\n")).unwrap();
+        assert_eq!(parsed["key-collisions"], decisions);
+    }
+}
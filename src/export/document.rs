@@ -0,0 +1,294 @@
+use crate::bibtex::{self, Block, Entry, ParseError};
+use crate::zotero_api::{
+    ExportFormat,
+    item::{Collection, Item},
+};
+use std::collections::{BTreeMap, HashMap};
+
+/// Exported items parsed into bibliography entries, so that they can be post-processed before being written
+pub struct ExportDocument {
+    /// Blocks written before the entries, e.g. `@string` definitions
+    pub preamble: Vec<Block>,
+    pub entries: Vec<ExportEntry>,
+    /// Blocks written after the entries, e.g. generated entries that others refer to with `crossref`
+    pub trailer: Vec<Block>,
+    /// Child items (attachments, notes, annotations) by the key of their parent item
+    pub children: HashMap<String, Vec<Item>>,
+    /// Notes without a parent item
+    pub standalone_notes: Vec<Item>,
+    /// Names of the colored tags, only fetched if needed
+    pub tag_colors: Vec<String>,
+    /// All collections of the library by key, only fetched if needed
+    pub collections: BTreeMap<String, Collection>,
+}
+
+/// Order of the exported entries
+#[derive(clap::ValueEnum, Clone, Debug, Default, PartialEq)]
+pub enum SortOrder {
+    /// By citation key
+    #[default]
+    Key,
+    /// By year, entries without a year last
+    Year,
+    /// By the last name of the first author, then by year
+    Author,
+    /// By the time the items were last modified in Zotero, oldest first
+    DateModified,
+}
+
+/// A bibliography entry together with the Zotero item it was exported from
+pub struct ExportEntry {
+    pub item: Item,
+    pub entry: Entry,
+}
+
+impl ExportDocument {
+    /// Build the document from items that were fetched including their export in the given format
+    pub fn try_from_items(items: Vec<Item>, format: &ExportFormat) -> Result<Self, ParseError> {
+        let mut entries = Vec::new();
+        let mut children: HashMap<String, Vec<Item>> = HashMap::new();
+        let mut standalone_notes = Vec::new();
+        for item in items {
+            let entry = match item.export(format) {
+                Some(export) => bibtex::parse(export)?
+                    .into_iter()
+                    .find_map(|block| match block {
+                        Block::Entry(entry) => Some(entry),
+                        Block::Other(_) => None,
+                    }),
+                None => None,
+            };
+            match (entry, &item.data.parent_item) {
+                (Some(entry), _) => entries.push(ExportEntry { item, entry }),
+                (None, Some(parent)) => children.entry(parent.clone()).or_default().push(item),
+                (None, None) if item.data.item_type == "note" => standalone_notes.push(item),
+                (None, None) => log::debug!(
+                    "Skipping item {} of type '{}' without export",
+                    item.key,
+                    item.data.item_type
+                ),
+            }
+        }
+        Ok(Self {
+            preamble: Vec::new(),
+            entries,
+            trailer: Vec::new(),
+            children,
+            standalone_notes,
+            tag_colors: Vec::new(),
+            collections: BTreeMap::new(),
+        })
+    }
+
+    /// Child items of the given entry
+    pub fn children_of(&self, entry: &ExportEntry) -> &[Item] {
+        self.children
+            .get(&entry.item.key)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Order the entries (with equal ones by citation key) and the children by item key, independent of the order
+    /// of the API responses, so that exports of the same library version are identical
+    pub fn sort(&mut self, order: &SortOrder) {
+        let by_key = |a: &ExportEntry, b: &ExportEntry| {
+            (&a.entry.key, &a.item.key).cmp(&(&b.entry.key, &b.item.key))
+        };
+        let by_year = |a: &ExportEntry, b: &ExportEntry| {
+            // `None` sorts before `Some`, but entries without a year go last
+            (a.item.year().is_none(), a.item.year()).cmp(&(b.item.year().is_none(), b.item.year()))
+        };
+        match order {
+            SortOrder::Key => self.entries.sort_by(by_key),
+            SortOrder::Year => self
+                .entries
+                .sort_by(|a, b| by_year(a, b).then_with(|| by_key(a, b))),
+            SortOrder::Author => self.entries.sort_by(|a, b| {
+                first_author(&a.item)
+                    .cmp(&first_author(&b.item))
+                    .then_with(|| by_year(a, b))
+                    .then_with(|| by_key(a, b))
+            }),
+            SortOrder::DateModified => self.entries.sort_by(|a, b| {
+                a.item
+                    .data
+                    .field("dateModified")
+                    .cmp(&b.item.data.field("dateModified"))
+                    .then_with(|| by_key(a, b))
+            }),
+        }
+        for children in self.children.values_mut() {
+            children.sort_by(|a, b| a.key.cmp(&b.key));
+        }
+    }
+
+    /// Text to be written to the export file
+    pub fn render(&self) -> String {
+        let blocks: Vec<Block> = self
+            .preamble
+            .iter()
+            .cloned()
+            .chain(
+                self.entries
+                    .iter()
+                    .map(|entry| Block::Entry(entry.entry.clone())),
+            )
+            .chain(self.trailer.iter().cloned())
+            .collect();
+        bibtex::write(&blocks)
+    }
+
+    /// Texts to be written to numbered files of at most `entries_per_chunk` entries each.
+    /// Every chunk starts with the preamble, the trailer is written to the last chunk.
+    pub fn render_chunks(&self, entries_per_chunk: usize) -> Vec<String> {
+        let mut chunks: Vec<Vec<Block>> = self
+            .entries
+            .chunks(entries_per_chunk.max(1))
+            .map(|entries| {
+                self.preamble
+                    .iter()
+                    .cloned()
+                    .chain(
+                        entries
+                            .iter()
+                            .map(|entry| Block::Entry(entry.entry.clone())),
+                    )
+                    .collect()
+            })
+            .collect();
+        match chunks.last_mut() {
+            Some(last) => last.extend(self.trailer.iter().cloned()),
+            None => chunks.push(
+                self.preamble
+                    .iter()
+                    .chain(self.trailer.iter())
+                    .cloned()
+                    .collect(),
+            ),
+        }
+        chunks.iter().map(|blocks| bibtex::write(blocks)).collect()
+    }
+}
+
+/// Last name of the first author (or other creator if there are no authors) for sorting, ignoring case
+fn first_author(item: &Item) -> Option<String> {
+    let creators = &item.data.creators;
+    creators
+        .iter()
+        .find(|creator| creator.creator_type == "author")
+        .or_else(|| creators.first())
+        .and_then(|creator| creator.last_name.as_ref().or(creator.name.as_ref()))
+        .map(|name| name.to_lowercase())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    pub(crate) const ITEMS_JSON: &str = r#"[
+        {"key":"PARENT01","version":3,"data":{"itemType":"journalArticle","title":"Title"},
+         "biblatex":"\n@article{smith_title_2020,\n\ttitle = {Title},\n\tdate = {2020}\n}\n"},
+        {"key":"ATTACH01","version":4,"data":{"itemType":"attachment","parentItem":"PARENT01","linkMode":"imported_file","filename":"smith.pdf","contentType":"application/pdf"},
+         "biblatex":""},
+        {"key":"NOTE0001","version":5,"data":{"itemType":"note","note":"<p>standalone</p>"}}
+    ]"#;
+
+    pub(crate) fn document() -> ExportDocument {
+        let items: Vec<Item> = serde_json::from_str(ITEMS_JSON).unwrap();
+        ExportDocument::try_from_items(items, &ExportFormat::Biblatex).unwrap()
+    }
+
+    #[test]
+    fn build_document_from_items() {
+        let document = document();
+        assert_eq!(document.entries.len(), 1);
+        assert_eq!(document.entries[0].entry.key, "smith_title_2020");
+        let children = document.children_of(&document.entries[0]);
+        assert_eq!(children.len(), 1);
+        assert_eq!(children[0].key, "ATTACH01");
+        assert_eq!(document.standalone_notes.len(), 1);
+    }
+
+    #[test]
+    fn render_document() {
+        assert_eq!(
+            document().render(),
+            "\n@article{smith_title_2020,\n\ttitle = {Title},\n\tdate = {2020}\n}\n"
+        );
+    }
+
+    #[test]
+    fn sort_entries() {
+        let items: Vec<Item> = serde_json::from_value(serde_json::json!([
+            {"key":"ITEM0001","version":1,"meta":{"parsedDate":"2019"},
+             "data":{"itemType":"book","dateModified":"2024-05-01T10:00:00Z",
+                     "creators":[{"creatorType":"author","firstName":"Zoe","lastName":"Adams"}]},
+             "biblatex":"@book{c,\n}"},
+            {"key":"ITEM0002","version":1,
+             "data":{"itemType":"book","dateModified":"2023-01-01T10:00:00Z",
+                     "creators":[{"creatorType":"author","firstName":"Ann","lastName":"Brown"}]},
+             "biblatex":"@book{a,\n}"},
+            {"key":"ITEM0003","version":1,"meta":{"parsedDate":"2018-04-01"},
+             "data":{"itemType":"book","dateModified":"2025-02-01T10:00:00Z",
+                     "creators":[{"creatorType":"author","name":"brown university"}]},
+             "biblatex":"@book{b,\n}"}
+        ]))
+        .unwrap();
+        let mut document = ExportDocument::try_from_items(items, &ExportFormat::Biblatex).unwrap();
+        let mut keys = |order| {
+            document.sort(&order);
+            document
+                .entries
+                .iter()
+                .map(|entry| entry.entry.key.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(keys(SortOrder::Key), vec!["a", "b", "c"]);
+        assert_eq!(keys(SortOrder::Year), vec!["b", "c", "a"]);
+        assert_eq!(keys(SortOrder::Author), vec!["c", "a", "b"]);
+        assert_eq!(keys(SortOrder::DateModified), vec!["a", "c", "b"]);
+    }
+
+    #[test]
+    fn sort_entries_by_citation_key() {
+        let mut document = document();
+        let mut entry = document.entries[0].entry.clone();
+        entry.key = "adams_other_2019".to_owned();
+        document.entries.push(ExportEntry {
+            item: document.entries[0].item.clone(),
+            entry,
+        });
+        document.sort(&SortOrder::Key);
+        let keys: Vec<&str> = document
+            .entries
+            .iter()
+            .map(|entry| entry.entry.key.as_str())
+            .collect();
+        assert_eq!(keys, vec!["adams_other_2019", "smith_title_2020"]);
+    }
+
+    #[test]
+    fn render_document_in_chunks() {
+        let mut document = document();
+        let mut entry = document.entries[0].entry.clone();
+        entry.key = "second".to_owned();
+        document.entries.push(ExportEntry {
+            item: document.entries[0].item.clone(),
+            entry,
+        });
+        document
+            .preamble
+            .push(Block::Other("@string{j = {J}}".to_owned()));
+        document
+            .trailer
+            .push(Block::Other("@comment{end}".to_owned()));
+        assert_eq!(
+            document.render_chunks(1),
+            vec![
+                "\n@string{j = {J}}\n\n@article{smith_title_2020,\n\ttitle = {Title},\n\tdate = {2020}\n}\n",
+                "\n@string{j = {J}}\n\n@article{second,\n\ttitle = {Title},\n\tdate = {2020}\n}\n\n@comment{end}\n"
+            ]
+        );
+    }
+}
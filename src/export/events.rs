@@ -0,0 +1,25 @@
+use tokio::sync::mpsc;
+
+/// Something that happened while keeping the export in sync, for observers like the dashboard
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExportEvent {
+    /// Subscribed to the change notifications of the library
+    Connected,
+    /// The connection for change notifications was lost
+    Disconnected(String),
+    /// Zotero notified a change of the library
+    LibraryChanged,
+    ExportStarted,
+    /// The file was written with this library version and number of entries
+    Exported {
+        version: u64,
+        entries: usize,
+    },
+    /// The file was up to date already
+    UpToDate,
+    ExportFailed(String),
+    /// A line of the log
+    Log(String),
+}
+
+pub type EventSender = mpsc::UnboundedSender<ExportEvent>;
@@ -1,16 +1,90 @@
 use crate::ZOTEXON_VERSION;
-use crate::export::ExportTrigger;
+use crate::annotations::{AnnotationsError, AnnotationsExporter};
+use crate::attachments::{
+    AttachmentError, AttachmentMirror, LinkedAttachments, StorageAttachments,
+};
+use crate::bibtex::{self, Block, ParseError};
+use crate::digest::DigestRecorder;
+use crate::documents::{DocumentsError, ProjectDocuments};
+use crate::export::collisions::{self, CollisionError, KeyCollisions};
+use crate::export::document::{ExportDocument, SortOrder};
+use crate::export::journal::ChangeJournal;
+use crate::export::manifest::{Manifest, ManifestFile};
+use crate::export::merge::merge_entries;
+use crate::export::snapshot::{SnapshotError, SnapshotStore};
+use crate::export::strict::{StrictMode, incomplete_entries};
+use crate::export::{EventSender, ExportEvent, ExportTrigger};
+use crate::keys::{KeyList, KeysError};
+use crate::ndjson::{NdjsonError, NdjsonWriter};
+use crate::notes::{NotesError, NotesExporter};
+use crate::obsidian::{ObsidianError, ObsidianVault};
+use crate::skeletons::{NoteSkeletons, SkeletonError};
+use crate::stats::{HistoryRecord, LibraryHistory};
+use crate::transform::{LibraryData, Transform};
 use crate::zotero_api::ExportFormat;
-use crate::zotero_api::{ApiError, FetchItemsParams, FetchItemsResponse, client::ZoteroClient};
+use crate::zotero_api::retry::RetryBudget;
+use crate::zotero_api::{ApiError, FetchDataResponse, FetchItemsParams, client::ZoteroClient};
+use chrono::{DateTime, SecondsFormat, Utc};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
 use tokio::fs::OpenOptions;
-use tokio::io::AsyncBufReadExt;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWriteExt, BufWriter};
+use tokio::sync::Semaphore;
+
+/// How often a sync checks whether the API key can still access the library
+const KEY_CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
 
 pub struct FileExporter<TClient: ZoteroClient> {
     client: TClient,
     file_path: String,
     format: ExportFormat,
+    /// Key of the exported collection, if not the whole library is exported
+    collection: Option<String>,
+    /// Tag filters of the exported items, like the `tag` parameter of the Zotero API
+    tags: Vec<String>,
+    /// Name of a format that is requested instead of `format`
+    raw_format: Option<String>,
     trigger: ExportTrigger,
+    /// The file is a named pipe that another process reads, so it is never read back
+    fifo: bool,
+    /// File that the metadata of the export is kept in instead of the header of the exported file
+    state_file: Option<PathBuf>,
+    header: FileHeader,
+    /// Time of the `{timestamp}` of a header template instead of the time of the export
+    header_timestamp: Option<DateTime<Utc>>,
+    /// Splice the entries of changed items into the file instead of exporting the whole library
+    incremental: bool,
+    snapshots: Option<SnapshotStore>,
+    journal: Option<ChangeJournal>,
+    attachments: Option<AttachmentMirror>,
+    linked_attachments: Option<LinkedAttachments>,
+    storage_attachments: Option<StorageAttachments>,
+    notes: Option<NotesExporter>,
+    annotations: Option<AnnotationsExporter>,
+    obsidian: Option<ObsidianVault>,
+    skeletons: Option<NoteSkeletons>,
+    keys: Option<KeyList>,
+    documents: Option<ProjectDocuments>,
+    ndjson: Option<NdjsonWriter>,
+    transforms: Vec<Box<dyn Transform>>,
+    split: Option<usize>,
+    strict: Option<StrictMode>,
+    collisions: Option<KeyCollisions>,
+    export_permits: Option<Arc<Semaphore>>,
+    overwrite_other_library: bool,
+    allow_empty: bool,
+    events: Option<EventSender>,
+    retry_budget: Option<Arc<RetryBudget>>,
+    key_check: bool,
+    manifest: Option<Manifest>,
+    sort: Option<SortOrder>,
+    digest: Option<DigestRecorder>,
+    history: Option<LibraryHistory>,
+    targets: Vec<FileExporter<TClient>>,
 }
 
 impl<TClient: ZoteroClient> FileExporter<TClient> {
@@ -20,44 +94,342 @@ impl<TClient: ZoteroClient> FileExporter<TClient> {
         format: ExportFormat,
         trigger: ExportTrigger,
     ) -> Result<Self, ExportError> {
-        OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create(true)
-            .truncate(false)
-            .open(&file_path)
-            .await
-            .map_err(|e| ExportError::FileError {
-                file_path: file_path.clone(),
-                io_error: e,
-            })?;
+        let fifo = is_fifo(&file_path).await;
+        if fifo {
+            log::info!(
+                "File '{}' is a named pipe, every export is written to it completely",
+                file_path
+            );
+        } else {
+            OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(&file_path)
+                .await
+                .map_err(|e| ExportError::FileError {
+                    file_path: file_path.clone(),
+                    io_error: e,
+                })?;
+        }
         Ok(Self {
             client,
             file_path,
+            fifo,
+            state_file: None,
+            header: FileHeader::default(),
+            header_timestamp: None,
+            incremental: false,
             format,
+            collection: None,
+            tags: Vec::new(),
+            raw_format: None,
             trigger,
+            snapshots: None,
+            journal: None,
+            attachments: None,
+            linked_attachments: None,
+            storage_attachments: None,
+            notes: None,
+            annotations: None,
+            obsidian: None,
+            skeletons: None,
+            keys: None,
+            documents: None,
+            ndjson: None,
+            transforms: Vec::new(),
+            split: None,
+            strict: None,
+            collisions: None,
+            export_permits: None,
+            overwrite_other_library: false,
+            allow_empty: false,
+            events: None,
+            retry_budget: None,
+            key_check: false,
+            manifest: None,
+            sort: None,
+            digest: None,
+            history: None,
+            targets: Vec::new(),
         })
     }
 
+    /// Append the items that changed since the previous export to the journal
+    pub fn with_change_journal(mut self, journal: ChangeJournal) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    /// Export only the items of the collection with this key instead of the whole library
+    pub fn with_collection(mut self, key: String) -> Self {
+        self.collection = Some(key);
+        self
+    }
+
+    /// Export only the items matching all of the tag filters, e.g. `-toread` to leave out the items tagged `toread`
+    pub fn with_tags(mut self, tags: Vec<String>) -> Self {
+        self.tags = tags;
+        self
+    }
+
+    /// Request this format from the API instead of `format`, e.g. `mods`, and write it as the API returns it
+    pub fn with_raw_format(mut self, name: String) -> Self {
+        self.raw_format = Some(name);
+        self
+    }
+
+    /// Keep the metadata of the export (library version, time and content hash) in this JSON file instead of
+    /// a header comment, e.g. for tools that strip comments from the exported file. The header of an existing
+    /// export is taken over into the state file.
+    pub fn with_state_file(mut self, path: PathBuf) -> Self {
+        self.state_file = Some(path);
+        self
+    }
+
+    /// Write another first line than the metadata header, or none at all. The library version is then only known
+    /// from the state file, if there is one, and otherwise the whole library is exported every time.
+    pub fn with_header(mut self, header: FileHeader) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// Write this time for the `{timestamp}` of a header template instead of the time of the export,
+    /// e.g. the `SOURCE_DATE_EPOCH` of a reproducible build
+    pub fn with_header_timestamp(mut self, timestamp: DateTime<Utc>) -> Self {
+        self.header_timestamp = Some(timestamp);
+        self
+    }
+
+    /// After the first export, fetch only the items that changed since the previous export and splice their entries
+    /// into the file, replacing their previous entries, instead of exporting the whole library again.
+    /// Needs a state file, which also keeps the citation key of every item.
+    pub fn with_incremental(mut self) -> Self {
+        self.incremental = true;
+        self
+    }
+
+    /// Mirror the attachment files of the exported items and reference them in `file` fields
+    pub fn with_attachments(mut self, attachments: AttachmentMirror) -> Self {
+        self.attachments = Some(attachments);
+        self
+    }
+
+    /// Reference linked attachment files in `file` fields, resolving relative paths against a local base directory
+    pub fn with_linked_attachments(mut self, linked_attachments: LinkedAttachments) -> Self {
+        self.linked_attachments = Some(linked_attachments);
+        self
+    }
+
+    /// Reference the files of stored attachments in the storage directory of a local Zotero in `file` fields
+    pub fn with_storage_attachments(mut self, storage_attachments: StorageAttachments) -> Self {
+        self.storage_attachments = Some(storage_attachments);
+        self
+    }
+
+    /// Write the child notes of the exported items to Markdown files
+    pub fn with_notes(mut self, notes: NotesExporter) -> Self {
+        self.notes = Some(notes);
+        self
+    }
+
+    /// Write the PDF annotations of the exported items to one file per item
+    pub fn with_annotations(mut self, annotations: AnnotationsExporter) -> Self {
+        self.annotations = Some(annotations);
+        self
+    }
+
+    /// Write one Markdown note per exported item into an Obsidian vault folder
+    pub fn with_obsidian(mut self, obsidian: ObsidianVault) -> Self {
+        self.obsidian = Some(obsidian);
+        self
+    }
+
+    /// Create a literature-note stub for every exported item that doesn't have one yet
+    pub fn with_skeletons(mut self, skeletons: NoteSkeletons) -> Self {
+        self.skeletons = Some(skeletons);
+        self
+    }
+
+    /// Write the citation keys of the exported entries to a file, for editor completions
+    pub fn with_keys(mut self, keys: KeyList) -> Self {
+        self.keys = Some(keys);
+        self
+    }
+
+    /// Write filtered bibliographies for the documents of a project, e.g. one per chapter
+    pub fn with_documents(mut self, documents: ProjectDocuments) -> Self {
+        self.documents = Some(documents);
+        self
+    }
+
+    /// Modify the exported entries before they are written, transforms are applied in the order they were added
+    pub fn with_transform(mut self, transform: impl Transform + 'static) -> Self {
+        self.transforms.push(Box::new(transform));
+        self
+    }
+
+    /// Check that the entries have the required fields of their type before anything is written
+    pub fn with_strict(mut self, mode: StrictMode) -> Self {
+        self.strict = Some(mode);
+        self
+    }
+
+    /// Write the exported items as NDJSON, one JSON object per item
+    pub fn with_ndjson(mut self, ndjson: NdjsonWriter) -> Self {
+        self.ndjson = Some(ndjson);
+        self
+    }
+
+    /// Rename or skip entries whose citation keys collide, and check for or ask about the remaining collisions
+    pub fn with_collisions(mut self, collisions: KeyCollisions) -> Self {
+        self.collisions = Some(collisions);
+        self
+    }
+
+    /// Write the entries to numbered files of at most `entries_per_file` entries next to the export file,
+    /// e.g. `refs-001.bib`, and the names of these files to the export file itself
+    pub fn with_split(mut self, entries_per_file: usize) -> Self {
+        self.split = Some(entries_per_file);
+        self
+    }
+
+    /// Only export while holding a permit of the semaphore, to limit the number of exports that run at the same time
+    pub fn with_export_permits(mut self, permits: Arc<Semaphore>) -> Self {
+        self.export_permits = Some(permits);
+        self
+    }
+
+    /// Overwrite the file even if it was exported from another library
+    pub fn with_overwrite_other_library(mut self) -> Self {
+        self.overwrite_other_library = true;
+        self
+    }
+
+    /// Overwrite the file with an empty export even if it had entries before
+    pub fn with_allow_empty(mut self) -> Self {
+        self.allow_empty = true;
+        self
+    }
+
+    /// Reset the retry budget of the client for every export, so that it limits the retries of a single export
+    pub fn with_retry_budget(mut self, retry_budget: Arc<RetryBudget>) -> Self {
+        self.retry_budget = Some(retry_budget);
+        self
+    }
+
+    /// Check every hour while waiting for triggers whether the API key can still access the library,
+    /// so that a long-running sync stops with a clear error when the key was revoked
+    pub fn with_key_check(mut self) -> Self {
+        self.key_check = true;
+        self
+    }
+
+    /// Write a manifest of the exported files after every run
+    pub fn with_manifest(mut self, manifest: Manifest) -> Self {
+        self.manifest = Some(manifest);
+        self
+    }
+
+    /// Sort the entries in this order, so that exports of the same library version are byte-identical
+    pub fn with_sort(mut self, order: SortOrder) -> Self {
+        self.sort = Some(order);
+        self
+    }
+
+    /// Record the library version and number of entries of every export, for `stats --history`
+    pub fn with_history(mut self, history: LibraryHistory) -> Self {
+        self.history = Some(history);
+        self
+    }
+
+    /// Record the changed items of every export for the digest of the daemon
+    pub fn with_digest(mut self, digest: DigestRecorder) -> Self {
+        self.digest = Some(digest);
+        self
+    }
+
+    /// Report the progress of the exports to an observer, e.g. the dashboard
+    pub fn with_events(mut self, events: EventSender) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Also export to the file of the target, e.g. in another format, right after every export of this file.
+    /// The target is exported with its own options, the trigger and permits of this exporter are used.
+    pub fn with_target(mut self, target: FileExporter<TClient>) -> Self {
+        self.targets.push(target);
+        self
+    }
+
+    /// Keep a snapshot of every written export, so that it can be restored later
+    pub fn with_snapshots(mut self) -> Self {
+        self.snapshots = Some(SnapshotStore::for_file(&self.file_path));
+        self
+    }
+
     /// Export once, then wait on triggers for next exports and return when the trigger stream is closed
     pub async fn run(mut self) -> Result<ExportSuccess, ExportError> {
+        for exporter in std::iter::once(&self).chain(&self.targets) {
+            exporter.check_fifo_options()?;
+            exporter.check_verbatim_options()?;
+            exporter.check_incremental_options()?;
+        }
+        if self.header != FileHeader::Metadata && self.state_file.is_none() {
+            log::info!(
+                "The file has no metadata header, so the whole library is exported every time. Use --state-file to export only after changes"
+            );
+        }
         let mut has_changes = false;
         let mut keep_running = true;
         while keep_running {
+            let permit = match &self.export_permits {
+                Some(permits) => permits.acquire().await.ok(),
+                None => None,
+            };
+            if let Some(retry_budget) = &self.retry_budget {
+                retry_budget.reset();
+            }
             log::info!("Starting export");
-            match self.export_once().await {
+            self.emit(ExportEvent::ExportStarted);
+            let mut result = self.export_once().await;
+            if let (Ok(_), Some(manifest)) = (&result, &self.manifest)
+                && let Err(e) = self.write_manifest(manifest).await
+            {
+                result = Err(e);
+            }
+            for target in &self.targets {
+                if result.is_err() {
+                    break;
+                }
+                log::info!("Starting export to '{}'", target.file_path);
+                match target.export_once().await {
+                    Ok(ExportSuccess::Changes) => result = Ok(ExportSuccess::Changes),
+                    Ok(ExportSuccess::NoChanges) => {}
+                    Err(e) => result = Err(e),
+                }
+            }
+            drop(permit);
+            match result {
                 Ok(ExportSuccess::Changes) => {
                     has_changes = true;
                 }
                 Ok(ExportSuccess::NoChanges) => {
-                    // nothing to do
+                    self.emit(ExportEvent::UpToDate);
                 }
                 Err(e) => {
+                    let e = if is_permission_error(&e) {
+                        self.check_key().await.err().unwrap_or(e)
+                    } else {
+                        e
+                    };
                     log::error!("Aborting export due to error: {}", e);
+                    self.emit(ExportEvent::ExportFailed(e.to_string()));
                     return Err(e);
                 }
             }
-            keep_running = self.trigger.next().await.is_some();
+            keep_running = self.next_trigger().await?;
         }
         Ok(if has_changes {
             ExportSuccess::Changes
@@ -66,6 +438,46 @@ impl<TClient: ZoteroClient> FileExporter<TClient> {
         })
     }
 
+    /// Wait for the next trigger, checking the API key in between if enabled. Returns whether to export again.
+    async fn next_trigger(&mut self) -> Result<bool, ExportError> {
+        if !self.key_check {
+            return Ok(self.trigger.next().await.is_some());
+        }
+        let mut key_check = tokio::time::interval_at(
+            tokio::time::Instant::now() + KEY_CHECK_INTERVAL,
+            KEY_CHECK_INTERVAL,
+        );
+        loop {
+            let next = tokio::select! {
+                next = self.trigger.next() => Some(next),
+                _ = key_check.tick() => None,
+            };
+            match next {
+                Some(next) => return Ok(next.is_some()),
+                None => {
+                    if let Err(e) = self.check_key().await {
+                        log::error!("Stopping sync: {}", e);
+                        self.emit(ExportEvent::ExportFailed(e.to_string()));
+                        return Err(e);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Fail if the API key can't access the library anymore. Errors of the check itself are only logged.
+    async fn check_key(&self) -> Result<(), ExportError> {
+        log::debug!("Checking the access of the API key");
+        match self.client.check_key_access().await {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(ExportError::KeyRevoked),
+            Err(e) => {
+                log::warn!("Could not check the access of the API key: {}", e);
+                Ok(())
+            }
+        }
+    }
+
     async fn export_once(&self) -> Result<ExportSuccess, ExportError> {
         let metadata = self.try_read_file_metadata().await;
         let mut existing_export_version = None;
@@ -74,56 +486,763 @@ impl<TClient: ZoteroClient> FileExporter<TClient> {
                 "Found existing export with metadata: {}",
                 serde_json::to_string(&meta).unwrap_or_default()
             );
-            if meta.matches_format(&self.format) {
+            if let Some(library) = &meta.library
+                && *library != self.library()
+            {
+                if !self.overwrite_other_library {
+                    return Err(ExportError::OtherLibrary {
+                        file_path: self.file_path.clone(),
+                        library: library.clone(),
+                    });
+                }
+                log::warn!(
+                    "Overwriting file '{}' that was exported from library '{}'",
+                    &self.file_path,
+                    library
+                );
+            }
+            if meta.matches(
+                &self.format,
+                self.raw_format.as_deref(),
+                self.split,
+                &self.library(),
+                &self.tags,
+            ) {
                 existing_export_version = Some(meta.library_version);
             } else {
                 log::info!(
-                    "Existing export has a different format or zotexon version, performing new export now"
+                    "Existing export has a different library, format, splitting, tag filter or zotexon version, performing new export now"
                 );
             }
         } else {
             log::info!("No existing export found, performing new export now");
         }
-        let params = FetchItemsParams {
+        // the citation keys of the items are only kept in the state file of incremental exports
+        let merge_base = match (&metadata, existing_export_version) {
+            (Some(meta), Some(_)) if self.incremental && !meta.citation_keys.is_empty() => {
+                Some(meta.citation_keys.clone())
+            }
+            _ => None,
+        };
+        let mut params = FetchItemsParams {
             last_modified_version: existing_export_version,
+            since: None,
             format: self.format.clone(),
+            collection: self.collection.clone(),
+            tags: self.tags.clone(),
+            raw_format: self.raw_format.clone(),
         };
-        let response = self.client.fetch_items(&params).await?;
-        match response {
-            FetchItemsResponse::UpToDate => {
+        let mut response = match merge_base {
+            Some(citation_keys) => self.fetch_changes(&params, citation_keys).await?,
+            None => self.fetch_export(&params).await?,
+        };
+        if let (RenderedExport::UpToDate, Some(existing_version)) =
+            (&response, existing_export_version)
+        {
+            // Zotero only reports newer versions, a library that went back to an older version looks unchanged
+            let remote_version = self.client.fetch_library_version().await?;
+            if remote_version < existing_version {
+                log::warn!(
+                    "Library version {} is older than version {} of the existing export (library restored from a backup?), performing new export now",
+                    remote_version,
+                    existing_version
+                );
+                params.last_modified_version = None;
+                response = self.fetch_export(&params).await?;
+            }
+        }
+        let (last_modified_version, entries, citation_keys, file_content) = match response {
+            RenderedExport::UpToDate => {
                 log::info!(
                     "File '{}' is up to date with the Zotero library",
                     &self.file_path
                 );
-                Ok(ExportSuccess::NoChanges)
+                if let (Some(state_file), Some(metadata)) = (&self.state_file, &metadata) {
+                    self.migrate_header(state_file, metadata).await?;
+                }
+                return Ok(ExportSuccess::NoChanges);
             }
-            FetchItemsResponse::Updated {
+            RenderedExport::Updated {
                 last_modified_version,
-                text: items,
+                entries,
+                chunks,
+                citation_keys,
             } => {
-                let header = FileMetadata {
-                    zotexon_version: ZOTEXON_VERSION.to_owned(),
-                    library_version: last_modified_version,
-                    format: self.format.clone(),
+                self.check_not_empty(entries, metadata.as_ref())?;
+                let header = self.header(last_modified_version, entries);
+                let file_content = match self.split {
+                    Some(_) => self.write_chunks(header.as_deref(), &chunks).await?,
+                    None => with_header(header.as_deref(), &chunks.concat()),
                 };
-                let file_content = format!("{}\n{}", String::from(header), items);
-                tokio::fs::write(&self.file_path, file_content)
+                write_file(Path::new(&self.file_path), &file_content).await?;
+                (
+                    last_modified_version,
+                    entries,
+                    citation_keys,
+                    Some(file_content),
+                )
+            }
+            RenderedExport::Spooled {
+                last_modified_version,
+                spool_path,
+            } => {
+                let counted = match (&self.raw_format, &self.format) {
+                    // the items of raw formats aren't counted
+                    (Some(_), _) => Ok(0),
+                    (None, ExportFormat::Json) => count_spooled_items(&spool_path).await,
+                    (None, _) => count_spooled_entries(&spool_path).await,
+                };
+                let entries = match counted {
+                    Ok(entries) => entries,
+                    Err(e) => {
+                        remove_spool(&spool_path).await;
+                        return Err(e);
+                    }
+                };
+                if let Err(e) = self.check_not_empty(entries, metadata.as_ref()) {
+                    remove_spool(&spool_path).await;
+                    return Err(e);
+                }
+                let header = self.header(last_modified_version, entries);
+                self.write_spooled(header.as_deref(), &spool_path).await?;
+                (last_modified_version, entries, BTreeMap::new(), None)
+            }
+        };
+        log::info!(
+            "Wrote library export with version {} to file '{}'",
+            last_modified_version,
+            &self.file_path
+        );
+        if let Some(state_file) = &self.state_file {
+            self.write_state_file(state_file, last_modified_version, entries, citation_keys)
+                .await?;
+        }
+        self.emit(ExportEvent::Exported {
+            version: last_modified_version,
+            entries,
+        });
+        if let Some(history) = &self.history
+            && self.raw_format.is_none()
+        {
+            let record = HistoryRecord {
+                date: chrono::Utc::now(),
+                library_version: last_modified_version,
+                items: entries,
+            };
+            if let Err(e) = history.record(record).await {
+                log::warn!(
+                    "Could not record the library history in '{}': {}",
+                    history.path().display(),
+                    e
+                );
+            }
+        }
+        if let Some(snapshots) = &self.snapshots {
+            let file_content = match file_content {
+                Some(file_content) => file_content,
+                None => tokio::fs::read_to_string(&self.file_path)
                     .await
                     .map_err(|e| ExportError::FileError {
                         file_path: self.file_path.clone(),
                         io_error: e,
-                    })?;
+                    })?,
+            };
+            snapshots.save(last_modified_version, &file_content).await?;
+        }
+        Ok(ExportSuccess::Changes)
+    }
+
+    /// Describe the bibliography files and the key list in the manifest
+    async fn write_manifest(&self, manifest: &Manifest) -> Result<(), ExportError> {
+        let file_path = Path::new(&self.file_path);
+        let content = read_file(file_path).await?;
+        let metadata = self.try_read_file_metadata().await;
+        let library_version = metadata.as_ref().map(|meta| meta.library_version);
+        let format = self.format.to_string();
+        let mut files = Vec::new();
+        match self.split {
+            Some(_) => {
+                let entries = metadata.and_then(|meta| meta.entries).unwrap_or_default();
+                files.push(
+                    ManifestFile::new(file_path, "index", &content)
+                        .with_contents(library_version, entries),
+                );
+                let mut number = 1;
+                loop {
+                    let path = chunk_path(&self.file_path, number);
+                    let Ok(content) = tokio::fs::read(&path).await else {
+                        break;
+                    };
+                    files.push(
+                        ManifestFile::new(&path, &format, &content)
+                            .with_contents(library_version, count_entries(&content)),
+                    );
+                    number += 1;
+                }
+            }
+            None => files.push(
+                ManifestFile::new(file_path, &format, &content)
+                    .with_contents(library_version, count_entries(&content)),
+            ),
+        }
+        // the key list is only written with changes, so it might not exist yet
+        if let Some(keys) = &self.keys
+            && let Ok(content) = tokio::fs::read(keys.path()).await
+        {
+            files.push(ManifestFile::new(keys.path(), "keys", &content));
+        }
+        manifest
+            .write(&self.library(), &files)
+            .await
+            .map_err(|e| file_error(manifest.path(), e))
+    }
+
+    fn emit(&self, event: ExportEvent) {
+        if let Some(events) = &self.events {
+            let _ = events.send(event);
+        }
+    }
+
+    async fn fetch_export(&self, params: &FetchItemsParams) -> Result<RenderedExport, ExportError> {
+        if self.needs_item_data() {
+            return self.fetch(params).await;
+        }
+        let export = self.fetch_spooled(params).await?;
+        if let RenderedExport::Spooled { spool_path, .. } = &export
+            && self.raw_format.is_none()
+            && self.format != ExportFormat::Json
+        {
+            let collisions = has_spooled_collisions(spool_path).await;
+            if !matches!(collisions, Ok(false)) {
+                remove_spool(spool_path).await;
+            }
+            if collisions? {
+                // suffixes follow the date the items were added, which only the item data has
                 log::info!(
-                    "Wrote library export with version {} to file '{}'",
-                    last_modified_version,
-                    &self.file_path
+                    "The export has colliding citation keys, fetching the item data to resolve them"
                 );
-                Ok(ExportSuccess::Changes)
+                return self.fetch(params).await;
+            }
+        }
+        Ok(export)
+    }
+
+    fn metadata(&self, last_modified_version: u64, entries: usize) -> FileMetadata {
+        FileMetadata {
+            zotexon_version: ZOTEXON_VERSION.to_owned(),
+            library_version: last_modified_version,
+            format: self.format.clone(),
+            raw_format: self.raw_format.clone(),
+            split: self.split,
+            library: Some(self.library()),
+            entries: Some(entries),
+            tags: self.tags.clone(),
+            exported_at: None,
+            content_hash: None,
+            citation_keys: BTreeMap::new(),
+        }
+    }
+
+    /// First line of the exported file, if it has one. Exports that are written as the API returns them get none,
+    /// and the metadata header is left out when the metadata is kept in the state file.
+    fn header(&self, last_modified_version: u64, entries: usize) -> Option<String> {
+        if self.is_verbatim() {
+            return None;
+        }
+        let metadata = self.metadata(last_modified_version, entries);
+        match &self.header {
+            FileHeader::Metadata if self.state_file.is_none() => Some(String::from(metadata)),
+            FileHeader::Metadata | FileHeader::None => None,
+            FileHeader::Template(template) => Some(render_header(
+                template,
+                &metadata,
+                self.header_timestamp.unwrap_or_else(Utc::now),
+            )),
+        }
+    }
+
+    /// Record the metadata of the written export with the time and the hash of the file in the state file
+    async fn write_state_file(
+        &self,
+        state_file: &Path,
+        last_modified_version: u64,
+        entries: usize,
+        citation_keys: BTreeMap<String, String>,
+    ) -> Result<(), ExportError> {
+        let path = Path::new(&self.file_path);
+        let metadata = FileMetadata {
+            exported_at: Some(Utc::now()),
+            content_hash: Some(file_hash(path).await.map_err(|e| file_error(path, e))?),
+            citation_keys: if self.incremental {
+                citation_keys
+            } else {
+                BTreeMap::new()
+            },
+            ..self.metadata(last_modified_version, entries)
+        };
+        let json = serde_json::to_string_pretty(&metadata)
+            .map_err(|e| file_error(state_file, e.into()))?
+            + "\n";
+        tokio::fs::write(state_file, json)
+            .await
+            .map_err(|e| file_error(state_file, e))
+    }
+
+    /// Move the metadata from the header of an up-to-date export into the state file, without exporting again
+    async fn migrate_header(
+        &self,
+        state_file: &Path,
+        metadata: &FileMetadata,
+    ) -> Result<(), ExportError> {
+        let path = Path::new(&self.file_path);
+        let content = tokio::fs::read_to_string(path)
+            .await
+            .map_err(|e| file_error(path, e))?;
+        let Some((first_line, rest)) = content.split_once('\n') else {
+            return Ok(());
+        };
+        if FileMetadata::try_from(first_line.trim()).is_err() {
+            return Ok(());
+        }
+        write_file(path, rest).await?;
+        log::info!(
+            "Moved the metadata of file '{}' from its header to '{}'",
+            &self.file_path,
+            state_file.display()
+        );
+        self.write_state_file(
+            state_file,
+            metadata.library_version,
+            metadata.entries.unwrap_or_default(),
+            BTreeMap::new(),
+        )
+        .await
+    }
+
+    /// The metadata of the state file, if the exported file is still as it was written
+    async fn try_read_state_file(&self, state_file: &Path) -> Option<FileMetadata> {
+        let json = tokio::fs::read_to_string(state_file).await.ok()?;
+        let metadata: FileMetadata = serde_json::from_str(&json).ok()?;
+        let hash = file_hash(Path::new(&self.file_path)).await.ok()?;
+        if metadata.content_hash.as_deref() != Some(hash.as_str()) {
+            log::info!(
+                "File '{}' was changed since the export recorded in '{}'",
+                self.file_path,
+                state_file.display()
+            );
+            return None;
+        }
+        Some(metadata)
+    }
+
+    /// Refuse to replace an export that had entries with an empty one, which rather hints at a wrong filter,
+    /// a revoked permission or an API anomaly than at an emptied library
+    fn check_not_empty(
+        &self,
+        entries: usize,
+        metadata: Option<&FileMetadata>,
+    ) -> Result<(), ExportError> {
+        let previous_entries = metadata.and_then(|meta| meta.entries).unwrap_or_default();
+        if entries > 0 || previous_entries == 0 {
+            return Ok(());
+        }
+        if !self.allow_empty {
+            return Err(ExportError::EmptyExport {
+                file_path: self.file_path.clone(),
+                previous_entries,
+            });
+        }
+        log::warn!(
+            "Overwriting file '{}' that had {} entries with an empty export",
+            &self.file_path,
+            previous_entries
+        );
+        Ok(())
+    }
+
+    /// Identity of the exported library, e.g. `users/12345`, or `users/12345/collections/ABCD2345` for a collection
+    fn library(&self) -> String {
+        match &self.collection {
+            Some(key) => format!("{}/collections/{}", self.client.library(), key),
+            None => self.client.library().to_owned(),
+        }
+    }
+
+    /// Fetch the plain export page by page into a spool file next to the export file,
+    /// so that the export of a huge library is never held in memory
+    async fn fetch_spooled(
+        &self,
+        params: &FetchItemsParams,
+    ) -> Result<RenderedExport, ExportError> {
+        let spool_path = PathBuf::from(format!("{}.part", self.file_path));
+        let spool = tokio::fs::File::create(&spool_path)
+            .await
+            .map_err(|e| file_error(&spool_path, e))?;
+        let mut writer = BufWriter::new(spool);
+        let response = self.client.fetch_items(params, &mut writer).await;
+        drop(writer);
+        match response {
+            Ok(FetchDataResponse::Updated {
+                last_modified_version,
+                ..
+            }) => Ok(RenderedExport::Spooled {
+                last_modified_version,
+                spool_path,
+            }),
+            Ok(FetchDataResponse::UpToDate) => {
+                remove_spool(&spool_path).await;
+                Ok(RenderedExport::UpToDate)
+            }
+            Err(e) => {
+                remove_spool(&spool_path).await;
+                Err(e.into())
+            }
+        }
+    }
+
+    /// Write the header, if any, and the content of the spool file to the export file, then remove the spool file
+    async fn write_spooled(
+        &self,
+        header: Option<&str>,
+        spool_path: &Path,
+    ) -> Result<(), ExportError> {
+        let path = Path::new(&self.file_path);
+        let file = tokio::fs::File::create(path)
+            .await
+            .map_err(|e| file_error(path, e))?;
+        let mut writer = BufWriter::new(file);
+        let mut spool = tokio::fs::File::open(spool_path)
+            .await
+            .map_err(|e| file_error(spool_path, e))?;
+        if let Some(header) = header {
+            writer
+                .write_all(format!("{}\n", header).as_bytes())
+                .await
+                .map_err(|e| file_error(path, e))?;
+        }
+        tokio::io::copy(&mut spool, &mut writer)
+            .await
+            .map_err(|e| file_error(path, e))?;
+        writer.flush().await.map_err(|e| file_error(path, e))?;
+        remove_spool(spool_path).await;
+        Ok(())
+    }
+
+    /// Write the chunks to numbered files, remove the files of previous exports that had more chunks,
+    /// and return the index of the written files
+    async fn write_chunks(
+        &self,
+        header: Option<&str>,
+        chunks: &[String],
+    ) -> Result<String, ExportError> {
+        let mut index = String::new();
+        for (number, chunk) in chunks.iter().enumerate() {
+            let path = chunk_path(&self.file_path, number + 1);
+            write_file(&path, &with_header(header, chunk)).await?;
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            index.push_str(&name);
+            index.push('\n');
+        }
+        let index = with_header(header, &index);
+        let mut number = chunks.len() + 1;
+        loop {
+            let path = chunk_path(&self.file_path, number);
+            match tokio::fs::remove_file(&path).await {
+                Ok(()) => log::info!("Removed outdated file '{}'", path.display()),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => break,
+                Err(e) => {
+                    return Err(ExportError::FileError {
+                        file_path: path.display().to_string(),
+                        io_error: e,
+                    });
+                }
+            }
+            number += 1;
+        }
+        Ok(index)
+    }
+
+    /// Fetch the export including the item data, and post-process the single entries
+    async fn fetch(&self, params: &FetchItemsParams) -> Result<RenderedExport, ExportError> {
+        match self.client.fetch_items_with_data(params).await? {
+            FetchDataResponse::UpToDate => Ok(RenderedExport::UpToDate),
+            FetchDataResponse::Updated {
+                last_modified_version,
+                data,
+            } => {
+                let mut document = ExportDocument::try_from_items(data, &params.format)?;
+                if let Some(attachments) = &self.attachments {
+                    attachments
+                        .mirror(&self.client, &mut document, last_modified_version)
+                        .await?;
+                }
+                if let Some(linked_attachments) = &self.linked_attachments {
+                    linked_attachments.apply(&mut document);
+                }
+                if let Some(storage_attachments) = &self.storage_attachments {
+                    storage_attachments.apply(&mut document);
+                }
+                self.fetch_library_data(&mut document).await?;
+                for transform in &self.transforms {
+                    transform.apply(&mut document, &params.format);
+                }
+                match &self.collisions {
+                    Some(collisions) => collisions.resolve(&mut document)?,
+                    None => collisions::suffix_collisions(&mut document),
+                }
+                if let Some(order) = &self.sort {
+                    document.sort(order);
+                }
+                if let Some(mode) = &self.strict {
+                    let incomplete = incomplete_entries(&document);
+                    for entry in &incomplete {
+                        log::warn!("Incomplete entry: {}", entry);
+                    }
+                    if *mode == StrictMode::Fail && !incomplete.is_empty() {
+                        return Err(ExportError::Incomplete(incomplete.len()));
+                    }
+                }
+                if let Some(journal) = &self.journal
+                    && let Some(previous_version) = params.last_modified_version
+                {
+                    journal
+                        .record(&document, previous_version, last_modified_version)
+                        .await
+                        .map_err(|e| file_error(journal.path(), e))?;
+                }
+                if let Some(notes) = &self.notes {
+                    notes.write(&document).await?;
+                }
+                if let Some(annotations) = &self.annotations {
+                    annotations.write(&document).await?;
+                }
+                if let Some(obsidian) = &self.obsidian {
+                    obsidian.write(&document).await?;
+                }
+                if let Some(skeletons) = &self.skeletons {
+                    skeletons.write(&document).await?;
+                }
+                if let Some(keys) = &self.keys {
+                    keys.write(&document).await?;
+                }
+                if let Some(documents) = &self.documents {
+                    documents.write(&document).await?;
+                }
+                if let Some(ndjson) = &self.ndjson {
+                    ndjson.write(&document).await?;
+                }
+                if let Some(digest) = &self.digest {
+                    digest.record(&document);
+                }
+                Ok(RenderedExport::Updated {
+                    last_modified_version,
+                    entries: document.entries.len(),
+                    citation_keys: citation_keys(&document),
+                    chunks: match self.split {
+                        Some(entries_per_file) => document.render_chunks(entries_per_file),
+                        None => vec![document.render()],
+                    },
+                })
             }
         }
     }
 
+    /// Fetch only the items that changed since the existing export and splice their entries into the file,
+    /// removing the entries of items that were deleted or moved to the trash since then
+    async fn fetch_changes(
+        &self,
+        params: &FetchItemsParams,
+        mut citation_keys: BTreeMap<String, String>,
+    ) -> Result<RenderedExport, ExportError> {
+        let params = FetchItemsParams {
+            last_modified_version: params.last_modified_version,
+            since: params.last_modified_version,
+            format: params.format.clone(),
+            collection: params.collection.clone(),
+            tags: params.tags.clone(),
+            raw_format: None,
+        };
+        let (last_modified_version, items) =
+            match self.client.fetch_items_with_data(&params).await? {
+                FetchDataResponse::UpToDate => return Ok(RenderedExport::UpToDate),
+                FetchDataResponse::Updated {
+                    last_modified_version,
+                    data,
+                } => (last_modified_version, data),
+            };
+        let changed = ExportDocument::try_from_items(items, &params.format)?;
+        let since = params.since.unwrap_or_default();
+        let mut deleted = self.client.fetch_deleted(since).await?.items;
+        deleted.extend(self.client.fetch_trashed_keys(since).await?);
+        let path = Path::new(&self.file_path);
+        let content = tokio::fs::read_to_string(path)
+            .await
+            .map_err(|e| file_error(path, e))?;
+        // a header from the template is written anew
+        let content = match (&self.header, content.split_once('\n')) {
+            (FileHeader::Template(_), Some((_, rest))) => rest,
+            _ => content.as_str(),
+        };
+        log::info!(
+            "Merging {} changed entries into file '{}', removing the entries of {} deleted items",
+            changed.entries.len(),
+            &self.file_path,
+            deleted.len()
+        );
+        let blocks = merge_entries(
+            bibtex::parse(content)?,
+            changed
+                .entries
+                .into_iter()
+                .map(|entry| (entry.item.key, entry.entry))
+                .collect(),
+            &deleted,
+            &mut citation_keys,
+        );
+        Ok(RenderedExport::Updated {
+            last_modified_version,
+            entries: blocks
+                .iter()
+                .filter(|block| matches!(block, Block::Entry(_)))
+                .count(),
+            chunks: vec![bibtex::write(&blocks)],
+            citation_keys,
+        })
+    }
+
+    /// Fetch the library data that any of the transforms needs
+    async fn fetch_library_data(&self, document: &mut ExportDocument) -> Result<(), ExportError> {
+        let needs = |data| {
+            self.transforms
+                .iter()
+                .any(|transform| transform.needs().contains(&data))
+        };
+        if needs(LibraryData::TagColors) {
+            document.tag_colors = self
+                .client
+                .fetch_tag_colors()
+                .await?
+                .value
+                .into_iter()
+                .map(|tag_color| tag_color.name)
+                .collect();
+        }
+        let needs_collections = needs(LibraryData::Collections)
+            || self
+                .documents
+                .as_ref()
+                .is_some_and(ProjectDocuments::needs_collections);
+        if needs_collections
+            && let FetchDataResponse::Updated { data, .. } =
+                self.client.fetch_collections(None).await?
+        {
+            document.collections = data
+                .into_iter()
+                .map(|collection| (collection.key.clone(), collection))
+                .collect();
+        }
+        Ok(())
+    }
+
+    /// Whether any option needs the item data in addition to the plain export
+    fn needs_item_data(&self) -> bool {
+        // incremental exports need the item keys to find the previous entries of the items
+        self.incremental || self.needs_post_processing()
+    }
+
+    /// Whether any option modifies or writes out the entries, or needs the whole library
+    fn needs_post_processing(&self) -> bool {
+        self.attachments.is_some()
+            || self.journal.is_some()
+            || self.linked_attachments.is_some()
+            || self.storage_attachments.is_some()
+            || self.notes.is_some()
+            || self.annotations.is_some()
+            || self.obsidian.is_some()
+            || self.skeletons.is_some()
+            || self.keys.is_some()
+            || self.documents.is_some()
+            || self.ndjson.is_some()
+            || !self.transforms.is_empty()
+            || self.split.is_some()
+            || self.strict.is_some()
+            || self.collisions.is_some()
+            || self.sort.is_some()
+            || self.digest.is_some()
+    }
+
+    /// Whether the export is written as the API returns it, without header and post-processing
+    fn is_verbatim(&self) -> bool {
+        self.format == ExportFormat::Json || self.raw_format.is_some()
+    }
+
+    /// The post-processing only works on bibliography entries, not on exports that are written as the API returns them
+    fn check_verbatim_options(&self) -> Result<(), ExportError> {
+        if self.is_verbatim() && self.needs_item_data() {
+            return Err(ExportError::UnsupportedForFormat(
+                self.raw_format
+                    .clone()
+                    .unwrap_or_else(|| self.format.to_string()),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Incremental exports only splice in the entries as Zotero exports them, into a single file with a state file.
+    /// Filtered exports aren't supported, items that leave the filtered set aren't modified or deleted and would stay in the file.
+    fn check_incremental_options(&self) -> Result<(), ExportError> {
+        if self.incremental
+            && (self.state_file.is_none()
+                || self.split.is_some()
+                || self.collection.is_some()
+                || !self.tags.is_empty()
+                || self.is_verbatim()
+                || self.needs_post_processing())
+        {
+            return Err(ExportError::UnsupportedForIncremental);
+        }
+        Ok(())
+    }
+
+    /// Options that read the written file back or write files next to it don't work with a named pipe
+    fn check_fifo_options(&self) -> Result<(), ExportError> {
+        if !self.fifo {
+            return Ok(());
+        }
+        let unsupported = unsupported_fifo_option(FifoOptions {
+            state_file: self.state_file.is_some(),
+            incremental: self.incremental,
+            snapshots: self.snapshots.is_some(),
+            split: self.split.is_some(),
+            manifest: self.manifest.is_some(),
+        });
+        match unsupported {
+            Some(option) => Err(ExportError::UnsupportedForFifo {
+                file_path: self.file_path.clone(),
+                option,
+            }),
+            None => Ok(()),
+        }
+    }
+
     async fn try_read_file_metadata(&self) -> Option<FileMetadata> {
+        if self.fifo {
+            // reading would consume the data meant for the other process
+            return None;
+        }
+        if let Some(state_file) = &self.state_file {
+            if let Some(metadata) = self.try_read_state_file(state_file).await {
+                return Some(metadata);
+            }
+            log::debug!(
+                "No valid state file '{}', looking for the metadata in the header",
+                state_file.display()
+            );
+        }
+        // JSON and raw formats have no comments to keep the metadata in, so without a state file these exports are always complete
+        if self.is_verbatim() {
+            return None;
+        }
         let file = OpenOptions::new()
             .read(true)
             .open(&self.file_path)
@@ -136,6 +1255,244 @@ impl<TClient: ZoteroClient> FileExporter<TClient> {
     }
 }
 
+/// First line of the exported file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum FileHeader {
+    /// Comment with the metadata of the export, to export only after changes
+    #[default]
+    Metadata,
+    None,
+    /// Line with the placeholders `{version}`, `{timestamp}`, `{entries}`, `{library}`, `{format}` and `{zotexon_version}`
+    Template(String),
+}
+
+/// The header template with the placeholders replaced by the metadata of the export
+fn render_header(template: &str, metadata: &FileMetadata, now: DateTime<Utc>) -> String {
+    template
+        .replace("{version}", &metadata.library_version.to_string())
+        .replace(
+            "{timestamp}",
+            &now.to_rfc3339_opts(SecondsFormat::Secs, true),
+        )
+        .replace(
+            "{entries}",
+            &metadata.entries.unwrap_or_default().to_string(),
+        )
+        .replace("{library}", metadata.library.as_deref().unwrap_or_default())
+        .replace("{format}", &metadata.format.to_string())
+        .replace("{zotexon_version}", &metadata.zotexon_version)
+}
+
+fn citation_keys(document: &ExportDocument) -> BTreeMap<String, String> {
+    document
+        .entries
+        .iter()
+        .map(|entry| (entry.item.key.clone(), entry.entry.key.clone()))
+        .collect()
+}
+
+fn with_header(header: Option<&str>, content: &str) -> String {
+    match header {
+        Some(header) => format!("{}\n{}", header, content),
+        None => content.to_owned(),
+    }
+}
+
+/// Export text of the library, split into the contents of several files if configured
+enum RenderedExport {
+    UpToDate,
+    Updated {
+        last_modified_version: u64,
+        entries: usize,
+        chunks: Vec<String>,
+        /// Citation keys of the exported entries by item key
+        citation_keys: BTreeMap<String, String>,
+    },
+    /// The plain export, written to a spool file
+    Spooled {
+        last_modified_version: u64,
+        spool_path: PathBuf,
+    },
+}
+
+/// Options of an export to a named pipe that need to read the written file back or write files next to it
+struct FifoOptions {
+    state_file: bool,
+    incremental: bool,
+    snapshots: bool,
+    split: bool,
+    manifest: bool,
+}
+
+/// The first of the used options that doesn't work with a named pipe, if any. The state file is read back
+/// to check it, and incremental exports read the previous export from the file.
+fn unsupported_fifo_option(options: FifoOptions) -> Option<&'static str> {
+    [
+        (options.state_file, "--state-file"),
+        (options.incremental, "--incremental"),
+        (options.snapshots, "--snapshots"),
+        (options.split, "--split-entries"),
+        (options.manifest, "--manifest"),
+    ]
+    .into_iter()
+    .find_map(|(used, option)| used.then_some(option))
+}
+
+/// Whether the path is a named pipe (FIFO)
+async fn is_fifo(path: &str) -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        tokio::fs::metadata(path)
+            .await
+            .is_ok_and(|metadata| metadata.file_type().is_fifo())
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        false
+    }
+}
+
+/// Path of the numbered file for a chunk of the export, e.g. `refs-001.bib` for `refs.bib`
+fn chunk_path(file_path: &str, number: usize) -> PathBuf {
+    let path = Path::new(file_path);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(extension) => format!("{}-{:03}.{}", stem, number, extension.to_string_lossy()),
+        None => format!("{}-{:03}", stem, number),
+    };
+    path.with_file_name(name)
+}
+
+async fn write_file(path: &Path, content: &str) -> Result<(), ExportError> {
+    tokio::fs::write(path, content)
+        .await
+        .map_err(|e| file_error(path, e))
+}
+
+/// Number of entries in the plain export of the spool file
+async fn count_spooled_entries(spool_path: &Path) -> Result<usize, ExportError> {
+    let spool = tokio::fs::File::open(spool_path)
+        .await
+        .map_err(|e| file_error(spool_path, e))?;
+    let mut lines = tokio::io::BufReader::new(spool).lines();
+    let mut entries = 0;
+    while let Some(line) = lines
+        .next_line()
+        .await
+        .map_err(|e| file_error(spool_path, e))?
+    {
+        if is_entry_start(&line) {
+            entries += 1;
+        }
+    }
+    Ok(entries)
+}
+
+/// Whether several entries in the plain export of the spool file have the same citation key
+async fn has_spooled_collisions(spool_path: &Path) -> Result<bool, ExportError> {
+    let spool = tokio::fs::File::open(spool_path)
+        .await
+        .map_err(|e| file_error(spool_path, e))?;
+    let mut lines = tokio::io::BufReader::new(spool).lines();
+    let mut citekeys = BTreeSet::new();
+    while let Some(line) = lines
+        .next_line()
+        .await
+        .map_err(|e| file_error(spool_path, e))?
+    {
+        if is_entry_start(&line)
+            && let Some(citekey) = entry_citekey(&line)
+            && !citekeys.insert(citekey.to_owned())
+        {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Number of items in the JSON array of a spool file, which is read without keeping the items
+async fn count_spooled_items(spool_path: &Path) -> Result<usize, ExportError> {
+    let path = spool_path.to_owned();
+    let counted = tokio::task::spawn_blocking(move || {
+        let reader = std::io::BufReader::new(std::fs::File::open(&path)?);
+        let items: Vec<serde::de::IgnoredAny> = serde_json::from_reader(reader)?;
+        Ok::<_, std::io::Error>(items.len())
+    })
+    .await
+    .map_err(std::io::Error::other)
+    .flatten();
+    counted.map_err(|e| file_error(spool_path, e))
+}
+
+async fn read_file(path: &Path) -> Result<Vec<u8>, ExportError> {
+    tokio::fs::read(path).await.map_err(|e| file_error(path, e))
+}
+
+/// Number of entries in the content of an exported file
+fn count_entries(content: &[u8]) -> usize {
+    String::from_utf8_lossy(content)
+        .lines()
+        .filter(|line| is_entry_start(line))
+        .count()
+}
+
+/// Whether the line of an export starts a new entry, e.g. `@article{key,`
+fn is_entry_start(line: &str) -> bool {
+    line.starts_with('@')
+}
+
+/// Citation key of the line that starts an entry, e.g. `smith_2020` of `@article{smith_2020,`
+fn entry_citekey(line: &str) -> Option<&str> {
+    let (_, rest) = line.split_once('{')?;
+    let citekey = rest.split([',', '}']).next()?.trim();
+    (!citekey.is_empty()).then_some(citekey)
+}
+
+async fn remove_spool(path: &Path) {
+    if let Err(e) = tokio::fs::remove_file(path).await {
+        log::warn!("Failed to remove spool file '{}': {}", path.display(), e);
+    }
+}
+
+/// Whether the error might be caused by an API key that lost its access
+fn is_permission_error(error: &ExportError) -> bool {
+    matches!(
+        error,
+        ExportError::ClientError(ApiError::UnexpectedStatus {
+            status: reqwest::StatusCode::FORBIDDEN,
+            ..
+        })
+    )
+}
+
+/// SHA-256 hash of the exported file, to notice when it was changed by something else than the export
+async fn file_hash(path: &Path) -> std::io::Result<String> {
+    content_hash(tokio::fs::File::open(path).await?).await
+}
+
+/// SHA-256 hash of the content, read in chunks so that a large export is never held in memory as a whole
+async fn content_hash(mut content: impl AsyncRead + Unpin) -> std::io::Result<String> {
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = content.read(&mut buffer).await?;
+        if read == 0 {
+            return Ok(format!("{:x}", hasher.finalize()));
+        }
+        hasher.update(&buffer[..read]);
+    }
+}
+
+fn file_error(path: &Path, io_error: std::io::Error) -> ExportError {
+    ExportError::FileError {
+        file_path: path.display().to_string(),
+        io_error,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ExportSuccess {
     Changes,
     NoChanges,
@@ -151,6 +1508,58 @@ pub enum ExportError {
     },
     #[error("Error in Zotero client")]
     ClientError(#[from] ApiError),
+    #[error("Error while storing snapshot")]
+    Snapshot(#[from] SnapshotError),
+    #[error("Error while parsing the export of an item")]
+    Parse(#[from] ParseError),
+    #[error("Error while mirroring attachments")]
+    Attachments(#[from] AttachmentError),
+    #[error("Error while exporting notes")]
+    Notes(#[from] NotesError),
+    #[error("Error while exporting annotations")]
+    Annotations(#[from] AnnotationsError),
+    #[error("Error while writing Obsidian notes")]
+    Obsidian(#[from] ObsidianError),
+    #[error("Error while creating note skeletons")]
+    Skeletons(#[from] SkeletonError),
+    #[error("Error with colliding citation keys")]
+    Collisions(#[from] CollisionError),
+    #[error("Error while writing the key list")]
+    Keys(#[from] KeysError),
+    #[error("Error while writing the bibliographies of the documents")]
+    Documents(#[from] DocumentsError),
+    #[error("Error while writing the NDJSON items")]
+    Ndjson(#[from] NdjsonError),
+    #[error(
+        "File '{file_path}' was exported from library '{library}', refusing to overwrite it with another library"
+    )]
+    OtherLibrary { file_path: String, library: String },
+    #[error(
+        "The API key was revoked or lost access to the library. Create a new key at https://www.zotero.org/settings/keys/new"
+    )]
+    KeyRevoked,
+    #[error("File '{file_path}' is a named pipe, which can't be used with {option}")]
+    UnsupportedForFifo {
+        file_path: String,
+        option: &'static str,
+    },
+    #[error(
+        "The {0} format is written as Zotero returns it, options that modify or write out single entries need the biblatex or bibtex format"
+    )]
+    UnsupportedForFormat(String),
+    #[error(
+        "Incremental exports need --state-file and the biblatex or bibtex format, and can't be used with filters, options that modify or write out single entries or split the export"
+    )]
+    UnsupportedForIncremental,
+    #[error("{0} entries lack required fields, see the warnings above")]
+    Incomplete(usize),
+    #[error(
+        "The export is empty, but file '{file_path}' had {previous_entries} entries. Check the filters and permissions of the API key, or pass --allow-empty to overwrite it"
+    )]
+    EmptyExport {
+        file_path: String,
+        previous_entries: usize,
+    },
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -158,13 +1567,49 @@ struct FileMetadata {
     zotexon_version: String,
     library_version: u64,
     format: ExportFormat,
+    /// Format that was requested instead of `format`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    raw_format: Option<String>,
+    /// Entries per file if the export was split
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    split: Option<usize>,
+    /// Library that was exported, missing in files of older versions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    library: Option<String>,
+    /// Number of exported entries, missing in files of older versions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    entries: Option<usize>,
+    /// Tag filters of the exported items
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    /// Time of the export, only in state files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    exported_at: Option<DateTime<Utc>>,
+    /// SHA-256 hash of the exported file, only in state files
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    content_hash: Option<String>,
+    /// Citation keys of the exported entries by item key, only in state files of incremental exports
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    citation_keys: BTreeMap<String, String>,
 }
 
 impl FileMetadata {
     const PREFIX: &'static str = "% *** THIS FILE WAS AUTO-GENERATED BY ZOTEXON - DO NOT EDIT ***";
 
-    fn matches_format(&self, format: &ExportFormat) -> bool {
-        (format == &self.format) && (ZOTEXON_VERSION == self.zotexon_version)
+    fn matches(
+        &self,
+        format: &ExportFormat,
+        raw_format: Option<&str>,
+        split: Option<usize>,
+        library: &str,
+        tags: &[String],
+    ) -> bool {
+        (format == &self.format)
+            && (raw_format == self.raw_format.as_deref())
+            && (split == self.split)
+            && (self.library.as_deref() == Some(library))
+            && (tags == self.tags)
+            && (ZOTEXON_VERSION == self.zotexon_version)
     }
 }
 
@@ -193,6 +1638,22 @@ impl TryFrom<&str> for FileMetadata {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn forbidden_is_permission_error() {
+        let forbidden = ExportError::ClientError(ApiError::UnexpectedStatus {
+            status: reqwest::StatusCode::FORBIDDEN,
+            body: "Forbidden".to_owned(),
+        });
+        let server_error = ExportError::ClientError(ApiError::UnexpectedStatus {
+            status: reqwest::StatusCode::INTERNAL_SERVER_ERROR,
+            body: String::new(),
+        });
+        assert!(is_permission_error(&forbidden));
+        assert!(!is_permission_error(&server_error));
+        assert!(!is_permission_error(&ExportError::KeyRevoked));
+    }
 
     #[test]
     fn test_file_headline_string_conversion() {
@@ -200,6 +1661,14 @@ mod tests {
             zotexon_version: "0.1.0".to_owned(),
             library_version: 12345,
             format: Default::default(),
+            raw_format: None,
+            split: None,
+            library: Some("users/1".to_owned()),
+            entries: Some(300),
+            tags: vec!["-toread".to_owned()],
+            exported_at: None,
+            content_hash: None,
+            citation_keys: BTreeMap::new(),
         };
         let headline_str: String = headline.into();
 
@@ -207,5 +1676,132 @@ mod tests {
         assert!(parsed_headline.is_ok());
         let parsed_headline = parsed_headline.unwrap();
         assert_eq!(parsed_headline.library_version, 12345);
+        assert_eq!(parsed_headline.library.as_deref(), Some("users/1"));
+        assert_eq!(parsed_headline.entries, Some(300));
+        assert_eq!(parsed_headline.tags, vec!["-toread"]);
+    }
+
+    #[tokio::test]
+    async fn state_file_metadata() {
+        let hash_a = content_hash(&b"@book{a,\n}\n"[..]).await.unwrap();
+        let hash_b = content_hash(&b"@book{b,\n}\n"[..]).await.unwrap();
+        let metadata: FileMetadata = serde_json::from_value(serde_json::json!({
+            "zotexon_version": ZOTEXON_VERSION,
+            "library_version": 42,
+            "format": "biblatex",
+            "library": "users/1",
+            "entries": 2,
+            "exported_at": "2025-10-01T12:00:00Z",
+            "content_hash": hash_a
+        }))
+        .unwrap();
+        assert_eq!(
+            metadata.content_hash.as_deref(),
+            Some("e7dfce15a143abe0e1a75e1e284cf80e02a8e294e51d3a84fa3ff5b600accb46")
+        );
+        assert!(metadata.matches(&ExportFormat::Biblatex, None, None, "users/1", &[]));
+        assert!(!metadata.matches(&ExportFormat::Biblatex, Some("mods"), None, "users/1", &[]));
+        assert_ne!(hash_a, hash_b);
+    }
+
+    #[test]
+    fn header_template() {
+        let metadata = FileMetadata {
+            zotexon_version: "0.5.0".to_owned(),
+            library_version: 42,
+            format: ExportFormat::Bibtex,
+            raw_format: None,
+            split: None,
+            library: Some("groups/111".to_owned()),
+            entries: Some(7),
+            tags: Vec::new(),
+            exported_at: None,
+            content_hash: None,
+            citation_keys: BTreeMap::new(),
+        };
+        let now = Utc.with_ymd_and_hms(2025, 10, 1, 12, 30, 0).unwrap();
+        assert_eq!(
+            render_header(
+                "% {library} version {version} at {timestamp}: {entries} {format} entries",
+                &metadata,
+                now
+            ),
+            "% groups/111 version 42 at 2025-10-01T12:30:00Z: 7 bibtex entries"
+        );
+    }
+
+    #[test]
+    fn reject_options_for_fifo() {
+        let options = || FifoOptions {
+            state_file: false,
+            incremental: false,
+            snapshots: false,
+            split: false,
+            manifest: false,
+        };
+        assert_eq!(unsupported_fifo_option(options()), None);
+        assert_eq!(
+            unsupported_fifo_option(FifoOptions {
+                state_file: true,
+                ..options()
+            }),
+            Some("--state-file")
+        );
+        assert_eq!(
+            unsupported_fifo_option(FifoOptions {
+                incremental: true,
+                ..options()
+            }),
+            Some("--incremental")
+        );
+        assert_eq!(
+            unsupported_fifo_option(FifoOptions {
+                manifest: true,
+                ..options()
+            }),
+            Some("--manifest")
+        );
+    }
+
+    #[test]
+    fn test_chunk_path() {
+        assert_eq!(
+            chunk_path("export/refs.bib", 2),
+            PathBuf::from("export/refs-002.bib")
+        );
+        assert_eq!(chunk_path("refs", 12), PathBuf::from("refs-012"));
+    }
+
+    #[test]
+    fn test_is_entry_start() {
+        assert!(is_entry_start("@article{smith_2020,"));
+        assert!(!is_entry_start("\ttitle = {@home},"));
+        assert!(!is_entry_start(""));
+    }
+
+    #[test]
+    fn test_entry_citekey() {
+        assert_eq!(entry_citekey("@article{smith_2020,"), Some("smith_2020"));
+        assert_eq!(entry_citekey("@book{smith_2020}"), Some("smith_2020"));
+        assert_eq!(entry_citekey("@article{,"), None);
+    }
+
+    #[tokio::test]
+    async fn detect_spooled_collisions() {
+        let spool_path = std::env::temp_dir().join(format!(
+            "zotexon-collisions-{}.bib.part",
+            std::process::id()
+        ));
+        let entry = "@article{smith_2020,\n\ttitle = {Title},\n}\n";
+        tokio::fs::write(&spool_path, format!("{entry}@book{{lee_2021,\n}}\n"))
+            .await
+            .unwrap();
+        assert!(!has_spooled_collisions(&spool_path).await.unwrap());
+        tokio::fs::write(&spool_path, format!("{entry}{entry}"))
+            .await
+            .unwrap();
+        assert!(has_spooled_collisions(&spool_path).await.unwrap());
+        assert_eq!(count_spooled_entries(&spool_path).await.unwrap(), 2);
+        tokio::fs::remove_file(&spool_path).await.unwrap();
     }
 }
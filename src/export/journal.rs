@@ -0,0 +1,115 @@
+use crate::export::document::ExportDocument;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+
+/// Journal of the items that changed between exports, one JSON object per line.
+///
+/// Every export appends the entries whose item version is newer than the version of the previous export,
+/// with the user who made the change for items of group libraries.
+pub struct ChangeJournal {
+    path: PathBuf,
+}
+
+/// One changed item in the journal
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalRecord {
+    pub exported_at: DateTime<Utc>,
+    pub library_version: u64,
+    pub item_key: String,
+    pub citekey: String,
+    /// User of a group library who last modified or created the item
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+}
+
+impl ChangeJournal {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Append the entries of the document that changed since the previous export to the journal
+    pub async fn record(
+        &self,
+        document: &ExportDocument,
+        previous_version: u64,
+        library_version: u64,
+    ) -> std::io::Result<()> {
+        let records = changed_entries(document, previous_version, library_version, Utc::now());
+        if records.is_empty() {
+            return Ok(());
+        }
+        let mut lines = String::new();
+        for record in &records {
+            lines.push_str(&serde_json::to_string(record)?);
+            lines.push('\n');
+        }
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(lines.as_bytes()).await?;
+        log::debug!(
+            "Recorded {} changed items in journal '{}'",
+            records.len(),
+            self.path.display()
+        );
+        Ok(())
+    }
+}
+
+fn changed_entries(
+    document: &ExportDocument,
+    previous_version: u64,
+    library_version: u64,
+    exported_at: DateTime<Utc>,
+) -> Vec<JournalRecord> {
+    document
+        .entries
+        .iter()
+        .filter(|entry| entry.item.version > previous_version)
+        .map(|entry| JournalRecord {
+            exported_at,
+            library_version,
+            item_key: entry.item.key.clone(),
+            citekey: entry.entry.key.clone(),
+            user: entry.item.meta.last_modified_by().map(str::to_owned),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::document::tests::document;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn record_changed_entries_with_user() {
+        let mut document = document();
+        let exported_at = Utc::now();
+        assert_eq!(changed_entries(&document, 3, 5, exported_at), vec![]);
+        document.entries[0].item.meta = serde_json::from_value(serde_json::json!({
+            "createdByUser": {"id": 1, "username": "ada"},
+            "lastModifiedByUser": {"id": 2, "username": "bob"}
+        }))
+        .unwrap();
+        assert_eq!(
+            changed_entries(&document, 2, 5, exported_at),
+            vec![JournalRecord {
+                exported_at,
+                library_version: 5,
+                item_key: "PARENT01".to_owned(),
+                citekey: "smith_title_2020".to_owned(),
+                user: Some("bob".to_owned()),
+            }]
+        );
+    }
+}
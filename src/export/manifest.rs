@@ -0,0 +1,116 @@
+use crate::ZOTEXON_VERSION;
+use crate::paths::write_if_changed;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+/// Lists the files of an export with their format, library version, number of entries and content hash.
+///
+/// Written after every run, also without changes, so that Make, Ninja or CI pipelines can declare
+/// precise dependencies on the exported files.
+pub struct Manifest {
+    path: PathBuf,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct ManifestContent<'a> {
+    zotexon_version: &'a str,
+    library: &'a str,
+    files: &'a [ManifestFile],
+}
+
+/// An output file of the export
+#[derive(Serialize, Debug, PartialEq)]
+pub struct ManifestFile {
+    path: String,
+    /// Export format like `biblatex`, `index` for the index of a split export, or `keys` for the citation keys
+    format: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    library_version: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    entries: Option<usize>,
+    sha256: String,
+}
+
+impl ManifestFile {
+    pub fn new(path: &Path, format: &str, content: &[u8]) -> Self {
+        Self {
+            path: path.display().to_string(),
+            format: format.to_owned(),
+            library_version: None,
+            entries: None,
+            sha256: format!("{:x}", Sha256::digest(content)),
+        }
+    }
+
+    pub fn with_contents(mut self, library_version: Option<u64>, entries: usize) -> Self {
+        self.library_version = library_version;
+        self.entries = Some(entries);
+        self
+    }
+}
+
+impl Manifest {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Write the manifest, keeping the file untouched if nothing changed so that build systems don't rebuild
+    pub async fn write(&self, library: &str, files: &[ManifestFile]) -> std::io::Result<()> {
+        if write_if_changed(&self.path, &render(library, files)).await? {
+            log::info!(
+                "Wrote manifest of {} files to '{}'",
+                files.len(),
+                self.path.display()
+            );
+        }
+        Ok(())
+    }
+}
+
+fn render(library: &str, files: &[ManifestFile]) -> String {
+    let content = ManifestContent {
+        zotexon_version: ZOTEXON_VERSION,
+        library,
+        files,
+    };
+    serde_json::to_string_pretty(&content).unwrap_or_default() + "\n"
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn render_manifest() {
+        let files = [
+            ManifestFile::new(Path::new("refs.bib"), "biblatex", b"@book{a,}\n")
+                .with_contents(Some(42), 1),
+            ManifestFile::new(Path::new("keys.txt"), "keys", b"a\n"),
+        ];
+        let manifest: serde_json::Value = serde_json::from_str(&render("users/1", &files)).unwrap();
+        assert_eq!(manifest["library"], "users/1");
+        assert_eq!(
+            manifest["files"],
+            serde_json::json!([
+                {
+                    "path": "refs.bib",
+                    "format": "biblatex",
+                    "library_version": 42,
+                    "entries": 1,
+                    "sha256": format!("{:x}", Sha256::digest(b"@book{a,}\n"))
+                },
+                {
+                    "path": "keys.txt",
+                    "format": "keys",
+                    "sha256": "87428fc522803d31065e7bce3cf03fe475096631e5e07bbd7a0fde60c4cf25c7"
+                }
+            ])
+        );
+    }
+}
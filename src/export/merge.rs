@@ -0,0 +1,127 @@
+use crate::bibtex::{Block, Entry};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+
+/// Splice the entries of changed items into the blocks of an existing export. The entry of an item that was exported
+/// before replaces the entry with the citation key recorded for the item in `citation_keys`, entries of new items are
+/// appended, and the entries of the `deleted` items are removed. `citation_keys` maps item keys to citation keys
+/// and is updated accordingly.
+pub fn merge_entries(
+    blocks: Vec<Block>,
+    changed: Vec<(String, Entry)>,
+    deleted: &[String],
+    citation_keys: &mut BTreeMap<String, String>,
+) -> Vec<Block> {
+    let mut removals: HashMap<String, usize> = HashMap::new();
+    for item_key in deleted {
+        if let Some(key) = citation_keys.remove(item_key) {
+            *removals.entry(key).or_default() += 1;
+        }
+    }
+    let mut replacements: HashMap<String, VecDeque<Entry>> = HashMap::new();
+    let mut added = Vec::new();
+    for (item_key, entry) in changed {
+        match citation_keys.insert(item_key, entry.key.clone()) {
+            Some(previous_key) => replacements
+                .entry(previous_key)
+                .or_default()
+                .push_back(entry),
+            None => added.push(entry),
+        }
+    }
+    let mut merged: Vec<Block> = blocks
+        .into_iter()
+        .filter_map(|block| match block {
+            Block::Entry(entry) => {
+                if let Some(count) = removals.get_mut(&entry.key).filter(|count| **count > 0) {
+                    *count -= 1;
+                    return None;
+                }
+                match replacements
+                    .get_mut(&entry.key)
+                    .and_then(VecDeque::pop_front)
+                {
+                    Some(replacement) => Some(Block::Entry(replacement)),
+                    None => Some(Block::Entry(entry)),
+                }
+            }
+            other => Some(other),
+        })
+        .collect();
+    // entries whose previous entry isn't in the file anymore, e.g. because it was removed by hand
+    let mut missing: Vec<Entry> = replacements.into_values().flatten().collect();
+    missing.sort_by(|a, b| a.key.cmp(&b.key));
+    merged.extend(missing.into_iter().chain(added).map(Block::Entry));
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bibtex;
+    use pretty_assertions::assert_eq;
+
+    fn entry(text: &str) -> Entry {
+        match bibtex::parse(text).unwrap().remove(0) {
+            Block::Entry(entry) => entry,
+            Block::Other(_) => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn merge_changed_entries() {
+        let blocks = bibtex::parse(
+            "@string{jphys = {J. Phys.}}\n\n@book{optics_1990,\n\ttitle = {Optics}\n}\n\n@book{lenses_2001,\n\ttitle = {Lenses}\n}\n",
+        )
+        .unwrap();
+        let mut citation_keys = BTreeMap::from([
+            ("ITEM0001".to_owned(), "optics_1990".to_owned()),
+            ("ITEM0002".to_owned(), "lenses_2001".to_owned()),
+        ]);
+        let changed = vec![
+            (
+                "ITEM0003".to_owned(),
+                entry("@book{mirrors_2010,\n\ttitle = {Mirrors}\n}"),
+            ),
+            (
+                "ITEM0001".to_owned(),
+                entry("@book{optics_1991,\n\ttitle = {Optics}\n}"),
+            ),
+        ];
+        let merged = merge_entries(blocks, changed, &[], &mut citation_keys);
+        assert_eq!(
+            bibtex::write(&merged),
+            "\n@string{jphys = {J. Phys.}}\n\n@book{optics_1991,\n\ttitle = {Optics}\n}\n\n@book{lenses_2001,\n\ttitle = {Lenses}\n}\n\n@book{mirrors_2010,\n\ttitle = {Mirrors}\n}\n"
+        );
+        assert_eq!(
+            citation_keys.get("ITEM0001").map(String::as_str),
+            Some("optics_1991")
+        );
+        assert_eq!(
+            citation_keys.get("ITEM0003").map(String::as_str),
+            Some("mirrors_2010")
+        );
+    }
+
+    #[test]
+    fn remove_deleted_entries() {
+        let blocks = bibtex::parse(
+            "@book{optics_1990,\n\ttitle = {Optics}\n}\n\n@book{lenses_2001,\n\ttitle = {Lenses}\n}\n",
+        )
+        .unwrap();
+        let mut citation_keys = BTreeMap::from([
+            ("ITEM0001".to_owned(), "optics_1990".to_owned()),
+            ("ITEM0002".to_owned(), "lenses_2001".to_owned()),
+        ]);
+        let merged = merge_entries(
+            blocks,
+            Vec::new(),
+            &["ITEM0001".to_owned(), "UNKNOWN1".to_owned()],
+            &mut citation_keys,
+        );
+        assert_eq!(
+            bibtex::write(&merged),
+            "\n@book{lenses_2001,\n\ttitle = {Lenses}\n}\n"
+        );
+        assert_eq!(citation_keys.len(), 1);
+    }
+}
@@ -1,6 +1,22 @@
+mod collisions;
+pub mod document;
+mod events;
 mod file;
+mod journal;
+mod manifest;
+mod merge;
+mod snapshot;
+mod strict;
+mod target;
 mod trigger;
 mod websocket;
 
-pub use file::FileExporter;
+pub use collisions::{CollisionMode, KeyCollisions, KeyDecision};
+pub use events::{EventSender, ExportEvent};
+pub use file::{ExportError, ExportSuccess, FileExporter, FileHeader};
+pub use journal::ChangeJournal;
+pub use manifest::Manifest;
+pub use snapshot::rollback;
+pub use strict::StrictMode;
+pub use target::ExportTarget;
 pub use trigger::ExportTrigger;
@@ -0,0 +1,307 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+/// Content-addressed store of previous exports of a single file.
+///
+/// Snapshots live next to the exported file in a directory called `<file>.snapshots`.
+/// Every distinct file content is stored once under its SHA-256 hash,
+/// and `history.json` records which library version produced which content,
+/// and which snapshots were restored by rollbacks.
+pub struct SnapshotStore {
+    dir: PathBuf,
+}
+
+/// One entry in the snapshot history
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SnapshotRecord {
+    pub library_version: u64,
+    pub hash: String,
+    pub created_at: DateTime<Utc>,
+    /// Index of the restored snapshot in the history, if this record is a rollback instead of an export
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub restored: Option<usize>,
+}
+
+impl SnapshotStore {
+    const HISTORY_FILE: &'static str = "history.json";
+
+    /// Snapshot store belonging to the given export file
+    pub fn for_file(file_path: &str) -> Self {
+        Self {
+            dir: PathBuf::from(format!("{}.snapshots", file_path)),
+        }
+    }
+
+    /// Store the content of an export, unless the same content is already stored for this library version
+    pub async fn save(
+        &self,
+        library_version: u64,
+        content: &str,
+    ) -> Result<SnapshotRecord, SnapshotError> {
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(|e| self.io_error(&self.dir, e))?;
+        let hash = content_hash(content);
+        let object_path = self.object_path(&hash);
+        if !tokio::fs::try_exists(&object_path).await.unwrap_or(false) {
+            tokio::fs::write(&object_path, content)
+                .await
+                .map_err(|e| self.io_error(&object_path, e))?;
+        }
+        let mut history = self.history().await?;
+        let record = SnapshotRecord {
+            library_version,
+            hash,
+            created_at: Utc::now(),
+            restored: None,
+        };
+        if !is_duplicate(&history, &record) {
+            history.push(record.clone());
+            self.write_history(&history).await?;
+            log::debug!(
+                "Stored snapshot {} for library version {}",
+                record.hash,
+                record.library_version
+            );
+        }
+        Ok(record)
+    }
+
+    /// All recorded snapshots, oldest first
+    pub async fn history(&self) -> Result<Vec<SnapshotRecord>, SnapshotError> {
+        let path = self.dir.join(Self::HISTORY_FILE);
+        match tokio::fs::read_to_string(&path).await {
+            Ok(json) => serde_json::from_str(&json).map_err(|e| SnapshotError::CorruptHistory {
+                path: path.display().to_string(),
+                json_error: e,
+            }),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(self.io_error(&path, e)),
+        }
+    }
+
+    /// Content of a stored snapshot
+    pub async fn load(&self, record: &SnapshotRecord) -> Result<String, SnapshotError> {
+        let object_path = self.object_path(&record.hash);
+        tokio::fs::read_to_string(&object_path)
+            .await
+            .map_err(|e| self.io_error(&object_path, e))
+    }
+
+    /// Index of the snapshot to roll back to in the history:
+    /// - the snapshot of the given library version, or
+    /// - the one before the current snapshot, if no version is given. After a rollback, the current snapshot is
+    ///   the restored one, so that repeated rollbacks step back one export at a time.
+    pub fn select(
+        history: &[SnapshotRecord],
+        to_version: Option<u64>,
+    ) -> Result<usize, SnapshotError> {
+        let exports = |end: usize| {
+            history[..end]
+                .iter()
+                .enumerate()
+                .rev()
+                .filter(|(_, record)| record.restored.is_none())
+        };
+        match to_version {
+            Some(version) => exports(history.len())
+                .find(|(_, record)| record.library_version == version)
+                .map(|(index, _)| index)
+                .ok_or(SnapshotError::VersionNotFound(version)),
+            None => {
+                let current = match history.last() {
+                    Some(SnapshotRecord {
+                        restored: Some(index),
+                        ..
+                    }) => *index,
+                    _ => history.len().saturating_sub(1),
+                };
+                exports(current)
+                    .next()
+                    .map(|(index, _)| index)
+                    .ok_or(SnapshotError::NoPreviousSnapshot)
+            }
+        }
+    }
+
+    /// Record that the snapshot at the index of the history was restored
+    async fn record_rollback(
+        &self,
+        mut history: Vec<SnapshotRecord>,
+        index: usize,
+    ) -> Result<(), SnapshotError> {
+        let restored = &history[index];
+        let record = SnapshotRecord {
+            library_version: restored.library_version,
+            hash: restored.hash.clone(),
+            created_at: Utc::now(),
+            restored: Some(index),
+        };
+        history.push(record);
+        self.write_history(&history).await
+    }
+
+    async fn write_history(&self, history: &[SnapshotRecord]) -> Result<(), SnapshotError> {
+        let path = self.dir.join(Self::HISTORY_FILE);
+        let json = serde_json::to_string_pretty(history).unwrap_or_default();
+        tokio::fs::write(&path, json)
+            .await
+            .map_err(|e| self.io_error(&path, e))
+    }
+
+    fn object_path(&self, hash: &str) -> PathBuf {
+        self.dir.join(format!("{}.snapshot", hash))
+    }
+
+    fn io_error(&self, path: &Path, io_error: std::io::Error) -> SnapshotError {
+        SnapshotError::FileError {
+            path: path.display().to_string(),
+            io_error,
+        }
+    }
+}
+
+/// Restore a previous export from the snapshot history of `file_path`
+pub async fn rollback(file_path: &str, to_version: Option<u64>) -> Result<u64, SnapshotError> {
+    let store = SnapshotStore::for_file(file_path);
+    let history = store.history().await?;
+    let index = SnapshotStore::select(&history, to_version)?;
+    let record = history[index].clone();
+    let content = store.load(&record).await?;
+    tokio::fs::write(file_path, content)
+        .await
+        .map_err(|e| store.io_error(Path::new(file_path), e))?;
+    store.record_rollback(history, index).await?;
+    log::info!(
+        "Restored snapshot of library version {} (taken at {}) to file '{}'",
+        record.library_version,
+        record.created_at,
+        file_path
+    );
+    Ok(record.library_version)
+}
+
+/// Whether the export is the same as the last one in the history. Rollback records in between don't count,
+/// so that an export right after a rollback is recorded even if it has the content of the restored snapshot.
+fn is_duplicate(history: &[SnapshotRecord], record: &SnapshotRecord) -> bool {
+    history
+        .iter()
+        .rev()
+        .find(|previous| previous.restored.is_none())
+        .is_some_and(|previous| {
+            previous.hash == record.hash && previous.library_version == record.library_version
+        })
+}
+
+fn content_hash(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum SnapshotError {
+    #[error("Error with snapshot file '{path}'")]
+    FileError {
+        path: String,
+        #[source]
+        io_error: std::io::Error,
+    },
+    #[error("Snapshot history '{path}' is corrupt")]
+    CorruptHistory {
+        path: String,
+        #[source]
+        json_error: serde_json::Error,
+    },
+    #[error("No snapshot found for library version {0}")]
+    VersionNotFound(u64),
+    #[error("No previous snapshot to roll back to")]
+    NoPreviousSnapshot,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+    use pretty_assertions::assert_eq;
+
+    fn record(library_version: u64, hash: &str) -> SnapshotRecord {
+        SnapshotRecord {
+            library_version,
+            hash: hash.to_owned(),
+            created_at: DateTime::UNIX_EPOCH,
+            restored: None,
+        }
+    }
+
+    fn rollback_record(history: &[SnapshotRecord], index: usize) -> SnapshotRecord {
+        SnapshotRecord {
+            restored: Some(index),
+            ..history[index].clone()
+        }
+    }
+
+    #[test]
+    fn select_previous_snapshot_by_default() {
+        let history = vec![record(1, "a"), record(2, "b"), record(3, "c")];
+        assert_matches!(SnapshotStore::select(&history, None), Ok(1));
+    }
+
+    #[test]
+    fn select_snapshot_by_version() {
+        let history = vec![record(1, "a"), record(2, "b"), record(3, "c")];
+        assert_matches!(SnapshotStore::select(&history, Some(1)), Ok(0));
+    }
+
+    #[test]
+    fn roll_back_twice() {
+        let mut history = vec![record(1, "a"), record(2, "b"), record(3, "c")];
+        let first = SnapshotStore::select(&history, None).unwrap();
+        assert_eq!(first, 1);
+        history.push(rollback_record(&history, first));
+        let second = SnapshotStore::select(&history, None).unwrap();
+        assert_eq!(second, 0);
+        history.push(rollback_record(&history, second));
+        assert_matches!(
+            SnapshotStore::select(&history, None),
+            Err(SnapshotError::NoPreviousSnapshot)
+        );
+        // a new export after the rollbacks is the current snapshot again
+        history.push(record(4, "d"));
+        assert_matches!(SnapshotStore::select(&history, None), Ok(2));
+    }
+
+    #[test]
+    fn roll_back_after_export_of_restored_snapshot() {
+        let mut history = vec![record(1, "a"), record(2, "b"), record(3, "c")];
+        let first = SnapshotStore::select(&history, None).unwrap();
+        history.push(rollback_record(&history, first));
+        let export = record(2, "b");
+        assert!(!is_duplicate(&history, &export));
+        history.push(export);
+        assert!(is_duplicate(&history, &record(2, "b")));
+        // steps back from the latest export instead of the rollback
+        assert_matches!(SnapshotStore::select(&history, None), Ok(2));
+    }
+
+    #[test]
+    fn select_fails_without_previous_snapshot() {
+        let history = vec![record(1, "a")];
+        assert_matches!(
+            SnapshotStore::select(&history, None),
+            Err(SnapshotError::NoPreviousSnapshot)
+        );
+        assert_matches!(
+            SnapshotStore::select(&history, Some(5)),
+            Err(SnapshotError::VersionNotFound(5))
+        );
+    }
+
+    #[test]
+    fn content_hash_is_stable() {
+        assert_eq!(
+            content_hash("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
@@ -0,0 +1,159 @@
+use crate::bibtex::Entry;
+use crate::export::document::ExportDocument;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+/// Fields that entries of a type must have, alternatives are separated by `|`.
+/// The names of both BibLaTeX and BibTeX are accepted, so that the rules apply to both formats.
+const REQUIRED_FIELDS: [(&str, &[&str]); 16] = [
+    (
+        "article",
+        &["author", "title", "journaltitle|journal", "date|year"],
+    ),
+    ("book", &["author|editor", "title", "date|year"]),
+    ("collection", &["editor", "title", "date|year"]),
+    (
+        "inbook",
+        &["author", "title", "booktitle|chapter|pages", "date|year"],
+    ),
+    (
+        "incollection",
+        &["author", "title", "booktitle", "date|year"],
+    ),
+    (
+        "inproceedings",
+        &["author", "title", "booktitle", "date|year"],
+    ),
+    ("proceedings", &["title", "date|year"]),
+    (
+        "thesis",
+        &["author", "title", "type", "institution", "date|year"],
+    ),
+    ("phdthesis", &["author", "title", "school", "year|date"]),
+    ("mastersthesis", &["author", "title", "school", "year|date"]),
+    (
+        "report",
+        &["author", "title", "type", "institution", "date|year"],
+    ),
+    (
+        "techreport",
+        &["author", "title", "institution", "year|date"],
+    ),
+    (
+        "online",
+        &[
+            "author|editor",
+            "title",
+            "date|year|urldate",
+            "url|doi|eprint",
+        ],
+    ),
+    ("manual", &["title", "date|year"]),
+    ("patent", &["author", "title", "number", "date|year"]),
+    ("unpublished", &["author", "title", "date|year"]),
+];
+
+/// What to do with entries that lack required fields
+#[derive(clap::ValueEnum, Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum StrictMode {
+    /// List the incomplete entries and fail the export
+    Fail,
+    /// List the incomplete entries, but write the export
+    Warn,
+}
+
+impl Display for StrictMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            serde_variant::to_variant_name(self).unwrap_or_default()
+        )
+    }
+}
+
+/// An entry that lacks required fields of its type
+#[derive(Debug, PartialEq)]
+pub struct IncompleteEntry {
+    pub citekey: String,
+    pub entry_type: String,
+    pub missing: Vec<&'static str>,
+}
+
+impl Display for IncompleteEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "@{}{{{}}} is missing {}",
+            self.entry_type,
+            self.citekey,
+            self.missing.join(", ")
+        )
+    }
+}
+
+/// Entries of the document that lack required fields of their type
+pub fn incomplete_entries(document: &ExportDocument) -> Vec<IncompleteEntry> {
+    document
+        .entries
+        .iter()
+        .filter_map(|entry| {
+            let missing = missing_fields(&entry.entry);
+            (!missing.is_empty()).then(|| IncompleteEntry {
+                citekey: entry.entry.key.clone(),
+                entry_type: entry.entry.entry_type.to_lowercase(),
+                missing,
+            })
+        })
+        .collect()
+}
+
+fn missing_fields(entry: &Entry) -> Vec<&'static str> {
+    let Some((_, required)) = REQUIRED_FIELDS
+        .iter()
+        .find(|(entry_type, _)| entry.entry_type.eq_ignore_ascii_case(entry_type))
+    else {
+        return Vec::new();
+    };
+    required
+        .iter()
+        .filter(|alternatives| {
+            !alternatives.split('|').any(|name| {
+                entry
+                    .get(name)
+                    .is_some_and(|value| !value.trim().is_empty())
+            })
+        })
+        .copied()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bibtex::{Block, parse};
+    use assert_matches::assert_matches;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::complete_biblatex(
+        "@article{key,\n\tauthor = {A},\n\ttitle = {T},\n\tjournaltitle = {J},\n\tdate = {2020}\n}",
+        vec![]
+    )]
+    #[case::complete_bibtex(
+        "@article{key,\n\tauthor = {A},\n\ttitle = {T},\n\tjournal = {J},\n\tyear = {2020}\n}",
+        vec![]
+    )]
+    #[case::missing(
+        "@article{key,\n\tauthor = {},\n\ttitle = {T}\n}",
+        vec!["author", "journaltitle|journal", "date|year"]
+    )]
+    #[case::unknown_type("@misc{key,\n}", vec![])]
+    fn check_required_fields(#[case] text: &str, #[case] expected: Vec<&str>) {
+        assert_matches!(parse(text).unwrap().into_iter().next(), Some(Block::Entry(entry)) => {
+            assert_eq!(missing_fields(&entry), expected);
+        });
+    }
+}
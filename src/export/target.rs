@@ -0,0 +1,47 @@
+use crate::zotero_api::ExportFormat;
+use clap::ValueEnum;
+use std::str::FromStr;
+
+/// Another file that the library is exported to in the same run, given like `refs.json=json`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportTarget {
+    pub file: String,
+    pub format: ExportFormat,
+}
+
+impl FromStr for ExportTarget {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("'{}' is not a target like `refs.json=json`", text);
+        let (file, format) = text.rsplit_once('=').ok_or_else(invalid)?;
+        if file.trim().is_empty() {
+            return Err(invalid());
+        }
+        Ok(Self {
+            file: file.to_owned(),
+            format: ExportFormat::from_str(format.trim(), true)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("refs.json=json", Some(("refs.json", ExportFormat::Json)))]
+    #[case("out/a=b.bib=BibTeX", Some(("out/a=b.bib", ExportFormat::Bibtex)))]
+    #[case("refs.bib", None)]
+    #[case("=biblatex", None)]
+    #[case("refs.xml=mods", None)]
+    fn parse_target(#[case] text: &str, #[case] expected: Option<(&str, ExportFormat)>) {
+        let expected = expected.map(|(file, format)| ExportTarget {
+            file: file.to_owned(),
+            format,
+        });
+        assert_eq!(text.parse::<ExportTarget>().ok(), expected);
+    }
+}
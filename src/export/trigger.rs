@@ -1,6 +1,7 @@
 use crate::{
-    export::websocket::WebsocketTrigger,
-    zotero_api::{api_key::ApiKey, client::UserId},
+    export::{EventSender, websocket::WebsocketTrigger},
+    network::NetworkOptions,
+    zotero_api::api_key::ApiKey,
 };
 use tokio::sync::mpsc;
 use tokio_util::sync::CancellationToken;
@@ -27,14 +28,44 @@ impl ExportTrigger {
         Self { trigger_receiver }
     }
 
-    /// Create a trigger based on websocket notifications from Zotero
+    /// Also trigger whenever `receiver` receives a message.
+    /// The combined stream is closed when both this trigger and `receiver` are closed.
+    pub fn merge(mut self, mut receiver: mpsc::Receiver<()>) -> Self {
+        let (trigger_sender, trigger_receiver) = mpsc::channel(1);
+        tokio::spawn(async move {
+            let (mut self_open, mut receiver_open) = (true, true);
+            while self_open || receiver_open {
+                let next = tokio::select! {
+                    next = self.next(), if self_open => {
+                        self_open = next.is_some();
+                        next
+                    }
+                    next = receiver.recv(), if receiver_open => {
+                        receiver_open = next.is_some();
+                        next
+                    }
+                };
+                if next.is_some() && trigger_sender.send(()).await.is_err() {
+                    break;
+                }
+            }
+        });
+        Self { trigger_receiver }
+    }
+
+    /// Create a trigger based on websocket notifications from Zotero about the library with the topic, e.g. `/users/12345`
     pub async fn websocket(
         api_key: ApiKey,
-        user_id: UserId,
+        topic: String,
         cancellation_token: CancellationToken,
+        events: Option<EventSender>,
+        network: NetworkOptions,
     ) -> anyhow::Result<Self> {
         let (trigger_sender, trigger_receiver) = mpsc::channel(1);
-        let websocket_trigger = WebsocketTrigger::builder(api_key, user_id, trigger_sender)
+        let websocket_trigger = WebsocketTrigger::builder(api_key, topic, trigger_sender)
+            .with_events(events)
+            .with_network(network)
+            .with_cancellation(cancellation_token.clone())
             .try_build()
             .await?;
         tokio::spawn(async move {
@@ -55,4 +86,14 @@ mod tests {
         let mut trigger = ExportTrigger::none();
         assert!(trigger.next().await.is_none())
     }
+
+    #[tokio::test]
+    async fn trigger_merged() {
+        let (sender, receiver) = tokio::sync::mpsc::channel(1);
+        let mut trigger = ExportTrigger::none().merge(receiver);
+        sender.send(()).await.unwrap();
+        assert!(trigger.next().await.is_some());
+        drop(sender);
+        assert!(trigger.next().await.is_none())
+    }
 }
@@ -1,19 +1,33 @@
-use crate::zotero_api::{api_key::ApiKey, client::UserId};
+use crate::export::{EventSender, ExportEvent};
+use crate::network::NetworkOptions;
+use crate::zotero_api::api_key::ApiKey;
 use futures::{SinkExt, StreamExt};
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 use std::vec;
 use tokio::{net::TcpStream, sync::mpsc};
 use tokio_tungstenite::{
-    MaybeTlsStream, connect_async,
-    tungstenite::{self, Message},
+    MaybeTlsStream, client_async_tls,
+    tungstenite::{self, Message, error::UrlError},
 };
 use tokio_util::sync::CancellationToken;
 
+#[cfg(any(test, feature = "fake-stream"))]
+#[cfg_attr(not(test), allow(dead_code))]
+pub mod fake;
+
+const STREAM_URL: &str = "wss://stream.zotero.org";
+
+/// Time to connect and subscribe before giving up, so that an unreachable server doesn't block the start
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(30);
+
 type WebsocketStream = tokio_tungstenite::WebSocketStream<MaybeTlsStream<TcpStream>>;
 
 pub struct WebsocketTrigger {
     ws_stream: WebsocketStream,
+    subscription: Subscription,
     trigger_sender: mpsc::Sender<()>,
+    events: Option<EventSender>,
 }
 
 impl WebsocketTrigger {
@@ -25,55 +39,165 @@ impl WebsocketTrigger {
                     return Ok(());
                 }
                 result = self.ws_stream.read_response() => {
-                    match result {
-                        Ok(Response::TopicUpdated { .. }) => {
-                            log::info!("triggering export due to library change notification");
-                            let _ = self.trigger_sender.try_send(());
-                        },
-                        Ok(other) => {
-                            return Err(WebsocketError::UnexpectedResponse(other).into());
-                        },
-                        Err(e) => {
-                            return Err(e.into());
-                        }
+                    let handled = match result {
+                        Ok(response) => self.handle(response).await,
+                        Err(e) => Err(e),
+                    };
+                    if let Err(e) = handled {
+                        self.emit(ExportEvent::Disconnected(e.to_string()));
+                        return Err(e.into());
                     }
                 }
             }
         }
     }
 
+    /// React to a message of the server. Changes of the key's access are handled on the live connection:
+    /// a library that becomes accessible again is subscribed to again, without reconnecting.
+    async fn handle(&mut self, response: Response) -> Result<(), WebsocketError> {
+        match response {
+            Response::TopicUpdated { .. } => {
+                log::info!("triggering export due to library change notification");
+                self.trigger();
+            }
+            Response::TopicRemoved { topic } if self.subscription.topics.contains(&topic) => {
+                log::warn!(
+                    "API key lost access to {}, waiting for the access to return",
+                    topic
+                );
+                self.emit(ExportEvent::Log(format!(
+                    "API key lost access to {}",
+                    topic
+                )));
+            }
+            Response::TopicAdded { topic } if self.subscription.topics.contains(&topic) => {
+                log::info!("API key has access to {} again, subscribing", topic);
+                let request = Request::CreateSubscriptions {
+                    subscriptions: vec![Subscription {
+                        api_key: self.subscription.api_key.clone(),
+                        topics: vec![topic],
+                    }],
+                };
+                self.ws_stream.send_request(&request).await?;
+            }
+            Response::TopicAdded { topic } | Response::TopicRemoved { topic } => {
+                log::debug!("ignoring access change of unexported library {}", topic);
+            }
+            Response::SubscriptionsCreated { errors, .. } if errors.is_empty() => {
+                // changes while the library wasn't accessible were not notified
+                log::info!("subscribed again, triggering export");
+                self.trigger();
+            }
+            other => return Err(WebsocketError::UnexpectedResponse(other)),
+        }
+        Ok(())
+    }
+
+    fn trigger(&self) {
+        self.emit(ExportEvent::LibraryChanged);
+        let _ = self.trigger_sender.try_send(());
+    }
+
+    /// Builder of a trigger for changes of the library with the given topic, e.g. `/users/12345`
     pub fn builder(
         api_key: ApiKey,
-        user_id: UserId,
+        topic: String,
         trigger_sender: mpsc::Sender<()>,
     ) -> WebsocketTriggerBuilder {
         WebsocketTriggerBuilder {
             api_key,
-            user_id,
+            topic,
             trigger_sender,
+            events: None,
+            network: NetworkOptions::default(),
+            url: STREAM_URL.to_owned(),
+            cancellation_token: CancellationToken::new(),
+        }
+    }
+
+    fn emit(&self, event: ExportEvent) {
+        if let Some(events) = &self.events {
+            let _ = events.send(event);
         }
     }
 }
 
 pub struct WebsocketTriggerBuilder {
     api_key: ApiKey,
-    user_id: UserId,
+    topic: String,
     trigger_sender: mpsc::Sender<()>,
+    events: Option<EventSender>,
+    network: NetworkOptions,
+    url: String,
+    cancellation_token: CancellationToken,
 }
 
 impl WebsocketTriggerBuilder {
+    /// Connect to another streaming server than stream.zotero.org, e.g. a `fake::FakeStreamServer`
+    #[cfg(any(test, feature = "fake-stream"))]
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn with_url(mut self, url: &str) -> Self {
+        self.url = url.to_owned();
+        self
+    }
+
+    /// Connect with the same network options as the API client
+    pub fn with_network(mut self, network: NetworkOptions) -> Self {
+        self.network = network;
+        self
+    }
+
+    /// Report the connection state and change notifications to an observer, e.g. the dashboard
+    pub fn with_events(mut self, events: Option<EventSender>) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Abort connecting and subscribing when the token is cancelled, e.g. by Ctrl+C
+    pub fn with_cancellation(mut self, cancellation_token: CancellationToken) -> Self {
+        self.cancellation_token = cancellation_token;
+        self
+    }
+
     /// Try to build the WebSocket trigger, establishing the connection and subscribing to the user's library
     pub async fn try_build(self) -> anyhow::Result<WebsocketTrigger> {
-        let mut ws_stream = self.connect().await?;
-        self.subscribe(&mut ws_stream).await?;
+        let handshake = async {
+            let mut ws_stream = self.connect().await?;
+            self.subscribe(&mut ws_stream).await?;
+            Ok::<_, WebsocketError>(ws_stream)
+        };
+        let ws_stream = tokio::select! {
+            biased;
+            _ = self.cancellation_token.cancelled() => return Err(WebsocketError::Cancelled.into()),
+            result = tokio::time::timeout(HANDSHAKE_TIMEOUT, handshake) => {
+                result.map_err(|_| WebsocketError::Timeout(HANDSHAKE_TIMEOUT))??
+            }
+        };
+        if let Some(events) = &self.events {
+            let _ = events.send(ExportEvent::Connected);
+        }
         Ok(WebsocketTrigger {
             ws_stream,
+            subscription: self.subscription(),
             trigger_sender: self.trigger_sender,
+            events: self.events,
         })
     }
 
     async fn connect(&self) -> Result<WebsocketStream, WebsocketError> {
-        let (mut ws_stream, _) = connect_async("wss://stream.zotero.org").await?;
+        let url = reqwest::Url::parse(&self.url)
+            .map_err(|_| UrlError::UnableToConnect(self.url.clone()))
+            .map_err(tungstenite::Error::Url)?;
+        let (host, port) = url
+            .host_str()
+            .zip(url.port_or_known_default())
+            .ok_or(tungstenite::Error::Url(UrlError::NoHostName))?;
+        let tcp_stream = self
+            .network
+            .connect(host, port)
+            .await
+            .map_err(tungstenite::Error::Io)?;
+        let (mut ws_stream, _) = client_async_tls(self.url.as_str(), tcp_stream).await?;
         let response = ws_stream.read_response().await?;
         if let Response::Connected { .. } = response {
             log::debug!("WebSocket connected");
@@ -84,12 +208,16 @@ impl WebsocketTriggerBuilder {
         }
     }
 
+    fn subscription(&self) -> Subscription {
+        Subscription {
+            api_key: self.api_key.0.clone(),
+            topics: vec![self.topic.clone()],
+        }
+    }
+
     async fn subscribe(&self, ws_stream: &mut WebsocketStream) -> Result<(), WebsocketError> {
         let request = Request::CreateSubscriptions {
-            subscriptions: vec![Subscription {
-                api_key: self.api_key.0.clone(),
-                topics: vec![format!("/users/{}", self.user_id)],
-            }],
+            subscriptions: vec![self.subscription()],
         };
         ws_stream.send_request(&request).await?;
         let response = ws_stream.read_response().await?;
@@ -114,15 +242,20 @@ enum WebsocketError {
     JsonError(#[from] serde_json::Error),
     #[error("unexpected response: {0:?}")]
     UnexpectedResponse(Response),
+    #[error("cancelled while connecting")]
+    Cancelled,
+    #[error("no connection within {} s", .0.as_secs())]
+    Timeout(Duration),
 }
 
 #[derive(Debug, Serialize)]
+#[cfg_attr(any(test, feature = "fake-stream"), derive(Deserialize))]
 #[serde(rename_all = "camelCase", tag = "action")]
 enum Request {
     CreateSubscriptions { subscriptions: Vec<Subscription> },
 }
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "camelCase")]
 struct Subscription {
     api_key: String,
@@ -130,6 +263,7 @@ struct Subscription {
 }
 
 #[derive(Debug, Deserialize, PartialEq)]
+#[cfg_attr(any(test, feature = "fake-stream"), derive(Serialize))]
 #[serde(rename_all = "camelCase")]
 struct SubscriptionError {
     api_key: String,
@@ -138,6 +272,7 @@ struct SubscriptionError {
 }
 
 #[derive(Debug, Deserialize, PartialEq)]
+#[cfg_attr(any(test, feature = "fake-stream"), derive(Serialize))]
 #[serde(rename_all = "camelCase", tag = "event")]
 enum Response {
     Connected {
@@ -151,6 +286,14 @@ enum Response {
         topic: String,
         version: u64,
     },
+    /// The API key got access to a library, e.g. again after its permissions were changed
+    TopicAdded {
+        topic: String,
+    },
+    /// The API key lost access to a library, which is unsubscribed by the server
+    TopicRemoved {
+        topic: String,
+    },
 }
 
 trait WebsocketStreamExt {
@@ -190,6 +333,7 @@ impl WebsocketStreamExt for WebsocketStream {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::export::websocket::fake::{FakeStreamServer, StreamEvent};
     use assert_matches::assert_matches;
     use pretty_assertions::assert_eq;
     use rstest::rstest;
@@ -212,4 +356,111 @@ mod tests {
             assert_eq!(response, expected);
         });
     }
+
+    fn builder(
+        server: &FakeStreamServer,
+        trigger_sender: mpsc::Sender<()>,
+    ) -> WebsocketTriggerBuilder {
+        WebsocketTrigger::builder(
+            ApiKey("abc".to_owned()),
+            "/users/123".to_owned(),
+            trigger_sender,
+        )
+        .with_url(server.url())
+    }
+
+    #[tokio::test]
+    async fn trigger_on_topic_update() {
+        let server = FakeStreamServer::start().await.unwrap();
+        let (trigger_sender, mut trigger_receiver) = mpsc::channel(1);
+        let trigger = builder(&server, trigger_sender).try_build().await.unwrap();
+        assert_eq!(server.subscribed_topics(), vec!["/users/123".to_owned()]);
+        let cancel_token = CancellationToken::new();
+        let run = tokio::spawn(trigger.run(cancel_token.clone()));
+        server.send(StreamEvent::TopicUpdated {
+            topic: "/users/123".into(),
+            version: 5,
+        });
+        assert_eq!(trigger_receiver.recv().await, Some(()));
+        cancel_token.cancel();
+        assert_matches!(run.await.unwrap(), Ok(()));
+    }
+
+    #[tokio::test]
+    async fn fail_on_rejected_subscription() {
+        let server = FakeStreamServer::start_rejecting("Invalid key")
+            .await
+            .unwrap();
+        let (trigger_sender, _) = mpsc::channel(1);
+        assert!(builder(&server, trigger_sender).try_build().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn resubscribe_on_regained_access() {
+        let server = FakeStreamServer::start().await.unwrap();
+        let (trigger_sender, mut trigger_receiver) = mpsc::channel(1);
+        let trigger = builder(&server, trigger_sender).try_build().await.unwrap();
+        let cancel_token = CancellationToken::new();
+        let run = tokio::spawn(trigger.run(cancel_token.clone()));
+        server.send(StreamEvent::TopicRemoved {
+            topic: "/users/123".into(),
+        });
+        server.send(StreamEvent::TopicAdded {
+            topic: "/users/123".into(),
+        });
+        assert_eq!(trigger_receiver.recv().await, Some(()));
+        assert_eq!(
+            server.subscribed_topics(),
+            vec!["/users/123".to_owned(), "/users/123".to_owned()]
+        );
+        cancel_token.cancel();
+        assert_matches!(run.await.unwrap(), Ok(()));
+    }
+
+    #[tokio::test]
+    async fn cancel_handshake() {
+        // accepts connections, but never answers the handshake
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let (trigger_sender, _) = mpsc::channel(1);
+        let cancel_token = CancellationToken::new();
+        let build = tokio::spawn(
+            WebsocketTrigger::builder(
+                ApiKey("abc".to_owned()),
+                "/users/123".to_owned(),
+                trigger_sender,
+            )
+            .with_url(&url)
+            .with_cancellation(cancel_token.clone())
+            .try_build(),
+        );
+        cancel_token.cancel();
+        let result = tokio::time::timeout(Duration::from_secs(5), build)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_matches!(
+            result.map(|_| ()).unwrap_err().downcast::<WebsocketError>(),
+            Ok(WebsocketError::Cancelled)
+        );
+    }
+
+    #[tokio::test]
+    async fn report_disconnect() {
+        let server = FakeStreamServer::start().await.unwrap();
+        let (trigger_sender, _trigger_receiver) = mpsc::channel(1);
+        let (events, mut event_receiver) = mpsc::unbounded_channel();
+        let trigger = builder(&server, trigger_sender)
+            .with_events(Some(events))
+            .try_build()
+            .await
+            .unwrap();
+        server.send(StreamEvent::Disconnect);
+        assert!(trigger.run(CancellationToken::new()).await.is_err());
+        assert_eq!(event_receiver.recv().await, Some(ExportEvent::Connected));
+        assert_matches!(
+            event_receiver.recv().await,
+            Some(ExportEvent::Disconnected(_))
+        );
+    }
 }
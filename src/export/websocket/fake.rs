@@ -0,0 +1,175 @@
+//! In-process fake of the Zotero streaming server, so that the WebSocket trigger can be developed and tested
+//! without stream.zotero.org.
+
+use crate::export::websocket::{Request, Response, Subscription, SubscriptionError};
+use futures::{SinkExt, StreamExt};
+use std::sync::{Arc, Mutex};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+use tokio_tungstenite::{WebSocketStream, tungstenite};
+use tungstenite::Message;
+
+/// What the server sends to all subscribed clients
+#[derive(Clone, Debug)]
+pub enum StreamEvent {
+    /// A `topicUpdated` message, like after a change of the library
+    TopicUpdated { topic: String, version: u64 },
+    /// A `topicAdded` message, like after the API key got access to a library
+    TopicAdded { topic: String },
+    /// A `topicRemoved` message, like after the API key lost access to a library
+    TopicRemoved { topic: String },
+    /// Close the connections, like on a restart of the server
+    Disconnect,
+}
+
+/// Fake streaming server on a free local port.
+///
+/// Every client gets a `connected` message, and its `createSubscriptions` request is answered with
+/// `subscriptionsCreated`, listing all topics as subscribed or, if the server rejects subscriptions, as errors.
+/// The events sent with `send()` reach every connected client.
+pub struct FakeStreamServer {
+    url: String,
+    events: broadcast::Sender<StreamEvent>,
+    subscriptions: Arc<Mutex<Vec<Subscription>>>,
+}
+
+impl FakeStreamServer {
+    pub async fn start() -> std::io::Result<Self> {
+        Self::start_with(None).await
+    }
+
+    /// Start a server that rejects all subscriptions with the error, e.g. `Invalid key` for a revoked key
+    pub async fn start_rejecting(error: &str) -> std::io::Result<Self> {
+        Self::start_with(Some(error.to_owned())).await
+    }
+
+    async fn start_with(rejection: Option<String>) -> std::io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let url = format!("ws://{}", listener.local_addr()?);
+        let (events, _) = broadcast::channel(16);
+        let subscriptions = Arc::new(Mutex::new(Vec::new()));
+        let connection = Connection {
+            events: events.clone(),
+            subscriptions: subscriptions.clone(),
+            rejection,
+        };
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let connection = connection.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = connection.serve(stream).await {
+                        log::debug!("fake stream connection failed: {}", e);
+                    }
+                });
+            }
+        });
+        Ok(Self {
+            url,
+            events,
+            subscriptions,
+        })
+    }
+
+    /// URL for `WebsocketTriggerBuilder::with_url()`
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn send(&self, event: StreamEvent) {
+        // without connected clients there is nobody to notify
+        let _ = self.events.send(event);
+    }
+
+    /// The topics of all subscriptions that clients created so far, e.g. `/users/123`
+    pub fn subscribed_topics(&self) -> Vec<String> {
+        self.subscriptions
+            .lock()
+            .unwrap()
+            .iter()
+            .flat_map(|subscription| subscription.topics.clone())
+            .collect()
+    }
+}
+
+#[derive(Clone)]
+struct Connection {
+    events: broadcast::Sender<StreamEvent>,
+    subscriptions: Arc<Mutex<Vec<Subscription>>>,
+    rejection: Option<String>,
+}
+
+impl Connection {
+    async fn serve(self, stream: TcpStream) -> Result<(), tungstenite::Error> {
+        let mut events = self.events.subscribe();
+        let mut ws_stream = tokio_tungstenite::accept_async(stream).await?;
+        send(&mut ws_stream, &Response::Connected { retry: 10000 }).await?;
+        loop {
+            tokio::select! {
+                message = ws_stream.next() => match message {
+                    Some(Ok(Message::Text(text))) => {
+                        if let Ok(Request::CreateSubscriptions { subscriptions }) = serde_json::from_str(text.as_str()) {
+                            let response = self.subscribe(subscriptions);
+                            send(&mut ws_stream, &response).await?;
+                        }
+                    }
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => return Err(e),
+                    None => return Ok(()),
+                },
+                event = events.recv() => match event {
+                    Ok(StreamEvent::TopicUpdated { topic, version }) => {
+                        send(&mut ws_stream, &Response::TopicUpdated { topic, version }).await?;
+                    }
+                    Ok(StreamEvent::TopicAdded { topic }) => {
+                        send(&mut ws_stream, &Response::TopicAdded { topic }).await?;
+                    }
+                    Ok(StreamEvent::TopicRemoved { topic }) => {
+                        send(&mut ws_stream, &Response::TopicRemoved { topic }).await?;
+                    }
+                    Ok(StreamEvent::Disconnect) | Err(broadcast::error::RecvError::Closed) => {
+                        return ws_stream.close(None).await;
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => {}
+                },
+            }
+        }
+    }
+
+    fn subscribe(&self, subscriptions: Vec<Subscription>) -> Response {
+        match &self.rejection {
+            Some(error) => Response::SubscriptionsCreated {
+                errors: subscriptions
+                    .iter()
+                    .flat_map(|subscription| {
+                        subscription.topics.iter().map(|topic| SubscriptionError {
+                            api_key: subscription.api_key.clone(),
+                            topic: topic.clone(),
+                            error: error.clone(),
+                        })
+                    })
+                    .collect(),
+                subscriptions: Vec::new(),
+            },
+            None => {
+                self.subscriptions
+                    .lock()
+                    .unwrap()
+                    .extend(subscriptions.iter().cloned());
+                Response::SubscriptionsCreated {
+                    subscriptions,
+                    errors: Vec::new(),
+                }
+            }
+        }
+    }
+}
+
+async fn send(
+    ws_stream: &mut WebSocketStream<TcpStream>,
+    response: &Response,
+) -> Result<(), tungstenite::Error> {
+    let text = serde_json::to_string(response).map_err(|e| {
+        tungstenite::Error::Io(std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    })?;
+    ws_stream.send(Message::Text(text.into())).await
+}
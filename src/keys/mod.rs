@@ -0,0 +1,109 @@
+use crate::export::document::ExportDocument;
+use crate::paths::write_if_changed;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Writes the citation keys of the exported entries to a file, so that editor completions and cite pickers
+/// don't have to parse the bibliography.
+///
+/// A file ending in `.json` gets an object with the title, authors and year by key, any other file one key per line.
+pub struct KeyList {
+    path: PathBuf,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct KeyInfo<'a> {
+    title: &'a str,
+    author: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    year: Option<&'a str>,
+}
+
+impl KeyList {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub async fn write(&self, document: &ExportDocument) -> Result<(), KeysError> {
+        let is_json = self
+            .path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("json"));
+        let content = if is_json {
+            render_json(document)
+        } else {
+            render_text(document)
+        };
+        if write_if_changed(&self.path, &content)
+            .await
+            .map_err(|io_error| KeysError::FileError {
+                path: self.path.display().to_string(),
+                io_error,
+            })?
+        {
+            log::info!(
+                "Wrote {} citation keys to '{}'",
+                document.entries.len(),
+                self.path.display()
+            );
+        }
+        Ok(())
+    }
+}
+
+/// One citation key per line, in the order of the export
+fn render_text(document: &ExportDocument) -> String {
+    document
+        .entries
+        .iter()
+        .map(|entry| format!("{}\n", entry.entry.key))
+        .collect()
+}
+
+fn render_json(document: &ExportDocument) -> String {
+    let keys: BTreeMap<&str, KeyInfo> = document
+        .entries
+        .iter()
+        .map(|entry| {
+            let info = KeyInfo {
+                title: &entry.item.data.title,
+                author: entry.item.data.author_names().join(", "),
+                year: entry.item.year(),
+            };
+            (entry.entry.key.as_str(), info)
+        })
+        .collect();
+    serde_json::to_string_pretty(&keys).unwrap_or_default() + "\n"
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum KeysError {
+    #[error("Error with key list file '{path}'")]
+    FileError {
+        path: String,
+        #[source]
+        io_error: std::io::Error,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::document::tests::document;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn render_key_lists() {
+        let document = document();
+        assert_eq!(render_text(&document), "smith_title_2020\n");
+        assert_eq!(
+            render_json(&document),
+            "{\n  \"smith_title_2020\": {\n    \"title\": \"Title\",\n    \"author\": \"\"\n  }\n}\n"
+        );
+    }
+}
@@ -0,0 +1,32 @@
+use crate::zotero_api::client::UserId;
+
+/// Page of an item in the Zotero web library
+pub fn web_library_url(user_id: UserId, item_key: &str) -> String {
+    format!(
+        "https://www.zotero.org/users/{}/items/{}",
+        user_id, item_key
+    )
+}
+
+/// Link that selects an item in the local Zotero app
+pub fn zotero_select_url(item_key: &str) -> String {
+    format!("zotero://select/library/items/{}", item_key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn item_urls() {
+        assert_eq!(
+            web_library_url(475425, "X42A7DEE"),
+            "https://www.zotero.org/users/475425/items/X42A7DEE"
+        );
+        assert_eq!(
+            zotero_select_url("X42A7DEE"),
+            "zotero://select/library/items/X42A7DEE"
+        );
+    }
+}
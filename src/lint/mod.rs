@@ -0,0 +1,190 @@
+use crate::export::document::{ExportDocument, ExportEntry};
+use std::collections::HashMap;
+use std::fmt::Display;
+
+/// Item types that usually have a DOI
+const DOI_ITEM_TYPES: [&str; 3] = ["journalArticle", "conferencePaper", "preprint"];
+
+/// Years before this are most likely typos or missing dates parsed wrongly
+const EARLIEST_PLAUSIBLE_YEAR: i32 = 1450;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum IssueKind {
+    MissingDoi,
+    EmptyAuthors,
+    SuspiciousYear,
+    DuplicateTitle,
+    NonAsciiKey,
+}
+
+impl IssueKind {
+    fn title(&self) -> &'static str {
+        match self {
+            IssueKind::MissingDoi => "Missing DOI",
+            IssueKind::EmptyAuthors => "No authors or editors",
+            IssueKind::SuspiciousYear => "Missing or suspicious year",
+            IssueKind::DuplicateTitle => "Duplicate title",
+            IssueKind::NonAsciiKey => "Non-ASCII citation key",
+        }
+    }
+}
+
+/// A quality issue of a single item
+#[derive(Debug, PartialEq)]
+pub struct Issue {
+    pub kind: IssueKind,
+    pub citekey: String,
+    pub item_key: String,
+    pub detail: String,
+}
+
+impl Issue {
+    fn new(kind: IssueKind, entry: &ExportEntry, detail: impl Into<String>) -> Self {
+        Self {
+            kind,
+            citekey: entry.entry.key.clone(),
+            item_key: entry.item.key.clone(),
+            detail: detail.into(),
+        }
+    }
+
+    /// Link that selects the item in the Zotero desktop app
+    pub fn zotero_uri(&self) -> String {
+        format!("zotero://select/library/items/{}", self.item_key)
+    }
+}
+
+/// Quality issues of the exported entries, to clean up the library systematically
+#[derive(Debug, Default, PartialEq)]
+pub struct LintReport {
+    pub issues: Vec<Issue>,
+}
+
+impl LintReport {
+    pub fn from_document(document: &ExportDocument, current_year: i32) -> Self {
+        let mut issues = Vec::new();
+        let mut titles: HashMap<String, Vec<&ExportEntry>> = HashMap::new();
+        for entry in &document.entries {
+            let data = &entry.item.data;
+            if DOI_ITEM_TYPES.contains(&data.item_type.as_str())
+                && data.field("DOI").is_none()
+                && entry.entry.get("doi").is_none()
+            {
+                issues.push(Issue::new(IssueKind::MissingDoi, entry, &data.item_type));
+            }
+            if data.creators.is_empty() {
+                issues.push(Issue::new(IssueKind::EmptyAuthors, entry, ""));
+            }
+            match entry.item.year().and_then(|year| year.parse::<i32>().ok()) {
+                None => issues.push(Issue::new(IssueKind::SuspiciousYear, entry, "no date")),
+                Some(year) if !(EARLIEST_PLAUSIBLE_YEAR..=current_year + 1).contains(&year) => {
+                    issues.push(Issue::new(
+                        IssueKind::SuspiciousYear,
+                        entry,
+                        year.to_string(),
+                    ))
+                }
+                Some(_) => {}
+            }
+            if !entry.entry.key.is_ascii() {
+                issues.push(Issue::new(IssueKind::NonAsciiKey, entry, ""));
+            }
+            let title = normalize_title(&data.title);
+            if !title.is_empty() {
+                titles.entry(title).or_default().push(entry);
+            }
+        }
+        for duplicates in titles.values().filter(|entries| entries.len() > 1) {
+            for entry in duplicates {
+                issues.push(Issue::new(
+                    IssueKind::DuplicateTitle,
+                    entry,
+                    &entry.item.data.title,
+                ));
+            }
+        }
+        issues.sort_by(|a, b| a.kind.cmp(&b.kind).then(a.citekey.cmp(&b.citekey)));
+        Self { issues }
+    }
+}
+
+/// Title reduced to lowercase letters and digits, so that differences in case and punctuation don't matter
+pub fn normalize_title(title: &str) -> String {
+    title
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+impl Display for LintReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Issues: {}", self.issues.len())?;
+        let mut kind = None;
+        for issue in &self.issues {
+            if kind != Some(issue.kind) {
+                let count = self
+                    .issues
+                    .iter()
+                    .filter(|other| other.kind == issue.kind)
+                    .count();
+                writeln!(f, "\n{} ({}):", issue.kind.title(), count)?;
+                kind = Some(issue.kind);
+            }
+            write!(f, "  {}", issue.citekey)?;
+            if !issue.detail.is_empty() {
+                write!(f, " ({})", issue.detail)?;
+            }
+            writeln!(f, "  {}", issue.zotero_uri())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zotero_api::ExportFormat;
+    use crate::zotero_api::item::Item;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn lint_report() {
+        let items: Vec<Item> = serde_json::from_value(serde_json::json!([
+            {"key":"ITEM0001","version":1,"meta":{"parsedDate":"2020"},
+             "data":{"itemType":"journalArticle","title":"On Optics","creators":[{"creatorType":"author","lastName":"Smith"}]},
+             "biblatex":"@article{smith_2020,\n}"},
+            {"key":"ITEM0002","version":1,"meta":{"parsedDate":"2201"},
+             "data":{"itemType":"book","title":"On optics!","creators":[]},
+             "biblatex":"@book{müller_2201,\n}"},
+            {"key":"ITEM0003","version":1,
+             "data":{"itemType":"journalArticle","title":"Other","DOI":"10.1000/1","creators":[{"creatorType":"author","lastName":"Doe"}]},
+             "biblatex":"@article{doe,\n}"}
+        ]))
+        .unwrap();
+        let document = ExportDocument::try_from_items(items, &ExportFormat::Biblatex).unwrap();
+        let report = LintReport::from_document(&document, 2025);
+        assert_eq!(
+            report.to_string(),
+            "Issues: 7
+
+Missing DOI (1):
+  smith_2020 (journalArticle)  zotero://select/library/items/ITEM0001
+
+No authors or editors (1):
+  müller_2201  zotero://select/library/items/ITEM0002
+
+Missing or suspicious year (2):
+  doe (no date)  zotero://select/library/items/ITEM0003
+  müller_2201 (2201)  zotero://select/library/items/ITEM0002
+
+Duplicate title (2):
+  müller_2201 (On optics!)  zotero://select/library/items/ITEM0002
+  smith_2020 (On Optics)  zotero://select/library/items/ITEM0001
+
+Non-ASCII citation key (1):
+  müller_2201  zotero://select/library/items/ITEM0002
+"
+        );
+    }
+}
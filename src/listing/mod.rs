@@ -0,0 +1,105 @@
+use crate::zotero_api::item::{Collection, Group, collection_path};
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+/// Table of the collections of a library, sorted by their path so that subcollections follow their parent,
+/// to look up the keys and names for `--collection`
+pub struct CollectionList(pub BTreeMap<String, Collection>);
+
+impl Display for CollectionList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.0.is_empty() {
+            return writeln!(f, "The library has no collections");
+        }
+        let mut rows: Vec<(String, &Collection)> = self
+            .0
+            .values()
+            .map(|collection| {
+                let path = collection_path(&self.0, &collection.key).unwrap_or_default();
+                (path, collection)
+            })
+            .collect();
+        rows.sort_by(|(a, _), (b, _)| a.cmp(b));
+        writeln!(f, "{:<8}  {:>5}  Collection", "Key", "Items")?;
+        for (path, collection) in rows {
+            writeln!(
+                f,
+                "{:<8}  {:>5}  {}",
+                collection.key, collection.meta.num_items, path
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Table of the groups whose libraries can be read, sorted by name, to look up the IDs for `--group`
+pub struct GroupList(pub Vec<Group>);
+
+impl Display for GroupList {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.0.is_empty() {
+            return writeln!(f, "The API key can't read the library of any group");
+        }
+        let mut groups: Vec<&Group> = self.0.iter().collect();
+        groups.sort_by(|a, b| a.data.name.cmp(&b.data.name));
+        writeln!(f, "{:>10}  {:>5}  {:<12}  Name", "ID", "Items", "Type")?;
+        for group in groups {
+            writeln!(
+                f,
+                "{:>10}  {:>5}  {:<12}  {}",
+                group.id, group.meta.num_items, group.data.group_type, group.data.name
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn collection_list() {
+        let collections: Vec<Collection> = serde_json::from_str(
+            r#"[
+                {"key":"CCCCCCCC","version":1,"data":{"name":"Figures","parentCollection":"BBBBBBBB"},"meta":{"numItems":3}},
+                {"key":"AAAAAAAA","version":1,"data":{"name":"Thesis","parentCollection":false},"meta":{"numItems":0}},
+                {"key":"BBBBBBBB","version":1,"data":{"name":"Chapter 1","parentCollection":"AAAAAAAA"},"meta":{"numItems":12}},
+                {"key":"DDDDDDDD","version":1,"data":{"name":"Reading","parentCollection":false}}
+            ]"#,
+        )
+        .unwrap();
+        let collections = collections
+            .into_iter()
+            .map(|collection| (collection.key.clone(), collection))
+            .collect();
+        assert_eq!(
+            CollectionList(collections).to_string(),
+            "Key       Items  Collection
+DDDDDDDD      0  Reading
+AAAAAAAA      0  Thesis
+BBBBBBBB     12  Thesis/Chapter 1
+CCCCCCCC      3  Thesis/Chapter 1/Figures
+"
+        );
+    }
+
+    #[test]
+    fn group_list() {
+        let groups: Vec<Group> = serde_json::from_str(
+            r#"[
+                {"id":222,"version":3,"meta":{"numItems":817},"data":{"id":222,"name":"Optics Lab","type":"Private"}},
+                {"id":111,"version":5,"meta":{"numItems":12},"data":{"id":111,"name":"Journal Club","type":"PublicClosed"}}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            GroupList(groups).to_string(),
+            "        ID  Items  Type          Name
+       111     12  PublicClosed  Journal Club
+       222    817  Private       Optics Lab
+"
+        );
+    }
+}
@@ -0,0 +1,136 @@
+use crate::export::document::{ExportDocument, ExportEntry};
+use crate::paths::write_if_changed;
+use crate::zotero_api::item::Item;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// Writes one JSON object per exported item (NDJSON), for piping into jq, databases or indexing tools
+/// without parsing the bibliography or loading one giant array.
+///
+/// Every line has the citation key, the entry type and fields of the exported entry, and the Zotero item data.
+pub struct NdjsonWriter {
+    /// File to write to, `None` for stdout
+    path: Option<PathBuf>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+struct NdjsonLine<'a> {
+    citation_key: &'a str,
+    entry_type: &'a str,
+    fields: BTreeMap<&'a str, &'a str>,
+    item: &'a Item,
+}
+
+impl NdjsonWriter {
+    /// Write to the file at `path`, or to stdout for `-`
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path: (path.as_os_str() != "-").then_some(path),
+        }
+    }
+
+    /// Whether the lines are printed to stdout, where nothing else may be printed
+    pub fn is_stdout(&self) -> bool {
+        self.path.is_none()
+    }
+
+    pub async fn write(&self, document: &ExportDocument) -> Result<(), NdjsonError> {
+        let content = render(document);
+        match &self.path {
+            Some(path) => {
+                if write_if_changed(path, &content).await.map_err(|io_error| {
+                    NdjsonError::FileError {
+                        path: path.display().to_string(),
+                        io_error,
+                    }
+                })? {
+                    log::info!(
+                        "Wrote {} items as NDJSON to '{}'",
+                        document.entries.len(),
+                        path.display()
+                    );
+                }
+            }
+            None => {
+                let mut stdout = std::io::stdout().lock();
+                stdout
+                    .write_all(content.as_bytes())
+                    .and_then(|_| stdout.flush())
+                    .map_err(|io_error| NdjsonError::FileError {
+                        path: "stdout".to_owned(),
+                        io_error,
+                    })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn render(document: &ExportDocument) -> String {
+    document
+        .entries
+        .iter()
+        .map(|entry| serde_json::to_string(&line(entry)).unwrap_or_default() + "\n")
+        .collect()
+}
+
+fn line(entry: &ExportEntry) -> NdjsonLine<'_> {
+    NdjsonLine {
+        citation_key: &entry.entry.key,
+        entry_type: &entry.entry.entry_type,
+        fields: entry
+            .entry
+            .fields
+            .iter()
+            .map(|field| (field.name.as_str(), field.value.text()))
+            .collect(),
+        item: &entry.item,
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum NdjsonError {
+    #[error("Failed to write the NDJSON items to '{path}'")]
+    FileError {
+        path: String,
+        #[source]
+        io_error: std::io::Error,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zotero_api::ExportFormat;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn one_line_per_entry() {
+        let items: Vec<Item> = serde_json::from_value(serde_json::json!([
+            {"key": "A", "version": 1, "data": {"itemType": "book", "title": "First"}, "biblatex": "@book{first,\n\ttitle = {First}\n}"},
+            {"key": "B", "version": 2, "data": {"itemType": "book", "title": "Second"}, "biblatex": "@book{second,\n\ttitle = {Second}\n}"}
+        ]))
+        .unwrap();
+        let document = ExportDocument::try_from_items(items, &ExportFormat::Biblatex).unwrap();
+        let rendered = render(&document);
+        let lines: Vec<serde_json::Value> = rendered
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["citationKey"], "first");
+        assert_eq!(lines[0]["entryType"], "book");
+        assert_eq!(lines[0]["fields"], serde_json::json!({"title": "First"}));
+        assert_eq!(lines[1]["item"]["key"], "B");
+        assert_eq!(lines[1]["item"]["data"]["title"], "Second");
+    }
+
+    #[test]
+    fn dash_is_stdout() {
+        assert!(NdjsonWriter::new(PathBuf::from("-")).is_stdout());
+        assert!(!NdjsonWriter::new(PathBuf::from("items.ndjson")).is_stdout());
+    }
+}
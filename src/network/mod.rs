@@ -0,0 +1,166 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use tokio::net::{TcpSocket, TcpStream};
+
+/// IP version of the connections to the Zotero servers
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum IpFamily {
+    Ipv4,
+    Ipv6,
+}
+
+impl IpFamily {
+    fn of(address: &IpAddr) -> Self {
+        match address {
+            IpAddr::V4(_) => Self::Ipv4,
+            IpAddr::V6(_) => Self::Ipv6,
+        }
+    }
+
+    fn unspecified_address(self) -> IpAddr {
+        match self {
+            Self::Ipv4 => Ipv4Addr::UNSPECIFIED.into(),
+            Self::Ipv6 => Ipv6Addr::UNSPECIFIED.into(),
+        }
+    }
+}
+
+/// How the connections to the Zotero servers are made, for VPN and dual-stack setups that need a specific route.
+///
+/// Applies to the requests of the API client as well as to the WebSocket connection of the sync mode.
+#[derive(Clone, Debug, Default)]
+pub struct NetworkOptions {
+    ip_family: Option<IpFamily>,
+    local_address: Option<IpAddr>,
+    interface: Option<String>,
+}
+
+impl NetworkOptions {
+    pub fn new(
+        ip_family: Option<IpFamily>,
+        local_address: Option<IpAddr>,
+        interface: Option<String>,
+    ) -> Result<Self, NetworkError> {
+        if let (Some(family), Some(address)) = (ip_family, local_address)
+            && IpFamily::of(&address) != family
+        {
+            return Err(NetworkError::FamilyMismatch(address, family));
+        }
+        if interface.is_some() && !cfg!(any(target_os = "android", target_os = "linux")) {
+            return Err(NetworkError::InterfaceUnsupported);
+        }
+        Ok(Self {
+            ip_family,
+            local_address,
+            interface,
+        })
+    }
+
+    /// The local address to connect from. Binding to the unspecified address of a family restricts the connections to that family.
+    fn bind_address(&self) -> Option<IpAddr> {
+        self.local_address
+            .or(self.ip_family.map(IpFamily::unspecified_address))
+    }
+
+    /// Whether a resolved address of a server can be connected to with these options
+    fn accepts(&self, address: &SocketAddr) -> bool {
+        self.bind_address()
+            .is_none_or(|bind| IpFamily::of(&bind) == IpFamily::of(&address.ip()))
+    }
+
+    /// Configure an HTTP client to connect with these options
+    pub fn apply(&self, mut builder: reqwest::ClientBuilder) -> reqwest::ClientBuilder {
+        if let Some(address) = self.bind_address() {
+            builder = builder.local_address(address);
+        }
+        #[cfg(any(target_os = "android", target_os = "linux"))]
+        if let Some(interface) = &self.interface {
+            builder = builder.interface(interface);
+        }
+        builder
+    }
+
+    /// Open a TCP connection to `host`, trying its addresses that match these options one after another
+    pub async fn connect(&self, host: &str, port: u16) -> std::io::Result<TcpStream> {
+        let mut last_error = None;
+        for address in tokio::net::lookup_host((host, port)).await? {
+            if !self.accepts(&address) {
+                continue;
+            }
+            match self.connect_to(address).await {
+                Ok(stream) => return Ok(stream),
+                Err(e) => {
+                    log::debug!("Could not connect to {}: {}", address, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("{} has no address that matches the network options", host),
+            )
+        }))
+    }
+
+    async fn connect_to(&self, address: SocketAddr) -> std::io::Result<TcpStream> {
+        let socket = match address {
+            SocketAddr::V4(_) => TcpSocket::new_v4()?,
+            SocketAddr::V6(_) => TcpSocket::new_v6()?,
+        };
+        if let Some(local_address) = self.local_address {
+            socket.bind(SocketAddr::new(local_address, 0))?;
+        }
+        #[cfg(any(target_os = "android", target_os = "linux"))]
+        if let Some(interface) = &self.interface {
+            socket.bind_device(Some(interface.as_bytes()))?;
+        }
+        socket.connect(address).await
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum NetworkError {
+    #[error("The local address {0} is not an {1:?} address")]
+    FamilyMismatch(IpAddr, IpFamily),
+    #[error("Binding to a network interface is only supported on Linux")]
+    InterfaceUnsupported,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(None, None, "203.0.113.1:443", true)]
+    #[case(None, None, "[2001:db8::1]:443", true)]
+    #[case(Some(IpFamily::Ipv4), None, "[2001:db8::1]:443", false)]
+    #[case(Some(IpFamily::Ipv6), None, "[2001:db8::1]:443", true)]
+    #[case(None, Some("192.168.1.5"), "203.0.113.1:443", true)]
+    #[case(None, Some("192.168.1.5"), "[2001:db8::1]:443", false)]
+    fn accept_server_addresses(
+        #[case] ip_family: Option<IpFamily>,
+        #[case] local_address: Option<&str>,
+        #[case] server: &str,
+        #[case] accepted: bool,
+    ) {
+        let options =
+            NetworkOptions::new(ip_family, local_address.map(|a| a.parse().unwrap()), None)
+                .unwrap();
+        assert_eq!(options.accepts(&server.parse().unwrap()), accepted);
+    }
+
+    #[test]
+    fn reject_address_of_other_family() {
+        assert_matches!(
+            NetworkOptions::new(
+                Some(IpFamily::Ipv6),
+                Some("192.168.1.5".parse().unwrap()),
+                None
+            ),
+            Err(NetworkError::FamilyMismatch(_, IpFamily::Ipv6))
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use crate::export::document::{ExportDocument, ExportEntry};
+use crate::paths::{sanitize_file_name, write_if_changed};
+use crate::zotero_api::item::Item;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Characters of the first line of a standalone note that are used for its file name
+const MAX_NAME_CHARS: usize = 100;
+
+/// Writes the child notes of every exported item to a Markdown file named by its citation key,
+/// and every standalone note to a Markdown file named by its first line.
+///
+/// Files are only rewritten when their content changed, so that note-taking tools watching the directory
+/// don't see spurious modifications on every export.
+pub struct NotesExporter {
+    dir: PathBuf,
+}
+
+impl NotesExporter {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    pub async fn write(&self, document: &ExportDocument) -> Result<(), NotesError> {
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(|e| file_error(&self.dir, e))?;
+        let mut written = 0;
+        for entry in &document.entries {
+            let notes = child_notes(document, entry);
+            if notes.is_empty() {
+                continue;
+            }
+            let path = self
+                .dir
+                .join(format!("{}.md", sanitize_file_name(&entry.entry.key)));
+            let content = render_notes(entry, &notes);
+            if write_if_changed(&path, &content)
+                .await
+                .map_err(|e| file_error(&path, e))?
+            {
+                written += 1;
+            }
+        }
+        for (name, content) in standalone_note_files(document) {
+            let path = self.dir.join(format!("{}.md", name));
+            if write_if_changed(&path, &content)
+                .await
+                .map_err(|e| file_error(&path, e))?
+            {
+                written += 1;
+            }
+        }
+        log::info!("Wrote {} note file(s) to '{}'", written, self.dir.display());
+        Ok(())
+    }
+}
+
+/// Child notes of an entry
+pub(crate) fn child_notes<'a>(document: &'a ExportDocument, entry: &ExportEntry) -> Vec<&'a Item> {
+    document
+        .children_of(entry)
+        .iter()
+        .filter(|child| child.data.item_type == "note")
+        .collect()
+}
+
+/// One Markdown document with all notes of an entry, separated by horizontal rules
+pub(crate) fn render_notes(entry: &ExportEntry, notes: &[&Item]) -> String {
+    let mut content = format!("# {} (@{})\n", entry.item.data.title, entry.entry.key);
+    for (i, note) in notes.iter().enumerate() {
+        if i > 0 {
+            content.push_str("\n---\n");
+        }
+        content.push('\n');
+        content.push_str(html_to_markdown(note.data.field("note").unwrap_or_default()).trim());
+        content.push('\n');
+    }
+    content
+}
+
+/// File names (without extension) and contents of the standalone notes. A note is named by its first line like
+/// in Zotero, or by its item key if that name is empty or taken by a citation key or another note.
+fn standalone_note_files(document: &ExportDocument) -> Vec<(String, String)> {
+    // file systems may ignore case
+    let mut used: HashSet<String> = document
+        .entries
+        .iter()
+        .map(|entry| sanitize_file_name(&entry.entry.key).to_lowercase())
+        .collect();
+    document
+        .standalone_notes
+        .iter()
+        .map(|note| {
+            let markdown = html_to_markdown(note.data.field("note").unwrap_or_default());
+            let markdown = markdown.trim();
+            let title = markdown
+                .lines()
+                .next()
+                .unwrap_or_default()
+                .trim_start_matches('#')
+                .trim();
+            // without the escapes of Markdown, e.g. in `smith\_2020`
+            let title: String = title
+                .replace("\\", "")
+                .chars()
+                .take(MAX_NAME_CHARS)
+                .collect();
+            let name = sanitize_file_name(&title);
+            let name = if name.is_empty() || !used.insert(name.to_lowercase()) {
+                sanitize_file_name(&note.key)
+            } else {
+                name
+            };
+            (name, format!("{}\n", markdown))
+        })
+        .collect()
+}
+
+/// Convert the HTML of a Zotero note to Markdown
+pub fn html_to_markdown(html: &str) -> String {
+    html2md::parse_html(html)
+}
+
+fn file_error(path: &Path, io_error: std::io::Error) -> NotesError {
+    NotesError::FileError {
+        path: path.display().to_string(),
+        io_error,
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum NotesError {
+    #[error("Error with note file '{path}'")]
+    FileError {
+        path: String,
+        #[source]
+        io_error: std::io::Error,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::document::tests::document;
+    use pretty_assertions::assert_eq;
+
+    fn note(html: &str) -> Item {
+        serde_json::from_value(serde_json::json!({
+            "key": "NOTE0002",
+            "version": 1,
+            "data": { "itemType": "note", "parentItem": "PARENT01", "note": html }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn convert_zotero_note_html() {
+        let markdown = html_to_markdown(
+            r#"<div data-schema-version="8"><h1>Summary</h1><p>Some <strong>bold</strong> and <em>italic</em> text, see <a href="https://example.org">link</a>.</p><ul><li>first</li><li>second</li></ul></div>"#,
+        );
+        assert!(markdown.contains("Summary"));
+        assert!(markdown.contains("**bold**"));
+        assert!(markdown.contains("*italic*"));
+        assert!(markdown.contains("[link](https://example.org)"));
+        assert!(markdown.contains("first"));
+    }
+
+    #[test]
+    fn name_standalone_notes() {
+        let mut document = document();
+        document.standalone_notes = vec![
+            note("<h1>Reading list</h1><p>Optics</p>"),
+            note("<p>Reading list</p>"),
+            note("<p>smith_title_2020</p>"),
+            note(""),
+        ];
+        for (i, note) in document.standalone_notes.iter_mut().enumerate() {
+            note.key = format!("NOTE000{}", i + 1);
+        }
+        let files = standalone_note_files(&document);
+        let names: Vec<&str> = files.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["Reading list", "NOTE0002", "NOTE0003", "NOTE0004"]
+        );
+        assert_eq!(files[1].1, "Reading list\n");
+    }
+
+    #[test]
+    fn render_multiple_notes() {
+        let document = document();
+        let first = note("<p>First</p>");
+        let second = note("<p>Second</p>");
+        let content = render_notes(&document.entries[0], &[&first, &second]);
+        assert_eq!(
+            content,
+            "# Title (@smith_title_2020)\n\nFirst\n\n---\n\nSecond\n"
+        );
+    }
+}
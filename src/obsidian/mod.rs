@@ -0,0 +1,222 @@
+use crate::export::document::{ExportDocument, ExportEntry};
+use crate::paths::{sanitize_file_name, write_if_changed};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Writes one Markdown note per exported item into an Obsidian vault folder.
+///
+/// Every note starts with a YAML front matter (citekey, title, authors, year, DOI, tags) that is managed by this tool,
+/// while everything below it belongs to the user: existing notes are updated in place by replacing only the front matter.
+/// Notes are found again by the `zotero-key` in their front matter, so a note keeps its file name even if the citation key changes.
+pub struct ObsidianVault {
+    dir: PathBuf,
+}
+
+const FRONT_MATTER_DELIMITER: &str = "---";
+const ZOTERO_KEY: &str = "zotero-key";
+
+impl ObsidianVault {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    pub async fn write(&self, document: &ExportDocument) -> Result<(), ObsidianError> {
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(|e| file_error(&self.dir, e))?;
+        let mut existing = self.existing_notes().await?;
+        let mut written = 0;
+        for entry in &document.entries {
+            let front_matter = front_matter(entry);
+            let (path, content) = match existing.remove(&entry.item.key) {
+                Some((path, content)) => {
+                    let body = strip_front_matter(&content).to_owned();
+                    (path, format!("{}{}", front_matter, body))
+                }
+                None => (
+                    self.dir
+                        .join(format!("{}.md", sanitize_file_name(&entry.entry.key))),
+                    format!("{}\n# {}\n", front_matter, entry.item.data.title),
+                ),
+            };
+            if write_if_changed(&path, &content)
+                .await
+                .map_err(|e| file_error(&path, e))?
+            {
+                written += 1;
+            }
+        }
+        log::info!(
+            "Updated {} note(s) in Obsidian vault folder '{}'",
+            written,
+            self.dir.display()
+        );
+        Ok(())
+    }
+
+    /// Notes in the vault folder that were created by this tool, by Zotero item key
+    async fn existing_notes(&self) -> Result<HashMap<String, (PathBuf, String)>, ObsidianError> {
+        let mut notes = HashMap::new();
+        let mut dir = tokio::fs::read_dir(&self.dir)
+            .await
+            .map_err(|e| file_error(&self.dir, e))?;
+        while let Some(file) = dir
+            .next_entry()
+            .await
+            .map_err(|e| file_error(&self.dir, e))?
+        {
+            let path = file.path();
+            if path.extension().is_none_or(|ext| ext != "md") {
+                continue;
+            }
+            let Ok(content) = tokio::fs::read_to_string(&path).await else {
+                continue;
+            };
+            if let Some(key) = zotero_key(&content) {
+                notes.insert(key.to_owned(), (path, content));
+            }
+        }
+        Ok(notes)
+    }
+}
+
+fn front_matter(entry: &ExportEntry) -> String {
+    let data = &entry.item.data;
+    let mut yaml = format!("{}\n", FRONT_MATTER_DELIMITER);
+    yaml.push_str(&format!("citekey: {}\n", quote(&entry.entry.key)));
+    yaml.push_str(&format!("title: {}\n", quote(&data.title)));
+    yaml.push_str(&yaml_list("authors", &data.author_names()));
+    if let Some(year) = entry.item.year() {
+        yaml.push_str(&format!("year: {}\n", year));
+    }
+    if let Some(doi) = data.field("DOI") {
+        yaml.push_str(&format!("doi: {}\n", quote(doi)));
+    }
+    let tags: Vec<String> = data.tags.iter().map(|tag| obsidian_tag(&tag.tag)).collect();
+    yaml.push_str(&yaml_list("tags", &tags));
+    yaml.push_str(&format!("{}: {}\n", ZOTERO_KEY, entry.item.key));
+    yaml.push_str(&format!(
+        "zotero-uri: {}\n",
+        quote(&format!("zotero://select/library/items/{}", entry.item.key))
+    ));
+    yaml.push_str(FRONT_MATTER_DELIMITER);
+    yaml.push('\n');
+    yaml
+}
+
+fn yaml_list(name: &str, values: &[String]) -> String {
+    if values.is_empty() {
+        return format!("{}: []\n", name);
+    }
+    let mut yaml = format!("{}:\n", name);
+    for value in values {
+        yaml.push_str(&format!("  - {}\n", quote(value)));
+    }
+    yaml
+}
+
+/// Double-quoted YAML string
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Obsidian tags must not contain spaces and must not start with `#`
+fn obsidian_tag(tag: &str) -> String {
+    tag.trim_start_matches('#')
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+/// Text after the front matter, or the whole text if there is no front matter
+fn strip_front_matter(content: &str) -> &str {
+    front_matter_range(content)
+        .map(|(_, end)| &content[end..])
+        .unwrap_or(content)
+}
+
+/// Byte range of the front matter, including the delimiter lines
+fn front_matter_range(content: &str) -> Option<(usize, usize)> {
+    let rest = content
+        .strip_prefix(FRONT_MATTER_DELIMITER)?
+        .strip_prefix('\n')?;
+    let offset = content.len() - rest.len();
+    let closing = rest.find(&format!("\n{}\n", FRONT_MATTER_DELIMITER))?;
+    Some((0, offset + closing + FRONT_MATTER_DELIMITER.len() + 2))
+}
+
+fn zotero_key(content: &str) -> Option<&str> {
+    let (start, end) = front_matter_range(content)?;
+    content[start..end].lines().find_map(|line| {
+        line.strip_prefix(ZOTERO_KEY)
+            .and_then(|rest| rest.strip_prefix(':'))
+            .map(str::trim)
+    })
+}
+
+fn file_error(path: &Path, io_error: std::io::Error) -> ObsidianError {
+    ObsidianError::FileError {
+        path: path.display().to_string(),
+        io_error,
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum ObsidianError {
+    #[error("Error with Obsidian note '{path}'")]
+    FileError {
+        path: String,
+        #[source]
+        io_error: std::io::Error,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zotero_api::{ExportFormat, item::Item};
+    use pretty_assertions::assert_eq;
+
+    fn document() -> ExportDocument {
+        let items: Vec<Item> = serde_json::from_value(serde_json::json!([
+            {"key":"PARENT01","version":1,"meta":{"parsedDate":"2020-03"},
+             "data":{"itemType":"journalArticle","title":"A \"quoted\" title","DOI":"10.1000/xyz",
+                     "creators":[{"creatorType":"author","firstName":"Jane","lastName":"Smith"},{"creatorType":"editor","name":"Ed"}],
+                     "tags":[{"tag":"#to read"}]},
+             "biblatex":"@article{smith_2020,\n}"}
+        ]))
+        .unwrap();
+        ExportDocument::try_from_items(items, &ExportFormat::Biblatex).unwrap()
+    }
+
+    #[test]
+    fn render_front_matter() {
+        let document = document();
+        assert_eq!(
+            front_matter(&document.entries[0]),
+            r#"---
+citekey: "smith_2020"
+title: "A \"quoted\" title"
+authors:
+  - "Jane Smith"
+year: 2020
+doi: "10.1000/xyz"
+tags:
+  - "to-read"
+zotero-key: PARENT01
+zotero-uri: "zotero://select/library/items/PARENT01"
+---
+"#
+        );
+    }
+
+    #[test]
+    fn update_keeps_user_content() {
+        let existing =
+            "---\ncitekey: \"old\"\nzotero-key: PARENT01\n---\n\n# My notes\nsome text\n";
+        assert_eq!(zotero_key(existing), Some("PARENT01"));
+        assert_eq!(strip_front_matter(existing), "\n# My notes\nsome text\n");
+        assert_eq!(zotero_key("# No front matter"), None);
+        assert_eq!(strip_front_matter("# No front matter"), "# No front matter");
+    }
+}
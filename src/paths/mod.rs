@@ -0,0 +1,177 @@
+use std::path::{Component, MAIN_SEPARATOR, Path, PathBuf};
+
+/// Device names that Windows reserves in every directory, with or without extension
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Windows paths longer than this need the extended-length prefix `\\?\`
+const MAX_PATH: usize = 260;
+
+/// Replace characters that are not allowed in file names on common platforms,
+/// e.g. when a citation key or attachment filename is used as part of a path.
+///
+/// Names that Windows reserves (e.g. `con.md`) get an underscore, and trailing dots and spaces,
+/// which Windows drops silently, are replaced.
+pub fn sanitize_file_name(name: &str) -> String {
+    let mut sanitized: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    let kept = sanitized.trim_end_matches(['.', ' ']).len();
+    if kept < sanitized.len() {
+        sanitized.replace_range(kept.., &"_".repeat(sanitized.len() - kept));
+    }
+    if is_reserved_name(&sanitized) {
+        let stem_end = sanitized.find('.').unwrap_or(sanitized.len());
+        sanitized.insert(stem_end, '_');
+    }
+    sanitized
+}
+
+/// Whether Windows reserves the file name for a device, e.g. `NUL` or `com1.txt`
+fn is_reserved_name(name: &str) -> bool {
+    let stem = name.split('.').next().unwrap_or_default().trim_end();
+    RESERVED_NAMES
+        .iter()
+        .any(|reserved| stem.eq_ignore_ascii_case(reserved))
+}
+
+/// Prepare a path given by the user for this platform.
+///
+/// Both `/` and `\\` are accepted as separators, so that config files can be shared between platforms.
+/// On Windows, names reserved for devices are rejected with a clear error, and long paths as well as
+/// UNC paths (`\\server\share\...`) get the extended-length prefix so that the 260 characters limit doesn't apply.
+pub fn platform_path(path: &Path) -> Result<PathBuf, PathError> {
+    let normalized = normalize_separators(&path.to_string_lossy());
+    if cfg!(windows) {
+        if let Some(name) = reserved_component(Path::new(&normalized)) {
+            return Err(PathError::ReservedName {
+                path: path.display().to_string(),
+                name,
+            });
+        }
+        let absolute = std::path::absolute(&normalized).map_err(|io_error| PathError::Invalid {
+            path: path.display().to_string(),
+            io_error,
+        })?;
+        Ok(PathBuf::from(extended_length_path(
+            &absolute.to_string_lossy(),
+        )))
+    } else {
+        Ok(PathBuf::from(normalized))
+    }
+}
+
+/// Use the separator of this platform for both `/` and `\\`
+fn normalize_separators(path: &str) -> String {
+    if path.starts_with(r"\\?\") {
+        // extended-length paths are taken literally
+        return path.to_owned();
+    }
+    path.chars()
+        .map(|c| {
+            if c == '/' || c == '\\' {
+                MAIN_SEPARATOR
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+/// First component of the path that is a reserved name
+fn reserved_component(path: &Path) -> Option<String> {
+    path.components().find_map(|component| match component {
+        Component::Normal(name) if is_reserved_name(&name.to_string_lossy()) => {
+            Some(name.to_string_lossy().into_owned())
+        }
+        _ => None,
+    })
+}
+
+/// Extended-length form of an absolute Windows path if it needs one: `\\?\C:\...` for long paths,
+/// and `\\?\UNC\server\share\...` for UNC paths
+fn extended_length_path(absolute: &str) -> String {
+    if absolute.starts_with(r"\\?\") || absolute.starts_with(r"\\.\") {
+        absolute.to_owned()
+    } else if let Some(unc) = absolute.strip_prefix(r"\\") {
+        format!(r"\\?\UNC\{}", unc)
+    } else if absolute.len() >= MAX_PATH {
+        format!(r"\\?\{}", absolute)
+    } else {
+        absolute.to_owned()
+    }
+}
+
+/// Write the content to the file, unless the file already has exactly this content.
+/// Returns whether the file was written.
+pub async fn write_if_changed(path: &Path, content: &str) -> std::io::Result<bool> {
+    let unchanged = tokio::fs::read_to_string(path)
+        .await
+        .is_ok_and(|existing| existing == content);
+    if !unchanged {
+        tokio::fs::write(path, content).await?;
+    }
+    Ok(!unchanged)
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum PathError {
+    #[error("Path '{path}' contains '{name}', which Windows reserves for a device")]
+    ReservedName { path: String, name: String },
+    #[error("Invalid path '{path}'")]
+    Invalid {
+        path: String,
+        #[source]
+        io_error: std::io::Error,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn sanitize_file_names() {
+        assert_eq!(sanitize_file_name("a/b:c?.pdf"), "a_b_c_.pdf");
+        assert_eq!(sanitize_file_name("smith_title_2020"), "smith_title_2020");
+        assert_eq!(sanitize_file_name("con.md"), "con_.md");
+        assert_eq!(sanitize_file_name("LPT1"), "LPT1_");
+        assert_eq!(sanitize_file_name("console.md"), "console.md");
+        assert_eq!(sanitize_file_name("title. "), "title__");
+    }
+
+    #[test]
+    fn find_reserved_components() {
+        assert_eq!(
+            reserved_component(Path::new("exports/nul/refs.bib")),
+            Some("nul".to_owned())
+        );
+        assert_eq!(reserved_component(Path::new("exports/refs.bib")), None);
+    }
+
+    #[test]
+    fn prefix_long_and_unc_paths() {
+        assert_eq!(extended_length_path(r"C:\refs.bib"), r"C:\refs.bib");
+        assert_eq!(
+            extended_length_path(r"\\server\share\refs.bib"),
+            r"\\?\UNC\server\share\refs.bib"
+        );
+        let long = format!(r"C:\{}\refs.bib", "a".repeat(MAX_PATH));
+        assert_eq!(extended_length_path(&long), format!(r"\\?\{}", long));
+        assert_eq!(extended_length_path(r"\\?\C:\refs.bib"), r"\\?\C:\refs.bib");
+    }
+
+    #[test]
+    fn normalize_mixed_separators() {
+        let expected = ["a", "b", "c"].join(std::path::MAIN_SEPARATOR_STR);
+        assert_eq!(normalize_separators(r"a/b\c"), expected);
+    }
+}
@@ -0,0 +1,76 @@
+use crate::zotero_api::item::Item;
+use std::fmt::Display;
+
+/// Item types that are never picked, because they don't have an export of their own
+const CHILD_ITEM_TYPES: [&str; 3] = ["attachment", "note", "annotation"];
+
+/// An item in the list of the interactive picker
+#[derive(Debug, PartialEq)]
+pub struct PickerOption {
+    pub key: String,
+    label: String,
+}
+
+impl Display for PickerOption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.label)
+    }
+}
+
+/// Options for all regular items, sorted by label so that related items are close to each other
+pub fn options<'a>(items: impl Iterator<Item = &'a Item>) -> Vec<PickerOption> {
+    let mut options: Vec<_> = items
+        .filter(|item| !CHILD_ITEM_TYPES.contains(&item.data.item_type.as_str()))
+        .map(|item| PickerOption {
+            key: item.key.clone(),
+            label: format!(
+                "{} ({})  {}",
+                item.data.short_authors(),
+                item.year().unwrap_or("n.d."),
+                item.data.title
+            ),
+        })
+        .collect();
+    options.sort_by(|a, b| a.label.cmp(&b.label));
+    options
+}
+
+/// Let the user pick items in a list that is filtered while typing.
+/// Returns `None` if the picker was cancelled.
+pub fn pick(
+    options: Vec<PickerOption>,
+) -> Result<Option<Vec<PickerOption>>, inquire::InquireError> {
+    inquire::MultiSelect::new("Items to export:", options)
+        .with_help_message("type to filter, space to select, enter to export, esc to cancel")
+        .with_page_size(15)
+        .prompt_skippable()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn list_regular_items() {
+        let items: Vec<Item> = serde_json::from_value(serde_json::json!([
+            {"key":"ITEM0001","version":1,"meta":{"parsedDate":"2021"},
+             "data":{"itemType":"book","title":"Zebras","creators":[{"creatorType":"author","name":"Zoo"}]}},
+            {"key":"ITEM0002","version":1,
+             "data":{"itemType":"attachment","title":"Full Text PDF","parentItem":"ITEM0001"}},
+            {"key":"ITEM0003","version":1,"meta":{"parsedDate":"1999-01-01"},
+             "data":{"itemType":"journalArticle","title":"Aardvarks","creators":[{"creatorType":"author","firstName":"Ann","lastName":"Able"}]}}
+        ]))
+        .unwrap();
+        assert_eq!(
+            options(items.iter())
+                .iter()
+                .map(|option| (option.key.as_str(), option.to_string()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("ITEM0003", "Ann Able (1999)  Aardvarks".to_owned()),
+                ("ITEM0001", "Zoo (2021)  Zebras".to_owned()),
+            ]
+        );
+    }
+}
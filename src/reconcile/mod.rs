@@ -0,0 +1,234 @@
+use crate::bibtex::Entry;
+use crate::export::document::{ExportDocument, ExportEntry};
+use crate::lint::normalize_title;
+use std::collections::HashMap;
+use std::fmt::Display;
+
+/// Fields that are compared between matched entries, with the names of the field in BibTeX and BibLaTeX
+const COMPARED_FIELDS: [(&str, &[&str]); 7] = [
+    ("title", &["title"]),
+    ("author", &["author"]),
+    ("year", &["year", "date"]),
+    ("journal", &["journaltitle", "journal"]),
+    ("volume", &["volume"]),
+    ("pages", &["pages"]),
+    ("doi", &["doi"]),
+];
+
+/// A field whose value differs between an entry of the existing file and the export of the matched item
+#[derive(Debug, PartialEq)]
+pub struct FieldDifference {
+    pub field: &'static str,
+    pub existing: String,
+    pub library: String,
+}
+
+/// An entry of the existing file that matches an item of the library, but differs from its export
+#[derive(Debug, PartialEq)]
+pub struct DifferingEntry {
+    pub citekey: String,
+    /// Citation key of the matched item in the export
+    pub library_citekey: String,
+    pub item_key: String,
+    pub differences: Vec<FieldDifference>,
+}
+
+/// Compares a hand-maintained bibliography with the library, to help moving it into Zotero:
+/// which entries are missing from the library, and which differ from the export of their item.
+///
+/// Entries are matched to items by DOI, or else by title.
+#[derive(Debug, Default, PartialEq)]
+pub struct ReconcileReport {
+    pub matched: usize,
+    /// Citation keys of the entries without a matching item
+    pub missing: Vec<String>,
+    pub differing: Vec<DifferingEntry>,
+}
+
+impl ReconcileReport {
+    pub fn new(existing: &[Entry], document: &ExportDocument) -> Self {
+        let mut by_doi = HashMap::new();
+        let mut by_title = HashMap::new();
+        for entry in &document.entries {
+            if let Some(doi) = entry_doi(entry) {
+                by_doi.entry(doi).or_insert(entry);
+            }
+            let title = normalize_title(&entry.item.data.title);
+            if !title.is_empty() {
+                by_title.entry(title).or_insert(entry);
+            }
+        }
+        let mut report = Self::default();
+        for entry in existing {
+            let matched = entry
+                .get("doi")
+                .map(normalize_doi)
+                .and_then(|doi| by_doi.get(&doi))
+                .or_else(|| {
+                    entry
+                        .get("title")
+                        .map(normalize_title)
+                        .and_then(|title| by_title.get(&title))
+                });
+            let Some(library_entry) = matched else {
+                report.missing.push(entry.key.clone());
+                continue;
+            };
+            report.matched += 1;
+            let differences = differences(entry, &library_entry.entry);
+            if !differences.is_empty() || entry.key != library_entry.entry.key {
+                report.differing.push(DifferingEntry {
+                    citekey: entry.key.clone(),
+                    library_citekey: library_entry.entry.key.clone(),
+                    item_key: library_entry.item.key.clone(),
+                    differences,
+                });
+            }
+        }
+        report
+    }
+}
+
+fn entry_doi(entry: &ExportEntry) -> Option<String> {
+    entry
+        .item
+        .data
+        .field("DOI")
+        .or_else(|| entry.entry.get("doi"))
+        .map(normalize_doi)
+}
+
+/// DOIs are case-insensitive and often written as link
+fn normalize_doi(doi: &str) -> String {
+    let doi = doi.trim().to_lowercase();
+    [
+        "https://doi.org/",
+        "http://doi.org/",
+        "https://dx.doi.org/",
+        "doi:",
+    ]
+    .iter()
+    .find_map(|prefix| doi.strip_prefix(prefix))
+    .unwrap_or(&doi)
+    .to_owned()
+}
+
+fn differences(existing: &Entry, library: &Entry) -> Vec<FieldDifference> {
+    COMPARED_FIELDS
+        .iter()
+        .filter_map(|(field, names)| {
+            let existing = field_value(existing, field, names)?;
+            let library = field_value(library, field, names)?;
+            (comparable(&existing) != comparable(&library)).then_some(FieldDifference {
+                field,
+                existing,
+                library,
+            })
+        })
+        .collect()
+}
+
+/// Value of the first of the field names that the entry has, only the year of dates
+fn field_value(entry: &Entry, field: &str, names: &[&str]) -> Option<String> {
+    let value = names.iter().find_map(|name| entry.get(name))?;
+    match field {
+        "year" => value.get(..4).map(str::to_owned),
+        "doi" => Some(normalize_doi(value)),
+        _ => Some(value.to_owned()),
+    }
+}
+
+/// Value without braces, case and differences in whitespace or dashes
+fn comparable(value: &str) -> String {
+    value
+        .replace(['{', '}'], "")
+        .replace("--", "-")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+impl Display for ReconcileReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Matched: {}, missing from the library: {}, differing: {}",
+            self.matched,
+            self.missing.len(),
+            self.differing.len()
+        )?;
+        if !self.missing.is_empty() {
+            writeln!(f, "\nMissing from the library ({}):", self.missing.len())?;
+            for citekey in &self.missing {
+                writeln!(f, "  {}", citekey)?;
+            }
+        }
+        if !self.differing.is_empty() {
+            writeln!(f, "\nDiffering ({}):", self.differing.len())?;
+            for entry in &self.differing {
+                write!(f, "  {}", entry.citekey)?;
+                if entry.library_citekey != entry.citekey {
+                    write!(f, " (exported as {})", entry.library_citekey)?;
+                }
+                writeln!(f, "  zotero://select/library/items/{}", entry.item_key)?;
+                for difference in &entry.differences {
+                    writeln!(
+                        f,
+                        "    {}: {} | library: {}",
+                        difference.field, difference.existing, difference.library
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bibtex::{self, Block};
+    use crate::zotero_api::ExportFormat;
+    use crate::zotero_api::item::Item;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn reconcile_report() {
+        let items: Vec<Item> = serde_json::from_value(serde_json::json!([
+            {"key":"ITEM0001","version":1,
+             "data":{"itemType":"journalArticle","title":"On Optics","DOI":"10.1000/ABC"},
+             "biblatex":"@article{smith_optics_2020,\n\ttitle = {On Optics},\n\tdate = {2020-03},\n\tpages = {1--10},\n\tdoi = {10.1000/ABC}\n}"},
+            {"key":"ITEM0002","version":1,
+             "data":{"itemType":"book","title":"A Book"},
+             "biblatex":"@book{doe_book_2019,\n\ttitle = {A Book},\n\tdate = {2019}\n}"}
+        ]))
+        .unwrap();
+        let document = ExportDocument::try_from_items(items, &ExportFormat::Biblatex).unwrap();
+        let existing: Vec<Entry> = bibtex::parse(
+            "@article{smith2020,\n\ttitle = {On {O}ptics},\n\tyear = {2021},\n\tpages = {1-10},\n\tdoi = {https://doi.org/10.1000/abc}\n}\n\
+             @book{doe_book_2019,\n\ttitle = {A book},\n\tyear = 2019\n}\n\
+             @misc{unknown,\n\ttitle = {Not in Zotero}\n}",
+        )
+        .unwrap()
+        .into_iter()
+        .filter_map(|block| match block {
+            Block::Entry(entry) => Some(entry),
+            Block::Other(_) => None,
+        })
+        .collect();
+        let report = ReconcileReport::new(&existing, &document);
+        assert_eq!(
+            report.to_string(),
+            "Matched: 2, missing from the library: 1, differing: 1
+
+Missing from the library (1):
+  unknown
+
+Differing (1):
+  smith2020 (exported as smith_optics_2020)  zotero://select/library/items/ITEM0001
+    year: 2021 | library: 2020
+"
+        );
+    }
+}
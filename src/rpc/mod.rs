@@ -0,0 +1,314 @@
+//! JSON-RPC 2.0 server for editor plugins, e.g. for citation completion.
+//!
+//! Requests and responses are single lines of JSON. The methods are:
+//! - `completeKeys({"prefix": "smi"})`: entries of the export whose citation key starts with the prefix
+//! - `getItem({"key": "smith_2020"})`: type and fields of the entry with the citation key
+//! - `exportNow()`: start an export right away
+
+use crate::bibtex::{self, Block, Entry};
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::path::PathBuf;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::sync::mpsc;
+
+/// Maximum number of completions returned by `completeKeys`
+const MAX_COMPLETIONS: usize = 50;
+
+/// Where the server listens for requests
+#[derive(Debug, Clone, PartialEq)]
+pub enum RpcTransport {
+    Stdio,
+    /// TCP address, e.g. `127.0.0.1:7878`
+    Tcp(String),
+}
+
+impl From<String> for RpcTransport {
+    fn from(value: String) -> Self {
+        if value == "stdio" {
+            Self::Stdio
+        } else {
+            Self::Tcp(value)
+        }
+    }
+}
+
+/// Answers the requests of editor plugins from the export file, and triggers exports on request
+#[derive(Clone)]
+pub struct RpcServer {
+    file_path: PathBuf,
+    trigger_sender: mpsc::Sender<()>,
+}
+
+#[derive(Deserialize)]
+struct Request {
+    #[serde(default)]
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Debug, PartialEq)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    const PARSE_ERROR: i64 = -32700;
+    const METHOD_NOT_FOUND: i64 = -32601;
+    const INVALID_PARAMS: i64 = -32602;
+    const INTERNAL_ERROR: i64 = -32603;
+    /// The requested citation key is not in the export
+    const UNKNOWN_KEY: i64 = -32001;
+
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl RpcServer {
+    pub fn new(file_path: impl Into<PathBuf>, trigger_sender: mpsc::Sender<()>) -> Self {
+        Self {
+            file_path: file_path.into(),
+            trigger_sender,
+        }
+    }
+
+    /// Serve requests until stdin is closed, or forever on a TCP address
+    pub async fn run(self, transport: RpcTransport) -> std::io::Result<()> {
+        match transport {
+            RpcTransport::Stdio => {
+                let stdin = tokio::io::BufReader::new(tokio::io::stdin());
+                self.serve(stdin, tokio::io::stdout()).await
+            }
+            RpcTransport::Tcp(address) => {
+                let listener = TcpListener::bind(&address).await?;
+                log::info!("Serving JSON-RPC on {}", address);
+                loop {
+                    let (stream, peer) = listener.accept().await?;
+                    log::debug!("JSON-RPC connection from {}", peer);
+                    let server = self.clone();
+                    tokio::spawn(async move {
+                        let (reader, writer) = stream.into_split();
+                        if let Err(e) = server
+                            .serve(tokio::io::BufReader::new(reader), writer)
+                            .await
+                        {
+                            log::warn!("JSON-RPC connection from {} failed: {}", peer, e);
+                        }
+                    });
+                }
+            }
+        }
+    }
+
+    /// Answer the requests of one connection, one per line
+    async fn serve(
+        &self,
+        reader: impl AsyncBufRead + Unpin,
+        mut writer: impl AsyncWrite + Unpin,
+    ) -> std::io::Result<()> {
+        let mut lines = reader.lines();
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(response) = self.handle(&line).await {
+                writer.write_all(response.to_string().as_bytes()).await?;
+                writer.write_all(b"\n").await?;
+                writer.flush().await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Response to a request line, `None` for notifications (requests without `id`)
+    async fn handle(&self, line: &str) -> Option<Value> {
+        let request = match serde_json::from_str::<Request>(line) {
+            Ok(request) => request,
+            Err(e) => {
+                return Some(response(
+                    Value::Null,
+                    Err(RpcError::new(RpcError::PARSE_ERROR, e.to_string())),
+                ));
+            }
+        };
+        log::debug!("JSON-RPC request '{}'", request.method);
+        let result = match request.method.as_str() {
+            "completeKeys" => self.entries().await.and_then(|entries| {
+                string_param(&request.params, "prefix")
+                    .map(|prefix| complete_keys(&entries, prefix))
+            }),
+            "getItem" => self.entries().await.and_then(|entries| {
+                string_param(&request.params, "key").and_then(|key| get_item(&entries, key))
+            }),
+            "exportNow" => {
+                // a full channel means that an export is pending already
+                let _ = self.trigger_sender.try_send(());
+                Ok(json!(true))
+            }
+            method => Err(RpcError::new(
+                RpcError::METHOD_NOT_FOUND,
+                format!("Unknown method '{}'", method),
+            )),
+        };
+        request.id.map(|id| response(id, result))
+    }
+
+    /// Entries of the export file as it is now
+    async fn entries(&self) -> Result<Vec<Entry>, RpcError> {
+        let text = tokio::fs::read_to_string(&self.file_path)
+            .await
+            .map_err(|e| {
+                RpcError::new(
+                    RpcError::INTERNAL_ERROR,
+                    format!("Error reading '{}': {}", self.file_path.display(), e),
+                )
+            })?;
+        let blocks = bibtex::parse(&text)
+            .map_err(|e| RpcError::new(RpcError::INTERNAL_ERROR, e.to_string()))?;
+        Ok(blocks
+            .into_iter()
+            .filter_map(|block| match block {
+                Block::Entry(entry) => Some(entry),
+                Block::Other(_) => None,
+            })
+            .collect())
+    }
+}
+
+fn response(id: Value, result: Result<Value, RpcError>) -> Value {
+    match result {
+        Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+        Err(error) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": {"code": error.code, "message": error.message}
+        }),
+    }
+}
+
+fn string_param<'a>(params: &'a Value, name: &str) -> Result<&'a str, RpcError> {
+    params.get(name).and_then(Value::as_str).ok_or_else(|| {
+        RpcError::new(
+            RpcError::INVALID_PARAMS,
+            format!("Missing string parameter '{}'", name),
+        )
+    })
+}
+
+fn complete_keys(entries: &[Entry], prefix: &str) -> Value {
+    let prefix = prefix.to_lowercase();
+    let completions: Vec<Value> = entries
+        .iter()
+        .filter(|entry| entry.key.to_lowercase().starts_with(&prefix))
+        .take(MAX_COMPLETIONS)
+        .map(|entry| {
+            json!({
+                "key": entry.key,
+                "title": entry.get("title"),
+                "author": entry.get("author").or_else(|| entry.get("editor")),
+                "year": entry.get("year").or_else(|| entry.get("date")),
+            })
+        })
+        .collect();
+    Value::Array(completions)
+}
+
+fn get_item(entries: &[Entry], key: &str) -> Result<Value, RpcError> {
+    let entry = entries
+        .iter()
+        .find(|entry| entry.key == key)
+        .ok_or_else(|| {
+            RpcError::new(
+                RpcError::UNKNOWN_KEY,
+                format!("No entry with citation key '{}'", key),
+            )
+        })?;
+    let fields: serde_json::Map<String, Value> = entry
+        .fields
+        .iter()
+        .map(|field| (field.name.clone(), json!(field.value.text())))
+        .collect();
+    Ok(json!({"key": entry.key, "type": entry.entry_type, "fields": fields}))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn entries() -> Vec<Entry> {
+        bibtex::parse(
+            "@article{smith_2020,\n\ttitle = {On Optics},\n\tauthor = {Smith, Ann},\n\tdate = {2020}\n}\n\n\
+             @book{doe_2019,\n\ttitle = {Light},\n\teditor = {Doe, Bob},\n\tyear = {2019}\n}",
+        )
+        .unwrap()
+        .into_iter()
+        .filter_map(|block| match block {
+            Block::Entry(entry) => Some(entry),
+            Block::Other(_) => None,
+        })
+        .collect()
+    }
+
+    #[test]
+    fn complete_citation_keys() {
+        assert_eq!(
+            complete_keys(&entries(), "SMI"),
+            json!([{"key": "smith_2020", "title": "On Optics", "author": "Smith, Ann", "year": "2020"}])
+        );
+        assert_eq!(complete_keys(&entries(), "x"), json!([]));
+    }
+
+    #[test]
+    fn get_entry_by_key() {
+        assert_eq!(
+            get_item(&entries(), "doe_2019"),
+            Ok(json!({
+                "key": "doe_2019",
+                "type": "book",
+                "fields": {"title": "Light", "editor": "Doe, Bob", "year": "2019"}
+            }))
+        );
+        assert_eq!(
+            get_item(&entries(), "nope").map_err(|e| e.code),
+            Err(RpcError::UNKNOWN_KEY)
+        );
+    }
+
+    #[tokio::test]
+    async fn answer_requests() {
+        let (trigger_sender, mut trigger_receiver) = mpsc::channel(1);
+        let server = RpcServer::new("does-not-exist.bib", trigger_sender);
+        assert_eq!(
+            server
+                .handle(r#"{"jsonrpc":"2.0","id":1,"method":"exportNow"}"#)
+                .await,
+            Some(json!({"jsonrpc": "2.0", "id": 1, "result": true}))
+        );
+        assert_eq!(trigger_receiver.try_recv(), Ok(()));
+        assert_eq!(
+            server
+                .handle(r#"{"jsonrpc":"2.0","id":2,"method":"rename"}"#)
+                .await,
+            Some(json!({
+                "jsonrpc": "2.0",
+                "id": 2,
+                "error": {"code": -32601, "message": "Unknown method 'rename'"}
+            }))
+        );
+        assert_eq!(
+            server
+                .handle(r#"{"jsonrpc":"2.0","method":"exportNow"}"#)
+                .await,
+            None
+        );
+    }
+}
@@ -0,0 +1,141 @@
+use crate::cite::html_to_text;
+use crate::export::document::ExportDocument;
+use crate::zotero_api::item::Item;
+use std::fmt::Display;
+
+/// Maximum number of characters of a note in the list of results
+const MAX_NOTE_LENGTH: usize = 60;
+
+/// Items that matched a search, numbered from 1 so that some of them can be selected.
+/// Child items in the document are listed below their parents.
+pub struct SearchResults<'a> {
+    pub document: &'a ExportDocument,
+}
+
+impl Display for SearchResults<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.document.entries.is_empty() {
+            return writeln!(f, "No matching items");
+        }
+        for (index, entry) in self.document.entries.iter().enumerate() {
+            writeln!(
+                f,
+                "{:>3}  {}  {} ({})  {}",
+                index + 1,
+                entry.entry.key,
+                entry.item.data.short_authors(),
+                entry.item.year().unwrap_or("n.d."),
+                entry.item.data.title
+            )?;
+            for child in self.document.children_of(entry) {
+                writeln!(f, "     - {}: {}", child.data.item_type, child_label(child))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Title of an attachment, or the beginning of the text of a note
+fn child_label(child: &Item) -> String {
+    match child.data.field("note") {
+        Some(note) => {
+            let text = html_to_text(note);
+            match text.char_indices().nth(MAX_NOTE_LENGTH) {
+                Some((end, _)) => format!("{}...", &text[..end]),
+                None => text,
+            }
+        }
+        None => child.data.title.clone(),
+    }
+}
+
+/// Keep only the entries with the given numbers (counting from 1), in their original order.
+/// Returns the first number that doesn't belong to an entry.
+pub fn select(document: &mut ExportDocument, numbers: &[usize]) -> Result<(), usize> {
+    let count = document.entries.len();
+    if let Some(invalid) = numbers.iter().find(|number| !(1..=count).contains(*number)) {
+        return Err(*invalid);
+    }
+    let mut number = 0;
+    document.entries.retain(|_| {
+        number += 1;
+        numbers.contains(&number)
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zotero_api::ExportFormat;
+    use crate::zotero_api::item::Item;
+    use pretty_assertions::assert_eq;
+
+    fn document() -> ExportDocument {
+        let items: Vec<Item> = serde_json::from_value(serde_json::json!([
+            {"key":"ITEM0001","version":1,"meta":{"parsedDate":"2017-06-12"},
+             "data":{"itemType":"conferencePaper","title":"Attention Is All You Need","creators":[
+                {"creatorType":"author","firstName":"Ashish","lastName":"Vaswani"},
+                {"creatorType":"author","firstName":"Noam","lastName":"Shazeer"},
+                {"creatorType":"author","firstName":"Niki","lastName":"Parmar"}]},
+             "biblatex":"@inproceedings{vaswani_2017,\n}"},
+            {"key":"ITEM0002","version":1,
+             "data":{"itemType":"report","title":"Transformers","creators":[]},
+             "biblatex":"@report{transformers,\n}"}
+        ]))
+        .unwrap();
+        ExportDocument::try_from_items(items, &ExportFormat::Biblatex).unwrap()
+    }
+
+    #[test]
+    fn list_results() {
+        assert_eq!(
+            SearchResults {
+                document: &document()
+            }
+            .to_string(),
+            "  1  vaswani_2017  Ashish Vaswani et al. (2017)  Attention Is All You Need
+  2  transformers  Unknown (n.d.)  Transformers
+"
+        );
+    }
+
+    #[test]
+    fn list_results_with_children() {
+        let mut document = document();
+        let children: Vec<Item> = serde_json::from_value(serde_json::json!([
+            {"key":"ATTACH01","version":1,
+             "data":{"itemType":"attachment","title":"Full Text PDF","parentItem":"ITEM0002"}},
+            {"key":"NOTE0001","version":1,
+             "data":{"itemType":"note","note":"<p>Compare with <b>RNNs</b> &amp; check the appendix, which has all the hyperparameters</p>","parentItem":"ITEM0002"}}
+        ]))
+        .unwrap();
+        document.children.insert("ITEM0002".to_owned(), children);
+        select(&mut document, &[2]).unwrap();
+        assert_eq!(
+            SearchResults {
+                document: &document
+            }
+            .to_string(),
+            "  1  transformers  Unknown (n.d.)  Transformers
+     - attachment: Full Text PDF
+     - note: Compare with RNNs & check the appendix, which has all the hy...
+"
+        );
+    }
+
+    #[test]
+    fn select_results() {
+        let mut document = document();
+        assert_eq!(select(&mut document, &[3]), Err(3));
+        assert_eq!(select(&mut document, &[2]), Ok(()));
+        assert_eq!(
+            document
+                .entries
+                .iter()
+                .map(|entry| entry.entry.key.as_str())
+                .collect::<Vec<_>>(),
+            vec!["transformers"]
+        );
+    }
+}
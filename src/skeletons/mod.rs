@@ -0,0 +1,151 @@
+use crate::export::document::{ExportDocument, ExportEntry};
+use crate::paths::sanitize_file_name;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+
+/// Creates a literature-note stub `<citekey>.<extension>` for every exported item that doesn't have one yet.
+///
+/// Existing notes are never touched. File names follow the citar convention, and the default template
+/// is an org-roam node with `ROAM_REFS` pointing to the citation key, as expected by org-roam-bibtex.
+pub struct NoteSkeletons {
+    dir: PathBuf,
+    template: String,
+    extension: String,
+}
+
+/// Default template, an org-roam node referencing the citation key
+const ORG_ROAM_TEMPLATE: &str = ":PROPERTIES:
+:ID: ${id}
+:ROAM_REFS: @${citekey}
+:END:
+#+title: ${title}
+#+author: ${authors}
+
+";
+
+impl NoteSkeletons {
+    pub fn new(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            template: ORG_ROAM_TEMPLATE.to_owned(),
+            extension: "org".to_owned(),
+        }
+    }
+
+    /// Use a custom template for new notes, written with the given file extension.
+    ///
+    /// Supported placeholders: `${citekey}`, `${title}`, `${authors}`, `${year}`, `${doi}`, `${zotero-key}` and `${id}`.
+    pub fn with_template(mut self, template: String, extension: String) -> Self {
+        self.template = template;
+        self.extension = extension;
+        self
+    }
+
+    pub async fn write(&self, document: &ExportDocument) -> Result<(), SkeletonError> {
+        tokio::fs::create_dir_all(&self.dir)
+            .await
+            .map_err(|e| file_error(&self.dir, e))?;
+        let mut created = 0;
+        for entry in &document.entries {
+            let path = self.dir.join(format!(
+                "{}.{}",
+                sanitize_file_name(&entry.entry.key),
+                self.extension
+            ));
+            let file = tokio::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+                .await;
+            let mut file = match file {
+                Ok(file) => file,
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+                Err(e) => return Err(file_error(&path, e)),
+            };
+            file.write_all(render(&self.template, entry).as_bytes())
+                .await
+                .map_err(|e| file_error(&path, e))?;
+            created += 1;
+        }
+        log::info!(
+            "Created {} note skeleton(s) in '{}'",
+            created,
+            self.dir.display()
+        );
+        Ok(())
+    }
+}
+
+/// Fill the placeholders of a template with the data of an entry
+fn render(template: &str, entry: &ExportEntry) -> String {
+    let data = &entry.item.data;
+    [
+        ("citekey", entry.entry.key.clone()),
+        ("title", data.title.clone()),
+        ("authors", data.author_names().join(", ")),
+        ("year", entry.item.year().unwrap_or_default().to_owned()),
+        ("doi", data.field("DOI").unwrap_or_default().to_owned()),
+        ("zotero-key", entry.item.key.clone()),
+        ("id", node_id(&entry.item.key)),
+    ]
+    .iter()
+    .fold(template.to_owned(), |text, (name, value)| {
+        text.replace(&format!("${{{}}}", name), value)
+    })
+}
+
+/// UUID-formatted ID derived from the item key, so that the same item always gets the same org-roam node ID
+fn node_id(item_key: &str) -> String {
+    let hex = format!("{:x}", Sha256::digest(item_key.as_bytes()));
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..32]
+    )
+}
+
+fn file_error(path: &Path, io_error: std::io::Error) -> SkeletonError {
+    SkeletonError::FileError {
+        path: path.display().to_string(),
+        io_error,
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum SkeletonError {
+    #[error("Error with note skeleton '{path}'")]
+    FileError {
+        path: String,
+        #[source]
+        io_error: std::io::Error,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::document::tests::document;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn render_org_roam_template() {
+        let document = document();
+        let id = node_id("PARENT01");
+        assert_eq!(
+            render(ORG_ROAM_TEMPLATE, &document.entries[0]),
+            format!(
+                ":PROPERTIES:\n:ID: {}\n:ROAM_REFS: @smith_title_2020\n:END:\n#+title: Title\n#+author: \n\n",
+                id
+            )
+        );
+        assert_eq!(id.len(), 36);
+        assert_eq!(
+            render("${unknown} ${year}", &document.entries[0]),
+            "${unknown} "
+        );
+    }
+}
@@ -0,0 +1,137 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+
+/// Width of the longest bar of the growth chart
+const CHART_WIDTH: usize = 40;
+
+/// Size of the library at one point in time
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HistoryRecord {
+    pub date: DateTime<Utc>,
+    pub library_version: u64,
+    pub items: usize,
+}
+
+/// Number of items of a library over time, recorded by every export and `stats` run
+/// as one JSON object per line, to report the growth of the library.
+pub struct LibraryHistory {
+    path: PathBuf,
+}
+
+impl LibraryHistory {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// Default location of the history of a library like `users/12345`
+    pub fn default_path(library: &str) -> Option<PathBuf> {
+        Some(
+            dirs::cache_dir()?
+                .join("zotexon")
+                .join(format!("history-{}.jsonl", library.replace('/', "-"))),
+        )
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// All records, oldest first. Lines that can't be parsed are skipped.
+    pub async fn records(&self) -> std::io::Result<Vec<HistoryRecord>> {
+        match tokio::fs::read_to_string(&self.path).await {
+            Ok(text) => Ok(text
+                .lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Append the record, unless the last record has the same library version already
+    pub async fn record(&self, record: HistoryRecord) -> std::io::Result<()> {
+        let records = self.records().await?;
+        if records
+            .last()
+            .is_some_and(|last| last.library_version == record.library_version)
+        {
+            return Ok(());
+        }
+        if let Some(parent) = self.path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        let line = serde_json::to_string(&record)? + "\n";
+        file.write_all(line.as_bytes()).await
+    }
+}
+
+/// Table of the recorded sizes with the change to the previous record and a bar chart
+pub struct HistoryReport<'a>(pub &'a [HistoryRecord]);
+
+impl Display for HistoryReport<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let Some(max) = self.0.iter().map(|record| record.items).max() else {
+            return writeln!(f, "No history recorded yet, it is recorded by every export");
+        };
+        writeln!(
+            f,
+            "{:<10}  {:>8}  {:>6}  {:>6}",
+            "Date", "Version", "Items", "Change"
+        )?;
+        let mut previous = None;
+        for record in self.0 {
+            let change = match previous {
+                Some(previous) => format!("{:+}", record.items as i64 - previous as i64),
+                None => String::new(),
+            };
+            let bar = "#".repeat((record.items * CHART_WIDTH).div_ceil(max.max(1)));
+            writeln!(
+                f,
+                "{:<10}  {:>8}  {:>6}  {:>6}  {}",
+                record.date.format("%Y-%m-%d"),
+                record.library_version,
+                record.items,
+                change,
+                bar
+            )?;
+            previous = Some(record.items);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use pretty_assertions::assert_eq;
+
+    fn record(day: u32, library_version: u64, items: usize) -> HistoryRecord {
+        HistoryRecord {
+            date: Utc.with_ymd_and_hms(2025, 10, day, 12, 0, 0).unwrap(),
+            library_version,
+            items,
+        }
+    }
+
+    #[test]
+    fn history_report() {
+        let records = [record(1, 100, 20), record(8, 130, 40), record(15, 135, 38)];
+        assert_eq!(
+            HistoryReport(&records).to_string(),
+            "Date         Version   Items  Change
+2025-10-01       100      20          ####################
+2025-10-08       130      40     +20  ########################################
+2025-10-15       135      38      -2  ######################################
+"
+        );
+    }
+}
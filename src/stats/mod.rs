@@ -0,0 +1,170 @@
+mod history;
+
+use crate::cache::ItemCache;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+pub use history::{HistoryRecord, HistoryReport, LibraryHistory};
+
+/// Overview of a library, computed from the cached item data
+#[derive(Debug, Default, PartialEq)]
+pub struct LibraryStats {
+    pub library_version: u64,
+    pub regular_items: usize,
+    pub notes: usize,
+    /// Regular items without any attachments or notes, according to their `numChildren`
+    pub without_children: usize,
+    pub by_type: BTreeMap<String, usize>,
+    pub by_year: BTreeMap<String, usize>,
+    pub by_tag: BTreeMap<String, usize>,
+    pub by_collection: BTreeMap<String, usize>,
+    pub attachments: AttachmentStats,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct AttachmentStats {
+    pub total: usize,
+    pub by_link_mode: BTreeMap<String, usize>,
+    pub by_content_type: BTreeMap<String, usize>,
+}
+
+impl LibraryStats {
+    const UNKNOWN: &'static str = "unknown";
+
+    pub fn from_cache(cache: &ItemCache) -> Self {
+        let mut stats = Self {
+            library_version: cache.library_version(),
+            ..Default::default()
+        };
+        for item in cache.items() {
+            match item.data.item_type.as_str() {
+                "note" => stats.notes += 1,
+                "attachment" => {
+                    let attachments = &mut stats.attachments;
+                    attachments.total += 1;
+                    count(
+                        &mut attachments.by_link_mode,
+                        item.data.field("linkMode").unwrap_or(Self::UNKNOWN),
+                    );
+                    count(
+                        &mut attachments.by_content_type,
+                        item.data.field("contentType").unwrap_or(Self::UNKNOWN),
+                    );
+                }
+                "annotation" => {}
+                item_type => {
+                    stats.regular_items += 1;
+                    if item.meta.num_children == Some(0) {
+                        stats.without_children += 1;
+                    }
+                    count(&mut stats.by_type, item_type);
+                    count(&mut stats.by_year, item.year().unwrap_or(Self::UNKNOWN));
+                    for tag in &item.data.tags {
+                        count(&mut stats.by_tag, &tag.tag);
+                    }
+                    for collection in &item.data.collections {
+                        let name = cache
+                            .collection_path(collection)
+                            .unwrap_or_else(|| collection.clone());
+                        count(&mut stats.by_collection, &name);
+                    }
+                }
+            }
+        }
+        stats
+    }
+}
+
+fn count(counts: &mut BTreeMap<String, usize>, key: &str) {
+    *counts.entry(key.to_owned()).or_default() += 1;
+}
+
+/// Write one section of the report, with the largest counts first
+fn write_section(
+    f: &mut std::fmt::Formatter<'_>,
+    title: &str,
+    counts: &BTreeMap<String, usize>,
+    sort_by_count: bool,
+) -> std::fmt::Result {
+    writeln!(f, "\n{}:", title)?;
+    if counts.is_empty() {
+        return writeln!(f, "  (none)");
+    }
+    let mut entries: Vec<_> = counts.iter().collect();
+    if sort_by_count {
+        entries.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+    }
+    let width = entries.iter().map(|(k, _)| k.chars().count()).max();
+    for (key, count) in entries {
+        writeln!(
+            f,
+            "  {:<width$}  {:>6}",
+            key,
+            count,
+            width = width.unwrap_or(0)
+        )?;
+    }
+    Ok(())
+}
+
+impl Display for LibraryStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Library version: {}", self.library_version)?;
+        writeln!(f, "Items:           {}", self.regular_items)?;
+        writeln!(f, "Notes:           {}", self.notes)?;
+        writeln!(f, "Attachments:     {}", self.attachments.total)?;
+        writeln!(
+            f,
+            "Items without attachments or notes: {}",
+            self.without_children
+        )?;
+        write_section(f, "Items by type", &self.by_type, true)?;
+        write_section(f, "Items by year", &self.by_year, false)?;
+        write_section(f, "Items by tag", &self.by_tag, true)?;
+        write_section(f, "Items by collection", &self.by_collection, true)?;
+        write_section(
+            f,
+            "Attachments by link mode",
+            &self.attachments.by_link_mode,
+            true,
+        )?;
+        write_section(
+            f,
+            "Attachments by content type",
+            &self.attachments.by_content_type,
+            true,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::tests::cache_from_json;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn stats_from_cache() {
+        let cache = cache_from_json(
+            r#"[
+                {"key":"A","version":1,"meta":{"parsedDate":"2020-01-01","numChildren":2},"data":{"itemType":"journalArticle","tags":[{"tag":"ml"}],"collections":["C"]}},
+                {"key":"B","version":1,"meta":{"parsedDate":"2021","numChildren":0},"data":{"itemType":"book","tags":[{"tag":"ml"},{"tag":"toread"}]}},
+                {"key":"D","version":1,"data":{"itemType":"book"}},
+                {"key":"E","version":1,"data":{"itemType":"note","parentItem":"A"}},
+                {"key":"F","version":1,"data":{"itemType":"attachment","parentItem":"A","linkMode":"imported_file","contentType":"application/pdf"}}
+            ]"#,
+            r#"[{"key":"C","version":1,"data":{"name":"Thesis","parentCollection":false}}]"#,
+        );
+        let stats = LibraryStats::from_cache(&cache);
+        assert_eq!(stats.regular_items, 3);
+        assert_eq!(stats.notes, 1);
+        assert_eq!(stats.without_children, 1);
+        assert_eq!(stats.by_type["book"], 2);
+        assert_eq!(stats.by_year["2020"], 1);
+        assert_eq!(stats.by_year["unknown"], 1);
+        assert_eq!(stats.by_tag["ml"], 2);
+        assert_eq!(stats.by_collection["Thesis"], 1);
+        assert_eq!(stats.attachments.total, 1);
+        assert_eq!(stats.attachments.by_content_type["application/pdf"], 1);
+    }
+}
@@ -0,0 +1,107 @@
+use crate::export::ExportEvent;
+use anstream::{AutoStream, ColorChoice};
+use anstyle::{AnsiColor, Style};
+use std::io::Write;
+use tokio::sync::mpsc;
+
+const SUCCESS: Style = AnsiColor::Green.on_default().bold();
+const WARNING: Style = AnsiColor::Yellow.on_default().bold();
+const FAILURE: Style = AnsiColor::Red.on_default().bold();
+
+/// Prints what an export did as short lines on stderr, separate from the log, e.g.
+/// `✔ Fetched 312 items, wrote refs.bib (v4821)`.
+///
+/// Colors are only used on terminals and not with `NO_COLOR`, plain output has neither colors nor symbols.
+pub struct StatusPrinter {
+    file: String,
+    plain: bool,
+}
+
+impl StatusPrinter {
+    pub fn new(file: String) -> Self {
+        Self { file, plain: false }
+    }
+
+    /// Print without colors and symbols, e.g. for screen readers or log files
+    pub fn with_plain(mut self) -> Self {
+        self.plain = true;
+        self
+    }
+
+    /// Print the events until the export is done
+    pub async fn run(self, mut events: mpsc::UnboundedReceiver<ExportEvent>) {
+        let choice = if self.plain {
+            ColorChoice::Never
+        } else {
+            ColorChoice::Auto
+        };
+        let mut stderr = AutoStream::new(std::io::stderr(), choice);
+        while let Some(event) = events.recv().await {
+            if let Some(line) = self.line(&event) {
+                // the status is only informational, the export continues if it can't be printed
+                let _ = writeln!(stderr, "{}", line);
+            }
+        }
+    }
+
+    fn line(&self, event: &ExportEvent) -> Option<String> {
+        match event {
+            ExportEvent::Connected => Some(format!(
+                "Listening for changes of the library to update {}",
+                self.file
+            )),
+            ExportEvent::Disconnected(reason) => {
+                Some(self.styled(WARNING, "!", &format!("Connection lost: {}", reason)))
+            }
+            ExportEvent::LibraryChanged => Some("Library changed, exporting".to_owned()),
+            ExportEvent::Exported { version, entries } => Some(self.styled(
+                SUCCESS,
+                "✔",
+                &format!(
+                    "Fetched {} items, wrote {} (v{})",
+                    entries, self.file, version
+                ),
+            )),
+            ExportEvent::UpToDate => {
+                Some(self.styled(SUCCESS, "✔", &format!("{} is up to date", self.file)))
+            }
+            ExportEvent::ExportFailed(error) => Some(self.styled(
+                FAILURE,
+                "✘",
+                &format!("Export to {} failed: {}", self.file, error),
+            )),
+            ExportEvent::ExportStarted | ExportEvent::Log(_) => None,
+        }
+    }
+
+    fn styled(&self, style: Style, symbol: &str, text: &str) -> String {
+        if self.plain {
+            text.to_owned()
+        } else {
+            format!("{style}{symbol}{style:#} {text}")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn exported_line() {
+        let event = ExportEvent::Exported {
+            version: 4821,
+            entries: 312,
+        };
+        let printer = StatusPrinter::new("refs.bib".to_owned());
+        assert_eq!(
+            printer.line(&event).unwrap(),
+            "\u{1b}[1m\u{1b}[32m✔\u{1b}[0m Fetched 312 items, wrote refs.bib (v4821)"
+        );
+        assert_eq!(
+            printer.with_plain().line(&event).unwrap(),
+            "Fetched 312 items, wrote refs.bib (v4821)"
+        );
+    }
+}
@@ -0,0 +1,125 @@
+use crate::export::document::ExportDocument;
+use crate::transform::Transform;
+use crate::zotero_api::ExportFormat;
+
+/// Appended to truncated abstracts
+const ELLIPSIS: &str = "...";
+
+/// Shortens long `abstract` fields to a maximum number of characters, and removes them from entries of
+/// the given types, as full abstracts make up a large part of the file size.
+pub struct Abstracts {
+    max_length: Option<usize>,
+    dropped_types: Vec<String>,
+}
+
+impl Abstracts {
+    pub fn new(max_length: Option<usize>, dropped_types: Vec<String>) -> Self {
+        Self {
+            max_length,
+            dropped_types,
+        }
+    }
+}
+
+impl Transform for Abstracts {
+    fn apply(&self, document: &mut ExportDocument, _format: &ExportFormat) {
+        for entry in &mut document.entries {
+            let entry = &mut entry.entry;
+            if self.dropped_types.iter().any(|dropped| {
+                entry
+                    .entry_type
+                    .eq_ignore_ascii_case(dropped.trim_start_matches('@'))
+            }) {
+                entry.remove("abstract");
+                continue;
+            }
+            if let Some(max_length) = self.max_length
+                && let Some(text) = entry.get("abstract")
+                && let Some(truncated) = truncate(text, max_length)
+            {
+                entry.set("abstract", truncated);
+            }
+        }
+    }
+}
+
+/// Cut the text at the last word boundary before `max_length` characters, or `None` if it is short enough.
+/// Braces that are opened in the remaining text are closed, so that the entry stays valid.
+fn truncate(text: &str, max_length: usize) -> Option<String> {
+    if text.chars().count() <= max_length {
+        return None;
+    }
+    let end = text
+        .char_indices()
+        .nth(max_length)
+        .map(|(index, _)| index)
+        .unwrap_or(text.len());
+    let cut = &text[..end];
+    let at_word_boundary = text[end..].starts_with(char::is_whitespace);
+    let cut = match cut.rfind(char::is_whitespace) {
+        Some(index) if index > 0 && !at_word_boundary => &cut[..index],
+        _ => cut,
+    };
+    let mut truncated = cut
+        .trim_end_matches(|c: char| c.is_whitespace() || c == '\\' || c == ',' || c == ';')
+        .to_owned();
+    truncated.push_str(ELLIPSIS);
+    let mut depth = 0usize;
+    let mut escaped = false;
+    for c in truncated.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    truncated.push_str(&"}".repeat(depth));
+    Some(truncated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zotero_api::item::Item;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::short("Short abstract.", 20, None)]
+    #[case::word_boundary("We study the optics of lenses.", 20, Some("We study the optics..."))]
+    #[case::open_brace(
+        "We study {\\emph{optics} of lenses}.",
+        25,
+        Some("We study {\\emph{optics}...}")
+    )]
+    #[case::single_word("Supercalifragilistic", 5, Some("Super..."))]
+    fn truncate_abstract(
+        #[case] text: &str,
+        #[case] max_length: usize,
+        #[case] expected: Option<&str>,
+    ) {
+        assert_eq!(truncate(text, max_length).as_deref(), expected);
+    }
+
+    #[test]
+    fn drop_abstract_by_type() {
+        let items: Vec<Item> = serde_json::from_value(serde_json::json!([
+            {"key":"PARENT01","version":1,"data":{"itemType":"book","title":"Title"},
+             "biblatex":"@book{book,\n\tabstract = {About the book}\n}"},
+            {"key":"PARENT02","version":1,"data":{"itemType":"journalArticle","title":"Title"},
+             "biblatex":"@article{article,\n\tabstract = {About the article}\n}"}
+        ]))
+        .unwrap();
+        let mut document = ExportDocument::try_from_items(items, &ExportFormat::Biblatex).unwrap();
+        Abstracts::new(Some(9), vec!["book".to_owned()])
+            .apply(&mut document, &ExportFormat::Biblatex);
+        let abstracts: Vec<Option<&str>> = document
+            .entries
+            .iter()
+            .map(|entry| entry.entry.get("abstract"))
+            .collect();
+        assert_eq!(abstracts, vec![None, Some("About the...")]);
+    }
+}
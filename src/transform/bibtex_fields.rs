@@ -0,0 +1,119 @@
+use crate::bibtex::Entry;
+use crate::export::document::ExportDocument;
+use crate::transform::Transform;
+use crate::transform::dates::month_macro;
+use crate::zotero_api::ExportFormat;
+
+/// BibLaTeX fields with a classic BibTeX equivalent
+const RENAMED_FIELDS: [(&str, &str); 3] = [
+    ("journaltitle", "journal"),
+    ("location", "address"),
+    ("annotation", "annote"),
+];
+
+/// BibLaTeX entry types with a classic BibTeX equivalent
+const RENAMED_TYPES: [(&str, &str); 7] = [
+    ("online", "misc"),
+    ("report", "techreport"),
+    ("collection", "book"),
+    ("mvbook", "book"),
+    ("dataset", "misc"),
+    ("software", "misc"),
+    ("patent", "misc"),
+];
+
+/// Converts BibLaTeX-only fields and entry types of a BibTeX export to their classic equivalents,
+/// as Zotero's BibTeX translator doesn't always produce what older styles expect
+pub struct BibtexFields;
+
+impl Transform for BibtexFields {
+    fn apply(&self, document: &mut ExportDocument, format: &ExportFormat) {
+        if *format != ExportFormat::Bibtex {
+            return;
+        }
+        for entry in &mut document.entries {
+            down_convert(&mut entry.entry);
+        }
+    }
+}
+
+fn down_convert(entry: &mut Entry) {
+    for (from, to) in RENAMED_FIELDS {
+        entry.rename(from, to);
+    }
+    if let Some(date) = entry.get("date").map(str::to_owned) {
+        let mut parts = date.split('-');
+        let year = parts.next().filter(|year| year.len() == 4);
+        if let Some(year) = year
+            && entry.get("year").is_none()
+        {
+            entry.set("year", year);
+        }
+        let month = parts.next().and_then(|month| month.parse().ok());
+        if let Some(month) = month.and_then(month_macro)
+            && entry.get("month").is_none()
+        {
+            entry.set_value("month", month);
+        }
+        // Dates that can't be split into year and month are kept for the reader
+        if year.is_some() {
+            entry.remove("date");
+        }
+    }
+    if entry.entry_type.eq_ignore_ascii_case("thesis") {
+        let is_masters = entry
+            .get("type")
+            .is_some_and(|thesis_type| thesis_type.to_lowercase().contains("master"));
+        entry.entry_type = if is_masters {
+            "mastersthesis"
+        } else {
+            "phdthesis"
+        }
+        .to_owned();
+    }
+    if ["phdthesis", "mastersthesis"]
+        .iter()
+        .any(|thesis| entry.entry_type.eq_ignore_ascii_case(thesis))
+    {
+        entry.rename("institution", "school");
+    }
+    if let Some((_, to)) = RENAMED_TYPES
+        .iter()
+        .find(|(from, _)| entry.entry_type.eq_ignore_ascii_case(from))
+    {
+        entry.entry_type = to.to_string();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bibtex::{Block, parse};
+    use assert_matches::assert_matches;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::article(
+        "@article{key,\n\tjournaltitle = {Journal},\n\tdate = {2020-03-15},\n\tlocation = {Berlin}\n}",
+        "@article{key,\n\tjournal = {Journal},\n\taddress = {Berlin},\n\tyear = {2020},\n\tmonth = mar\n}"
+    )]
+    #[case::keep_existing_fields(
+        "@article{key,\n\tjournal = {J.},\n\tjournaltitle = {Journal},\n\tyear = {2019},\n\tdate = {2020}\n}",
+        "@article{key,\n\tjournal = {J.},\n\tyear = {2019}\n}"
+    )]
+    #[case::unparsable_date(
+        "@online{key,\n\tdate = {circa 1850}\n}",
+        "@misc{key,\n\tdate = {circa 1850}\n}"
+    )]
+    #[case::thesis(
+        "@thesis{key,\n\ttype = {Master's thesis},\n\tinstitution = {University}\n}",
+        "@mastersthesis{key,\n\ttype = {Master's thesis},\n\tschool = {University}\n}"
+    )]
+    fn convert_to_bibtex(#[case] text: &str, #[case] expected: &str) {
+        assert_matches!(parse(text).unwrap().into_iter().next(), Some(Block::Entry(mut entry)) => {
+            down_convert(&mut entry);
+            assert_eq!(entry.to_string(), expected);
+        });
+    }
+}
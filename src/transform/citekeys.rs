@@ -0,0 +1,365 @@
+use crate::export::document::ExportDocument;
+use crate::transform::Transform;
+use crate::zotero_api::ExportFormat;
+use crate::zotero_api::item::Item;
+use std::str::FromStr;
+
+/// Pattern of Better BibTeX's default citation keys, e.g. `smithOpticsLenses2020`
+pub const DEFAULT_PATTERN: &str = "[auth:lower][shorttitle3_3][year]";
+
+/// Words that don't count for the `title` fields of a pattern
+const FUNCTION_WORDS: [&str; 22] = [
+    "a", "an", "the", "and", "or", "nor", "but", "of", "on", "in", "at", "to", "for", "with", "by",
+    "from", "as", "into", "about", "over", "under", "via",
+];
+
+/// Replaces the citation keys generated by Zotero with keys like those of Better BibTeX, so that existing
+/// `\cite` commands keep working when switching from Better BibTeX.
+///
+/// A key pinned in the Extra field with a line `Citation Key: smith2020optics` (or in Zotero's `citationKey` field)
+/// is used as it is, all other keys are generated from a pattern like `[auth:lower][year][shorttitle]`.
+/// Entries for which the pattern gives an empty key keep the key of Zotero.
+pub struct CitationKeys {
+    pattern: KeyPattern,
+}
+
+impl CitationKeys {
+    pub fn new(pattern: KeyPattern) -> Self {
+        Self { pattern }
+    }
+}
+
+impl Transform for CitationKeys {
+    fn apply(&self, document: &mut ExportDocument, _format: &ExportFormat) {
+        for entry in &mut document.entries {
+            let key = match pinned_key(&entry.item) {
+                Some(pinned) => pinned.to_owned(),
+                None => self.pattern.generate(&entry.item),
+            };
+            if !key.is_empty() {
+                entry.entry.key = key;
+            }
+        }
+    }
+}
+
+/// Citation key pinned by the user, from Zotero's `citationKey` field or a `Citation Key:` line in the Extra field
+fn pinned_key(item: &Item) -> Option<&str> {
+    item.data
+        .field("citationKey")
+        .map(str::trim)
+        .or_else(|| {
+            item.data.field("extra")?.lines().find_map(|line| {
+                let (name, value) = line.split_once(':')?;
+                name.trim()
+                    .eq_ignore_ascii_case("citation key")
+                    .then_some(value.trim())
+            })
+        })
+        .filter(|key| !key.is_empty())
+}
+
+/// Pattern for citation keys in Better BibTeX's legacy syntax: fields in brackets with optional modifiers,
+/// and literal text in between, e.g. `[auth:lower]_[year]`.
+///
+/// Fields:
+/// - `auth`: last name of the first author
+/// - `authors`, `authorsN`: last names of all or the first N authors, followed by `EtAl` if there are more
+/// - `year`: year of the date of the item
+/// - `title`: all significant words of the title, capitalized
+/// - `shorttitle`, `shorttitleN_M`: the first N (default 3) significant words of the title, of which the first M
+///   (default 0) are capitalized
+/// - `veryshorttitle`: the first significant word of the title
+///
+/// Modifiers: `lower` and `upper`
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyPattern(Vec<Part>);
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Literal(String),
+    Field(Field, Vec<Modifier>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Field {
+    Auth,
+    Authors(Option<usize>),
+    Year,
+    Title,
+    ShortTitle { words: usize, capitalized: usize },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Modifier {
+    Lower,
+    Upper,
+}
+
+impl FromStr for KeyPattern {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut parts = Vec::new();
+        let mut rest = text;
+        while !rest.is_empty() {
+            match rest.find('[') {
+                Some(0) => {
+                    let end = rest.find(']').ok_or_else(|| {
+                        format!("Unclosed '[' in citation key pattern '{}'", text)
+                    })?;
+                    parts.push(parse_field(&rest[1..end])?);
+                    rest = &rest[end + 1..];
+                }
+                Some(start) => {
+                    parts.push(Part::Literal(rest[..start].to_owned()));
+                    rest = &rest[start..];
+                }
+                None => {
+                    parts.push(Part::Literal(rest.to_owned()));
+                    rest = "";
+                }
+            }
+        }
+        if !parts.iter().any(|part| matches!(part, Part::Field(..))) {
+            return Err(format!(
+                "Citation key pattern '{}' has no field like [auth] or [year]",
+                text
+            ));
+        }
+        Ok(Self(parts))
+    }
+}
+
+fn parse_field(text: &str) -> Result<Part, String> {
+    let mut names = text.split(':');
+    let name = names.next().unwrap_or_default();
+    let field = match name {
+        "auth" => Field::Auth,
+        "authors" => Field::Authors(None),
+        "year" => Field::Year,
+        "title" => Field::Title,
+        "shorttitle" => Field::ShortTitle {
+            words: 3,
+            capitalized: 0,
+        },
+        "veryshorttitle" => Field::ShortTitle {
+            words: 1,
+            capitalized: 0,
+        },
+        _ => {
+            if let Some(count) = name.strip_prefix("authors").and_then(|n| n.parse().ok()) {
+                Field::Authors(Some(count))
+            } else if let Some((words, capitalized)) = name
+                .strip_prefix("shorttitle")
+                .and_then(|n| n.split_once('_'))
+                .and_then(|(words, capitalized)| {
+                    Some((words.parse().ok()?, capitalized.parse().ok()?))
+                })
+            {
+                Field::ShortTitle { words, capitalized }
+            } else {
+                return Err(format!("Unknown citation key field '[{}]'", name));
+            }
+        }
+    };
+    let modifiers = names
+        .map(|modifier| match modifier {
+            "lower" => Ok(Modifier::Lower),
+            "upper" => Ok(Modifier::Upper),
+            _ => Err(format!(
+                "Unknown modifier ':{}' of citation key field '[{}]'",
+                modifier, text
+            )),
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(Part::Field(field, modifiers))
+}
+
+impl KeyPattern {
+    /// Key of the item, empty if none of the fields of the pattern has a value
+    fn generate(&self, item: &Item) -> String {
+        let mut key = String::new();
+        let mut has_value = false;
+        for part in &self.0 {
+            match part {
+                Part::Literal(text) => key.push_str(&clean(text, |c| "-_:.+/".contains(c))),
+                Part::Field(field, modifiers) => {
+                    let mut value = field_value(field, item);
+                    for modifier in modifiers {
+                        value = match modifier {
+                            Modifier::Lower => value.to_lowercase(),
+                            Modifier::Upper => value.to_uppercase(),
+                        };
+                    }
+                    has_value |= !value.is_empty();
+                    key.push_str(&value);
+                }
+            }
+        }
+        if has_value { key } else { String::new() }
+    }
+}
+
+fn field_value(field: &Field, item: &Item) -> String {
+    match field {
+        Field::Auth => last_names(item).into_iter().next().unwrap_or_default(),
+        Field::Authors(count) => {
+            let names = last_names(item);
+            match count {
+                Some(count) if names.len() > *count => names[..*count].concat() + "EtAl",
+                _ => names.concat(),
+            }
+        }
+        Field::Year => item.year().unwrap_or_default().to_owned(),
+        Field::Title => title_words(item)
+            .iter()
+            .map(|word| capitalize(word))
+            .collect(),
+        Field::ShortTitle { words, capitalized } => title_words(item)
+            .iter()
+            .take(*words)
+            .enumerate()
+            .map(|(i, word)| {
+                if i < *capitalized {
+                    capitalize(word)
+                } else {
+                    word.to_lowercase()
+                }
+            })
+            .collect(),
+    }
+}
+
+/// Last names of the authors, or of the other creators like editors if there are no authors
+fn last_names(item: &Item) -> Vec<String> {
+    let creators = &item.data.creators;
+    let has_authors = creators
+        .iter()
+        .any(|creator| creator.creator_type == "author");
+    creators
+        .iter()
+        .filter(|creator| !has_authors || creator.creator_type == "author")
+        .filter_map(|creator| creator.last_name.as_ref().or(creator.name.as_ref()))
+        .map(|name| clean(name, |_| false))
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// Words of the title without function words like `the` or `of`
+fn title_words(item: &Item) -> Vec<String> {
+    deunicode::deunicode(&item.data.title)
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| {
+            !word.is_empty()
+                && !FUNCTION_WORDS
+                    .iter()
+                    .any(|function_word| word.eq_ignore_ascii_case(function_word))
+        })
+        .map(str::to_owned)
+        .collect()
+}
+
+/// Text transliterated to ASCII, without the characters that aren't allowed in citation keys
+fn clean(text: &str, allowed: impl Fn(char) -> bool) -> String {
+    deunicode::deunicode(text)
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || allowed(*c))
+        .collect()
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    fn item() -> Item {
+        serde_json::from_value(serde_json::json!({
+            "key": "ITEM0001",
+            "version": 1,
+            "meta": {"parsedDate": "2020-03-01"},
+            "data": {
+                "itemType": "journalArticle",
+                "title": "The Optics of Curved Lenses: A Review",
+                "creators": [
+                    {"creatorType": "editor", "firstName": "Eve", "lastName": "Editor"},
+                    {"creatorType": "author", "firstName": "Jörg", "lastName": "Müller-Lüdenscheidt"},
+                    {"creatorType": "author", "name": "Optics Society"},
+                    {"creatorType": "author", "firstName": "Ada", "lastName": "Lovelace"}
+                ]
+            }
+        }))
+        .unwrap()
+    }
+
+    #[rstest]
+    #[case(DEFAULT_PATTERN, "mullerludenscheidtOpticsCurvedLenses2020")]
+    #[case("[auth][year][shorttitle]", "MullerLudenscheidt2020opticscurvedlenses")]
+    #[case("[auth:lower]_[year]", "mullerludenscheidt_2020")]
+    #[case(
+        "[authors2][veryshorttitle:upper]",
+        "MullerLudenscheidtOpticsSocietyEtAlOPTICS"
+    )]
+    #[case("[authors]", "MullerLudenscheidtOpticsSocietyLovelace")]
+    #[case("[title]", "OpticsCurvedLensesReview")]
+    #[case("key {[year]}", "key2020")]
+    fn generate_key(#[case] pattern: &str, #[case] expected: &str) {
+        let pattern: KeyPattern = pattern.parse().unwrap();
+        assert_eq!(pattern.generate(&item()), expected);
+    }
+
+    #[rstest]
+    #[case("[auth")]
+    #[case("[author]")]
+    #[case("[auth:title]")]
+    #[case("smith")]
+    fn invalid_pattern(#[case] pattern: &str) {
+        assert!(pattern.parse::<KeyPattern>().is_err());
+    }
+
+    #[test]
+    fn pinned_and_generated_keys() {
+        let mut pinned = item();
+        pinned.data.fields.insert(
+            "extra".to_owned(),
+            "Original date: 1850\nCitation Key: mueller2020optics".into(),
+        );
+        let mut untitled = item();
+        untitled.data.title.clear();
+        untitled.data.creators.clear();
+        untitled.meta.parsed_date = None;
+        untitled
+            .other
+            .insert("biblatex".to_owned(), "@article{zotero_2020,\n}".into());
+        let items: Vec<Item> = [item(), pinned, untitled]
+            .into_iter()
+            .map(|mut item| {
+                item.other
+                    .entry("biblatex")
+                    .or_insert_with(|| "@article{muller-ludenscheidt_optics_2020,\n}".into());
+                item
+            })
+            .collect();
+        let mut document = ExportDocument::try_from_items(items, &ExportFormat::Biblatex).unwrap();
+        CitationKeys::new("[auth:lower][year]".parse().unwrap())
+            .apply(&mut document, &ExportFormat::Biblatex);
+        let keys: Vec<&str> = document
+            .entries
+            .iter()
+            .map(|entry| entry.entry.key.as_str())
+            .collect();
+        assert_eq!(
+            keys,
+            vec!["mullerludenscheidt2020", "mueller2020optics", "zotero_2020"]
+        );
+    }
+}
@@ -0,0 +1,93 @@
+use crate::export::document::ExportDocument;
+use crate::transform::{LibraryData, Transform};
+use crate::zotero_api::ExportFormat;
+use crate::zotero_api::item::collection_path;
+
+/// Adds the collections of every item to its `keywords`, e.g. `collection:Thesis/Chapter 2`,
+/// so that bibliographies can be filtered by collection with BibLaTeX's `keyword` option.
+pub struct CollectionKeywords {
+    prefix: String,
+}
+
+impl CollectionKeywords {
+    pub fn new(prefix: String) -> Self {
+        Self { prefix }
+    }
+}
+
+impl Transform for CollectionKeywords {
+    fn apply(&self, document: &mut ExportDocument, _format: &ExportFormat) {
+        for entry in &mut document.entries {
+            let mut keywords: Vec<String> = entry
+                .entry
+                .get("keywords")
+                .map(|keywords| {
+                    keywords
+                        .split(',')
+                        .map(|keyword| keyword.trim().to_owned())
+                        .filter(|keyword| !keyword.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default();
+            let mut paths: Vec<String> = entry
+                .item
+                .data
+                .collections
+                .iter()
+                .filter_map(|key| collection_path(&document.collections, key))
+                .map(|path| format!("{}{}", self.prefix, path))
+                .collect();
+            paths.sort();
+            if paths.is_empty() {
+                continue;
+            }
+            for path in paths {
+                if !keywords.contains(&path) {
+                    keywords.push(path);
+                }
+            }
+            entry.entry.set("keywords", keywords.join(", "));
+        }
+    }
+
+    fn needs(&self) -> &[LibraryData] {
+        &[LibraryData::Collections]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zotero_api::item::{Collection, Item};
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn add_collection_keywords() {
+        let items: Vec<Item> = serde_json::from_value(serde_json::json!([
+            {"key":"PARENT01","version":1,"data":{"itemType":"book","title":"Title","collections":["B","A"]},
+             "biblatex":"@book{key,\n\tkeywords = {optics}\n}"},
+            {"key":"PARENT02","version":1,"data":{"itemType":"book","title":"Title"},
+             "biblatex":"@book{other,\n}"}
+        ]))
+        .unwrap();
+        let collections: Vec<Collection> = serde_json::from_str(
+            r#"[
+                {"key":"A","version":1,"data":{"name":"Thesis","parentCollection":false}},
+                {"key":"B","version":1,"data":{"name":"Chapter 2","parentCollection":"A"}}
+            ]"#,
+        )
+        .unwrap();
+        let mut document = ExportDocument::try_from_items(items, &ExportFormat::Biblatex).unwrap();
+        document.collections = collections
+            .into_iter()
+            .map(|collection| (collection.key.clone(), collection))
+            .collect();
+        CollectionKeywords::new("collection:".to_owned())
+            .apply(&mut document, &ExportFormat::Biblatex);
+        assert_eq!(
+            document.entries[0].entry.get("keywords"),
+            Some("optics, collection:Thesis, collection:Thesis/Chapter 2")
+        );
+        assert_eq!(document.entries[1].entry.get("keywords"), None);
+    }
+}
@@ -0,0 +1,219 @@
+use crate::bibtex::{Block, Entry};
+use crate::export::document::ExportDocument;
+use crate::transform::Transform;
+use crate::zotero_api::ExportFormat;
+use std::collections::{BTreeMap, HashSet};
+
+/// Entry types of parts of a book
+const PART_TYPES: [&str; 2] = ["incollection", "inbook"];
+
+/// Fields that describe the book rather than the part, moved to the book entry if all parts agree on them
+const BOOK_FIELDS: [&str; 15] = [
+    "booktitle",
+    "booksubtitle",
+    "editor",
+    "publisher",
+    "location",
+    "address",
+    "date",
+    "year",
+    "month",
+    "isbn",
+    "series",
+    "number",
+    "volume",
+    "volumes",
+    "edition",
+];
+
+/// Adds a book entry for every book that several exported chapters are part of, and lets the chapters
+/// refer to it with `crossref` instead of repeating the book's metadata.
+///
+/// Chapters belong to the same book if they have the same ISBN, or the same book title and year.
+/// The book entries are written after all other entries, as BibTeX requires.
+pub struct Crossref;
+
+impl Transform for Crossref {
+    fn apply(&self, document: &mut ExportDocument, format: &ExportFormat) {
+        let mut books: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+        for (index, entry) in document.entries.iter().enumerate() {
+            let is_part = PART_TYPES
+                .iter()
+                .any(|part| entry.entry.entry_type.eq_ignore_ascii_case(part));
+            if let Some(book) = book_id(&entry.entry).filter(|_| is_part) {
+                books.entry(book).or_default().push(index);
+            }
+        }
+        let mut keys: HashSet<String> = document
+            .entries
+            .iter()
+            .map(|entry| entry.entry.key.clone())
+            .collect();
+        for indices in books.values().filter(|indices| indices.len() > 1) {
+            let mut parts: Vec<&mut Entry> = document
+                .entries
+                .iter_mut()
+                .enumerate()
+                .filter(|(index, _)| indices.contains(index))
+                .map(|(_, entry)| &mut entry.entry)
+                .collect();
+            let book = book_entry(&mut parts, format, &mut keys);
+            document.trailer.push(Block::Entry(book));
+        }
+    }
+}
+
+/// Identifies the book that an entry is part of
+fn book_id(entry: &Entry) -> Option<String> {
+    if let Some(isbn) = entry.get("isbn") {
+        return Some(isbn.replace('-', "").to_lowercase());
+    }
+    let title = entry.get("booktitle")?.to_lowercase();
+    Some(format!("{}|{}", title, year(entry).unwrap_or_default()))
+}
+
+fn year(entry: &Entry) -> Option<&str> {
+    entry
+        .get("date")
+        .or(entry.get("year"))
+        .and_then(|date| date.get(..4))
+}
+
+/// Build the book entry from the fields that all parts agree on, and replace these fields by a `crossref` in the parts
+fn book_entry(
+    parts: &mut [&mut Entry],
+    format: &ExportFormat,
+    keys: &mut HashSet<String>,
+) -> Entry {
+    let year = year(parts[0]).map(str::to_owned);
+    let all_incollection = parts
+        .iter()
+        .all(|part| part.entry_type.eq_ignore_ascii_case("incollection"));
+    let mut book = Entry {
+        entry_type: match format {
+            ExportFormat::Biblatex if all_incollection => "collection",
+            _ => "book",
+        }
+        .to_owned(),
+        key: String::new(),
+        fields: Vec::new(),
+    };
+    for name in BOOK_FIELDS {
+        let Some(value) = parts[0].get(name).map(str::to_owned) else {
+            continue;
+        };
+        if parts
+            .iter()
+            .all(|part| part.get(name) == Some(value.as_str()))
+        {
+            match name {
+                "booktitle" => {
+                    book.set("title", value.as_str());
+                    // BibTeX only inherits fields of the same name
+                    if *format == ExportFormat::Bibtex {
+                        book.set("booktitle", value.as_str());
+                    }
+                }
+                "booksubtitle" => book.set("subtitle", value.as_str()),
+                _ => book.set(name, value.as_str()),
+            }
+            for part in parts.iter_mut() {
+                part.remove(name);
+            }
+        }
+    }
+    book.key = unique_key(&book_key(&book, year.as_deref()), keys);
+    for part in parts.iter_mut() {
+        part.set("crossref", book.key.as_str());
+    }
+    book
+}
+
+/// Key made of the first words of the book title and the year, e.g. `handbook_of_science_2020`
+fn book_key(book: &Entry, year: Option<&str>) -> String {
+    let title: Vec<String> = book
+        .get("title")
+        .unwrap_or("book")
+        .split_whitespace()
+        .map(|word| {
+            word.chars()
+                .filter(char::is_ascii_alphanumeric)
+                .collect::<String>()
+                .to_lowercase()
+        })
+        .filter(|word| !word.is_empty())
+        .take(3)
+        .collect();
+    title
+        .into_iter()
+        .chain(year.map(str::to_owned))
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+fn unique_key(key: &str, keys: &mut HashSet<String>) -> String {
+    let mut unique = key.to_owned();
+    let mut suffix = 'a';
+    while keys.contains(&unique) {
+        unique = format!("{}{}", key, suffix);
+        suffix = char::from(suffix as u8 + 1);
+    }
+    keys.insert(unique.clone());
+    unique
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zotero_api::item::Item;
+    use pretty_assertions::assert_eq;
+
+    fn chapter(key: &str, export: &str) -> serde_json::Value {
+        serde_json::json!({
+            "key": key, "version": 1,
+            "data": { "itemType": "bookSection", "title": "Chapter" },
+            "biblatex": export
+        })
+    }
+
+    #[test]
+    fn crossref_shared_book() {
+        let items: Vec<Item> = serde_json::from_value(serde_json::json!([
+            chapter("CHAPTER1", "@incollection{smith_2020,\n\ttitle = {First},\n\tbooktitle = {Handbook of Science},\n\tpublisher = {Pub},\n\tpages = {1--10},\n\tdate = {2020}\n}"),
+            chapter("CHAPTER2", "@incollection{doe_2020,\n\ttitle = {Second},\n\tbooktitle = {Handbook of Science},\n\tpublisher = {Other Pub},\n\tdate = {2020-05}\n}"),
+            chapter("CHAPTER3", "@incollection{lone_2020,\n\ttitle = {Third},\n\tbooktitle = {Other Book},\n\tdate = {2020}\n}"),
+        ]))
+        .unwrap();
+        let mut document = ExportDocument::try_from_items(items, &ExportFormat::Biblatex).unwrap();
+        Crossref.apply(&mut document, &ExportFormat::Biblatex);
+        assert_eq!(
+            document.render(),
+            "
+@incollection{smith_2020,
+\ttitle = {First},
+\tpublisher = {Pub},
+\tpages = {1--10},
+\tdate = {2020},
+\tcrossref = {handbook_of_science_2020}
+}
+
+@incollection{doe_2020,
+\ttitle = {Second},
+\tpublisher = {Other Pub},
+\tdate = {2020-05},
+\tcrossref = {handbook_of_science_2020}
+}
+
+@incollection{lone_2020,
+\ttitle = {Third},
+\tbooktitle = {Other Book},
+\tdate = {2020}
+}
+
+@collection{handbook_of_science_2020,
+\ttitle = {Handbook of Science}
+}
+"
+        );
+    }
+}
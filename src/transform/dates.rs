@@ -0,0 +1,137 @@
+use crate::bibtex::Value;
+use crate::export::document::{ExportDocument, ExportEntry};
+use crate::transform::Transform;
+use crate::zotero_api::ExportFormat;
+
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+/// Writes dates in the standard form of each format, based on the date parsed by Zotero:
+/// an ISO `date` field for BibLaTeX, and `year` with a three-letter `month` macro for BibTeX.
+pub struct Dates;
+
+impl Transform for Dates {
+    fn apply(&self, document: &mut ExportDocument, format: &ExportFormat) {
+        for entry in &mut document.entries {
+            match format {
+                ExportFormat::Biblatex => biblatex_date(entry),
+                ExportFormat::Bibtex => bibtex_date(entry),
+                ExportFormat::Json => {}
+            }
+        }
+    }
+}
+
+fn biblatex_date(entry: &mut ExportEntry) {
+    let Some(date) = &entry.item.meta.parsed_date else {
+        return;
+    };
+    entry.entry.set("date", date.as_str());
+    entry.entry.remove("year");
+    entry.entry.remove("month");
+}
+
+fn bibtex_date(entry: &mut ExportEntry) {
+    let parsed_date = entry.item.meta.parsed_date.as_deref().unwrap_or_default();
+    let mut parts = parsed_date.split('-');
+    if let Some(year) = parts.next().filter(|year| !year.is_empty()) {
+        entry.entry.set("year", year);
+    }
+    let month = parts
+        .next()
+        .and_then(|month| month.parse().ok())
+        .or_else(|| entry.entry.get("month").and_then(month_number));
+    if let Some(month) = month.and_then(month_macro) {
+        entry.entry.set_value("month", month);
+    }
+}
+
+/// `month` value as three-letter macro, for a month number from 1 to 12
+pub(super) fn month_macro(month: usize) -> Option<Value> {
+    MONTHS
+        .get(month.wrapping_sub(1))
+        .map(|name| Value::Bare(name.to_string()))
+}
+
+/// Number of a month given as number, name or abbreviation, e.g. `3`, `March` or `mar`
+fn month_number(month: &str) -> Option<usize> {
+    let month = month.trim().to_ascii_lowercase();
+    month.parse().ok().or_else(|| {
+        MONTHS
+            .iter()
+            .position(|name| month.len() >= 3 && month.starts_with(name))
+            .map(|index| index + 1)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zotero_api::item::Item;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    fn document(parsed_date: Option<&str>, export: &str) -> ExportDocument {
+        let mut item = serde_json::json!({
+            "key": "PARENT01", "version": 1,
+            "data": { "itemType": "book", "title": "Title" },
+            "biblatex": export
+        });
+        if let Some(date) = parsed_date {
+            item["meta"] = serde_json::json!({ "parsedDate": date });
+        }
+        let items: Vec<Item> = serde_json::from_value(serde_json::json!([item])).unwrap();
+        ExportDocument::try_from_items(items, &ExportFormat::Biblatex).unwrap()
+    }
+
+    #[rstest]
+    #[case::biblatex_iso_date(
+        ExportFormat::Biblatex,
+        Some("2020-03-15"),
+        "@book{key,\n\tyear = {2020},\n\tmonth = {March}\n}",
+        "@book{key,\n\tdate = {2020-03-15}\n}"
+    )]
+    #[case::biblatex_without_date(
+        ExportFormat::Biblatex,
+        None,
+        "@book{key,\n\tdate = {circa 1850}\n}",
+        "@book{key,\n\tdate = {circa 1850}\n}"
+    )]
+    #[case::bibtex_month_macro(
+        ExportFormat::Bibtex,
+        Some("2020-03"),
+        "@book{key,\n\tyear = {2020},\n\tmonth = {3}\n}",
+        "@book{key,\n\tyear = {2020},\n\tmonth = mar\n}"
+    )]
+    #[case::bibtex_month_name(
+        ExportFormat::Bibtex,
+        Some("2020"),
+        "@book{key,\n\tmonth = {September}\n}",
+        "@book{key,\n\tmonth = sep,\n\tyear = {2020}\n}"
+    )]
+    #[case::bibtex_unknown_month(
+        ExportFormat::Bibtex,
+        None,
+        "@book{key,\n\tmonth = {13}\n}",
+        "@book{key,\n\tmonth = {13}\n}"
+    )]
+    fn normalize_dates(
+        #[case] format: ExportFormat,
+        #[case] parsed_date: Option<&str>,
+        #[case] export: &str,
+        #[case] expected: &str,
+    ) {
+        let mut document = document(parsed_date, export);
+        Dates.apply(&mut document, &format);
+        assert_eq!(document.entries[0].entry.to_string(), expected);
+    }
+
+    #[rstest]
+    #[case("3", Some(3))]
+    #[case("Dec.", Some(12))]
+    #[case("ma", None)]
+    fn parse_month(#[case] month: &str, #[case] expected: Option<usize>) {
+        assert_eq!(month_number(month), expected);
+    }
+}
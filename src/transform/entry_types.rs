@@ -0,0 +1,45 @@
+use crate::export::document::ExportDocument;
+use crate::transform::Transform;
+use crate::zotero_api::ExportFormat;
+use std::collections::BTreeMap;
+
+/// Replaces the entry type chosen by Zotero's translator, based on the Zotero item type,
+/// e.g. `preprint` to `online` instead of `unpublished`
+pub struct EntryTypes {
+    /// Entry type by Zotero item type
+    types: BTreeMap<String, String>,
+}
+
+impl EntryTypes {
+    pub fn new(types: BTreeMap<String, String>) -> Self {
+        Self { types }
+    }
+}
+
+impl Transform for EntryTypes {
+    fn apply(&self, document: &mut ExportDocument, _format: &ExportFormat) {
+        for entry in &mut document.entries {
+            if let Some(entry_type) = self.types.get(&entry.item.data.item_type) {
+                entry.entry.entry_type = entry_type.trim_start_matches('@').to_owned();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::document::tests::document;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn map_entry_types() {
+        let mut document = document();
+        EntryTypes::new(BTreeMap::from([
+            ("journalArticle".to_owned(), "@online".to_owned()),
+            ("book".to_owned(), "mvbook".to_owned()),
+        ]))
+        .apply(&mut document, &ExportFormat::Biblatex);
+        assert_eq!(document.entries[0].entry.entry_type, "online");
+    }
+}
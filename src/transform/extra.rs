@@ -0,0 +1,90 @@
+use crate::bibtex::Entry;
+use crate::export::document::ExportDocument;
+use crate::transform::Transform;
+use crate::zotero_api::ExportFormat;
+
+/// Applies Better BibTeX-style field overrides from the Extra field of an item.
+///
+/// Every line `tex.<field>: <value>` (or `tex.<field>= <value>`) sets the field in the entry, replacing the value
+/// exported by Zotero. Lines prefixed with `biblatex.` or `bibtex.` only apply to the respective format.
+/// An empty value removes the field.
+pub struct ExtraOverrides;
+
+impl Transform for ExtraOverrides {
+    fn apply(&self, document: &mut ExportDocument, format: &ExportFormat) {
+        for entry in &mut document.entries {
+            if let Some(extra) = entry.item.data.field("extra") {
+                apply_overrides(&mut entry.entry, extra, format);
+            }
+        }
+    }
+}
+
+fn apply_overrides(entry: &mut Entry, extra: &str, format: &ExportFormat) {
+    for (name, value) in overrides(extra, format) {
+        if value.is_empty() {
+            entry.remove(&name);
+        } else {
+            entry.set(&name, value);
+        }
+    }
+}
+
+/// Field overrides in the lines of an Extra field that apply to the given format
+fn overrides<'a>(extra: &'a str, format: &ExportFormat) -> Vec<(String, &'a str)> {
+    extra
+        .lines()
+        .filter_map(|line| {
+            let separator = line.find([':', '='])?;
+            let (prefix, name) = line[..separator].trim().split_once('.')?;
+            let applies = match prefix.to_ascii_lowercase().as_str() {
+                "tex" => true,
+                "biblatex" => *format == ExportFormat::Biblatex,
+                "bibtex" => *format == ExportFormat::Bibtex,
+                _ => false,
+            };
+            let valid_name = !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "_-".contains(c));
+            (applies && valid_name)
+                .then(|| (name.to_ascii_lowercase(), line[separator + 1..].trim()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bibtex::{Block, parse};
+    use assert_matches::assert_matches;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    const EXTRA: &str = "Original date: 1850\ntex.shorttitle: Short\nbiblatex.eventtitle= Conference\nbibtex.address: Berlin\ntex.note:\nTEX.Pubstate: forthcoming";
+
+    #[rstest]
+    #[case::biblatex(ExportFormat::Biblatex, vec![("shorttitle", "Short"), ("eventtitle", "Conference"), ("note", ""), ("pubstate", "forthcoming")])]
+    #[case::bibtex(ExportFormat::Bibtex, vec![("shorttitle", "Short"), ("address", "Berlin"), ("note", ""), ("pubstate", "forthcoming")])]
+    fn parse_overrides(#[case] format: ExportFormat, #[case] expected: Vec<(&str, &str)>) {
+        let overrides = overrides(EXTRA, &format);
+        let overrides: Vec<(&str, &str)> = overrides
+            .iter()
+            .map(|(name, value)| (name.as_str(), *value))
+            .collect();
+        assert_eq!(overrides, expected);
+    }
+
+    #[test]
+    fn override_fields() {
+        let blocks =
+            parse("@article{key,\n\tshorttitle = {Long},\n\tnote = {Remove me}\n}").unwrap();
+        assert_matches!(blocks.into_iter().next(), Some(Block::Entry(mut entry)) => {
+            apply_overrides(&mut entry, EXTRA, &ExportFormat::Biblatex);
+            assert_eq!(
+                entry.to_string(),
+                "@article{key,\n\tshorttitle = {Short},\n\teventtitle = {Conference},\n\tpubstate = {forthcoming}\n}"
+            );
+        });
+    }
+}
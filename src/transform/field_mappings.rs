@@ -0,0 +1,93 @@
+use crate::bibtex::Entry;
+use crate::export::document::ExportDocument;
+use crate::transform::Transform;
+use crate::zotero_api::ExportFormat;
+use crate::zotero_api::item::ItemData;
+use std::collections::BTreeMap;
+
+/// Writes Zotero item fields and creators to entry fields of the config, e.g. `archiveLocation` to `location`
+/// or the contributors to `editora`, replacing what Zotero's translator wrote to these entry fields.
+/// Items without a value for the Zotero field or creators of the type keep their entry field.
+pub struct FieldMappings {
+    /// Entry field by Zotero item field
+    fields: BTreeMap<String, String>,
+    /// Entry field by Zotero creator type
+    creator_types: BTreeMap<String, String>,
+}
+
+impl FieldMappings {
+    pub fn new(fields: BTreeMap<String, String>, creator_types: BTreeMap<String, String>) -> Self {
+        Self {
+            fields,
+            creator_types,
+        }
+    }
+
+    fn map(&self, data: &ItemData, entry: &mut Entry) {
+        for (field, entry_field) in &self.fields {
+            if let Some(value) = data.field(field) {
+                entry.set(entry_field, value);
+            }
+        }
+        for (creator_type, entry_field) in &self.creator_types {
+            let names: Vec<String> = data
+                .creators
+                .iter()
+                .filter(|creator| creator.creator_type == *creator_type)
+                .filter_map(|creator| {
+                    match (&creator.last_name, &creator.first_name, &creator.name) {
+                        (Some(last), Some(first), _) => Some(format!("{}, {}", last, first)),
+                        (Some(last), None, _) => Some(last.clone()),
+                        // a single-field name, e.g. of an institution, is not split into first and last name
+                        (None, _, Some(name)) => Some(format!("{{{}}}", name)),
+                        _ => None,
+                    }
+                })
+                .collect();
+            if !names.is_empty() {
+                entry.set(entry_field, names.join(" and "));
+            }
+        }
+    }
+}
+
+impl Transform for FieldMappings {
+    fn apply(&self, document: &mut ExportDocument, _format: &ExportFormat) {
+        for entry in &mut document.entries {
+            self.map(&entry.item.data, &mut entry.entry);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::document::tests::document;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn map_fields_and_creators() {
+        let mut document = document();
+        let data = &mut document.entries[0].item.data;
+        data.fields
+            .insert("archiveLocation".to_owned(), "Box 3".into());
+        data.creators = serde_json::from_value(serde_json::json!([
+            {"creatorType": "author", "firstName": "Ann", "lastName": "Smith"},
+            {"creatorType": "contributor", "firstName": "Bo", "lastName": "Lee"},
+            {"creatorType": "contributor", "name": "Example Lab"}
+        ]))
+        .unwrap();
+        FieldMappings::new(
+            BTreeMap::from([
+                ("archiveLocation".to_owned(), "location".to_owned()),
+                ("archive".to_owned(), "library".to_owned()),
+            ]),
+            BTreeMap::from([("contributor".to_owned(), "editora".to_owned())]),
+        )
+        .apply(&mut document, &ExportFormat::Biblatex);
+        let entry = &document.entries[0].entry;
+        assert_eq!(entry.get("location"), Some("Box 3"));
+        assert_eq!(entry.get("library"), None);
+        assert_eq!(entry.get("editora"), Some("Lee, Bo and {Example Lab}"));
+    }
+}
@@ -0,0 +1,249 @@
+use crate::bibtex::Entry;
+use crate::export::document::ExportDocument;
+use crate::transform::Transform;
+use crate::zotero_api::ExportFormat;
+
+/// Prefixes that turn a DOI into a URL or a URI
+const DOI_PREFIXES: [&str; 5] = [
+    "https://doi.org/",
+    "http://doi.org/",
+    "https://dx.doi.org/",
+    "http://dx.doi.org/",
+    "doi:",
+];
+
+/// Cleans up the identifiers of entries:
+/// - DOIs are stripped of `https://doi.org/` and `doi:` prefixes, a `doi.org` link in `url` is moved to `doi`,
+/// - a `url` that only links to the DOI is dropped, or optionally any `url` when there is a DOI,
+/// - ISBNs are validated and written with hyphens. Hyphenation needs the ranges of the registration groups,
+///   which are only known for the English-language groups; other ISBNs are written without hyphens.
+///   The field is kept as it is if any of its ISBNs is invalid.
+pub struct Identifiers {
+    drop_url_with_doi: bool,
+}
+
+impl Identifiers {
+    pub fn new(drop_url_with_doi: bool) -> Self {
+        Self { drop_url_with_doi }
+    }
+
+    fn normalize(&self, entry: &mut Entry) {
+        let url_doi = entry
+            .get("url")
+            .and_then(strip_doi_prefix)
+            .map(str::to_owned);
+        let doi = entry
+            .get("doi")
+            .map(|doi| strip_doi_prefix(doi).unwrap_or(doi).trim().to_owned())
+            .or(url_doi.clone());
+        if let Some(doi) = &doi {
+            entry.set("doi", doi.as_str());
+            if self.drop_url_with_doi
+                || url_doi.is_some_and(|url_doi| url_doi.eq_ignore_ascii_case(doi))
+            {
+                entry.remove("url");
+                entry.remove("urldate");
+            }
+        }
+        if let Some(isbn) = entry.get("isbn") {
+            // a single ISBN may be written with spaces, several ISBNs are separated by commas or semicolons
+            let normalized = normalize_isbn(isbn).or_else(|| {
+                isbn.split([',', ';'])
+                    .filter(|isbn| !isbn.trim().is_empty())
+                    .map(normalize_isbn)
+                    .collect::<Option<Vec<_>>>()
+                    .filter(|isbns| !isbns.is_empty())
+                    .map(|isbns| isbns.join(", "))
+            });
+            match normalized {
+                Some(normalized) => entry.set("isbn", normalized),
+                None => log::warn!("Invalid ISBN '{}' in entry '{}'", isbn, entry.key),
+            }
+        }
+    }
+}
+
+impl Transform for Identifiers {
+    fn apply(&self, document: &mut ExportDocument, _format: &ExportFormat) {
+        for entry in &mut document.entries {
+            self.normalize(&mut entry.entry);
+        }
+    }
+}
+
+fn strip_doi_prefix(text: &str) -> Option<&str> {
+    let text = text.trim();
+    DOI_PREFIXES.iter().find_map(|prefix| {
+        text.get(..prefix.len())
+            .filter(|start| start.eq_ignore_ascii_case(prefix))
+            .map(|_| text[prefix.len()..].trim_start())
+    })
+}
+
+/// Hyphenated ISBN, or `None` if the ISBN is invalid
+fn normalize_isbn(isbn: &str) -> Option<String> {
+    let digits: Vec<u32> = isbn
+        .chars()
+        .filter(|c| *c != '-' && !c.is_whitespace())
+        .map(|c| match c {
+            'x' | 'X' => Some(10),
+            c => c.to_digit(10),
+        })
+        .collect::<Option<_>>()?;
+    // Only the check digit of an ISBN-10 may be X
+    if digits.iter().rev().skip(1).any(|digit| *digit == 10) {
+        return None;
+    }
+    let valid = match digits.len() {
+        10 => {
+            digits
+                .iter()
+                .enumerate()
+                .map(|(i, digit)| (10 - i as u32) * digit)
+                .sum::<u32>()
+                % 11
+                == 0
+        }
+        13 => {
+            digits[12] != 10
+                && digits
+                    .iter()
+                    .enumerate()
+                    .map(|(i, digit)| if i % 2 == 0 { *digit } else { 3 * digit })
+                    .sum::<u32>()
+                    % 10
+                    == 0
+        }
+        _ => false,
+    };
+    if !valid {
+        return None;
+    }
+    let text: String = digits
+        .iter()
+        .map(|digit| match digit {
+            10 => 'X',
+            digit => char::from_digit(*digit, 10).unwrap_or_default(),
+        })
+        .collect();
+    Some(hyphenate_isbn(&text).unwrap_or(text))
+}
+
+/// Registrant ranges of the English-language groups 0 and 1, as (last 7-digit number of the range, registrant length)
+const GROUP_RANGES: [(char, [(u32, usize); 6]); 2] = [
+    (
+        '0',
+        [
+            (1999999, 2),
+            (6999999, 3),
+            (8499999, 4),
+            (8999999, 5),
+            (9499999, 6),
+            (9999999, 7),
+        ],
+    ),
+    (
+        '1',
+        [
+            (999999, 2),
+            (3999999, 3),
+            (5499999, 4),
+            (8697999, 5),
+            (9989999, 6),
+            (9999999, 7),
+        ],
+    ),
+];
+
+fn hyphenate_isbn(isbn: &str) -> Option<String> {
+    let (prefix, rest) = match isbn.len() {
+        13 if isbn.starts_with("978") => isbn.split_at(3),
+        10 => ("", isbn),
+        _ => return None,
+    };
+    let group = rest.chars().next()?;
+    let ranges = GROUP_RANGES
+        .iter()
+        .find(|(range_group, _)| *range_group == group)
+        .map(|(_, ranges)| ranges)?;
+    let number: u32 = rest.get(1..8)?.parse().ok()?;
+    let registrant_len = ranges
+        .iter()
+        .find(|(end, _)| number <= *end)
+        .map(|(_, len)| *len)?;
+    let registrant = &rest[1..1 + registrant_len];
+    let publication = &rest[1 + registrant_len..rest.len() - 1];
+    let check = &rest[rest.len() - 1..];
+    let parts = [prefix, &rest[..1], registrant, publication, check];
+    Some(
+        parts
+            .iter()
+            .filter(|part| !part.is_empty())
+            .copied()
+            .collect::<Vec<_>>()
+            .join("-"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bibtex::{Block, parse};
+    use assert_matches::assert_matches;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("9780306406157", Some("978-0-306-40615-7"))]
+    #[case("0-306-40615-2", Some("0-306-40615-2"))]
+    #[case("978-1-86197-876-9", Some("978-1-86197-876-9"))]
+    #[case("080442957x", Some("0-8044-2957-X"))]
+    #[case("9783161484100", Some("9783161484100"))]
+    #[case("978 3 16 148410 0", Some("9783161484100"))]
+    #[case("9780306406158", None)]
+    #[case("12345", None)]
+    fn normalize_isbns(#[case] isbn: &str, #[case] expected: Option<&str>) {
+        assert_eq!(normalize_isbn(isbn).as_deref(), expected);
+    }
+
+    fn normalized(text: &str, drop_url_with_doi: bool) -> String {
+        assert_matches!(parse(text).unwrap().into_iter().next(), Some(Block::Entry(mut entry)) => {
+            Identifiers::new(drop_url_with_doi).normalize(&mut entry);
+            entry.to_string()
+        })
+    }
+
+    #[rstest]
+    #[case::doi_prefix(
+        "@article{key,\n\tdoi = {https://doi.org/10.1000/XYZ},\n\turl = {https://example.org}\n}",
+        false,
+        "@article{key,\n\tdoi = {10.1000/XYZ},\n\turl = {https://example.org}\n}"
+    )]
+    #[case::drop_url(
+        "@article{key,\n\tdoi = {doi:10.1000/xyz},\n\turl = {https://example.org},\n\turldate = {2020-01-01}\n}",
+        true,
+        "@article{key,\n\tdoi = {10.1000/xyz}\n}"
+    )]
+    #[case::doi_from_url(
+        "@article{key,\n\turl = {https://dx.doi.org/10.1000/xyz},\n\tisbn = {9780306406157; 0306406152}\n}",
+        false,
+        "@article{key,\n\tisbn = {978-0-306-40615-7, 0-306-40615-2},\n\tdoi = {10.1000/xyz}\n}"
+    )]
+    #[case::isbn_with_spaces(
+        "@book{key,\n\tisbn = {978 0 306 40615 7}\n}",
+        false,
+        "@book{key,\n\tisbn = {978-0-306-40615-7}\n}"
+    )]
+    #[case::invalid_isbn_kept(
+        "@book{key,\n\tisbn = {9780306406157, 12345}\n}",
+        false,
+        "@book{key,\n\tisbn = {9780306406157, 12345}\n}"
+    )]
+    fn normalize_entry(
+        #[case] text: &str,
+        #[case] drop_url_with_doi: bool,
+        #[case] expected: &str,
+    ) {
+        assert_eq!(normalized(text, drop_url_with_doi), expected);
+    }
+}
@@ -0,0 +1,145 @@
+use crate::bibtex::Block;
+use crate::export::document::ExportDocument;
+use crate::transform::{LibraryData, Transform};
+use crate::zotero_api::ExportFormat;
+use crate::zotero_api::item::Collection;
+use std::collections::{BTreeMap, HashSet};
+
+/// Adds JabRef's `groupstree` metadata, with an explicit group for every collection, so that JabRef shows
+/// the exported entries in the same group structure as Zotero.
+pub struct JabrefGroups;
+
+impl Transform for JabrefGroups {
+    fn apply(&self, document: &mut ExportDocument, _format: &ExportFormat) {
+        let mut members: BTreeMap<&str, Vec<&str>> = BTreeMap::new();
+        for entry in &document.entries {
+            for collection in &entry.item.data.collections {
+                members
+                    .entry(collection.as_str())
+                    .or_default()
+                    .push(entry.entry.key.as_str());
+            }
+        }
+        let mut lines = vec!["0 AllEntriesGroup:;".to_owned()];
+        let mut visited = HashSet::new();
+        for root in children(&document.collections, None) {
+            groups(
+                &document.collections,
+                &members,
+                root,
+                1,
+                &mut visited,
+                &mut lines,
+            );
+        }
+        document.trailer.push(Block::Other(
+            "@comment{jabref-meta: groupsversion:3;}".to_owned(),
+        ));
+        document.trailer.push(Block::Other(format!(
+            "@comment{{jabref-meta: groupstree:\n{}\n}}",
+            lines.join("\n")
+        )));
+    }
+
+    fn needs(&self) -> &[LibraryData] {
+        &[LibraryData::Collections]
+    }
+}
+
+/// Collections with the given parent, sorted by name like in Zotero.
+/// Collections whose parent doesn't exist are treated as top-level collections.
+fn children<'a>(
+    collections: &'a BTreeMap<String, Collection>,
+    parent: Option<&str>,
+) -> Vec<&'a Collection> {
+    let mut children: Vec<&Collection> = collections
+        .values()
+        .filter(|collection| {
+            let collection_parent = collection
+                .data
+                .parent_collection
+                .as_deref()
+                .filter(|key| collections.contains_key(*key));
+            collection_parent == parent
+        })
+        .collect();
+    children.sort_by(|a, b| a.data.name.cmp(&b.data.name));
+    children
+}
+
+/// Add the group lines of a collection and its subcollections, depth-first
+fn groups<'a>(
+    collections: &'a BTreeMap<String, Collection>,
+    members: &BTreeMap<&str, Vec<&str>>,
+    collection: &'a Collection,
+    level: usize,
+    visited: &mut HashSet<&'a str>,
+    lines: &mut Vec<String>,
+) {
+    if !visited.insert(collection.key.as_str()) {
+        return;
+    }
+    let mut group = format!("ExplicitGroup:{};0;", escape(&collection.data.name));
+    for key in members.get(collection.key.as_str()).into_iter().flatten() {
+        group.push_str(&escape(key));
+        group.push(';');
+    }
+    lines.push(format!("{} {};", level, escape(&group)));
+    for child in children(collections, Some(&collection.key)) {
+        groups(collections, members, child, level + 1, visited, lines);
+    }
+}
+
+/// JabRef escapes its separator `;` and the escape character with a backslash
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace(';', "\\;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zotero_api::item::Item;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn groups_from_collections() {
+        let items: Vec<Item> = serde_json::from_value(serde_json::json!([
+            {"key":"PARENT01","version":1,"data":{"itemType":"book","title":"Title","collections":["B","C"]},
+             "biblatex":"@book{first,\n}"},
+            {"key":"PARENT02","version":1,"data":{"itemType":"book","title":"Title","collections":["B"]},
+             "biblatex":"@book{second,\n}"}
+        ]))
+        .unwrap();
+        let collections: Vec<Collection> = serde_json::from_str(
+            r#"[
+                {"key":"A","version":1,"data":{"name":"Thesis","parentCollection":false}},
+                {"key":"B","version":1,"data":{"name":"Chapter 2","parentCollection":"A"}},
+                {"key":"C","version":1,"data":{"name":"Reading; later","parentCollection":false}}
+            ]"#,
+        )
+        .unwrap();
+        let mut document = ExportDocument::try_from_items(items, &ExportFormat::Biblatex).unwrap();
+        document.collections = collections
+            .into_iter()
+            .map(|collection| (collection.key.clone(), collection))
+            .collect();
+        JabrefGroups.apply(&mut document, &ExportFormat::Biblatex);
+        let trailer: Vec<String> = document
+            .trailer
+            .iter()
+            .map(|block| block.to_string())
+            .collect();
+        assert_eq!(
+            trailer,
+            vec![
+                "@comment{jabref-meta: groupsversion:3;}",
+                "@comment{jabref-meta: groupstree:
+0 AllEntriesGroup:;
+1 ExplicitGroup:Reading\\\\\\; later\\;0\\;first\\;;
+1 ExplicitGroup:Thesis\\;0\\;;
+2 ExplicitGroup:Chapter 2\\;0\\;first\\;second\\;;
+}"
+            ]
+        );
+    }
+}
@@ -0,0 +1,137 @@
+use crate::bibtex::{Block, Value};
+use crate::export::document::ExportDocument;
+use crate::transform::Transform;
+use crate::zotero_api::ExportFormat;
+use std::collections::{BTreeMap, HashMap};
+
+/// Abbreviations of common journals, in the `<full name>;<abbreviation>` format of JabRef's abbreviation lists
+const BUNDLED_LIST: &str = include_str!("journals.csv");
+
+/// Fields that hold the journal name in BibLaTeX and BibTeX
+const JOURNAL_FIELDS: [&str; 2] = ["journaltitle", "journal"];
+
+/// Replaces known journal names with `@string` macros that expand to the abbreviated journal name.
+///
+/// The definitions of all used macros are written at the top of the file, so the full names can be swapped in again
+/// by changing just the definitions.
+pub struct JournalStrings {
+    /// Macro name and abbreviation by normalized full name
+    journals: HashMap<String, (String, String)>,
+}
+
+impl JournalStrings {
+    /// Use the bundled abbreviation list
+    pub fn bundled() -> Self {
+        Self {
+            journals: HashMap::new(),
+        }
+        .with_list(BUNDLED_LIST)
+    }
+
+    /// Add the abbreviations of a list in JabRef's format (`<full name>;<abbreviation>` per line),
+    /// overriding abbreviations that are already known
+    pub fn with_list(mut self, list: &str) -> Self {
+        for line in list.lines() {
+            let mut columns = line
+                .split(';')
+                .map(|column| column.trim().trim_matches('"'));
+            let (Some(name), Some(abbreviation)) = (columns.next(), columns.next()) else {
+                continue;
+            };
+            let mut macro_name: String = abbreviation
+                .chars()
+                .filter(char::is_ascii_alphanumeric)
+                .collect::<String>()
+                .to_ascii_lowercase();
+            // Macro names must not start with a digit
+            if macro_name.starts_with(|c: char| c.is_ascii_digit()) {
+                macro_name.insert(0, 'j');
+            }
+            if name.is_empty() || macro_name.is_empty() {
+                continue;
+            }
+            self.journals.insert(
+                normalize(name),
+                (macro_name.clone(), abbreviation.to_owned()),
+            );
+            // Entries that already use the abbreviation get the macro as well
+            self.journals.insert(
+                normalize(abbreviation),
+                (macro_name, abbreviation.to_owned()),
+            );
+        }
+        self
+    }
+}
+
+impl Transform for JournalStrings {
+    fn apply(&self, document: &mut ExportDocument, _format: &ExportFormat) {
+        let mut used = BTreeMap::new();
+        for entry in &mut document.entries {
+            for field in &JOURNAL_FIELDS {
+                let Some((macro_name, abbreviation)) = entry
+                    .entry
+                    .get(field)
+                    .and_then(|journal| self.journals.get(&normalize(journal)))
+                else {
+                    continue;
+                };
+                entry
+                    .entry
+                    .set_value(field, Value::Bare(macro_name.clone()));
+                used.insert(macro_name.clone(), abbreviation.clone());
+            }
+        }
+        document.preamble.extend(
+            used.into_iter()
+                .map(|(name, value)| Block::Other(format!("@string{{{} = {{{}}}}}", name, value))),
+        );
+    }
+}
+
+/// Journal name without TeX markup, in lowercase with single spaces, for lookup
+fn normalize(name: &str) -> String {
+    name.replace(['{', '}', '\\'], "")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::document::tests::document;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("Physical Review Letters", Some("physrevlett"))]
+    #[case("Astronomy \\& {Astrophysics}", Some("astronastrophys"))]
+    #[case("Phys. Rev. Lett.", Some("physrevlett"))]
+    #[case("Unknown Journal", None)]
+    #[case("3 Biotech", Some("j3biotech"))]
+    fn look_up_journal(#[case] journal: &str, #[case] expected: Option<&str>) {
+        let strings = JournalStrings::bundled().with_list("3 Biotech;3 Biotech");
+        assert_eq!(
+            strings
+                .journals
+                .get(&normalize(journal))
+                .map(|(name, _)| name.as_str()),
+            expected
+        );
+    }
+
+    #[test]
+    fn replace_journal_with_macro() {
+        let mut document = document();
+        document.entries[0].entry.set("journaltitle", "My Journal");
+        JournalStrings::bundled()
+            .with_list("\"My Journal\";\"My J.\"\ninvalid line")
+            .apply(&mut document, &ExportFormat::Biblatex);
+        assert_eq!(
+            document.render(),
+            "\n@string{myj = {My J.}}\n\n@article{smith_title_2020,\n\ttitle = {Title},\n\tdate = {2020},\n\tjournaltitle = myj\n}\n"
+        );
+    }
+}
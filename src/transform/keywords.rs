@@ -0,0 +1,172 @@
+use crate::config::KeywordsConfig;
+use crate::export::document::ExportDocument;
+use crate::transform::{LibraryData, Transform};
+use crate::zotero_api::ExportFormat;
+use crate::zotero_api::item::Tag;
+
+/// Writes the tags of every item to the `keywords` field, following the rules of the configuration.
+/// Entries without remaining tags get no `keywords` field.
+pub struct Keywords {
+    config: KeywordsConfig,
+}
+
+impl Keywords {
+    pub fn new(config: KeywordsConfig) -> Self {
+        Self { config }
+    }
+
+    fn keywords(&self, tags: &[Tag], tag_colors: &[String]) -> Vec<String> {
+        let mut keywords: Vec<String> = Vec::new();
+        for tag in tags {
+            if self.config.exclude_automatic && tag.tag_type == 1 {
+                continue;
+            }
+            if self.config.colored_only && !tag_colors.contains(&tag.tag) {
+                continue;
+            }
+            let name = self
+                .config
+                .strip_prefixes
+                .iter()
+                .fold(tag.tag.trim(), |name, prefix| {
+                    name.strip_prefix(prefix.as_str()).unwrap_or(name)
+                })
+                .trim();
+            let included = self.config.include.is_empty()
+                || self
+                    .config
+                    .include
+                    .iter()
+                    .any(|pattern| matches(pattern, name));
+            let excluded = self
+                .config
+                .exclude
+                .iter()
+                .any(|pattern| matches(pattern, name));
+            if name.is_empty() || !included || excluded {
+                continue;
+            }
+            let keyword = self
+                .config
+                .map
+                .iter()
+                .find(|(from, _)| from.eq_ignore_ascii_case(name))
+                .map(|(_, keyword)| keyword.as_str())
+                .unwrap_or(name);
+            if !keywords.iter().any(|existing| existing == keyword) {
+                keywords.push(keyword.to_owned());
+            }
+        }
+        keywords
+    }
+}
+
+impl Transform for Keywords {
+    fn apply(&self, document: &mut ExportDocument, _format: &ExportFormat) {
+        for entry in &mut document.entries {
+            let keywords = self.keywords(&entry.item.data.tags, &document.tag_colors);
+            if keywords.is_empty() {
+                entry.entry.remove("keywords");
+            } else {
+                entry.entry.set("keywords", keywords.join(", "));
+            }
+        }
+    }
+
+    fn needs(&self) -> &[LibraryData] {
+        if self.config.colored_only {
+            &[LibraryData::TagColors]
+        } else {
+            &[]
+        }
+    }
+}
+
+/// Case-insensitive match of a tag against a pattern, where a trailing `*` matches any suffix
+fn matches(pattern: &str, name: &str) -> bool {
+    let name = name.to_lowercase();
+    let pattern = pattern.to_lowercase();
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => name == pattern,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::collections::BTreeMap;
+
+    fn tags(tags: &[(&str, u8)]) -> Vec<Tag> {
+        tags.iter()
+            .map(|(tag, tag_type)| Tag {
+                tag: tag.to_string(),
+                tag_type: *tag_type,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn all_tags_by_default() {
+        let keywords = Keywords::new(KeywordsConfig::default());
+        assert_eq!(
+            keywords.keywords(&tags(&[("#todo", 0), ("Physics", 1)]), &[]),
+            vec!["#todo", "Physics"]
+        );
+    }
+
+    #[test]
+    fn apply_rules() {
+        let keywords = Keywords::new(KeywordsConfig {
+            exclude_automatic: true,
+            colored_only: false,
+            strip_prefixes: vec!["#".to_owned()],
+            include: vec![],
+            exclude: vec!["status/*".to_owned(), "TODO".to_owned()],
+            map: BTreeMap::from([("ml".to_owned(), "machine learning".to_owned())]),
+        });
+        assert_eq!(
+            keywords.keywords(
+                &tags(&[
+                    ("#todo", 0),
+                    ("Physics", 1),
+                    ("#ML", 0),
+                    ("machine learning", 0),
+                    ("Status/Read", 0),
+                    ("Optics", 0),
+                ]),
+                &[]
+            ),
+            vec!["machine learning", "Optics"]
+        );
+    }
+
+    #[test]
+    fn include_only_matching_tags() {
+        let keywords = Keywords::new(KeywordsConfig {
+            include: vec!["topic:*".to_owned()],
+            ..Default::default()
+        });
+        assert_eq!(
+            keywords.keywords(&tags(&[("topic:optics", 0), ("optics", 0)]), &[]),
+            vec!["topic:optics"]
+        );
+    }
+
+    #[test]
+    fn only_colored_tags() {
+        let keywords = Keywords::new(KeywordsConfig {
+            colored_only: true,
+            ..Default::default()
+        });
+        assert_eq!(keywords.needs(), &[LibraryData::TagColors]);
+        assert_eq!(
+            keywords.keywords(
+                &tags(&[("Important", 0), ("Subject", 1)]),
+                &["Important".to_owned()]
+            ),
+            vec!["Important"]
+        );
+    }
+}
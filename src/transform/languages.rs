@@ -0,0 +1,108 @@
+use crate::export::document::ExportDocument;
+use crate::transform::Transform;
+use crate::zotero_api::ExportFormat;
+
+/// Babel/polyglossia language names with the language codes and names that are written in Zotero's
+/// language field. Regional variants come first, so that e.g. `en-US` isn't matched by `en`.
+const LANGUAGES: [(&str, &[&str]); 37] = [
+    ("american", &["en-us", "american english"]),
+    ("british", &["en-gb", "british english"]),
+    ("australian", &["en-au"]),
+    ("canadian", &["en-ca"]),
+    ("newzealand", &["en-nz"]),
+    ("english", &["en", "eng", "english", "englisch"]),
+    ("naustrian", &["de-at", "austrian", "austrian german"]),
+    ("nswissgerman", &["de-ch", "swiss german", "swissgerman"]),
+    ("ngerman", &["de", "deu", "ger", "german", "deutsch"]),
+    ("brazilian", &["pt-br", "brazilian portuguese"]),
+    ("portuguese", &["pt", "por", "portuguese", "português"]),
+    (
+        "french",
+        &["fr", "fra", "fre", "french", "français", "francais"],
+    ),
+    ("spanish", &["es", "spa", "spanish", "español", "espanol"]),
+    ("italian", &["it", "ita", "italian", "italiano"]),
+    ("dutch", &["nl", "nld", "dut", "dutch", "nederlands"]),
+    ("swedish", &["sv", "swe", "swedish", "svenska"]),
+    ("danish", &["da", "dan", "danish", "dansk"]),
+    ("nynorsk", &["nn", "nno", "nynorsk"]),
+    (
+        "norsk",
+        &["no", "nb", "nor", "nob", "norwegian", "norsk", "bokmål"],
+    ),
+    ("finnish", &["fi", "fin", "finnish", "suomi"]),
+    ("icelandic", &["is", "isl", "ice", "icelandic"]),
+    ("polish", &["pl", "pol", "polish", "polski"]),
+    ("czech", &["cs", "ces", "cze", "czech", "čeština"]),
+    ("slovak", &["sk", "slk", "slo", "slovak"]),
+    ("slovene", &["sl", "slv", "slovene", "slovenian"]),
+    ("croatian", &["hr", "hrv", "croatian", "hrvatski"]),
+    ("serbian", &["sr", "srp", "serbian"]),
+    ("bulgarian", &["bg", "bul", "bulgarian"]),
+    ("russian", &["ru", "rus", "russian", "русский"]),
+    ("ukrainian", &["uk", "ukr", "ukrainian"]),
+    ("greek", &["el", "ell", "gre", "greek"]),
+    ("turkish", &["tr", "tur", "turkish", "türkçe"]),
+    ("hungarian", &["hu", "hun", "hungarian", "magyar"]),
+    ("romanian", &["ro", "ron", "rum", "romanian"]),
+    ("catalan", &["ca", "cat", "catalan", "català"]),
+    ("latin", &["la", "lat", "latin"]),
+    ("hebrew", &["he", "heb", "hebrew"]),
+];
+
+/// Writes Zotero's language field as a `langid` (BibLaTeX) or `hyphenation` (BibTeX) field with a
+/// language name that babel and polyglossia understand, e.g. `ngerman` for `de`, `German` or `deu`.
+/// Entries with unknown languages keep what Zotero exported.
+pub struct Languages;
+
+impl Transform for Languages {
+    fn apply(&self, document: &mut ExportDocument, format: &ExportFormat) {
+        let field = match format {
+            ExportFormat::Biblatex => "langid",
+            ExportFormat::Bibtex => "hyphenation",
+            ExportFormat::Json => return,
+        };
+        for entry in &mut document.entries {
+            let Some(language) = entry.item.data.field("language") else {
+                continue;
+            };
+            match langid(language) {
+                Some(langid) => entry.entry.set(field, langid),
+                None => log::debug!("Unknown language '{}' of item {}", language, entry.item.key),
+            }
+        }
+    }
+}
+
+/// Babel language name for a language code or name, e.g. `american` for `en_US`
+fn langid(language: &str) -> Option<&'static str> {
+    let language = language.trim().to_lowercase().replace('_', "-");
+    let find = |alias: &str| {
+        LANGUAGES
+            .iter()
+            .find(|(langid, aliases)| *langid == alias || aliases.contains(&alias))
+            .map(|(langid, _)| *langid)
+    };
+    find(&language).or_else(|| find(language.split(['-', ' ', ',', ';']).next()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("en", Some("english"))]
+    #[case("English", Some("english"))]
+    #[case("eng", Some("english"))]
+    #[case("en_US", Some("american"))]
+    #[case("de-DE", Some("ngerman"))]
+    #[case("Deutsch", Some("ngerman"))]
+    #[case("ngerman", Some("ngerman"))]
+    #[case("fr-CA", Some("french"))]
+    #[case("Klingon", None)]
+    fn normalize_language(#[case] language: &str, #[case] expected: Option<&str>) {
+        assert_eq!(langid(language), expected);
+    }
+}
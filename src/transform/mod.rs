@@ -0,0 +1,58 @@
+//! Modifications of the exported entries, applied after Zotero's export was parsed and before it is written.
+
+use crate::export::document::ExportDocument;
+use crate::zotero_api::ExportFormat;
+
+mod abstracts;
+mod bibtex_fields;
+mod citekeys;
+mod collections;
+mod crossref;
+mod dates;
+mod entry_types;
+mod extra;
+mod field_mappings;
+mod identifiers;
+mod jabref;
+mod journals;
+mod keywords;
+mod languages;
+mod pages;
+#[cfg(feature = "plugins")]
+mod plugin;
+mod related;
+
+pub use abstracts::Abstracts;
+pub use bibtex_fields::BibtexFields;
+pub use citekeys::{CitationKeys, DEFAULT_PATTERN, KeyPattern};
+pub use collections::CollectionKeywords;
+pub use crossref::Crossref;
+pub use dates::Dates;
+pub use entry_types::EntryTypes;
+pub use extra::ExtraOverrides;
+pub use field_mappings::FieldMappings;
+pub use identifiers::Identifiers;
+pub use jabref::JabrefGroups;
+pub use journals::JournalStrings;
+pub use keywords::Keywords;
+pub use languages::Languages;
+pub use pages::{PageRangeDash, PageRanges};
+#[cfg(feature = "plugins")]
+pub use plugin::WasmPlugin;
+pub use related::Related;
+
+pub trait Transform: Send + Sync {
+    fn apply(&self, document: &mut ExportDocument, format: &ExportFormat);
+
+    /// Library data besides the items that has to be fetched into the document before the transform is applied
+    fn needs(&self) -> &[LibraryData] {
+        &[]
+    }
+}
+
+/// Library data that is only fetched if a transform needs it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LibraryData {
+    TagColors,
+    Collections,
+}
@@ -0,0 +1,100 @@
+use crate::export::document::ExportDocument;
+use crate::transform::Transform;
+use crate::zotero_api::ExportFormat;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+
+/// Dash written between the first and the last page of a range
+#[derive(clap::ValueEnum, Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+pub enum PageRangeDash {
+    /// `--`, typeset as en dash by TeX
+    DoubleHyphen,
+    /// `-`, e.g. for BibLaTeX which formats ranges itself
+    Hyphen,
+    /// The Unicode en dash `–`
+    EnDash,
+}
+
+impl Display for PageRangeDash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            serde_variant::to_variant_name(self).unwrap_or_default()
+        )
+    }
+}
+
+impl PageRangeDash {
+    fn text(&self) -> &'static str {
+        match self {
+            PageRangeDash::DoubleHyphen => "--",
+            PageRangeDash::Hyphen => "-",
+            PageRangeDash::EnDash => "–",
+        }
+    }
+}
+
+/// Writes all page ranges in `pages` fields with the same dash, and removes repeated pages like in `123-123-145`
+pub struct PageRanges {
+    dash: PageRangeDash,
+}
+
+impl PageRanges {
+    pub fn new(dash: PageRangeDash) -> Self {
+        Self { dash }
+    }
+
+    fn normalize(&self, pages: &str) -> String {
+        pages
+            .split(',')
+            .map(|range| {
+                let mut pages: Vec<&str> = range
+                    .split(['-', '–', '—'])
+                    .map(str::trim)
+                    .filter(|page| !page.is_empty())
+                    .collect();
+                pages.dedup();
+                match pages.as_slice() {
+                    [page] => page.to_string(),
+                    [first, last] => format!("{}{}{}", first, self.dash.text(), last),
+                    // Not a range, keep as it is
+                    _ => range.trim().to_owned(),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl Transform for PageRanges {
+    fn apply(&self, document: &mut ExportDocument, _format: &ExportFormat) {
+        for entry in &mut document.entries {
+            if let Some(pages) = entry.entry.get("pages") {
+                let pages = self.normalize(pages);
+                entry.entry.set("pages", pages);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("123-145", PageRangeDash::DoubleHyphen, "123--145")]
+    #[case("123 – 145", PageRangeDash::DoubleHyphen, "123--145")]
+    #[case("123-123-145", PageRangeDash::DoubleHyphen, "123--145")]
+    #[case("123--123", PageRangeDash::DoubleHyphen, "123")]
+    #[case("e12---e20", PageRangeDash::Hyphen, "e12-e20")]
+    #[case("1-5,7-9", PageRangeDash::EnDash, "1–5, 7–9")]
+    #[case("1-5-9", PageRangeDash::Hyphen, "1-5-9")]
+    #[case("xii", PageRangeDash::Hyphen, "xii")]
+    fn normalize_pages(#[case] pages: &str, #[case] dash: PageRangeDash, #[case] expected: &str) {
+        assert_eq!(PageRanges::new(dash).normalize(pages), expected);
+    }
+}
@@ -0,0 +1,184 @@
+use crate::bibtex::{self, Block, Entry, ParseError};
+use crate::export::document::ExportDocument;
+use crate::transform::Transform;
+use crate::zotero_api::ExportFormat;
+use std::path::Path;
+use std::sync::Mutex;
+use wasmi::{Config, Engine, Linker, Memory, Module, Store, TypedFunc};
+
+/// Instructions a plugin may execute per entry, so that a plugin stuck in a loop can't stall the export
+const FUEL_PER_ENTRY: u64 = 10_000_000;
+
+/// Transform implemented by a WebAssembly module, for bespoke field munging that the built-in transforms don't cover.
+///
+/// The module exports its `memory` and two functions:
+/// - `alloc(len: i32) -> i32` reserves `len` bytes for the input and returns their address
+/// - `transform(ptr: i32, len: i32) -> i64` gets an entry as BibTeX text like `@article{key,\n\ttitle = {...}\n}`
+///   and returns the address of the transformed entry in the upper and its length in the lower 32 bits.
+///   A length of 0 keeps the entry as it is.
+///
+/// Plugins can't import anything, so they have no access to files or the network.
+/// Entries that a plugin fails on are kept unchanged with a warning.
+pub struct WasmPlugin {
+    name: String,
+    instance: Mutex<PluginInstance>,
+}
+
+struct PluginInstance {
+    store: Store<()>,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    transform: TypedFunc<(i32, i32), i64>,
+}
+
+impl WasmPlugin {
+    pub fn load(path: &Path) -> Result<Self, PluginError> {
+        let wasm = std::fs::read(path).map_err(|io_error| PluginError::FileError {
+            path: path.display().to_string(),
+            io_error,
+        })?;
+        Self::from_wasm(path.display().to_string(), &wasm)
+    }
+
+    fn from_wasm(name: String, wasm: &[u8]) -> Result<Self, PluginError> {
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, wasm)?;
+        let mut store = Store::new(&engine, ());
+        store.set_fuel(FUEL_PER_ENTRY).map_err(wasmi::Error::from)?;
+        let instance = Linker::<()>::new(&engine)
+            .instantiate(&mut store, &module)?
+            .start(&mut store)?;
+        let memory = instance
+            .get_memory(&store, "memory")
+            .ok_or(PluginError::MissingExport("memory"))?;
+        let alloc = instance.get_typed_func(&store, "alloc")?;
+        let transform = instance.get_typed_func(&store, "transform")?;
+        Ok(Self {
+            name,
+            instance: Mutex::new(PluginInstance {
+                store,
+                memory,
+                alloc,
+                transform,
+            }),
+        })
+    }
+
+    /// The transformed entry, or `None` if the plugin keeps it
+    fn transform_entry(&self, entry: &Entry) -> Result<Option<Entry>, PluginError> {
+        let mut instance = self.instance.lock().unwrap();
+        let PluginInstance {
+            store,
+            memory,
+            alloc,
+            transform,
+        } = &mut *instance;
+        store.set_fuel(FUEL_PER_ENTRY).map_err(wasmi::Error::from)?;
+        let input = entry.to_string();
+        let len = i32::try_from(input.len()).map_err(|_| PluginError::TooLarge)?;
+        let ptr = alloc.call(&mut *store, len)?;
+        memory
+            .write(&mut *store, ptr as u32 as usize, input.as_bytes())
+            .map_err(wasmi::Error::from)?;
+        let result = transform.call(&mut *store, (ptr, len))? as u64;
+        let (output_ptr, output_len) = ((result >> 32) as usize, (result & 0xffff_ffff) as usize);
+        if output_len == 0 {
+            return Ok(None);
+        }
+        let mut output = vec![0; output_len];
+        memory
+            .read(&*store, output_ptr, &mut output)
+            .map_err(wasmi::Error::from)?;
+        let output = String::from_utf8(output).map_err(|_| PluginError::NotUtf8)?;
+        bibtex::parse(&output)?
+            .into_iter()
+            .find_map(|block| match block {
+                Block::Entry(entry) => Some(entry),
+                Block::Other(_) => None,
+            })
+            .map(Some)
+            .ok_or(PluginError::NoEntry)
+    }
+}
+
+impl Transform for WasmPlugin {
+    fn apply(&self, document: &mut ExportDocument, _format: &ExportFormat) {
+        for entry in &mut document.entries {
+            match self.transform_entry(&entry.entry) {
+                Ok(Some(transformed)) => entry.entry = transformed,
+                Ok(None) => {}
+                Err(e) => log::warn!(
+                    "Plugin '{}' failed on entry '{}', keeping it unchanged: {}",
+                    self.name,
+                    entry.entry.key,
+                    e
+                ),
+            }
+        }
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+pub enum PluginError {
+    #[error("Error reading plugin '{path}'")]
+    FileError {
+        path: String,
+        #[source]
+        io_error: std::io::Error,
+    },
+    #[error("WebAssembly error: {0}")]
+    Wasm(#[from] wasmi::Error),
+    #[error("The plugin doesn't export '{0}'")]
+    MissingExport(&'static str),
+    #[error("The entry is too large for the plugin")]
+    TooLarge,
+    #[error("The plugin returned text that is not UTF-8")]
+    NotUtf8,
+    #[error("The plugin returned an invalid entry")]
+    Parse(#[from] ParseError),
+    #[error("The plugin returned no entry")]
+    NoEntry,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zotero_api::item::Item;
+    use pretty_assertions::assert_eq;
+
+    /// Replaces entries whose key starts with `n` by a fixed entry, loops forever on keys starting with `l`, and keeps the others
+    const PLUGIN: &str = r#"
+        (module
+          (memory (export "memory") 1)
+          (data (i32.const 0) "@misc{fixed,\n\tnote = {plugin}\n}")
+          (func (export "alloc") (param $len i32) (result i32)
+            i32.const 1024)
+          (func (export "transform") (param $ptr i32) (param $len i32) (result i64)
+            ;; the 7th character tells the entries apart: `@misc{n...`, `@misc{l...`
+            (if (i32.eq (i32.load8_u (i32.add (local.get $ptr) (i32.const 6))) (i32.const 108))
+              (then (loop $forever (br $forever))))
+            (if (result i64) (i32.eq (i32.load8_u (i32.add (local.get $ptr) (i32.const 6))) (i32.const 110))
+              (then (i64.const 31))
+              (else (i64.const 0)))))
+    "#;
+
+    #[test]
+    fn apply_plugin() {
+        let plugin =
+            WasmPlugin::from_wasm("test".to_owned(), &wat::parse_str(PLUGIN).unwrap()).unwrap();
+        let items: Vec<Item> = serde_json::from_value(serde_json::json!([
+            {"key":"ITEM0001","version":1,"data":{"itemType":"book","title":"A"},"biblatex":"@misc{note,\n}"},
+            {"key":"ITEM0002","version":1,"data":{"itemType":"book","title":"B"},"biblatex":"@misc{keep,\n}"},
+            {"key":"ITEM0003","version":1,"data":{"itemType":"book","title":"C"},"biblatex":"@misc{loop,\n}"}
+        ]))
+        .unwrap();
+        let mut document = ExportDocument::try_from_items(items, &ExportFormat::Biblatex).unwrap();
+        plugin.apply(&mut document, &ExportFormat::Biblatex);
+        assert_eq!(
+            document.render(),
+            "\n@misc{fixed,\n\tnote = {plugin}\n}\n\n@misc{keep\n}\n\n@misc{loop\n}\n"
+        );
+    }
+}
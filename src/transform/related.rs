@@ -0,0 +1,112 @@
+use crate::export::document::ExportDocument;
+use crate::transform::Transform;
+use crate::zotero_api::ExportFormat;
+use crate::zotero_api::item::Item;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Zotero's relation predicate for the "Related" items of an item
+const RELATED_PREDICATE: &str = "dc:relation";
+
+/// Adds the citation keys of Zotero's "Related" items to the BibLaTeX `related` field.
+///
+/// Zotero doesn't know the kind of relation, so `relatedtype` is only written if configured for all
+/// relations; single entries can set it with `tex.relatedtype` in the Extra field.
+pub struct Related {
+    related_type: Option<String>,
+}
+
+impl Related {
+    pub fn new(related_type: Option<String>) -> Self {
+        Self { related_type }
+    }
+}
+
+impl Transform for Related {
+    fn apply(&self, document: &mut ExportDocument, format: &ExportFormat) {
+        if *format != ExportFormat::Biblatex {
+            return;
+        }
+        let citekeys: HashMap<String, String> = document
+            .entries
+            .iter()
+            .map(|entry| (entry.item.key.clone(), entry.entry.key.clone()))
+            .collect();
+        for entry in &mut document.entries {
+            let related: Vec<&str> = related_item_keys(&entry.item)
+                .iter()
+                .filter_map(|key| citekeys.get(*key).map(String::as_str))
+                .collect();
+            if related.is_empty() {
+                continue;
+            }
+            entry.entry.set("related", related.join(","));
+            if let Some(related_type) = &self.related_type
+                && entry.entry.get("relatedtype").is_none()
+            {
+                entry.entry.set("relatedtype", related_type.as_str());
+            }
+        }
+    }
+}
+
+/// Keys of the items that are linked as related in Zotero
+fn related_item_keys(item: &Item) -> Vec<&str> {
+    let uris = match item
+        .data
+        .fields
+        .get("relations")
+        .and_then(|relations| relations.get(RELATED_PREDICATE))
+    {
+        Some(Value::String(uri)) => vec![uri.as_str()],
+        Some(Value::Array(uris)) => uris.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    };
+    // URIs look like `http://zotero.org/users/<id>/items/<key>`
+    uris.into_iter()
+        .filter_map(|uri| uri.rsplit_once("/items/").map(|(_, key)| key))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn item(key: &str, citekey: &str, relations: serde_json::Value) -> serde_json::Value {
+        serde_json::json!({
+            "key": key, "version": 1,
+            "data": { "itemType": "book", "title": "Title", "relations": relations },
+            "biblatex": format!("@book{{{},\n}}", citekey)
+        })
+    }
+
+    #[test]
+    fn add_related_citekeys() {
+        let items: Vec<Item> = serde_json::from_value(serde_json::json!([
+            item("ORIGINAL", "original", serde_json::json!({
+                "dc:relation": ["http://zotero.org/users/1/items/REPRINT1", "http://zotero.org/users/1/items/MISSING1"]
+            })),
+            item("REPRINT1", "reprint", serde_json::json!({
+                "dc:relation": "http://zotero.org/users/1/items/ORIGINAL"
+            })),
+            item("UNRELATE", "unrelated", serde_json::json!({})),
+        ]))
+        .unwrap();
+        let mut document = ExportDocument::try_from_items(items, &ExportFormat::Biblatex).unwrap();
+        Related::new(Some("reprintof".to_owned())).apply(&mut document, &ExportFormat::Biblatex);
+        let related: Vec<(Option<&str>, Option<&str>)> = document
+            .entries
+            .iter()
+            .map(|entry| (entry.entry.get("related"), entry.entry.get("relatedtype")))
+            .collect();
+        assert_eq!(
+            related,
+            vec![
+                (Some("reprint"), Some("reprintof")),
+                (Some("original"), Some("reprintof")),
+                (None, None)
+            ]
+        );
+    }
+}
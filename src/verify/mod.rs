@@ -0,0 +1,242 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+/// Kind of a document that cites entries of the bibliography
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SourceKind {
+    /// `\cite{key}`, `\parencite[p. 3]{a,b}` and the other cite commands of natbib and BibLaTeX
+    Latex,
+    /// `@key` and `#cite(<key>)`
+    Typst,
+    /// Pandoc citations like `[@key, p. 3; -@other]`
+    Markdown,
+}
+
+impl SourceKind {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_string_lossy().to_lowercase();
+        match extension.as_str() {
+            "tex" | "ltx" => Some(Self::Latex),
+            "typ" => Some(Self::Typst),
+            "md" | "markdown" | "qmd" | "rmd" => Some(Self::Markdown),
+            _ => None,
+        }
+    }
+}
+
+/// Citation keys that are cited in a document
+pub fn citation_keys(text: &str, kind: SourceKind) -> Vec<String> {
+    match kind {
+        SourceKind::Latex => latex_keys(text),
+        SourceKind::Typst => {
+            let mut keys = at_keys(text);
+            let mut rest = text;
+            while let Some(start) = rest.find("#cite(<") {
+                rest = &rest[start + "#cite(<".len()..];
+                if let Some(end) = rest.find('>') {
+                    keys.push(rest[..end].to_owned());
+                }
+            }
+            keys
+        }
+        SourceKind::Markdown => at_keys(text),
+    }
+}
+
+fn latex_keys(text: &str) -> Vec<String> {
+    let mut keys = Vec::new();
+    for line in text.lines() {
+        let line = strip_latex_comment(line);
+        let mut rest = line;
+        while let Some(start) = rest.find('\\') {
+            rest = &rest[start + 1..];
+            let name_length = rest
+                .find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(rest.len());
+            let name = &rest[..name_length];
+            rest = &rest[name_length..];
+            if !name.to_lowercase().contains("cite") {
+                continue;
+            }
+            rest = rest.strip_prefix('*').unwrap_or(rest);
+            // optional arguments and, for multicite commands like `\cites`, several key groups
+            loop {
+                rest = rest.trim_start();
+                let close = match rest.chars().next() {
+                    Some('[') => ']',
+                    Some('(') => ')',
+                    Some('{') => '}',
+                    _ => break,
+                };
+                let Some(end) = rest.find(close) else {
+                    break;
+                };
+                if close == '}' {
+                    keys.extend(
+                        rest[1..end]
+                            .split(',')
+                            .map(str::trim)
+                            .filter(|key| !key.is_empty() && *key != "*")
+                            .map(str::to_owned),
+                    );
+                }
+                rest = &rest[end + 1..];
+            }
+        }
+    }
+    keys
+}
+
+/// Line without a `%` comment, keeping escaped `\%`
+fn strip_latex_comment(line: &str) -> &str {
+    let mut previous = None;
+    for (index, c) in line.char_indices() {
+        if c == '%' && previous != Some('\\') {
+            return &line[..index];
+        }
+        previous = Some(c);
+    }
+    line
+}
+
+/// Keys of `@key` citations, which must not be preceded by a letter or digit to skip email addresses
+fn at_keys(text: &str) -> Vec<String> {
+    let mut keys = Vec::new();
+    let mut previous = None;
+    for (index, c) in text.char_indices() {
+        if c == '@' && !previous.is_some_and(|p: char| p.is_alphanumeric()) {
+            let key: String = text[index + 1..]
+                .chars()
+                .take_while(|c| c.is_alphanumeric() || "_-:./".contains(*c))
+                .collect();
+            let key = key.trim_end_matches(['.', ':', '/', '-']);
+            if key.starts_with(|c: char| c.is_alphanumeric() || c == '_') {
+                keys.push(key.to_owned());
+            }
+        }
+        previous = Some(c);
+    }
+    keys
+}
+
+/// All documents that can cite entries in the given files and directories, skipping hidden directories
+pub fn source_files(paths: &[PathBuf]) -> std::io::Result<Vec<(PathBuf, SourceKind)>> {
+    let mut files = Vec::new();
+    let mut pending = paths.to_vec();
+    while let Some(path) = pending.pop() {
+        if path.is_dir() {
+            for dir_entry in std::fs::read_dir(&path)? {
+                let child = dir_entry?.path();
+                let hidden = child
+                    .file_name()
+                    .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+                if !hidden {
+                    pending.push(child);
+                }
+            }
+        } else if let Some(kind) = SourceKind::from_path(&path) {
+            files.push((path, kind));
+        }
+    }
+    files.sort_by(|(a, _), (b, _)| a.cmp(b));
+    Ok(files)
+}
+
+/// Result of comparing the cited keys of a project with the keys of a bibliography
+#[derive(Debug, Default, PartialEq)]
+pub struct VerifyReport {
+    /// Cited keys that are not in the bibliography, with the files that cite them
+    pub missing: BTreeMap<String, BTreeSet<String>>,
+    /// Keys of the bibliography that are never cited
+    pub unused: BTreeSet<String>,
+}
+
+impl VerifyReport {
+    /// Compare the cited keys by file with the keys of the bibliography
+    pub fn new(citations: &[(String, Vec<String>)], bibliography_keys: &BTreeSet<String>) -> Self {
+        let mut report = Self::default();
+        let mut cited = BTreeSet::new();
+        for (file, keys) in citations {
+            for key in keys {
+                cited.insert(key.as_str());
+                if !bibliography_keys.contains(key) {
+                    report
+                        .missing
+                        .entry(key.clone())
+                        .or_default()
+                        .insert(file.clone());
+                }
+            }
+        }
+        report.unused = bibliography_keys
+            .iter()
+            .filter(|key| !cited.contains(key.as_str()))
+            .cloned()
+            .collect();
+        report
+    }
+}
+
+impl Display for VerifyReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Missing from the bibliography: {}", self.missing.len())?;
+        for (key, files) in &self.missing {
+            let files: Vec<&str> = files.iter().map(String::as_str).collect();
+            writeln!(f, "  {}  ({})", key, files.join(", "))?;
+        }
+        writeln!(f, "Never cited: {}", self.unused.len())?;
+        for key in &self.unused {
+            writeln!(f, "  {}", key)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::latex(
+        SourceKind::Latex,
+        "As shown \\cite{smith_2020, doe}, and \\parencite[see][p.~3]{lee}.\n% \\cite{commented}\n\\nocite{*}\\textcites[1]{a}[2]{b} 50\\% \\citeauthor*{c}",
+        vec!["smith_2020", "doe", "lee", "a", "b", "c"]
+    )]
+    #[case::typst(
+        SourceKind::Typst,
+        "See @smith_2020 and #cite(<doe:2019>). Mail me@example.org.",
+        vec!["smith_2020", "doe:2019"]
+    )]
+    #[case::markdown(
+        SourceKind::Markdown,
+        "Blah [@smith_2020, p. 3; -@doe]. @lee says.",
+        vec!["smith_2020", "doe", "lee"]
+    )]
+    fn find_citation_keys(
+        #[case] kind: SourceKind,
+        #[case] text: &str,
+        #[case] expected: Vec<&str>,
+    ) {
+        assert_eq!(citation_keys(text, kind), expected);
+    }
+
+    #[test]
+    fn compare_keys() {
+        let citations = vec![
+            ("a.tex".to_owned(), vec!["x".to_owned(), "y".to_owned()]),
+            ("b.tex".to_owned(), vec!["y".to_owned()]),
+        ];
+        let bibliography = BTreeSet::from(["x".to_owned(), "z".to_owned()]);
+        assert_eq!(
+            VerifyReport::new(&citations, &bibliography).to_string(),
+            "Missing from the bibliography: 1
+  y  (a.tex, b.tex)
+Never cited: 1
+  z
+"
+        );
+    }
+}
@@ -0,0 +1,95 @@
+use log::{LevelFilter, Log, Metadata, Record};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Number of warnings logged so far, for `--fail-on-warnings`
+#[derive(Clone, Default)]
+pub struct WarningCounter(Arc<AtomicUsize>);
+
+impl WarningCounter {
+    pub fn count(&self) -> usize {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Fail if any warnings were logged
+    pub fn check(&self) -> Result<(), WarningsError> {
+        match self.count() {
+            0 => Ok(()),
+            count => Err(WarningsError(count)),
+        }
+    }
+}
+
+/// Passes all records on to another logger, counting the warnings and errors, also those that the other logger filters out
+struct CountingLogger<L> {
+    inner: L,
+    warnings: WarningCounter,
+}
+
+impl<L: Log> Log for CountingLogger<L> {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::Level::Warn || self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if record.level() <= log::Level::Warn {
+            self.warnings.0.fetch_add(1, Ordering::Relaxed);
+        }
+        if self.inner.enabled(record.metadata()) {
+            self.inner.log(record);
+        }
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Install the logger, counting the warnings that are logged through it
+pub fn init(logger: env_logger::Logger) -> WarningCounter {
+    let warnings = WarningCounter::default();
+    log::set_max_level(logger.filter().max(LevelFilter::Warn));
+    if let Err(e) = log::set_boxed_logger(Box::new(CountingLogger {
+        inner: logger,
+        warnings: warnings.clone(),
+    })) {
+        eprintln!("Could not set up logging: {}", e);
+    }
+    warnings
+}
+
+#[derive(thiserror::Error, Debug)]
+#[error("{0} warnings were logged, failing because of --fail-on-warnings")]
+pub struct WarningsError(pub usize);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+    use pretty_assertions::assert_eq;
+
+    struct ErrorsOnly;
+
+    impl Log for ErrorsOnly {
+        fn enabled(&self, metadata: &Metadata) -> bool {
+            metadata.level() <= log::Level::Error
+        }
+
+        fn log(&self, _: &Record) {}
+
+        fn flush(&self) {}
+    }
+
+    #[test]
+    fn count_filtered_warnings() {
+        let logger = CountingLogger {
+            inner: ErrorsOnly,
+            warnings: WarningCounter::default(),
+        };
+        for level in [log::Level::Info, log::Level::Warn, log::Level::Error] {
+            logger.log(&Record::builder().level(level).build());
+        }
+        assert_eq!(logger.warnings.count(), 2);
+        assert_matches!(logger.warnings.check(), Err(WarningsError(2)));
+    }
+}
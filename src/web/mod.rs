@@ -0,0 +1,257 @@
+//! Read-only web page for syncs on shared servers: the state of the sync, recent changes and errors,
+//! and a download of the export file. Only `GET` requests are answered, nothing can be changed.
+
+use crate::dashboard::DashboardState;
+use crate::export::{EventSender, ExportEvent};
+use chrono::{DateTime, Local};
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+/// Seconds after which the page reloads itself
+const REFRESH_SECONDS: u32 = 10;
+
+/// Number of entries shown in the lists of the page
+const SHOWN_ENTRIES: usize = 20;
+
+#[derive(Clone)]
+pub struct WebUi {
+    file_path: PathBuf,
+    state: Arc<Mutex<DashboardState>>,
+}
+
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    /// File name to download the body as
+    download: Option<String>,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn text(status: &'static str, body: &str) -> Self {
+        Self {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            download: None,
+            body: body.as_bytes().to_vec(),
+        }
+    }
+}
+
+impl WebUi {
+    pub fn new(file_path: impl Into<PathBuf>) -> Self {
+        Self {
+            file_path: file_path.into(),
+            state: Arc::new(Mutex::new(DashboardState::new())),
+        }
+    }
+
+    /// Sender for the export events, which updates the page and passes the events on to `next`, if any
+    pub fn observe(&self, next: Option<EventSender>) -> EventSender {
+        let (sender, mut receiver) = mpsc::unbounded_channel::<ExportEvent>();
+        let state = self.state.clone();
+        tokio::spawn(async move {
+            while let Some(event) = receiver.recv().await {
+                state.lock().unwrap().apply(event.clone(), Local::now());
+                if let Some(next) = &next {
+                    let _ = next.send(event);
+                }
+            }
+        });
+        sender
+    }
+
+    /// Answer requests until cancelled
+    pub async fn serve(self, listener: TcpListener, cancellation_token: CancellationToken) {
+        loop {
+            let (stream, peer) = tokio::select! {
+                _ = cancellation_token.cancelled() => return,
+                accepted = listener.accept() => match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        log::warn!("Could not accept web UI connection: {}", e);
+                        continue;
+                    }
+                },
+            };
+            let web_ui = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = web_ui.answer(stream).await {
+                    log::debug!("Web UI connection from {} failed: {}", peer, e);
+                }
+            });
+        }
+    }
+
+    async fn answer(&self, stream: TcpStream) -> std::io::Result<()> {
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        let request_line = lines.next_line().await?.unwrap_or_default();
+        // the headers don't matter, but have to be read before answering
+        while let Some(line) = lines.next_line().await? {
+            if line.is_empty() {
+                break;
+            }
+        }
+        let mut parts = request_line.split_whitespace();
+        let response = match (parts.next(), parts.next()) {
+            (Some("GET"), Some(path)) => self.respond(path).await,
+            _ => Response::text(
+                "405 Method Not Allowed",
+                "Only GET requests are supported\n",
+            ),
+        };
+        let mut head = format!(
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n",
+            response.status,
+            response.content_type,
+            response.body.len()
+        );
+        if let Some(name) = &response.download {
+            head.push_str(&format!(
+                "Content-Disposition: attachment; filename=\"{}\"\r\n",
+                name.replace('"', "")
+            ));
+        }
+        head.push_str("\r\n");
+        writer.write_all(head.as_bytes()).await?;
+        writer.write_all(&response.body).await?;
+        writer.shutdown().await
+    }
+
+    async fn respond(&self, path: &str) -> Response {
+        match path.split('?').next().unwrap_or_default() {
+            "/" => Response {
+                status: "200 OK",
+                content_type: "text/html; charset=utf-8",
+                download: None,
+                body: page(&self.state.lock().unwrap(), &self.file_name()).into_bytes(),
+            },
+            "/export" => match tokio::fs::read(&self.file_path).await {
+                Ok(body) => Response {
+                    status: "200 OK",
+                    content_type: "text/plain; charset=utf-8",
+                    download: Some(self.file_name()),
+                    body,
+                },
+                Err(e) => {
+                    log::warn!(
+                        "Could not read '{}' for the web UI: {}",
+                        self.file_path.display(),
+                        e
+                    );
+                    Response::text("404 Not Found", "The export is not available yet\n")
+                }
+            },
+            _ => Response::text("404 Not Found", "Not found\n"),
+        }
+    }
+
+    fn file_name(&self) -> String {
+        self.file_path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+}
+
+fn page(state: &DashboardState, file_name: &str) -> String {
+    let last_export = match state.last_export() {
+        Some((time, version)) => {
+            format!("{} (version {})", time.format("%Y-%m-%d %H:%M:%S"), version)
+        }
+        None => "none yet".to_owned(),
+    };
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta http-equiv="refresh" content="{refresh}">
+<title>zotexon</title>
+<style>body {{ font-family: sans-serif; margin: 2em; }} td {{ padding-right: 1em; }} .connected {{ color: green; }} .disconnected {{ color: red; }}</style>
+</head>
+<body>
+<h1>zotexon</h1>
+<table>
+<tr><td>Connection</td><td class="{connection}">{connection}</td></tr>
+<tr><td>Last export</td><td>{last_export}</td></tr>
+<tr><td>State</td><td>{exporting}</td></tr>
+<tr><td>Export</td><td><a href="/export">{file_name}</a></td></tr>
+</table>
+<h2>Recent changes</h2>
+{activity}
+<h2>Errors and messages</h2>
+{messages}
+</body>
+</html>
+"#,
+        refresh = REFRESH_SECONDS,
+        connection = state.connection(),
+        exporting = if state.is_exporting() {
+            "exporting"
+        } else {
+            "idle"
+        },
+        file_name = escape(file_name),
+        activity = list(state.activity()),
+        messages = list(state.messages()),
+    )
+}
+
+fn list(entries: &VecDeque<(DateTime<Local>, String)>) -> String {
+    if entries.is_empty() {
+        return "<p>none</p>".to_owned();
+    }
+    let items: String = entries
+        .iter()
+        .take(SHOWN_ENTRIES)
+        .map(|(time, text)| {
+            format!(
+                "<li>{}  {}</li>\n",
+                time.format("%Y-%m-%d %H:%M:%S"),
+                escape(text)
+            )
+        })
+        .collect();
+    format!("<ul>\n{}</ul>", items)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn page_shows_state() {
+        let now = Local.with_ymd_and_hms(2025, 10, 1, 12, 30, 0).unwrap();
+        let mut state = DashboardState::new();
+        state.apply(ExportEvent::Connected, now);
+        state.apply(
+            ExportEvent::Exported {
+                version: 42,
+                entries: 3,
+            },
+            now,
+        );
+        state.apply(ExportEvent::ExportFailed("<timeout>".to_owned()), now);
+        let page = page(&state, "refs.bib");
+        assert!(page.contains(r#"<td class="connected">connected</td>"#));
+        assert!(page.contains("<td>2025-10-01 12:30:00 (version 42)</td>"));
+        assert!(page.contains("<li>2025-10-01 12:30:00  Exported version 42</li>"));
+        assert!(page.contains("Export failed: &lt;timeout&gt;"));
+        assert!(page.contains(r#"<a href="/export">refs.bib</a>"#));
+    }
+}
@@ -1,9 +1,100 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+/// Environment variable that the API key is read from if it isn't given otherwise
+pub const API_KEY_ENV: &str = "ZOTEXON_API_KEY";
+
+/// Environment variable that the API key is read from if `ZOTEXON_API_KEY` isn't set, named after the repository
+const ZOTEX_API_KEY_ENV: &str = "ZOTEX_API_KEY";
+
+/// Service and user of the entry in the keyring of the system that `login` stores the API key in
+#[cfg(feature = "keyring")]
+const KEYRING_ENTRY: (&str, &str) = ("zotexon", "api-key");
+
 /// Wrapper for the API key string.
 #[derive(Clone)]
 pub struct ApiKey(pub String);
 
+impl ApiKey {
+    /// The API key from the first of these sources that is given: the key itself, e.g. from the command line,
+    /// a file containing the key, e.g. a secret mounted into a container, the environment variable `ZOTEXON_API_KEY`
+    /// or `ZOTEX_API_KEY`, or the keyring of the system if the key was stored there with `login`.
+    /// Surrounding whitespace, like the trailing newline of a file, is removed.
+    pub fn resolve(key: Option<&str>, key_file: Option<&Path>) -> Result<Self, ApiKeyError> {
+        if let Some(key) = key {
+            return Self::from_text(key).ok_or(ApiKeyError::Missing);
+        }
+        if let Some(path) = key_file {
+            let text =
+                std::fs::read_to_string(path).map_err(|io_error| ApiKeyError::FileError {
+                    path: path.display().to_string(),
+                    io_error,
+                })?;
+            return Self::from_text(&text)
+                .ok_or_else(|| ApiKeyError::EmptyFile(path.display().to_string()));
+        }
+        if let Some(key) = [API_KEY_ENV, ZOTEX_API_KEY_ENV]
+            .iter()
+            .find_map(|name| Self::from_text(&std::env::var(name).ok()?))
+        {
+            return Ok(key);
+        }
+        #[cfg(feature = "keyring")]
+        match Self::load_from_keyring() {
+            Ok(Some(key)) => return Ok(key),
+            Ok(None) => {}
+            // e.g. no keyring on a server, where the key is given otherwise
+            Err(e) => log::debug!("Could not read the API key from the keyring: {:?}", e),
+        }
+        Err(ApiKeyError::Missing)
+    }
+
+    /// The key stored with `login`, `None` if there is none
+    #[cfg(feature = "keyring")]
+    pub fn load_from_keyring() -> Result<Option<Self>, ApiKeyError> {
+        match keyring_entry()?.get_password() {
+            Ok(key) => Ok(Self::from_text(&key)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(ApiKeyError::Keyring(e)),
+        }
+    }
+
+    /// Store the key in the keyring of the system, replacing a key stored before
+    #[cfg(feature = "keyring")]
+    pub fn store_in_keyring(&self) -> Result<(), ApiKeyError> {
+        keyring_entry()?
+            .set_password(&self.0)
+            .map_err(ApiKeyError::Keyring)
+    }
+
+    /// Remove the stored key from the keyring of the system, returns whether there was one
+    #[cfg(feature = "keyring")]
+    pub fn remove_from_keyring() -> Result<bool, ApiKeyError> {
+        match keyring_entry()?.delete_credential() {
+            Ok(()) => Ok(true),
+            Err(keyring::Error::NoEntry) => Ok(false),
+            Err(e) => Err(ApiKeyError::Keyring(e)),
+        }
+    }
+
+    fn from_text(text: &str) -> Option<Self> {
+        let key = text.trim();
+        (!key.is_empty()).then(|| Self(key.to_owned()))
+    }
+}
+
+#[cfg(feature = "keyring")]
+fn keyring_entry() -> Result<keyring::Entry, ApiKeyError> {
+    let (service, user) = KEYRING_ENTRY;
+    keyring::Entry::new(service, user).map_err(ApiKeyError::Keyring)
+}
+
 /// Structure for what the GET /keys/current endpoint returns on success.
-#[derive(Debug, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ApiKeyInfo {
     #[serde(rename = "userID")]
     pub user_id: u64,
@@ -15,22 +106,254 @@ impl ApiKeyInfo {
     pub fn can_access_library(&self) -> bool {
         self.access.user.library
     }
+
+    /// Whether the key can read the group library, either by access to this group or to all groups
+    pub fn can_access_group(&self, group_id: u64) -> bool {
+        ["all".to_owned(), group_id.to_string()]
+            .iter()
+            .filter_map(|group| self.access.groups.get(group))
+            .any(|access| access.library)
+    }
 }
 
 /// Details about what the API key can access (only the subset that is relevant for this tool)
-#[derive(Debug, serde::Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 struct KeyAccessInfo {
+    /// Missing for keys that only give access to groups
+    #[serde(default)]
     user: KeyUserAccessInfo,
+    /// Access by group ID, or `all` for all groups of the user
+    #[serde(default)]
+    groups: BTreeMap<String, KeyUserAccessInfo>,
 }
 
-/// Details about what the API key can access of the user items (only the subset that is relevant for this tool)
-#[derive(Debug, serde::Deserialize)]
+/// Details about what the API key can access of the items of a library (only the subset that is relevant for this tool)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 struct KeyUserAccessInfo {
+    #[serde(default)]
     library: bool,
+    #[serde(default)]
+    notes: bool,
+    #[serde(default)]
+    write: bool,
+}
+
+impl Display for KeyUserAccessInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.library {
+            return write!(f, "no access");
+        }
+        let rights: Vec<&str> = [(true, "read"), (self.notes, "notes"), (self.write, "write")]
+            .into_iter()
+            .filter_map(|(granted, right)| granted.then_some(right))
+            .collect();
+        write!(f, "{}", rights.join(", "))
+    }
+}
+
+/// What an API key can access, for checking a key, e.g. a secret of a CI pipeline
+pub struct KeyAccessReport<'a>(pub &'a ApiKeyInfo);
+
+impl Display for KeyAccessReport<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let info = self.0;
+        writeln!(f, "{:<12}  {} (ID {})", "User", info.username, info.user_id)?;
+        writeln!(f, "{:<12}  {}", "Library", info.access.user)?;
+        for (group, access) in &info.access.groups {
+            let name = match group.as_str() {
+                "all" => "All groups".to_owned(),
+                group_id => format!("Group {}", group_id),
+            };
+            writeln!(f, "{:<12}  {}", name, access)?;
+        }
+        Ok(())
+    }
+}
+
+/// Local copy of the key info of an API key, so that repeated invocations don't have to fetch it every time.
+///
+/// The file name is derived from a hash of the key, the key itself is never stored.
+pub struct KeyInfoCache {
+    path: PathBuf,
+}
+
+/// Key info as it was fetched at some point
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedKeyInfo {
+    pub info: ApiKeyInfo,
+    /// `Last-Modified-Version` of the response, for revalidating the info conditionally
+    pub version: Option<u64>,
+    pub fetched_at: DateTime<Utc>,
+}
+
+impl CachedKeyInfo {
+    /// Age up to which the info is used without asking the API. Requests with a revoked key still fail right away.
+    const MAX_AGE: Duration = Duration::hours(1);
+
+    pub fn is_fresh(&self, now: DateTime<Utc>) -> bool {
+        now - self.fetched_at < Self::MAX_AGE
+    }
+}
+
+impl KeyInfoCache {
+    /// Cache in the default cache directory of the system, if there is one
+    pub fn for_key(api_key: &ApiKey) -> Option<Self> {
+        let cache_dir = dirs::cache_dir()?;
+        Some(Self {
+            path: cache_dir
+                .join("zotexon")
+                .join("keys")
+                .join(format!("{}.json", key_hash(api_key))),
+        })
+    }
+
+    /// The cached key info, `None` if there is none or it can't be read
+    pub fn load(&self) -> Option<CachedKeyInfo> {
+        let json = std::fs::read_to_string(&self.path).ok()?;
+        serde_json::from_str(&json).ok()
+    }
+
+    /// Store the key info, failures are only logged because the cache is an optimization
+    pub fn store(&self, cached: &CachedKeyInfo) {
+        let result = self
+            .path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| {
+                std::fs::write(
+                    &self.path,
+                    serde_json::to_string(cached).unwrap_or_default(),
+                )
+            });
+        if let Err(e) = result {
+            log::warn!(
+                "Could not cache the API key info in '{}': {}",
+                self.path.display(),
+                e
+            );
+        }
+    }
+
+    /// Forget the key info, e.g. when the key was rejected
+    pub fn remove(&self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Name of the cache file of a key, which doesn't reveal the key
+fn key_hash(api_key: &ApiKey) -> String {
+    let hex = format!("{:x}", Sha256::digest(api_key.0.as_bytes()));
+    hex[..16].to_owned()
 }
 
 #[derive(thiserror::Error, Debug)]
 pub enum ApiKeyError {
     #[error("Insufficient access rights for API key. Needs at least read access to user library.")]
     InsufficientRights,
+    #[error("Insufficient access rights for API key. Needs at least read access to group {0}.")]
+    NoGroupAccess(u64),
+    #[cfg_attr(
+        not(feature = "keyring"),
+        error(
+            "No API key given. Use --api-key, --api-key-file or the environment variable {API_KEY_ENV}"
+        )
+    )]
+    #[cfg_attr(
+        feature = "keyring",
+        error(
+            "No API key given. Use --api-key, --api-key-file, the environment variable {API_KEY_ENV} or store one with `login`"
+        )
+    )]
+    Missing,
+    #[error("Error reading the API key from '{path}'")]
+    FileError {
+        path: String,
+        #[source]
+        io_error: std::io::Error,
+    },
+    #[error("The API key file '{0}' is empty")]
+    EmptyFile(String),
+    #[error(
+        "The API key contains characters that can't be sent to the Zotero API, e.g. a line break"
+    )]
+    InvalidCharacters,
+    #[cfg(feature = "keyring")]
+    #[error("Error accessing the keyring of the system")]
+    Keyring(#[source] keyring::Error),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn cached_key_info_expires() {
+        let cached: CachedKeyInfo = serde_json::from_value(serde_json::json!({
+            "info": {"userID": 12345, "username": "ada", "access": {"user": {"library": true}}},
+            "version": 7,
+            "fetched_at": "2025-10-01T12:00:00Z"
+        }))
+        .unwrap();
+        let fetched_at = cached.fetched_at;
+        assert!(cached.is_fresh(fetched_at + Duration::minutes(59)));
+        assert!(!cached.is_fresh(fetched_at + Duration::minutes(61)));
+        assert!(cached.info.can_access_library());
+    }
+
+    #[test]
+    fn group_access() {
+        let info: ApiKeyInfo = serde_json::from_value(serde_json::json!({
+            "userID": 12345, "username": "ada",
+            "access": {"groups": {"111": {"library": true}, "222": {"library": false}}}
+        }))
+        .unwrap();
+        assert!(!info.can_access_library());
+        assert!(info.can_access_group(111));
+        assert!(!info.can_access_group(222));
+        assert!(!info.can_access_group(333));
+        let info: ApiKeyInfo = serde_json::from_value(serde_json::json!({
+            "userID": 12345, "username": "ada",
+            "access": {"user": {"library": true}, "groups": {"all": {"library": true, "write": false}}}
+        }))
+        .unwrap();
+        assert!(info.can_access_group(333));
+    }
+
+    #[test]
+    fn key_access_report() {
+        let info: ApiKeyInfo = serde_json::from_value(serde_json::json!({
+            "userID": 12345, "username": "ada",
+            "access": {
+                "user": {"library": true, "files": true, "notes": true},
+                "groups": {"all": {"library": true, "write": true}, "222": {"library": false}}
+            }
+        }))
+        .unwrap();
+        assert_eq!(
+            KeyAccessReport(&info).to_string(),
+            "User          ada (ID 12345)
+Library       read, notes
+Group 222     no access
+All groups    read, write
+"
+        );
+    }
+
+    #[test]
+    fn resolve_key_from_argument() {
+        assert_eq!(
+            ApiKey::resolve(Some(" secret-key\n"), None).unwrap().0,
+            "secret-key"
+        );
+        assert!(ApiKey::from_text(" \n").is_none());
+    }
+
+    #[test]
+    fn cache_file_name_hides_key() {
+        let hash = key_hash(&ApiKey("secret-key".to_owned()));
+        assert_eq!(hash.len(), 16);
+        assert!(!hash.contains("secret"));
+        assert_eq!(hash, key_hash(&ApiKey("secret-key".to_owned())));
+    }
 }
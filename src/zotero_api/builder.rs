@@ -1,57 +1,223 @@
+use chrono::Utc;
 use reqwest::{StatusCode, header::HeaderMap};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
 
+use crate::network::NetworkOptions;
 use crate::zotero_api::{
-    API_BASE_URL, ApiError,
-    api_key::{ApiKey, ApiKeyError, ApiKeyInfo},
-    client::ReqwestZoteroClient,
+    API_BASE_URL, ApiError, DEFAULT_API_VERSION,
+    api_key::{ApiKey, ApiKeyError, ApiKeyInfo, CachedKeyInfo, KeyInfoCache},
+    check_api_version,
+    client::{GroupId, ReqwestZoteroClient},
+    har::{self, HarRecorder},
     headers,
+    retry::RetryBudget,
+    throttle::Throttle,
 };
 
+/// Time to validate the API key before giving up, so that an unreachable API doesn't block the start
+const KEY_CHECK_TIMEOUT: Duration = Duration::from_secs(30);
+
 pub struct ZoteroClientBuilder {
-    http_client: reqwest::Client,
+    api_key: ApiKey,
+    api_version: u32,
+    throttle: Option<Arc<Throttle>>,
+    publications: bool,
+    group: Option<GroupId>,
+    library_check: bool,
+    key_info_cache: Option<KeyInfoCache>,
+    retry_budget: Option<Arc<RetryBudget>>,
+    network: NetworkOptions,
+    har_capture: Option<PathBuf>,
+    cancellation_token: CancellationToken,
 }
 
 impl ZoteroClientBuilder {
     pub fn new(api_key: ApiKey) -> Self {
-        let mut headers = HeaderMap::new();
-        headers.insert(headers::ZOTERO_API_VERSION, "3".parse().unwrap());
-        headers.insert(headers::ZOTERO_API_KEY, api_key.0.parse().unwrap());
-        log::debug!("Default http headers: {:?}", headers);
-        let http_client = reqwest::Client::builder()
-            .default_headers(headers)
-            .build()
-            .unwrap();
-        Self { http_client }
+        Self {
+            api_key,
+            api_version: DEFAULT_API_VERSION,
+            throttle: None,
+            publications: false,
+            group: None,
+            library_check: true,
+            key_info_cache: None,
+            retry_budget: None,
+            network: NetworkOptions::default(),
+            har_capture: None,
+            cancellation_token: CancellationToken::new(),
+        }
+    }
+
+    /// Request this version of the Zotero API instead of the one this tool was written for.
+    /// Responses of another version are rejected instead of being misparsed.
+    pub fn with_api_version(mut self, api_version: u32) -> Self {
+        self.api_version = api_version;
+        self
+    }
+
+    /// Limit the rate at which the client downloads, the throttle can be shared with other downloads
+    pub fn with_throttle(mut self, throttle: Arc<Throttle>) -> Self {
+        self.throttle = Some(throttle);
+        self
+    }
+
+    /// Limit the retries of failed requests, the budget can be shared with the exporter that resets it for every export
+    pub fn with_retry_budget(mut self, retry_budget: Arc<RetryBudget>) -> Self {
+        self.retry_budget = Some(retry_budget);
+        self
+    }
+
+    /// Connect with another IP family, local address or network interface than the system would choose
+    pub fn with_network(mut self, network: NetworkOptions) -> Self {
+        self.network = network;
+        self
+    }
+
+    /// Record all requests and responses, with the API key redacted, in a HAR file at `path` for bug reports
+    pub fn with_har_capture(mut self, path: PathBuf) -> Self {
+        self.har_capture = Some(path);
+        self
+    }
+
+    /// Let the client fetch only the items in "My Publications" of the user instead of the whole library
+    pub fn with_publications(mut self) -> Self {
+        self.publications = true;
+        self
+    }
+
+    /// Let the client fetch the library of the group instead of the user's library
+    pub fn with_group(mut self, group_id: GroupId) -> Self {
+        self.group = Some(group_id);
+        self
+    }
+
+    /// Accept keys that can't read the user's library, e.g. keys for some groups only, to find out what they can read.
+    /// Fetching the user's library fails with such a key.
+    pub fn without_library_check(mut self) -> Self {
+        self.library_check = false;
+        self
+    }
+
+    /// Reuse the key info of recent invocations from this cache, and revalidate it conditionally when it is older
+    pub fn with_key_info_cache(mut self, cache: KeyInfoCache) -> Self {
+        self.key_info_cache = Some(cache);
+        self
+    }
+
+    /// Abort the validation of the API key when the token is cancelled, e.g. by Ctrl+C
+    pub fn with_cancellation(mut self, cancellation_token: CancellationToken) -> Self {
+        self.cancellation_token = cancellation_token;
+        self
     }
 
     /// Validates the given API key and returns a client instance ready to be used.
     /// Fails if the key is invalid, has insufficient rights, or if something else went wrong with the Zotero API.
     pub async fn build(self) -> Result<ReqwestZoteroClient, ClientBuildError> {
-        let response = self
-            .http_client
-            .get(format!("{}/keys/current", API_BASE_URL))
-            .send()
-            .await
-            .map_err(ApiError::from)?;
-        if response.status() != StatusCode::OK {
-            return Err(ClientBuildError::ApiError(ApiError::UnexpectedStatus {
-                status: response.status(),
-                body: response.text().await.unwrap_or_default(),
-            }));
-        }
-        let key_info = response
-            .json::<ApiKeyInfo>()
-            .await
-            .map_err(ApiError::from)?;
+        let mut headers = HeaderMap::new();
+        headers.insert(headers::ZOTERO_API_VERSION, self.api_version.into());
+        let api_key = self
+            .api_key
+            .0
+            .parse()
+            .map_err(|_| ApiKeyError::InvalidCharacters)?;
+        headers.insert(headers::ZOTERO_API_KEY, api_key);
+        log::debug!("Default http headers: {:?}", headers);
+        let har = self
+            .har_capture
+            .clone()
+            .map(|path| Arc::new(HarRecorder::new(path, headers.clone())));
+        let http_client = self
+            .network
+            .apply(reqwest::Client::builder())
+            .default_headers(headers)
+            .build()
+            .unwrap();
+        let key_info = tokio::select! {
+            biased;
+            _ = self.cancellation_token.cancelled() => return Err(ClientBuildError::Cancelled),
+            result = tokio::time::timeout(KEY_CHECK_TIMEOUT, self.key_info(&http_client, har.as_deref())) => {
+                result.map_err(|_| ClientBuildError::Timeout(KEY_CHECK_TIMEOUT))??
+            }
+        };
         log::info!("Got a valid API key for user {}", key_info.username);
-        if key_info.can_access_library() {
-            Ok(ReqwestZoteroClient::new(self.http_client, key_info.user_id))
-        } else {
+        if let Some(group_id) = self.group
+            && !key_info.can_access_group(group_id)
+        {
+            log::error!("Key does not have access to group {}", group_id);
+            return Err(ClientBuildError::ApiKeyError(ApiKeyError::NoGroupAccess(
+                group_id,
+            )));
+        }
+        if self.group.is_none() && self.library_check && !key_info.can_access_library() {
             log::error!("Key does not have access to library");
-            Err(ClientBuildError::ApiKeyError(
+            return Err(ClientBuildError::ApiKeyError(
                 ApiKeyError::InsufficientRights,
-            ))
+            ));
+        }
+        let client = ReqwestZoteroClient::new(
+            http_client,
+            key_info,
+            self.throttle,
+            self.api_version,
+            self.retry_budget.unwrap_or_default(),
+            har,
+        );
+        Ok(match self.group {
+            Some(group_id) => client.with_group(group_id),
+            None if self.publications => client.with_publications(),
+            None => client,
+        })
+    }
+
+    /// Info about the API key, from the cache if it is fresh enough or still valid
+    async fn key_info(
+        &self,
+        http_client: &reqwest::Client,
+        har: Option<&HarRecorder>,
+    ) -> Result<ApiKeyInfo, ApiError> {
+        let cached = self.key_info_cache.as_ref().and_then(KeyInfoCache::load);
+        let now = Utc::now();
+        if let Some(cached) = &cached
+            && cached.is_fresh(now)
+        {
+            log::debug!("Using cached API key info from {}", cached.fetched_at);
+            return Ok(cached.info.clone());
+        }
+        let mut request = http_client.get(format!("{}/keys/current", API_BASE_URL));
+        if let Some(version) = cached.as_ref().and_then(|cached| cached.version) {
+            request = request.header(headers::IF_MODIFIED_SINCE_VERSION, version);
+        }
+        let response = har::execute(http_client, request.build()?, har).await?;
+        check_api_version(self.api_version, response.headers())?;
+        let version = response
+            .headers()
+            .get(headers::LAST_MODIFIED_VERSION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse().ok());
+        let info = match (response.status(), cached) {
+            (StatusCode::NOT_MODIFIED, Some(cached)) => cached.info,
+            (StatusCode::OK, _) => response.json::<ApiKeyInfo>().await?,
+            (status, _) => {
+                if let Some(cache) = &self.key_info_cache {
+                    cache.remove();
+                }
+                return Err(ApiError::UnexpectedStatus {
+                    status,
+                    body: response.text().await.unwrap_or_default(),
+                });
+            }
+        };
+        if let Some(cache) = &self.key_info_cache {
+            cache.store(&CachedKeyInfo {
+                info: info.clone(),
+                version,
+                fetched_at: now,
+            });
         }
+        Ok(info)
     }
 }
 
@@ -61,4 +227,8 @@ pub enum ClientBuildError {
     ApiError(#[from] ApiError),
     #[error("Error with API key")]
     ApiKeyError(#[from] ApiKeyError),
+    #[error("Cancelled while validating the API key")]
+    Cancelled,
+    #[error("The Zotero API did not respond within {} s", .0.as_secs())]
+    Timeout(Duration),
 }
@@ -1,27 +1,198 @@
-use crate::zotero_api::{API_BASE_URL, ApiError, FetchItemsParams, FetchItemsResponse, headers};
+use crate::zotero_api::{
+    API_BASE_URL, ApiError, ExportFormat, FetchDataResponse, FetchItemsParams,
+    api_key::ApiKeyInfo,
+    check_api_version,
+    har::{self, HarRecorder},
+    headers,
+    item::{
+        Collection, CreatorType, DeletedObjects, Group, Item, ItemField, ItemType, SavedSearch,
+        TagColors,
+    },
+    paging::{PageSize, page_url},
+    retry::{RetryBudget, is_transient_error, is_transient_status, server_delay},
+    throttle::{Throttle, read_body},
+};
 use reqwest::header::{self, HeaderMap};
+use serde::de::DeserializeOwned;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tokio::time::Instant;
 
 pub trait ZoteroClient {
-    async fn fetch_items(&self, params: &FetchItemsParams) -> Result<FetchItemsResponse, ApiError>;
+    /// Fetch the export of all items, writing every page to `writer` as soon as it arrives,
+    /// so that the export of a huge library is never held in memory as a whole
+    async fn fetch_items(
+        &self,
+        params: &FetchItemsParams,
+        writer: &mut (impl AsyncWrite + Unpin),
+    ) -> Result<FetchDataResponse<()>, ApiError>;
+
+    /// Fetch one page of the export of the items with exactly the given size, e.g. to measure the API.
+    /// Returns the text of the page, which is empty past the last item.
+    async fn fetch_items_page(
+        &self,
+        format: &ExportFormat,
+        start: u32,
+        limit: u32,
+    ) -> Result<String, ApiError>;
+
+    /// Fetch the JSON data of all items, each including its export in the requested format
+    async fn fetch_items_with_data(
+        &self,
+        params: &FetchItemsParams,
+    ) -> Result<FetchDataResponse<Vec<Item>>, ApiError>;
+
+    /// Fetch the JSON data of the top-level items matching all words of the query in any field or the full text,
+    /// each including its export in the given format
+    async fn search_items(&self, query: &str, format: &ExportFormat)
+    -> Result<Vec<Item>, ApiError>;
+
+    /// Fetch the JSON data of the items with the given keys, each including its export in the given format
+    async fn fetch_items_by_key(
+        &self,
+        keys: &[String],
+        format: &ExportFormat,
+    ) -> Result<Vec<Item>, ApiError>;
+
+    /// Fetch the bibliography entry of an item, formatted as HTML in the given CSL style, e.g. `apa`
+    async fn fetch_bibliography(&self, key: &str, style: &str) -> Result<String, ApiError>;
+
+    /// Download the file of a stored attachment item
+    async fn fetch_attachment_file(&self, key: &str) -> Result<Vec<u8>, ApiError>;
+
+    /// Fetch the JSON data of all items that were modified since the given library version
+    async fn fetch_item_data(
+        &self,
+        since: Option<u64>,
+    ) -> Result<FetchDataResponse<Vec<Item>>, ApiError>;
+
+    /// Fetch all collections that were modified since the given library version
+    async fn fetch_collections(
+        &self,
+        since: Option<u64>,
+    ) -> Result<FetchDataResponse<Vec<Collection>>, ApiError>;
+
+    /// Fetch the groups that the user is a member of
+    async fn fetch_groups(&self) -> Result<Vec<Group>, ApiError>;
+
+    /// Fetch the saved searches of the library
+    async fn fetch_searches(&self) -> Result<Vec<SavedSearch>, ApiError>;
+
+    /// Fetch the keys of all objects that were deleted since the given library version
+    async fn fetch_deleted(&self, since: u64) -> Result<DeletedObjects, ApiError>;
+
+    /// Fetch the keys of the items in the trash that were modified since the given library version,
+    /// which includes the items that were moved to the trash since then
+    async fn fetch_trashed_keys(&self, since: u64) -> Result<Vec<String>, ApiError>;
+
+    /// Fetch the colored tags of the library
+    async fn fetch_tag_colors(&self) -> Result<TagColors, ApiError>;
+
+    /// Fetch the current version of the library
+    async fn fetch_library_version(&self) -> Result<u64, ApiError>;
+
+    /// Fetch the child items (attachments, notes) of an item in `format=json`
+    async fn fetch_children(&self, key: &str) -> Result<Vec<Item>, ApiError>;
+
+    /// Fetch the names of all item types of the Zotero schema, e.g. `journalArticle`
+    async fn fetch_item_types(&self) -> Result<Vec<String>, ApiError>;
+
+    /// Fetch the names of all item fields of the Zotero schema, e.g. `publicationTitle`
+    async fn fetch_item_fields(&self) -> Result<Vec<String>, ApiError>;
+
+    /// Fetch the names of the creator types of an item type of the Zotero schema, e.g. `author` and `editor`
+    async fn fetch_creator_types(&self, item_type: &str) -> Result<Vec<String>, ApiError>;
+
+    /// Check whether the API key can still access the library, e.g. because it might have been revoked
+    async fn check_key_access(&self) -> Result<bool, ApiError>;
+
     fn user_id(&self) -> UserId;
+
+    /// Info about the API key of the client, as it was checked when the client was built
+    fn key_info(&self) -> &ApiKeyInfo;
+
+    /// Topic of the exported library in the streaming API, e.g. `/users/12345` or `/groups/6789`
+    fn topic(&self) -> String;
+
+    /// Identity of the exported library, e.g. `users/12345` or `groups/6789`
+    fn library(&self) -> &str;
 }
 
+/// Clones share the connections, page size, throttle and retry budget
+#[derive(Clone)]
 pub struct ReqwestZoteroClient {
     http_client: reqwest::Client,
     user_id: UserId,
-    user_url: String,
+    key_info: Arc<ApiKeyInfo>,
+    /// URL of the exported library, e.g. `https://api.zotero.org/users/12345`
+    library_url: String,
+    /// Group of the exported library, if it is not the user's library
+    group: Option<GroupId>,
+    /// URL of the items of the exported library, either all items or those in "My Publications"
+    items_url: String,
+    /// Identity of the exported library, e.g. `users/12345`
+    library: String,
+    throttle: Option<Arc<Throttle>>,
+    page_size: Arc<PageSize>,
+    api_version: u32,
+    retry_budget: Arc<RetryBudget>,
+    har: Option<Arc<HarRecorder>>,
 }
 
 pub type UserId = u64;
 
+pub type GroupId = u64;
+
 impl ReqwestZoteroClient {
-    pub(in crate::zotero_api) fn new(http_client: reqwest::Client, user_id: UserId) -> Self {
-        let user_url = format!("{}/users/{}", API_BASE_URL, user_id);
-        log::debug!("User URL: {}", user_url);
+    pub(in crate::zotero_api) fn new(
+        http_client: reqwest::Client,
+        key_info: ApiKeyInfo,
+        throttle: Option<Arc<Throttle>>,
+        api_version: u32,
+        retry_budget: Arc<RetryBudget>,
+        har: Option<Arc<HarRecorder>>,
+    ) -> Self {
+        let user_id = key_info.user_id;
+        let library_url = format!("{}/users/{}", API_BASE_URL, user_id);
+        log::debug!("User URL: {}", library_url);
         Self {
             user_id,
-            user_url,
+            key_info: Arc::new(key_info),
+            group: None,
+            items_url: format!("{}/items", library_url),
+            library: format!("users/{}", user_id),
+            library_url,
             http_client,
+            throttle,
+            page_size: Arc::new(PageSize::new()),
+            api_version,
+            retry_budget,
+            har,
+        }
+    }
+
+    /// Export the library of a group that the user is a member of instead of the user's library
+    pub(in crate::zotero_api) fn with_group(mut self, group_id: GroupId) -> Self {
+        self.library_url = format!("{}/groups/{}", API_BASE_URL, group_id);
+        self.items_url = format!("{}/items", self.library_url);
+        self.library = format!("groups/{}", group_id);
+        self.group = Some(group_id);
+        self
+    }
+
+    /// Export only the items in "My Publications" of the user, which are public
+    pub(in crate::zotero_api) fn with_publications(mut self) -> Self {
+        self.items_url = format!("{}/publications/items", self.library_url);
+        self.library = format!("users/{}/publications", self.user_id);
+        self
+    }
+
+    /// URL of the items to export, either of the collection in the params or of the library
+    fn items_url_of(&self, params: &FetchItemsParams) -> String {
+        match &params.collection {
+            Some(key) => format!("{}/collections/{}/items", self.library_url, key),
+            None => self.items_url.clone(),
         }
     }
 
@@ -30,11 +201,131 @@ impl ReqwestZoteroClient {
         url: &str,
         headers: &HeaderMap,
     ) -> Result<FetchPageResponse, ApiError> {
-        let request = self.http_client.get(url).headers(headers.clone()).build()?;
+        loop {
+            let request = self.http_client.get(url).headers(headers.clone()).build()?;
+            let (failure, server_delay) = match self.execute(request).await {
+                Ok(response) if !is_transient_status(response.status()) => {
+                    self.retry_budget.on_success();
+                    check_api_version(self.api_version, response.headers())?;
+                    return self.parse_zotero_page_response(response).await;
+                }
+                Ok(response) => {
+                    let server_delay = server_delay(response.headers());
+                    let failure = ApiError::UnexpectedStatus {
+                        status: response.status(),
+                        body: response.text().await.unwrap_or_default(),
+                    };
+                    (failure, server_delay)
+                }
+                Err(e) if is_transient_error(&e) => (ApiError::from(e), None),
+                Err(e) => return Err(e.into()),
+            };
+            let delay = match self.retry_budget.on_failure(server_delay) {
+                Ok(delay) => delay,
+                Err(exhausted) => {
+                    return Err(ApiError::RetriesExhausted {
+                        exhausted: exhausted.to_string(),
+                        last_error: Box::new(failure),
+                    });
+                }
+            };
+            let reason = match std::error::Error::source(&failure) {
+                Some(source) => format!("{}: {}", failure, source),
+                None => failure.to_string(),
+            };
+            log::warn!(
+                "Request to {} failed, retrying in {:.1} s: {}",
+                url,
+                delay.as_secs_f64(),
+                reason
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Fetch the page of `url` that starts at `start`, with a page size adapted to the previous pages.
+    /// Returns the response and the start of the next page, if there is one.
+    async fn fetch_paged(
+        &self,
+        url: &str,
+        start: u32,
+        headers: &HeaderMap,
+    ) -> Result<(FetchPageResponse, Option<u32>), ApiError> {
+        let limit = self.page_size.limit();
+        let started = Instant::now();
+        let response = self
+            .fetch_page(&page_url(url, start, limit), headers)
+            .await?;
+        let next_start = match &response {
+            FetchPageResponse::Updated {
+                text,
+                has_next_page,
+                backoff,
+                ..
+            } => {
+                self.page_size
+                    .observe(started.elapsed(), text.len(), backoff.is_some());
+                if let Some(delay) = backoff
+                    && *has_next_page
+                {
+                    log::debug!(
+                        "The API asked to back off, waiting {} s before the next page",
+                        delay.as_secs()
+                    );
+                    tokio::time::sleep(*delay).await;
+                }
+                has_next_page.then_some(start + limit)
+            }
+            FetchPageResponse::UpToDate => None,
+        };
+        Ok((response, next_start))
+    }
+
+    /// Fetch all pages of a JSON array endpoint and merge them into one list
+    async fn fetch_json_pages<T: DeserializeOwned>(
+        &self,
+        url: String,
+        since: Option<u64>,
+    ) -> Result<FetchDataResponse<Vec<T>>, ApiError> {
+        let mut headers = HeaderMap::new();
+        if let Some(version) = since {
+            headers.insert(headers::IF_MODIFIED_SINCE_VERSION, version.into());
+        }
+        let mut next_start = Some(0);
+        let mut data = Vec::new();
+        let mut version = None;
+        while let Some(start) = next_start {
+            let (response, next) = self.fetch_paged(&url, start, &headers).await?;
+            match response {
+                FetchPageResponse::UpToDate => return Ok(FetchDataResponse::UpToDate),
+                FetchPageResponse::Updated {
+                    last_modified_version,
+                    text,
+                    ..
+                } => {
+                    data.extend(serde_json::from_str::<Vec<T>>(&text)?);
+                    version.get_or_insert(last_modified_version);
+                    next_start = next;
+                }
+            }
+        }
+        Ok(FetchDataResponse::Updated {
+            last_modified_version: version.unwrap_or_default(),
+            data,
+        })
+    }
+
+    /// Send the request, logging it and recording it in the HAR capture, if there is one
+    async fn execute(
+        &self,
+        request: reqwest::Request,
+    ) -> Result<reqwest::Response, reqwest::Error> {
         Self::log_request(&request);
-        let response = self.http_client.execute(request).await?;
-        Self::log_response(&response);
-        Self::parse_zotero_page_response(response).await
+        // the URL of a response replayed from the HAR capture is lost
+        let url = request.url().clone();
+        let response = har::execute(&self.http_client, request, self.har.as_deref()).await?;
+        Self::log_response(&url, &response);
+        Ok(response)
     }
 
     fn log_request(request: &reqwest::Request) {
@@ -46,16 +337,17 @@ impl ReqwestZoteroClient {
         );
     }
 
-    fn log_response(response: &reqwest::Response) {
+    fn log_response(url: &reqwest::Url, response: &reqwest::Response) {
         log::trace!(
             "Received response: {} {}\nHeaders: {:?}",
             response.status(),
-            response.url(),
+            url,
             response.headers()
         );
     }
 
     async fn parse_zotero_page_response(
+        &self,
         response: reqwest::Response,
     ) -> Result<FetchPageResponse, ApiError> {
         match response.status() {
@@ -66,12 +358,20 @@ impl ReqwestZoteroClient {
                     .and_then(|hv| hv.to_str().ok())
                     .and_then(|s| s.parse::<u64>().ok())
                     .unwrap_or(0);
-                let next_page_url = Self::try_get_next_page_url(response.headers());
-                let text = response.text().await?;
+                let has_next_page = Self::try_get_next_page_url(response.headers()).is_some();
+                let backoff = server_delay(response.headers());
+                let text = match &self.throttle {
+                    Some(throttle) => {
+                        String::from_utf8_lossy(&read_body(response, Some(throttle)).await?)
+                            .into_owned()
+                    }
+                    None => response.text().await?,
+                };
                 Ok(FetchPageResponse::Updated {
                     last_modified_version,
                     text,
-                    next_page_url,
+                    has_next_page,
+                    backoff,
                 })
             }
             reqwest::StatusCode::NOT_MODIFIED => Ok(FetchPageResponse::UpToDate),
@@ -105,56 +405,380 @@ enum FetchPageResponse {
     Updated {
         last_modified_version: u64,
         text: String,
-        next_page_url: Option<String>,
+        has_next_page: bool,
+        /// Delay that the API asked for to reduce the load, with a `Backoff` header
+        backoff: Option<Duration>,
     },
 }
 
 impl ZoteroClient for ReqwestZoteroClient {
-    async fn fetch_items(&self, params: &FetchItemsParams) -> Result<FetchItemsResponse, ApiError> {
-        let mut next_url = Some(format!("{}/items?format={}", self.user_url, params.format));
+    async fn fetch_items(
+        &self,
+        params: &FetchItemsParams,
+        writer: &mut (impl AsyncWrite + Unpin),
+    ) -> Result<FetchDataResponse<()>, ApiError> {
+        let url = format!(
+            "{}?format={}{}{}",
+            self.items_url_of(params),
+            encode_query_value(&params.format_name()),
+            tag_query(&params.tags),
+            since_query(params.since)
+        );
         let mut headers = HeaderMap::new();
         if let Some(version) = params.last_modified_version {
             headers.insert(headers::IF_MODIFIED_SINCE_VERSION, version.into());
         }
-        let mut result = Ok(FetchItemsResponse::UpToDate);
-
-        while let Some(url) = next_url {
-            match self.fetch_page(&url, &headers).await {
-                Ok(FetchPageResponse::Updated {
+        // the pages of the JSON export are arrays of their own, which are merged into one
+        let json = params.format_name() == "json";
+        if json {
+            writer.write_all(b"[").await?;
+        }
+        let mut next_start = Some(0);
+        let mut version = None;
+        let mut empty = true;
+        while let Some(start) = next_start {
+            let (response, next) = self.fetch_paged(&url, start, &headers).await?;
+            match response {
+                FetchPageResponse::UpToDate => return Ok(FetchDataResponse::UpToDate),
+                FetchPageResponse::Updated {
                     last_modified_version,
                     text,
-                    next_page_url,
-                }) => {
-                    if let Ok(FetchItemsResponse::Updated {
-                        text: existing_text,
-                        ..
-                    }) = &mut result
-                    {
-                        existing_text.push_str(&text);
-                    } else {
-                        result = Ok(FetchItemsResponse::Updated {
-                            last_modified_version,
-                            text,
-                        });
+                    ..
+                } => {
+                    let text = if json { json_array_items(&text) } else { &text };
+                    if json && !empty && !text.is_empty() {
+                        writer.write_all(b",\n").await?;
                     }
-                    next_url = next_page_url;
-                }
-                Ok(FetchPageResponse::UpToDate) => {
-                    result = Ok(FetchItemsResponse::UpToDate);
-                    next_url = None;
-                }
-                Err(e) => {
-                    result = Err(e);
-                    next_url = None;
+                    writer.write_all(text.as_bytes()).await?;
+                    empty &= text.is_empty();
+                    version.get_or_insert(last_modified_version);
+                    next_start = next;
                 }
             }
         }
-        result
+        if json {
+            writer.write_all(b"]\n").await?;
+        }
+        writer.flush().await?;
+        Ok(FetchDataResponse::Updated {
+            last_modified_version: version.unwrap_or_default(),
+            data: (),
+        })
+    }
+
+    async fn fetch_items_page(
+        &self,
+        format: &ExportFormat,
+        start: u32,
+        limit: u32,
+    ) -> Result<String, ApiError> {
+        let url = format!("{}?format={}", self.items_url, format);
+        match self
+            .fetch_page(&page_url(&url, start, limit), &HeaderMap::new())
+            .await?
+        {
+            FetchPageResponse::Updated { text, .. } => Ok(text),
+            FetchPageResponse::UpToDate => Ok(String::new()),
+        }
+    }
+
+    async fn fetch_items_with_data(
+        &self,
+        params: &FetchItemsParams,
+    ) -> Result<FetchDataResponse<Vec<Item>>, ApiError> {
+        let url = format!(
+            "{}?format=json&include=data,{}{}{}",
+            self.items_url_of(params),
+            params.format,
+            tag_query(&params.tags),
+            since_query(params.since)
+        );
+        self.fetch_json_pages(url, params.last_modified_version)
+            .await
+    }
+
+    async fn search_items(
+        &self,
+        query: &str,
+        format: &ExportFormat,
+    ) -> Result<Vec<Item>, ApiError> {
+        let url = format!(
+            "{}/top?format=json&include=data,{}&qmode=everything&q={}",
+            self.items_url,
+            format,
+            encode_query_value(query)
+        );
+        match self.fetch_json_pages(url, None).await? {
+            FetchDataResponse::Updated { data, .. } => Ok(data),
+            FetchDataResponse::UpToDate => Ok(Vec::new()),
+        }
+    }
+
+    async fn fetch_items_by_key(
+        &self,
+        keys: &[String],
+        format: &ExportFormat,
+    ) -> Result<Vec<Item>, ApiError> {
+        let mut items = Vec::new();
+        for keys in keys.chunks(MAX_ITEM_KEYS) {
+            let url = format!(
+                "{}?format=json&include=data,{}&itemKey={}",
+                self.items_url,
+                format,
+                keys.join(",")
+            );
+            if let FetchDataResponse::Updated { data, .. } =
+                self.fetch_json_pages(url, None).await?
+            {
+                items.extend(data);
+            }
+        }
+        Ok(items)
+    }
+
+    async fn fetch_bibliography(&self, key: &str, style: &str) -> Result<String, ApiError> {
+        let url = format!(
+            "{}/{}?format=bib&style={}",
+            self.items_url,
+            key,
+            encode_query_value(style)
+        );
+        match self.fetch_page(&url, &HeaderMap::new()).await? {
+            FetchPageResponse::Updated { text, .. } => Ok(text),
+            FetchPageResponse::UpToDate => Ok(String::new()),
+        }
+    }
+
+    async fn fetch_attachment_file(&self, key: &str) -> Result<Vec<u8>, ApiError> {
+        let url = format!("{}/{}/file", self.items_url, key);
+        let request = self.http_client.get(url).build()?;
+        let response = self.execute(request).await?;
+        match response.status() {
+            reqwest::StatusCode::OK => Ok(read_body(response, self.throttle.as_deref()).await?),
+            status => Err(ApiError::UnexpectedStatus {
+                status,
+                body: response.text().await.unwrap_or_default(),
+            }),
+        }
+    }
+
+    async fn fetch_item_data(
+        &self,
+        since: Option<u64>,
+    ) -> Result<FetchDataResponse<Vec<Item>>, ApiError> {
+        let url = format!(
+            "{}?format=json&since={}",
+            self.items_url,
+            since.unwrap_or_default()
+        );
+        self.fetch_json_pages(url, since).await
+    }
+
+    async fn fetch_collections(
+        &self,
+        since: Option<u64>,
+    ) -> Result<FetchDataResponse<Vec<Collection>>, ApiError> {
+        let url = format!(
+            "{}/collections?format=json&since={}",
+            self.library_url,
+            since.unwrap_or_default()
+        );
+        self.fetch_json_pages(url, since).await
+    }
+
+    async fn fetch_groups(&self) -> Result<Vec<Group>, ApiError> {
+        let url = format!("{}/users/{}/groups", API_BASE_URL, self.user_id);
+        match self.fetch_json_pages(url, None).await? {
+            FetchDataResponse::Updated { data, .. } => Ok(data),
+            FetchDataResponse::UpToDate => Ok(Vec::new()),
+        }
+    }
+
+    async fn fetch_searches(&self) -> Result<Vec<SavedSearch>, ApiError> {
+        let url = format!("{}/searches", self.library_url);
+        match self.fetch_json_pages(url, None).await? {
+            FetchDataResponse::Updated { data, .. } => Ok(data),
+            FetchDataResponse::UpToDate => Ok(Vec::new()),
+        }
+    }
+
+    async fn fetch_deleted(&self, since: u64) -> Result<DeletedObjects, ApiError> {
+        let url = format!("{}/deleted?since={}", self.library_url, since);
+        match self.fetch_page(&url, &HeaderMap::new()).await? {
+            FetchPageResponse::Updated { text, .. } => Ok(serde_json::from_str(&text)?),
+            FetchPageResponse::UpToDate => Ok(DeletedObjects::default()),
+        }
+    }
+
+    async fn fetch_trashed_keys(&self, since: u64) -> Result<Vec<String>, ApiError> {
+        let url = format!(
+            "{}/items/trash?format=keys&since={}",
+            self.library_url, since
+        );
+        match self.fetch_page(&url, &HeaderMap::new()).await? {
+            FetchPageResponse::Updated { text, .. } => Ok(text
+                .lines()
+                .map(str::trim)
+                .filter(|key| !key.is_empty())
+                .map(str::to_owned)
+                .collect()),
+            FetchPageResponse::UpToDate => Ok(Vec::new()),
+        }
+    }
+
+    async fn fetch_tag_colors(&self) -> Result<TagColors, ApiError> {
+        let url = format!("{}/settings/tagColors", self.library_url);
+        match self.fetch_page(&url, &HeaderMap::new()).await {
+            Ok(FetchPageResponse::Updated { text, .. }) => Ok(serde_json::from_str(&text)?),
+            Ok(FetchPageResponse::UpToDate) => Ok(TagColors::default()),
+            // The setting doesn't exist until the first tag gets a color
+            Err(ApiError::UnexpectedStatus {
+                status: reqwest::StatusCode::NOT_FOUND,
+                ..
+            }) => Ok(TagColors::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    async fn fetch_library_version(&self) -> Result<u64, ApiError> {
+        let url = format!("{}?format=versions&limit=1", self.items_url);
+        match self.fetch_page(&url, &HeaderMap::new()).await? {
+            FetchPageResponse::Updated {
+                last_modified_version,
+                ..
+            } => Ok(last_modified_version),
+            FetchPageResponse::UpToDate => Ok(0),
+        }
+    }
+
+    async fn fetch_children(&self, key: &str) -> Result<Vec<Item>, ApiError> {
+        let url = format!("{}/{}/children?format=json", self.items_url, key);
+        match self.fetch_json_pages(url, None).await? {
+            FetchDataResponse::Updated { data, .. } => Ok(data),
+            FetchDataResponse::UpToDate => Ok(Vec::new()),
+        }
+    }
+
+    async fn fetch_item_types(&self) -> Result<Vec<String>, ApiError> {
+        let url = format!("{}/itemTypes", API_BASE_URL);
+        match self.fetch_page(&url, &HeaderMap::new()).await? {
+            FetchPageResponse::Updated { text, .. } => {
+                Ok(serde_json::from_str::<Vec<ItemType>>(&text)?
+                    .into_iter()
+                    .map(|item_type| item_type.item_type)
+                    .collect())
+            }
+            FetchPageResponse::UpToDate => Ok(Vec::new()),
+        }
+    }
+
+    async fn fetch_item_fields(&self) -> Result<Vec<String>, ApiError> {
+        let url = format!("{}/itemFields", API_BASE_URL);
+        match self.fetch_page(&url, &HeaderMap::new()).await? {
+            FetchPageResponse::Updated { text, .. } => {
+                Ok(serde_json::from_str::<Vec<ItemField>>(&text)?
+                    .into_iter()
+                    .map(|item_field| item_field.field)
+                    .collect())
+            }
+            FetchPageResponse::UpToDate => Ok(Vec::new()),
+        }
+    }
+
+    async fn fetch_creator_types(&self, item_type: &str) -> Result<Vec<String>, ApiError> {
+        let url = format!(
+            "{}/itemTypeCreatorTypes?itemType={}",
+            API_BASE_URL,
+            encode_query_value(item_type)
+        );
+        match self.fetch_page(&url, &HeaderMap::new()).await? {
+            FetchPageResponse::Updated { text, .. } => {
+                Ok(serde_json::from_str::<Vec<CreatorType>>(&text)?
+                    .into_iter()
+                    .map(|creator_type| creator_type.creator_type)
+                    .collect())
+            }
+            FetchPageResponse::UpToDate => Ok(Vec::new()),
+        }
+    }
+
+    async fn check_key_access(&self) -> Result<bool, ApiError> {
+        let url = format!("{}/keys/current", API_BASE_URL);
+        match self.fetch_page(&url, &HeaderMap::new()).await {
+            Ok(FetchPageResponse::Updated { text, .. }) => {
+                let key_info: ApiKeyInfo = serde_json::from_str(&text)?;
+                Ok(key_info.user_id == self.user_id
+                    && match self.group {
+                        Some(group_id) => key_info.can_access_group(group_id),
+                        None => key_info.can_access_library(),
+                    })
+            }
+            Ok(FetchPageResponse::UpToDate) => Ok(true),
+            Err(ApiError::UnexpectedStatus {
+                status: reqwest::StatusCode::FORBIDDEN | reqwest::StatusCode::NOT_FOUND,
+                ..
+            }) => Ok(false),
+            Err(e) => Err(e),
+        }
     }
 
     fn user_id(&self) -> UserId {
         self.user_id
     }
+
+    fn key_info(&self) -> &ApiKeyInfo {
+        &self.key_info
+    }
+
+    fn topic(&self) -> String {
+        match self.group {
+            Some(group_id) => format!("/groups/{}", group_id),
+            None => format!("/users/{}", self.user_id),
+        }
+    }
+
+    fn library(&self) -> &str {
+        &self.library
+    }
+}
+
+/// Maximum number of keys in the `itemKey` parameter of one request
+const MAX_ITEM_KEYS: usize = 50;
+
+/// The items of a page of a JSON array without the enclosing brackets
+fn json_array_items(text: &str) -> &str {
+    let text = text.trim();
+    text.strip_prefix('[')
+        .and_then(|text| text.strip_suffix(']'))
+        .unwrap_or(text)
+        .trim()
+}
+
+/// Query parameters that filter the items by the tags, all of which have to match
+fn tag_query(tags: &[String]) -> String {
+    tags.iter()
+        .map(|tag| format!("&tag={}", encode_query_value(tag)))
+        .collect()
+}
+
+/// Query parameter that limits the items to those modified after the version, if any
+fn since_query(since: Option<u64>) -> String {
+    since
+        .map(|version| format!("&since={}", version))
+        .unwrap_or_default()
+}
+
+/// Percent-encode a value for the query string of a URL
+fn encode_query_value(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
 }
 
 #[cfg(test)]
@@ -197,4 +821,32 @@ mod tests {
         let next_page_url = ReqwestZoteroClient::try_get_next_page_url(&headers);
         assert_eq!(next_page_url, None);
     }
+
+    #[rstest]
+    #[case(
+        "[\n  {\"key\": \"A\"},\n  {\"key\": \"B\"}\n]\n",
+        "{\"key\": \"A\"},\n  {\"key\": \"B\"}"
+    )]
+    #[case("[]", "")]
+    #[case("", "")]
+    fn strip_json_array(#[case] page: &str, #[case] items: &str) {
+        assert_eq!(json_array_items(page), items);
+    }
+
+    #[test]
+    fn filter_by_tags() {
+        assert_eq!(tag_query(&[]), "");
+        assert_eq!(
+            tag_query(&["-to read".to_owned(), "ml || ai".to_owned()]),
+            "&tag=-to%20read&tag=ml%20%7C%7C%20ai"
+        );
+    }
+
+    #[test]
+    fn encode_search_query() {
+        assert_eq!(
+            encode_query_value("transformer attention&more/ü"),
+            "transformer%20attention%26more%2F%C3%BC"
+        );
+    }
 }
@@ -0,0 +1,294 @@
+use chrono::{DateTime, Utc};
+use reqwest::header::{self, HeaderMap};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tokio::time::Instant;
+
+use crate::zotero_api::headers;
+
+/// Headers whose values are replaced in the capture, so that it can be attached to bug reports
+const REDACTED_HEADERS: [&str; 2] = [headers::ZOTERO_API_KEY, "authorization"];
+
+/// Records all requests of a client with their responses, written as HTTP Archive (HAR) file when the recorder is dropped.
+///
+/// Responses are read completely before they are passed on, so download rate limits don't apply while capturing.
+pub struct HarRecorder {
+    path: PathBuf,
+    /// Headers that the HTTP client adds to every request, which the requests themselves don't contain
+    default_headers: HeaderMap,
+    entries: Mutex<Vec<HarEntry>>,
+}
+
+impl HarRecorder {
+    pub fn new(path: PathBuf, default_headers: HeaderMap) -> Self {
+        Self {
+            path,
+            default_headers,
+            entries: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Send the request like `client.execute()` and record it with the response
+    pub async fn execute(
+        &self,
+        client: &reqwest::Client,
+        request: reqwest::Request,
+    ) -> Result<reqwest::Response, reqwest::Error> {
+        let started = Utc::now();
+        let start = Instant::now();
+        let har_request = self.har_request(&request);
+        let response = client.execute(request).await?;
+        let status = response.status();
+        let version = response.version();
+        let response_headers = response.headers().clone();
+        let body = response.bytes().await?;
+        self.entries.lock().unwrap().push(HarEntry::new(
+            started,
+            start,
+            har_request,
+            status,
+            &response_headers,
+            &body,
+        ));
+        let mut rebuilt = http::Response::new(body);
+        *rebuilt.status_mut() = status;
+        *rebuilt.version_mut() = version;
+        *rebuilt.headers_mut() = response_headers;
+        Ok(rebuilt.into())
+    }
+
+    fn har_request(&self, request: &reqwest::Request) -> HarRequest {
+        let mut request_headers = self.default_headers.clone();
+        request_headers.extend(request.headers().clone());
+        HarRequest {
+            method: request.method().to_string(),
+            url: request.url().to_string(),
+            http_version: "HTTP/1.1",
+            cookies: Vec::new(),
+            headers: har_headers(&request_headers),
+            query_string: request
+                .url()
+                .query_pairs()
+                .map(|(name, value)| HarPair {
+                    name: name.into_owned(),
+                    value: value.into_owned(),
+                })
+                .collect(),
+            headers_size: -1,
+            body_size: 0,
+        }
+    }
+
+    fn write(&self, entries: Vec<HarEntry>) -> std::io::Result<()> {
+        let har = serde_json::json!({
+            "log": {
+                "version": "1.2",
+                "creator": {"name": "zotexon", "version": crate::ZOTEXON_VERSION},
+                "entries": entries,
+            }
+        });
+        std::fs::write(&self.path, serde_json::to_string_pretty(&har)?)
+    }
+}
+
+impl Drop for HarRecorder {
+    fn drop(&mut self) {
+        let entries = std::mem::take(&mut *self.entries.lock().unwrap());
+        if entries.is_empty() {
+            return;
+        }
+        match self.write(entries) {
+            Ok(()) => log::info!("Wrote the captured requests to '{}'", self.path.display()),
+            Err(e) => log::error!(
+                "Could not write the captured requests to '{}': {}",
+                self.path.display(),
+                e
+            ),
+        }
+    }
+}
+
+/// Send the request with the client, recording it if there is a recorder
+pub async fn execute(
+    client: &reqwest::Client,
+    request: reqwest::Request,
+    har: Option<&HarRecorder>,
+) -> Result<reqwest::Response, reqwest::Error> {
+    match har {
+        Some(har) => har.execute(client, request).await,
+        None => client.execute(request).await,
+    }
+}
+
+fn har_headers(headers: &HeaderMap) -> Vec<HarPair> {
+    headers
+        .iter()
+        .map(|(name, value)| HarPair {
+            name: name.to_string(),
+            value: if REDACTED_HEADERS
+                .iter()
+                .any(|redacted| name.as_str().eq_ignore_ascii_case(redacted))
+            {
+                "[REDACTED]".to_owned()
+            } else {
+                String::from_utf8_lossy(value.as_bytes()).into_owned()
+            },
+        })
+        .collect()
+}
+
+/// Entry of the HAR format 1.2, see http://www.softwareishard.com/blog/har-12-spec/
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HarEntry {
+    started_date_time: DateTime<Utc>,
+    time: f64,
+    request: HarRequest,
+    response: HarResponse,
+    cache: serde_json::Value,
+    timings: HarTimings,
+}
+
+impl HarEntry {
+    fn new(
+        started: DateTime<Utc>,
+        start: Instant,
+        request: HarRequest,
+        status: reqwest::StatusCode,
+        headers: &HeaderMap,
+        body: &[u8],
+    ) -> Self {
+        let time = start.elapsed().as_secs_f64() * 1000.0;
+        let mime_type = headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_owned();
+        // binary content like attachment files is left out, it doesn't help with API issues
+        let (text, comment) = match std::str::from_utf8(body) {
+            Ok(text) => (Some(text.to_owned()), None),
+            Err(_) => (None, Some("binary content omitted")),
+        };
+        Self {
+            started_date_time: started,
+            time,
+            request,
+            response: HarResponse {
+                status: status.as_u16(),
+                status_text: status.canonical_reason().unwrap_or_default(),
+                http_version: "HTTP/1.1",
+                cookies: Vec::new(),
+                headers: har_headers(headers),
+                content: HarContent {
+                    size: body.len(),
+                    mime_type,
+                    text,
+                    comment,
+                },
+                redirect_url: String::new(),
+                headers_size: -1,
+                body_size: body.len() as i64,
+            },
+            cache: serde_json::json!({}),
+            timings: HarTimings {
+                send: 0.0,
+                wait: time,
+                receive: 0.0,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HarRequest {
+    method: String,
+    url: String,
+    http_version: &'static str,
+    cookies: Vec<HarPair>,
+    headers: Vec<HarPair>,
+    query_string: Vec<HarPair>,
+    headers_size: i64,
+    body_size: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HarResponse {
+    status: u16,
+    status_text: &'static str,
+    http_version: &'static str,
+    cookies: Vec<HarPair>,
+    headers: Vec<HarPair>,
+    content: HarContent,
+    #[serde(rename = "redirectURL")]
+    redirect_url: String,
+    headers_size: i64,
+    body_size: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct HarContent {
+    size: usize,
+    mime_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment: Option<&'static str>,
+}
+
+#[derive(Debug, Serialize)]
+struct HarTimings {
+    send: f64,
+    wait: f64,
+    receive: f64,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+struct HarPair {
+    name: String,
+    value: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn redact_api_key() {
+        let mut request_headers = HeaderMap::new();
+        request_headers.insert(headers::ZOTERO_API_KEY, "secret".parse().unwrap());
+        request_headers.insert(headers::ZOTERO_API_VERSION, "3".parse().unwrap());
+        let recorder = HarRecorder::new(PathBuf::new(), request_headers);
+        let request = reqwest::Request::new(
+            reqwest::Method::GET,
+            "https://api.zotero.org/users/1/items?format=biblatex"
+                .parse()
+                .unwrap(),
+        );
+        let har_request = recorder.har_request(&request);
+        assert_eq!(
+            har_request.headers,
+            vec![
+                HarPair {
+                    name: "zotero-api-key".to_owned(),
+                    value: "[REDACTED]".to_owned()
+                },
+                HarPair {
+                    name: "zotero-api-version".to_owned(),
+                    value: "3".to_owned()
+                },
+            ]
+        );
+        assert_eq!(
+            har_request.query_string,
+            vec![HarPair {
+                name: "format".to_owned(),
+                value: "biblatex".to_owned()
+            }]
+        );
+    }
+}
@@ -0,0 +1,535 @@
+use crate::zotero_api::ExportFormat;
+use crate::zotero_api::client::GroupId;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+/// An item as returned by the Zotero API in `format=json`.
+///
+/// Only the fields that are relevant for this tool are typed, everything else is kept in `other`,
+/// so that items can be cached and serialized again without losing data.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Item {
+    pub key: String,
+    pub version: u64,
+    #[serde(default)]
+    pub meta: ItemMeta,
+    pub data: ItemData,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+impl Item {
+    /// Export of this item in the given format, if it was requested with `include=<format>`
+    pub fn export(&self, format: &ExportFormat) -> Option<&str> {
+        self.other
+            .get(&format.to_string())
+            .and_then(Value::as_str)
+            .filter(|export| !export.trim().is_empty())
+    }
+
+    /// Year of the item, based on the date parsed by Zotero
+    pub fn year(&self) -> Option<&str> {
+        self.meta
+            .parsed_date
+            .as_deref()
+            .and_then(|date| date.get(..4))
+            .filter(|year| year.chars().all(|c| c.is_ascii_digit()))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemMeta {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parsed_date: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_children: Option<u32>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+impl ItemMeta {
+    /// Name of the user who last modified the item, or who created it if it wasn't modified since.
+    /// Only items of group libraries have these users.
+    pub fn last_modified_by(&self) -> Option<&str> {
+        ["lastModifiedByUser", "createdByUser"]
+            .iter()
+            .find_map(|field| self.other.get(*field)?.get("username")?.as_str())
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemData {
+    pub item_type: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub title: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub creators: Vec<Creator>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<Tag>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub collections: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_item: Option<String>,
+    #[serde(flatten)]
+    pub fields: Map<String, Value>,
+}
+
+impl ItemData {
+    /// Value of an untyped string field, e.g. `DOI` or `extra`
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields
+            .get(name)
+            .and_then(Value::as_str)
+            .filter(|value| !value.is_empty())
+    }
+
+    /// Names of all creators of type `author`, as "First Last"
+    pub fn author_names(&self) -> Vec<String> {
+        self.creators
+            .iter()
+            .filter(|creator| creator.creator_type == "author")
+            .filter_map(
+                |creator| match (&creator.first_name, &creator.last_name, &creator.name) {
+                    (Some(first), Some(last), _) => Some(format!("{} {}", first, last)),
+                    (None, Some(last), _) => Some(last.clone()),
+                    (_, _, Some(name)) => Some(name.clone()),
+                    _ => None,
+                },
+            )
+            .filter(|name| !name.is_empty())
+            .collect()
+    }
+    /// Short list of the authors for listings, e.g. `Ada Lovelace and Charles Babbage` or `Ada Lovelace et al.`
+    pub fn short_authors(&self) -> String {
+        let names = self.author_names();
+        match names.as_slice() {
+            [] => "Unknown".to_owned(),
+            [first] => first.clone(),
+            [first, second] => format!("{} and {}", first, second),
+            [first, ..] => format!("{} et al.", first),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Creator {
+    pub creator_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Tag {
+    pub tag: String,
+    /// 0 for manual tags, 1 for automatic tags
+    #[serde(default, rename = "type")]
+    pub tag_type: u8,
+}
+
+/// An item type of the Zotero schema as returned by the `/itemTypes` endpoint
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ItemType {
+    pub item_type: String,
+}
+
+/// An item field of the Zotero schema as returned by the `/itemFields` endpoint
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct ItemField {
+    pub field: String,
+}
+
+/// A creator type of the Zotero schema as returned by the `/itemTypeCreatorTypes` endpoint
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatorType {
+    pub creator_type: String,
+}
+
+/// Names of the Zotero schema that the settings of the config can refer to
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Schema {
+    pub item_types: Vec<String>,
+    pub item_fields: Vec<String>,
+    /// Creator types of all item types
+    pub creator_types: Vec<String>,
+}
+
+/// A collection as returned by the Zotero API in `format=json`
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Collection {
+    pub key: String,
+    pub version: u64,
+    pub data: CollectionData,
+    #[serde(default)]
+    pub meta: CollectionMeta,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionMeta {
+    /// Number of items directly in the collection, not counting those of its subcollections
+    #[serde(default)]
+    pub num_items: usize,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionData {
+    pub name: String,
+    /// Zotero uses `false` for top-level collections
+    #[serde(default, deserialize_with = "key_or_false")]
+    pub parent_collection: Option<String>,
+}
+
+/// A group as returned by the `/users/{id}/groups` endpoint
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Group {
+    pub id: GroupId,
+    pub data: GroupData,
+    #[serde(default)]
+    pub meta: GroupMeta,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct GroupData {
+    pub name: String,
+    /// `Private`, `PublicClosed` or `PublicOpen`
+    #[serde(default, rename = "type")]
+    pub group_type: String,
+}
+
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupMeta {
+    #[serde(default)]
+    pub num_items: usize,
+}
+
+/// A saved search as returned by the `/searches` endpoint
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct SavedSearch {
+    pub key: String,
+    pub data: SavedSearchData,
+}
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct SavedSearchData {
+    pub name: String,
+    #[serde(default)]
+    pub conditions: Vec<SearchCondition>,
+}
+
+/// A condition of a saved search, e.g. `tag` `is` `toread`
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct SearchCondition {
+    pub condition: String,
+    pub operator: String,
+    #[serde(default)]
+    pub value: String,
+}
+
+impl Display for SearchCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} '{}'", self.condition, self.operator, self.value)
+    }
+}
+
+/// Filters of the items query that are equivalent to the conditions of a saved search
+#[derive(Debug, Default, PartialEq)]
+pub struct SearchFilters {
+    pub collection: Option<String>,
+    /// Tags like the `tag` parameter of the Zotero API
+    pub tags: Vec<String>,
+    /// Conditions that the items query can't express
+    pub unsupported: Vec<SearchCondition>,
+}
+
+impl SavedSearch {
+    /// Translate the conditions into filters of the items query. The API can only filter by one collection,
+    /// by tags, and match any of several conditions only for tags, other conditions are unsupported.
+    pub fn filters(&self) -> SearchFilters {
+        let conditions = &self.data.conditions;
+        let flag = |name: &str| {
+            conditions
+                .iter()
+                .find(|c| c.condition == name && c.operator != "false")
+        };
+        let match_any = flag("joinMode").is_some_and(|c| c.operator == "any");
+        let recursive = flag("recursive");
+        // besides the join mode, flags only change how Zotero shows the results or include subcollections
+        let conditions: Vec<&SearchCondition> = conditions
+            .iter()
+            .filter(|c| {
+                !matches!(
+                    c.condition.as_str(),
+                    "joinMode" | "noChildren" | "includeParentsAndChildren" | "recursive"
+                )
+            })
+            .collect();
+        let mut filters = SearchFilters::default();
+        if match_any && conditions.len() > 1 {
+            let (tags, unsupported): (Vec<_>, Vec<_>) = conditions
+                .into_iter()
+                .partition(|c| c.condition == "tag" && c.operator == "is");
+            if !tags.is_empty() {
+                filters.tags.push(
+                    tags.iter()
+                        .map(|c| c.value.as_str())
+                        .collect::<Vec<_>>()
+                        .join(" || "),
+                );
+            }
+            filters.unsupported = unsupported.into_iter().cloned().collect();
+            return filters;
+        }
+        for condition in conditions {
+            match (condition.condition.as_str(), condition.operator.as_str()) {
+                ("collection", "is") if filters.collection.is_none() => {
+                    filters.collection = Some(condition.value.clone())
+                }
+                ("tag", "is") => filters.tags.push(condition.value.clone()),
+                ("tag", "isNot") => filters.tags.push(format!("-{}", condition.value)),
+                _ => filters.unsupported.push(condition.clone()),
+            }
+        }
+        if let Some(recursive) = recursive.filter(|_| filters.collection.is_some()) {
+            filters.unsupported.push(recursive.clone());
+        }
+        filters
+    }
+}
+
+/// Objects deleted from a library, as returned by the `/deleted` endpoint
+#[derive(Deserialize, Debug, Default, PartialEq)]
+pub struct DeletedObjects {
+    #[serde(default)]
+    pub items: Vec<String>,
+    #[serde(default)]
+    pub collections: Vec<String>,
+}
+
+/// Full path of a collection, e.g. `Thesis/Chapter 2`, given all collections of the library by key
+pub fn collection_path(collections: &BTreeMap<String, Collection>, key: &str) -> Option<String> {
+    let collection = collections.get(key)?;
+    let mut path = collection.data.name.clone();
+    let mut parent = collection.data.parent_collection.as_deref();
+    // bounded by the number of collections, in case the hierarchy contains a cycle
+    for _ in 0..collections.len() {
+        let Some(parent_collection) = parent.and_then(|key| collections.get(key)) else {
+            break;
+        };
+        path = format!("{}/{}", parent_collection.data.name, path);
+        parent = parent_collection.data.parent_collection.as_deref();
+    }
+    Some(path)
+}
+
+/// Keys of the collections that `key_or_name` refers to: the collection with this key,
+/// or else all collections with this name or full path, e.g. `Chapter 2` or `Thesis/Chapter 2`
+pub fn find_collections(
+    collections: &BTreeMap<String, Collection>,
+    key_or_name: &str,
+) -> Vec<String> {
+    if collections.contains_key(key_or_name) {
+        return vec![key_or_name.to_owned()];
+    }
+    collections
+        .values()
+        .filter(|collection| {
+            collection.data.name == key_or_name
+                || collection_path(collections, &collection.key).as_deref() == Some(key_or_name)
+        })
+        .map(|collection| collection.key.clone())
+        .collect()
+}
+
+/// Tags that were assigned a color (and position) in the library, as returned by the `/settings/tagColors` endpoint
+#[derive(Deserialize, Debug, Default, PartialEq)]
+pub struct TagColors {
+    #[serde(default)]
+    pub value: Vec<TagColor>,
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+pub struct TagColor {
+    pub name: String,
+    pub color: String,
+}
+
+fn key_or_false<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(match Value::deserialize(deserializer)? {
+        Value::String(key) => Some(key),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    const ITEM_JSON: &str = r#"{
+        "key": "ABCD2345",
+        "version": 1234,
+        "library": { "type": "user", "id": 1 },
+        "meta": { "creatorSummary": "Smith", "parsedDate": "2020-03-01", "numChildren": 2 },
+        "data": {
+            "key": "ABCD2345",
+            "itemType": "journalArticle",
+            "title": "A title",
+            "creators": [{ "creatorType": "author", "firstName": "Jane", "lastName": "Smith" }],
+            "DOI": "10.1000/xyz",
+            "tags": [{ "tag": "toread" }, { "tag": "auto", "type": 1 }],
+            "collections": ["COLL1234"],
+            "relations": {}
+        }
+    }"#;
+
+    #[test]
+    fn deserialize_item() {
+        let item: Item = serde_json::from_str(ITEM_JSON).unwrap();
+        assert_eq!(item.key, "ABCD2345");
+        assert_eq!(item.year(), Some("2020"));
+        assert_eq!(item.meta.num_children, Some(2));
+        assert_eq!(item.data.field("DOI"), Some("10.1000/xyz"));
+        assert_eq!(item.data.tags[1].tag_type, 1);
+        assert_eq!(item.export(&ExportFormat::Biblatex), None);
+    }
+
+    #[test]
+    fn included_export() {
+        let item: Item = serde_json::from_str(
+            r#"{"key":"A","version":1,"data":{"itemType":"book"},"biblatex":"\n@book{a,\n}\n"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            item.export(&ExportFormat::Biblatex),
+            Some("\n@book{a,\n}\n")
+        );
+        assert_eq!(item.export(&ExportFormat::Bibtex), None);
+    }
+
+    #[test]
+    fn item_roundtrip_keeps_unknown_fields() {
+        let item: Item = serde_json::from_str(ITEM_JSON).unwrap();
+        let serialized = serde_json::to_string(&item).unwrap();
+        let reparsed: Item = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(reparsed, item);
+        assert!(reparsed.other.contains_key("library"));
+        assert!(reparsed.meta.other.contains_key("creatorSummary"));
+    }
+
+    #[test]
+    fn deserialize_collection_parent() {
+        let top: Collection = serde_json::from_str(
+            r#"{"key":"A","version":1,"data":{"name":"Thesis","parentCollection":false}}"#,
+        )
+        .unwrap();
+        let child: Collection = serde_json::from_str(
+            r#"{"key":"B","version":1,"data":{"name":"Chapter","parentCollection":"A"}}"#,
+        )
+        .unwrap();
+        assert_eq!(top.data.parent_collection, None);
+        assert_eq!(child.data.parent_collection, Some("A".into()));
+    }
+
+    #[test]
+    fn find_collection_by_key_or_name() {
+        let collections: Vec<Collection> = serde_json::from_str(
+            r#"[
+                {"key":"A","version":1,"data":{"name":"Thesis","parentCollection":false}},
+                {"key":"B","version":1,"data":{"name":"Figures","parentCollection":"A"}},
+                {"key":"C","version":1,"data":{"name":"Figures","parentCollection":false}}
+            ]"#,
+        )
+        .unwrap();
+        let collections: BTreeMap<String, Collection> = collections
+            .into_iter()
+            .map(|collection| (collection.key.clone(), collection))
+            .collect();
+        assert_eq!(find_collections(&collections, "A"), vec!["A"]);
+        assert_eq!(find_collections(&collections, "Thesis"), vec!["A"]);
+        assert_eq!(find_collections(&collections, "Thesis/Figures"), vec!["B"]);
+        assert_eq!(find_collections(&collections, "Figures"), vec!["B", "C"]);
+        assert_eq!(
+            find_collections(&collections, "Chapter"),
+            Vec::<String>::new()
+        );
+    }
+
+    fn search(conditions: serde_json::Value) -> SavedSearch {
+        serde_json::from_value(serde_json::json!({
+            "key": "SRCH0001",
+            "version": 3,
+            "data": {"key": "SRCH0001", "name": "To read", "conditions": conditions}
+        }))
+        .unwrap()
+    }
+
+    #[rstest]
+    #[case::all(
+        serde_json::json!([
+            {"condition": "collection", "operator": "is", "value": "COLL0001"},
+            {"condition": "tag", "operator": "is", "value": "toread"},
+            {"condition": "tag", "operator": "isNot", "value": "done"},
+            {"condition": "noChildren", "operator": "true", "value": ""},
+            {"condition": "title", "operator": "contains", "value": "optics"}
+        ]),
+        Some("COLL0001"),
+        vec!["toread", "-done"],
+        vec!["title contains 'optics'"]
+    )]
+    #[case::any_tags(
+        serde_json::json!([
+            {"condition": "joinMode", "operator": "any", "value": ""},
+            {"condition": "tag", "operator": "is", "value": "ml"},
+            {"condition": "tag", "operator": "is", "value": "ai"},
+            {"condition": "collection", "operator": "is", "value": "COLL0001"}
+        ]),
+        None,
+        vec!["ml || ai"],
+        vec!["collection is 'COLL0001'"]
+    )]
+    #[case::any_single(
+        serde_json::json!([
+            {"condition": "joinMode", "operator": "any", "value": ""},
+            {"condition": "collection", "operator": "is", "value": "COLL0001"},
+            {"condition": "recursive", "operator": "true", "value": ""}
+        ]),
+        Some("COLL0001"),
+        vec![],
+        vec!["recursive true ''"]
+    )]
+    fn saved_search_filters(
+        #[case] conditions: serde_json::Value,
+        #[case] collection: Option<&str>,
+        #[case] tags: Vec<&str>,
+        #[case] unsupported: Vec<&str>,
+    ) {
+        let filters = search(conditions).filters();
+        assert_eq!(filters.collection.as_deref(), collection);
+        assert_eq!(filters.tags, tags);
+        assert_eq!(
+            filters
+                .unsupported
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            unsupported
+        );
+    }
+}
@@ -1,18 +1,28 @@
 use std::fmt::Display;
 
+use reqwest::header::HeaderMap;
 use serde::{Deserialize, Serialize};
 
 pub mod api_key;
 pub mod builder;
 pub mod client;
+pub mod har;
+pub mod item;
+mod paging;
+pub mod retry;
+pub mod throttle;
 
 const API_BASE_URL: &str = "https://api.zotero.org";
 
+/// Version of the Zotero API that the responses are parsed for
+pub const DEFAULT_API_VERSION: u32 = 3;
+
 mod headers {
     pub const ZOTERO_API_VERSION: &str = "Zotero-API-Version";
     pub const ZOTERO_API_KEY: &str = "Zotero-API-Key";
     pub const LAST_MODIFIED_VERSION: &str = "Last-Modified-Version";
     pub const IF_MODIFIED_SINCE_VERSION: &str = "If-Modified-Since-Version";
+    pub const BACKOFF: &str = "Backoff";
 }
 
 /// Input for a request to fetch items from the Zotero API.
@@ -20,8 +30,30 @@ pub struct FetchItemsParams {
     /// Version of the library at the time of the last export
     pub last_modified_version: Option<u64>,
 
+    /// Only fetch the items that were modified after this library version instead of all items
+    pub since: Option<u64>,
+
     /// Format in which the library should be exported
     pub format: ExportFormat,
+
+    /// Key of the collection to export instead of all items of the library
+    pub collection: Option<String>,
+
+    /// Tags that the items have to match, each like the `tag` parameter of the Zotero API:
+    /// `toread`, `-toread` to exclude a tag, or `a || b` for either of two tags
+    pub tags: Vec<String>,
+
+    /// Name of a format that is requested instead of `format`, e.g. `mods`
+    pub raw_format: Option<String>,
+}
+
+impl FetchItemsParams {
+    /// Name of the requested format in the API
+    pub fn format_name(&self) -> String {
+        self.raw_format
+            .clone()
+            .unwrap_or_else(|| self.format.to_string())
+    }
 }
 
 /// Zotero export formats supported by this tool
@@ -31,6 +63,7 @@ pub enum ExportFormat {
     #[default]
     Biblatex,
     Bibtex,
+    Json,
 }
 
 impl Display for ExportFormat {
@@ -43,15 +76,12 @@ impl Display for ExportFormat {
     }
 }
 
-/// The happy path response when fetching items.
-pub enum FetchItemsResponse {
-    /// No updates since last fetch.
+/// The happy path response when fetching data like items, collections or the streamed export.
+pub enum FetchDataResponse<T> {
+    /// No updates since the given version.
     UpToDate,
-    /// New or updated items are available.
-    Updated {
-        last_modified_version: u64,
-        text: String,
-    },
+    /// New or updated data is available.
+    Updated { last_modified_version: u64, data: T },
 }
 
 /// Errors that can occur when interacting with the Zotero API.
@@ -65,18 +95,71 @@ pub enum ApiError {
         status: reqwest::StatusCode,
         body: String,
     },
+
+    #[error("Invalid JSON in response")]
+    JsonError(#[from] serde_json::Error),
+
+    #[error("Error while writing the response")]
+    IoError(#[from] std::io::Error),
+
+    #[error(
+        "Requested version {requested} of the Zotero API, but the server answered with version {received}. The server might have dropped support for version {requested}"
+    )]
+    VersionMismatch { requested: u32, received: String },
+
+    #[error("Giving up on the request because {exhausted}")]
+    RetriesExhausted {
+        exhausted: String,
+        #[source]
+        last_error: Box<ApiError>,
+    },
+}
+
+/// Check that the server answered with the requested API version, so that responses of another version aren't misparsed
+fn check_api_version(requested: u32, response_headers: &HeaderMap) -> Result<(), ApiError> {
+    match response_headers
+        .get(headers::ZOTERO_API_VERSION)
+        .map(|value| value.to_str().unwrap_or_default().trim())
+    {
+        Some(received) if received != requested.to_string() => Err(ApiError::VersionMismatch {
+            requested,
+            received: received.to_owned(),
+        }),
+        _ => Ok(()),
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::zotero_api::ExportFormat;
+    use crate::zotero_api::{ApiError, ExportFormat, check_api_version, headers};
+    use assert_matches::assert_matches;
     use pretty_assertions::assert_eq;
+    use reqwest::header::HeaderMap;
     use rstest::rstest;
 
     #[rstest]
     #[case(ExportFormat::Biblatex, "biblatex")]
     #[case(ExportFormat::Bibtex, "bibtex")]
+    #[case(ExportFormat::Json, "json")]
     fn export_format_to_str(#[case] format: ExportFormat, #[case] string_representation: &str) {
         assert_eq!(format.to_string(), string_representation);
     }
+
+    #[rstest]
+    #[case(None, None)]
+    #[case(Some("3"), None)]
+    #[case(Some("4"), Some("4"))]
+    fn compare_api_version(#[case] header: Option<&str>, #[case] mismatch: Option<&str>) {
+        let mut response_headers = HeaderMap::new();
+        if let Some(version) = header {
+            response_headers.insert(headers::ZOTERO_API_VERSION, version.parse().unwrap());
+        }
+        match mismatch {
+            Some(expected) => assert_matches!(
+                check_api_version(3, &response_headers),
+                Err(ApiError::VersionMismatch { requested: 3, received }) if received == expected
+            ),
+            None => assert_matches!(check_api_version(3, &response_headers), Ok(())),
+        }
+    }
 }
@@ -0,0 +1,102 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+/// Number of objects requested per page, adjusted to the observed responses.
+///
+/// Pages start at the maximum that the Zotero API allows, so that small libraries are fetched in one request.
+/// Slow or large pages and `Backoff` signals of the API halve the page size, fast and small pages double it again.
+#[derive(Debug)]
+pub struct PageSize {
+    limit: AtomicU32,
+}
+
+impl PageSize {
+    /// Maximum `limit` accepted by the Zotero API
+    const MAX: u32 = 100;
+    const MIN: u32 = 10;
+    /// Pages that take longer than this are made smaller
+    const SLOW: Duration = Duration::from_secs(10);
+    /// Pages that take less than this are made larger
+    const FAST: Duration = Duration::from_secs(2);
+    /// Pages with more bytes than this are made smaller
+    const LARGE: usize = 4 * 1024 * 1024;
+
+    pub fn new() -> Self {
+        Self {
+            limit: AtomicU32::new(Self::MAX),
+        }
+    }
+
+    pub fn limit(&self) -> u32 {
+        self.limit.load(Ordering::Relaxed)
+    }
+
+    /// Adjust the page size after a page was fetched with the given latency and size
+    pub fn observe(&self, latency: Duration, bytes: usize, backoff: bool) {
+        let limit = self.limit();
+        let adjusted = Self::adjust(limit, latency, bytes, backoff);
+        if adjusted != limit {
+            log::debug!(
+                "Changing page size from {} to {} (latency {:?}, {} bytes, backoff: {})",
+                limit,
+                adjusted,
+                latency,
+                bytes,
+                backoff
+            );
+            self.limit.store(adjusted, Ordering::Relaxed);
+        }
+    }
+
+    fn adjust(limit: u32, latency: Duration, bytes: usize, backoff: bool) -> u32 {
+        if backoff || latency > Self::SLOW || bytes > Self::LARGE {
+            (limit / 2).max(Self::MIN)
+        } else if latency < Self::FAST && bytes < Self::LARGE / 4 {
+            (limit * 2).min(Self::MAX)
+        } else {
+            limit
+        }
+    }
+}
+
+/// URL of a page of the given base URL
+pub fn page_url(url: &str, start: u32, limit: u32) -> String {
+    let separator = if url.contains('?') { '&' } else { '?' };
+    format!("{}{}start={}&limit={}", url, separator, start, limit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case::fast_small(50, 1, 1000, false, 100)]
+    #[case::at_maximum(100, 1, 1000, false, 100)]
+    #[case::slow(100, 15, 1000, false, 50)]
+    #[case::large(100, 1, 8 * 1024 * 1024, false, 50)]
+    #[case::backoff(100, 1, 1000, true, 50)]
+    #[case::at_minimum(10, 15, 1000, false, 10)]
+    #[case::moderate(40, 5, 1000, false, 40)]
+    fn adjust_page_size(
+        #[case] limit: u32,
+        #[case] latency_secs: u64,
+        #[case] bytes: usize,
+        #[case] backoff: bool,
+        #[case] expected: u32,
+    ) {
+        assert_eq!(
+            PageSize::adjust(limit, Duration::from_secs(latency_secs), bytes, backoff),
+            expected
+        );
+    }
+
+    #[test]
+    fn append_paging_parameters() {
+        assert_eq!(
+            page_url("https://api.zotero.org/users/1/items?format=json", 50, 25),
+            "https://api.zotero.org/users/1/items?format=json&start=50&limit=25"
+        );
+    }
+}
@@ -0,0 +1,250 @@
+use crate::zotero_api::headers;
+use reqwest::StatusCode;
+use reqwest::header::{self, HeaderMap};
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::Duration;
+
+/// Delay before a failed request is sent again the first time, doubled for every further failure in a row
+const DEFAULT_RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// Longest delay before a retry, however many requests failed in a row
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Limits the retries of all requests of a client, shared like the throttle.
+///
+/// A request that fails for a transient reason (connection problem, server error, rate limit) is retried,
+/// until either the total number of retries or the number of failures in a row is used up,
+/// so that a badly degraded network produces a clear error in time instead of minutes of retrying.
+/// The delay before a retry grows exponentially with the failures in a row, with random jitter so that
+/// parallel exports don't retry in lockstep, but is never shorter than the delay the server asks for.
+#[derive(Debug)]
+pub struct RetryBudget {
+    max_retries: u32,
+    max_consecutive_failures: u32,
+    base_delay: Duration,
+    /// Share of the delay that is added at most as random jitter
+    jitter: f64,
+    retries: AtomicU32,
+    consecutive_failures: AtomicU32,
+}
+
+impl Default for RetryBudget {
+    fn default() -> Self {
+        Self::new(10, 3)
+    }
+}
+
+impl RetryBudget {
+    pub fn new(max_retries: u32, max_consecutive_failures: u32) -> Self {
+        Self {
+            max_retries,
+            max_consecutive_failures: max_consecutive_failures.max(1),
+            base_delay: DEFAULT_RETRY_DELAY,
+            jitter: 0.0,
+            retries: AtomicU32::new(0),
+            consecutive_failures: AtomicU32::new(0),
+        }
+    }
+
+    /// Wait `base_delay` before the first retry and twice as long before every further retry in a row,
+    /// plus up to the `jitter` share of the delay at random
+    pub fn with_backoff(mut self, base_delay: Duration, jitter: f64) -> Self {
+        self.base_delay = base_delay;
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Record a failed request, with the delay that the server asked for, if any.
+    /// Returns the delay before it may be retried, or the reason why not.
+    pub fn on_failure(&self, server_delay: Option<Duration>) -> Result<Duration, BudgetExhausted> {
+        let consecutive_failures = self.consecutive_failures.fetch_add(1, Ordering::SeqCst) + 1;
+        if consecutive_failures >= self.max_consecutive_failures {
+            return Err(BudgetExhausted::ConsecutiveFailures(consecutive_failures));
+        }
+        let retries = self.retries.fetch_add(1, Ordering::SeqCst) + 1;
+        if retries > self.max_retries {
+            return Err(BudgetExhausted::Retries(self.max_retries));
+        }
+        let delay = backoff_delay(
+            self.base_delay,
+            consecutive_failures,
+            self.jitter * random_fraction(),
+        );
+        Ok(delay.max(server_delay.unwrap_or_default()))
+    }
+
+    pub fn on_success(&self) {
+        self.consecutive_failures.store(0, Ordering::SeqCst);
+    }
+
+    /// Start over with the full budget, e.g. for the next export of a sync session
+    pub fn reset(&self) {
+        self.retries.store(0, Ordering::SeqCst);
+        self.consecutive_failures.store(0, Ordering::SeqCst);
+    }
+}
+
+/// Delay before the retry after the given number of failures in a row, increased by the share `jitter`
+fn backoff_delay(base_delay: Duration, consecutive_failures: u32, jitter: f64) -> Duration {
+    let factor = 2u32.saturating_pow(consecutive_failures.saturating_sub(1));
+    base_delay
+        .saturating_mul(factor)
+        .min(MAX_RETRY_DELAY)
+        .mul_f64(1.0 + jitter)
+}
+
+/// Random number in `[0, 1)`, from the random keys of the standard library's hash maps
+fn random_fraction() -> f64 {
+    let random = std::collections::hash_map::RandomState::new()
+        .build_hasher()
+        .finish();
+    (random >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Why a failed request is not retried anymore
+#[derive(Debug, PartialEq)]
+pub enum BudgetExhausted {
+    Retries(u32),
+    ConsecutiveFailures(u32),
+}
+
+impl std::fmt::Display for BudgetExhausted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Retries(retries) => write!(f, "all {} retries are used up", retries),
+            Self::ConsecutiveFailures(failures) => {
+                write!(f, "{} requests failed in a row", failures)
+            }
+        }
+    }
+}
+
+/// Delay that the server asks for in seconds with a `Retry-After` header, e.g. with a 429 or 503 status,
+/// or a `Backoff` header, the longer one if there are both
+pub fn server_delay(headers: &HeaderMap) -> Option<Duration> {
+    [header::RETRY_AFTER.as_str(), headers::BACKOFF]
+        .into_iter()
+        .filter_map(|name| headers.get(name)?.to_str().ok()?.trim().parse::<u64>().ok())
+        .max()
+        .map(Duration::from_secs)
+}
+
+/// Whether a request that was answered with the status is worth retrying
+pub fn is_transient_status(status: StatusCode) -> bool {
+    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+/// Whether a request that failed without response is worth retrying: the connection failed or broke off,
+/// or the server took too long. Other errors, e.g. of building the request or following redirects, would only fail again.
+pub fn is_transient_error(error: &reqwest::Error) -> bool {
+    error.is_connect() || error.is_timeout() || error.is_body()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[test]
+    fn stop_after_consecutive_failures() {
+        let budget = RetryBudget::new(10, 3);
+        assert_eq!(budget.on_failure(None), Ok(Duration::from_secs(2)));
+        assert_eq!(budget.on_failure(None), Ok(Duration::from_secs(4)));
+        budget.on_success();
+        assert_eq!(budget.on_failure(None), Ok(Duration::from_secs(2)));
+        assert_eq!(budget.on_failure(None), Ok(Duration::from_secs(4)));
+        assert_eq!(
+            budget.on_failure(None),
+            Err(BudgetExhausted::ConsecutiveFailures(3))
+        );
+    }
+
+    #[test]
+    fn stop_after_total_retries() {
+        let budget = RetryBudget::new(2, 5);
+        for _ in 0..2 {
+            assert!(budget.on_failure(None).is_ok());
+            budget.on_success();
+        }
+        assert_eq!(budget.on_failure(None), Err(BudgetExhausted::Retries(2)));
+        budget.reset();
+        assert!(budget.on_failure(None).is_ok());
+    }
+
+    #[rstest]
+    #[case(1, 0.0, 500)]
+    #[case(3, 0.0, 2000)]
+    #[case(3, 0.5, 3000)]
+    #[case(12, 0.0, 60_000)]
+    #[case(40, 0.25, 75_000)]
+    fn exponential_backoff(
+        #[case] consecutive_failures: u32,
+        #[case] jitter: f64,
+        #[case] expected_millis: u64,
+    ) {
+        assert_eq!(
+            backoff_delay(Duration::from_millis(500), consecutive_failures, jitter),
+            Duration::from_millis(expected_millis)
+        );
+    }
+
+    #[test]
+    fn jitter_stays_within_share() {
+        let budget = RetryBudget::new(10, 5).with_backoff(Duration::from_secs(1), 0.5);
+        let delay = budget.on_failure(None).unwrap();
+        assert!(delay >= Duration::from_secs(1) && delay < Duration::from_millis(1500));
+    }
+
+    #[test]
+    fn wait_at_least_server_delay() {
+        let budget = RetryBudget::new(10, 5);
+        assert_eq!(
+            budget.on_failure(Some(Duration::from_secs(30))),
+            Ok(Duration::from_secs(30))
+        );
+        assert_eq!(
+            budget.on_failure(Some(Duration::from_secs(1))),
+            Ok(Duration::from_secs(4))
+        );
+    }
+
+    #[rstest]
+    #[case(None, None, None)]
+    #[case(Some("120"), None, Some(120))]
+    #[case(None, Some("5"), Some(5))]
+    #[case(Some("10"), Some("30"), Some(30))]
+    #[case(Some("Wed, 21 Oct 2015 07:28:00 GMT"), None, None)]
+    fn delay_of_headers(
+        #[case] retry_after: Option<&str>,
+        #[case] backoff: Option<&str>,
+        #[case] expected_secs: Option<u64>,
+    ) {
+        let mut headers = HeaderMap::new();
+        if let Some(retry_after) = retry_after {
+            headers.insert(header::RETRY_AFTER, retry_after.parse().unwrap());
+        }
+        if let Some(backoff) = backoff {
+            headers.insert(headers::BACKOFF, backoff.parse().unwrap());
+        }
+        assert_eq!(
+            server_delay(&headers),
+            expected_secs.map(Duration::from_secs)
+        );
+    }
+
+    #[test]
+    fn invalid_request_is_not_transient() {
+        let error = reqwest::Client::new().get("not a url").build().unwrap_err();
+        assert!(!is_transient_error(&error));
+    }
+
+    #[test]
+    fn transient_statuses() {
+        assert!(is_transient_status(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(is_transient_status(StatusCode::TOO_MANY_REQUESTS));
+        assert!(!is_transient_status(StatusCode::FORBIDDEN));
+        assert!(!is_transient_status(StatusCode::NOT_MODIFIED));
+    }
+}
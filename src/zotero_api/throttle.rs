@@ -0,0 +1,103 @@
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Limits the rate at which response bodies are downloaded, shared by all requests of a client.
+///
+/// Every downloaded chunk reserves its share of the bandwidth after the chunks before it,
+/// and the download waits until that share is used up.
+#[derive(Debug)]
+pub struct Throttle {
+    bytes_per_second: u64,
+    /// Time at which all reserved bytes will have been downloaded at the maximum rate
+    busy_until: Mutex<Instant>,
+}
+
+impl Throttle {
+    pub fn new(bytes_per_second: u64) -> Self {
+        Self {
+            bytes_per_second: bytes_per_second.max(1),
+            busy_until: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Wait until the given number of downloaded bytes fits into the maximum rate
+    pub async fn consume(&self, bytes: usize) {
+        let delay = self.reserve(bytes, Instant::now());
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    fn reserve(&self, bytes: usize, now: Instant) -> Duration {
+        let mut busy_until = self
+            .busy_until
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let start = (*busy_until).max(now);
+        *busy_until = start + Duration::from_secs_f64(bytes as f64 / self.bytes_per_second as f64);
+        busy_until.saturating_duration_since(now)
+    }
+}
+
+/// Read the body of a response, at the rate of the throttle if there is one
+pub async fn read_body(
+    mut response: reqwest::Response,
+    throttle: Option<&Throttle>,
+) -> Result<Vec<u8>, reqwest::Error> {
+    let Some(throttle) = throttle else {
+        return Ok(response.bytes().await?.to_vec());
+    };
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        throttle.consume(chunk.len()).await;
+        body.extend_from_slice(&chunk);
+    }
+    Ok(body)
+}
+
+/// Parse a rate in bytes per second with an optional unit, e.g. `500K` or `2M`
+pub fn parse_rate(text: &str) -> Result<u64, String> {
+    let text = text.trim();
+    let (number, factor) = match text.char_indices().last() {
+        Some((index, 'k' | 'K')) => (&text[..index], 1024),
+        Some((index, 'm' | 'M')) => (&text[..index], 1024 * 1024),
+        Some((index, 'g' | 'G')) => (&text[..index], 1024 * 1024 * 1024),
+        _ => (text, 1),
+    };
+    match number.trim().parse::<u64>() {
+        Ok(number) if number > 0 => Ok(number * factor),
+        _ => Err(format!(
+            "'{}' is not a rate like 800, 500K or 2M (bytes per second)",
+            text
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[test]
+    fn reserve_bandwidth() {
+        let throttle = Throttle::new(1000);
+        let now = Instant::now();
+        assert_eq!(throttle.reserve(500, now), Duration::from_millis(500));
+        assert_eq!(throttle.reserve(500, now), Duration::from_millis(1000));
+        // after an idle period, the rate starts over instead of allowing a burst
+        let later = now + Duration::from_secs(3);
+        assert_eq!(throttle.reserve(250, later), Duration::from_millis(250));
+    }
+
+    #[rstest]
+    #[case("800", Ok(800))]
+    #[case("500K", Ok(500 * 1024))]
+    #[case("2m", Ok(2 * 1024 * 1024))]
+    #[case("0", Err(()))]
+    #[case("fast", Err(()))]
+    fn parse_download_rate(#[case] text: &str, #[case] expected: Result<u64, ()>) {
+        assert_eq!(parse_rate(text).map_err(|_| ()), expected);
+    }
+}